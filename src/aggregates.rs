@@ -1,10 +1,291 @@
+use chrono::{self, offset::TimeZone, Datelike, Timelike};
 use llrb_index::Llrb;
 
+use std::{
+    cmp,
+    collections::{BTreeMap, BinaryHeap},
+};
+
 use crate::{
-    core::{Reduce, Result},
-    types,
+    core::{Durable, Error, Reduce, Result, Store},
+    err_at, types, util,
 };
 
+// number of years to look back for recent journal entries.
+const RECENT_YEARS: i32 = 10;
+
+// return the latest `n` journal entries, most recent first.
+pub fn recent_entries<S>(store: &mut S, n: usize) -> Result<Vec<types::JournalEntry>>
+where
+    S: Store,
+{
+    let now = chrono::Utc::now();
+
+    let mut entries = vec![];
+    for year in (0..RECENT_YEARS).map(|i| now.year() - i) {
+        if entries.len() >= n {
+            break;
+        }
+
+        let from = chrono::Utc.ymd(year, 1, 1).and_hms(0, 0, 0);
+        let to = if year == now.year() {
+            now.clone()
+        } else {
+            chrono::Utc.ymd(year, 12, 31).and_hms(23, 59, 59)
+        };
+        let mut items = vec![];
+        for item in store.iter_journal(from, to)? {
+            items.push(item?);
+        }
+        items.sort_by(|x, y| y.cmp(x));
+
+        let m = n - entries.len();
+        entries.extend(items.into_iter().take(m));
+    }
+
+    Ok(entries)
+}
+
+// return every ledger's balance, in `base` commodity, from all journal
+// entries created on or before `date`. Debits are positive and credits
+// are negative. Other commodities are converted using the `value` of
+// their metadata, as the value of one unit in `base` commodity.
+pub fn balances_as_of<S>(
+    store: &mut S,
+    date: chrono::DateTime<chrono::Utc>,
+    base: &str,
+) -> Result<Vec<(types::KeyLedger, f64)>>
+where
+    S: Store,
+{
+    let from = chrono::MIN_DATETIME;
+
+    let mut rates: BTreeMap<String, f64> = BTreeMap::new();
+    let mut balances: BTreeMap<String, (types::KeyLedger, f64)> = BTreeMap::new();
+    for entry in store.iter_journal(from, date)? {
+        for (ledger, value) in to_base_legs(store, &mut rates, &entry?, base)? {
+            let balance = balances
+                .entry(ledger.to_string())
+                .or_insert_with(|| (ledger, 0.0));
+            balance.1 += value;
+        }
+    }
+
+    Ok(balances.into_iter().map(|(_, balance)| balance).collect())
+}
+
+// legs of `entry` as (ledger, value) with value in `base` commodity,
+// debits are positive and credits are negative. Refer to_rate().
+fn to_base_legs<S>(
+    store: &mut S,
+    rates: &mut BTreeMap<String, f64>,
+    entry: &types::JournalEntry,
+    base: &str,
+) -> Result<Vec<(types::KeyLedger, f64)>>
+where
+    S: Store,
+{
+    let mut values = vec![];
+    for c in entry.creditors.iter() {
+        let rate = to_rate(store, rates, &c.commodity.name, base)?;
+        values.push((c.ledger.clone(), -c.commodity.value * rate));
+    }
+    for d in entry.debitors.iter() {
+        let rate = to_rate(store, rates, &d.commodity.name, base)?;
+        values.push((d.ledger.clone(), d.commodity.value * rate));
+    }
+    Ok(values)
+}
+
+// value of one unit of commodity `name` in `base` commodity, looked up
+// from store and memoized in `rates`.
+fn to_rate<S>(
+    store: &mut S,
+    rates: &mut BTreeMap<String, f64>,
+    name: &str,
+    base: &str,
+) -> Result<f64>
+where
+    S: Store,
+{
+    if name == base {
+        return Ok(1.0);
+    } else if let Some(rate) = rates.get(name) {
+        return Ok(*rate);
+    }
+
+    let key: types::KeyCommodity = (name.to_string(),).into();
+    let rate = match store.get_by(&key) {
+        Ok(c) if c.value != 0.0 => c.value,
+        _ => err_at!(InvalidInput, msg: format!("no rate for commodity {}", name))?,
+    };
+    rates.insert(name.to_string(), rate);
+    Ok(rate)
+}
+
+// return all ledgers belonging to `company`, sorted by name.
+pub fn ledgers_for_company<S>(store: &mut S, company: &str) -> Result<Vec<types::Ledger>>
+where
+    S: Store,
+{
+    let mut ledgers = vec![];
+    for ledger in store.iter::<types::Ledger>()? {
+        let ledger = ledger?;
+        if ledger.company == company {
+            ledgers.push(ledger);
+        }
+    }
+    ledgers.sort_by(|x, y| x.name.cmp(&y.name));
+
+    Ok(ledgers)
+}
+
+// return the ledger assigned account `code`.
+pub fn ledger_by_code<S>(store: &mut S, code: &str) -> Result<types::Ledger>
+where
+    S: Store,
+{
+    let code = code.trim();
+    for ledger in store.iter::<types::Ledger>()? {
+        let ledger = ledger?;
+        if !code.is_empty() && ledger.code == code {
+            return Ok(ledger);
+        }
+    }
+    err_at!(KeyNotFound, msg: format!("account code {}", code))
+}
+
+// account codes identify a single ledger, fail if `ledger`'s code is
+// already assigned to some other ledger in the store.
+pub fn check_ledger_code<S>(store: &mut S, ledger: &types::Ledger) -> Result<()>
+where
+    S: Store,
+{
+    let code = match ledger.to_code() {
+        Some(code) => code,
+        None => return Ok(()),
+    };
+    let key = ledger.to_key();
+    for other in store.iter::<types::Ledger>()? {
+        let other = other?;
+        if other.code == code && other.to_key() != key {
+            let msg = format!("account code {} assigned to {}", code, other.to_key());
+            err_at!(InvalidInput, msg: msg)?;
+        }
+    }
+    Ok(())
+}
+
+// return all ledgers sorted by account code, ledgers without a code
+// follow, sorted by key.
+pub fn ledgers_by_code<S>(store: &mut S) -> Result<Vec<types::Ledger>>
+where
+    S: Store,
+{
+    let mut ledgers = vec![];
+    for ledger in store.iter::<types::Ledger>()? {
+        ledgers.push(ledger?);
+    }
+    ledgers.sort_by(|x, y| {
+        let (xc, yc) = (x.to_code(), y.to_code());
+        (xc.is_none(), xc.map(|c| c.parse::<u64>().ok()))
+            .cmp(&(yc.is_none(), yc.map(|c| c.parse::<u64>().ok())))
+            .then_with(|| x.code.cmp(&y.code))
+            .then_with(|| x.to_key().cmp(&y.to_key()))
+    });
+
+    Ok(ledgers)
+}
+
+// return journal entries created within `period`, inclusive, that are
+// not yet reconciled, oldest first.
+pub fn unreconciled<S>(
+    store: &mut S,
+    period: (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+) -> Result<Vec<types::JournalEntry>>
+where
+    S: Store,
+{
+    let (from, to) = period;
+    let mut entries = vec![];
+    for entry in store.iter_journal(from, to)? {
+        let entry = entry?;
+        if !entry.reconciled {
+            entries.push(entry);
+        }
+    }
+    entries.sort();
+
+    Ok(entries)
+}
+
+// budget against actual spend for a ledger, in base commodity. Variance
+// is budget less actual, negative when over budget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetRow {
+    pub ledger: String,
+    pub budget: f64,
+    pub actual: f64,
+    pub variance: f64,
+    pub percent_used: f64,
+}
+
+// compare every ledger's budget for `period`, as (year, month), with its
+// net debit from journal entries within that month.
+pub fn budget_variance<S>(store: &mut S, period: (i32, u32)) -> Result<Vec<BudgetRow>>
+where
+    S: Store,
+{
+    let (year, month) = period;
+    let base = store.to_workspace()?.to_base_commodity();
+
+    let mut budgets = vec![];
+    for budget in store.iter::<types::Budget>()? {
+        let budget = budget?;
+        if budget.year == year && budget.month == month {
+            budgets.push(budget);
+        }
+    }
+    budgets.sort_by(|x, y| x.ledger.cmp(&y.ledger));
+    if budgets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let from = chrono::Utc.ymd(year, month, 1).and_hms(0, 0, 0);
+    let to = {
+        let (y, m) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        chrono::Utc.ymd(y, m, 1).and_hms(0, 0, 0) - chrono::Duration::seconds(1)
+    };
+
+    let mut rates: BTreeMap<String, f64> = BTreeMap::new();
+    let mut actuals: BTreeMap<String, f64> = BTreeMap::new();
+    for entry in store.iter_journal(from, to)? {
+        for (ledger, value) in to_base_legs(store, &mut rates, &entry?, &base)? {
+            *actuals.entry(ledger.to_string()).or_insert(0.0) += value;
+        }
+    }
+
+    let rows = budgets
+        .into_iter()
+        .map(|b| {
+            let actual = actuals.get(&b.ledger).cloned().unwrap_or(0.0);
+            let percent_used = match b.amount {
+                amount if amount == 0.0 => 0.0,
+                amount => actual * 100.0 / amount,
+            };
+            BudgetRow {
+                ledger: b.ledger,
+                budget: b.amount,
+                actual,
+                variance: b.amount - actual,
+                percent_used,
+            }
+        })
+        .collect();
+
+    Ok(rows)
+}
+
 struct FrequentlyUsedCompanies {
     companies: Llrb<String, usize>,
 }
@@ -39,3 +320,325 @@ impl Reduce<types::JournalEntry> for FrequentlyUsedCompanies {
         Ok(())
     }
 }
+
+// cash inflows and outflows, in base commodity, bucketed by month. Debits
+// to a cash ledger are inflows and credits from a cash ledger are outflows.
+pub struct CashFlow {
+    base: String,
+    cash_ledgers: Vec<String>,
+    // commodity-name -> value of one unit in base commodity.
+    rates: BTreeMap<String, f64>,
+    // (year, month) -> (inflow, outflow)
+    months: BTreeMap<(i32, u32), (f64, f64)>,
+    // applied to amounts converted to base commodity.
+    rounding: util::RoundingMode,
+}
+
+impl CashFlow {
+    pub fn new(base: String, cash_ledgers: Vec<String>) -> CashFlow {
+        CashFlow {
+            base,
+            cash_ledgers,
+            rates: BTreeMap::new(),
+            months: BTreeMap::new(),
+            rounding: Default::default(),
+        }
+    }
+
+    // treat all ledgers belonging to `group` as cash ledgers.
+    pub fn from_group(base: String, ledgers: &[types::Ledger], group: &str) -> CashFlow {
+        let cash_ledgers = ledgers
+            .iter()
+            .filter(|l| l.groups.iter().any(|g| g == group))
+            .map(|l| l.to_key())
+            .collect();
+        CashFlow::new(base, cash_ledgers)
+    }
+
+    pub fn set_rate(&mut self, commodity: String, rate: f64) -> &mut Self {
+        self.rates.insert(commodity, rate);
+        self
+    }
+
+    pub fn set_rounding(&mut self, rounding: util::RoundingMode) -> &mut Self {
+        self.rounding = rounding;
+        self
+    }
+
+    // return ((year, month), inflow, outflow, net) for every month with
+    // cash movement, in chronological order.
+    pub fn to_flows(&self) -> Vec<((i32, u32), f64, f64, f64)> {
+        self.months
+            .iter()
+            .map(|(ym, (inflow, outflow))| (*ym, *inflow, *outflow, inflow - outflow))
+            .collect()
+    }
+
+    fn to_base_value(&self, commodity: &types::Commodity) -> Result<f64> {
+        if commodity.name == self.base {
+            Ok(commodity.value)
+        } else {
+            match self.rates.get(&commodity.name) {
+                Some(rate) => {
+                    let value = commodity.value * rate;
+                    Ok(self.rounding.round(value, util::AMOUNT_PRECISION))
+                }
+                None => {
+                    let msg = format!("no rate for commodity {}", commodity.name);
+                    err_at!(InvalidInput, msg: msg)
+                }
+            }
+        }
+    }
+
+    fn is_cash(&self, ledger: &types::KeyLedger) -> bool {
+        let key = ledger.to_string();
+        self.cash_ledgers.iter().any(|l| l == &key)
+    }
+}
+
+impl Reduce<types::JournalEntry> for CashFlow {
+    fn reduce(&mut self, doc: &types::JournalEntry) -> Result<()> {
+        let (mut inflow, mut outflow) = (0.0, 0.0);
+        for d in doc.debitors.iter().filter(|d| self.is_cash(&d.ledger)) {
+            inflow += self.to_base_value(&d.commodity)?;
+        }
+        for c in doc.creditors.iter().filter(|c| self.is_cash(&c.ledger)) {
+            outflow += self.to_base_value(&c.commodity)?;
+        }
+
+        if inflow != 0.0 || outflow != 0.0 {
+            let ym = (doc.created.year(), doc.created.month());
+            let flow = self.months.entry(ym).or_insert((0.0, 0.0));
+            flow.0 += inflow;
+            flow.1 += outflow;
+        }
+
+        Ok(())
+    }
+}
+
+// count of journal entries by day of week and hour of day, from their
+// `created` timestamp in UTC. Rows are days of the week, in `start`
+// order, and columns are hours 0 to 23.
+pub struct ActivityHeatmap {
+    start: util::WeekStart,
+    cells: [[u32; 24]; 7],
+}
+
+impl ActivityHeatmap {
+    pub fn new(start: util::WeekStart) -> ActivityHeatmap {
+        ActivityHeatmap {
+            start,
+            cells: [[0; 24]; 7],
+        }
+    }
+
+    pub fn matrix(&self) -> Vec<Vec<u32>> {
+        self.cells.iter().map(|row| row.to_vec()).collect()
+    }
+}
+
+impl Reduce<types::JournalEntry> for ActivityHeatmap {
+    fn reduce(&mut self, doc: &types::JournalEntry) -> Result<()> {
+        let row = self.start.to_column(doc.created.weekday());
+        let col = doc.created.hour() as usize;
+        self.cells[row][col] += 1;
+
+        Ok(())
+    }
+}
+
+// journal entry ranked by its total debit amount, ties broken on the
+// entry's own order.
+struct Ranked(f64, types::JournalEntry);
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Ranked) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> cmp::Ordering {
+        self.0
+            .partial_cmp(&other.0)
+            .unwrap_or(cmp::Ordering::Equal)
+            .then_with(|| self.1.cmp(&other.1))
+    }
+}
+
+// the `n` largest journal entries by total debit amount, amounts across
+// commodities are summed as is. Feed it the entries of a period, EG: from
+// Store::iter_journal(). A min-heap of at most `n` entries is kept, the
+// smallest is evicted when a larger entry arrives.
+pub struct TopEntries {
+    n: usize,
+    heap: BinaryHeap<cmp::Reverse<Ranked>>,
+}
+
+impl TopEntries {
+    pub fn new(n: usize) -> TopEntries {
+        TopEntries {
+            n,
+            heap: BinaryHeap::with_capacity(n + 1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    // return (entry, total-debit), largest first.
+    pub fn top(&self) -> Vec<(types::JournalEntry, f64)> {
+        let mut items: Vec<&Ranked> = self.heap.iter().map(|r| &r.0).collect();
+        items.sort_by(|x, y| y.cmp(x));
+        items.into_iter().map(|r| (r.1.clone(), r.0)).collect()
+    }
+}
+
+impl Reduce<types::JournalEntry> for TopEntries {
+    fn reduce(&mut self, doc: &types::JournalEntry) -> Result<()> {
+        if self.n == 0 {
+            return Ok(());
+        }
+
+        let amount: f64 = doc.debitors.iter().map(|d| d.commodity.value).sum();
+        let item = Ranked(amount, doc.clone());
+        match self.heap.peek() {
+            Some(cmp::Reverse(min)) if self.heap.len() >= self.n => {
+                if &item > min {
+                    self.heap.pop();
+                    self.heap.push(cmp::Reverse(item));
+                }
+            }
+            _ => self.heap.push(cmp::Reverse(item)),
+        }
+
+        Ok(())
+    }
+}
+
+// how often, and how recently, a ledger was used in journal entries.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub count: usize,
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// ledger usage gathered from journal entries, keyed by `company:ledger`.
+pub struct FrequentlyUsedLedgers {
+    ledgers: BTreeMap<String, Usage>,
+}
+
+impl FrequentlyUsedLedgers {
+    pub fn new() -> FrequentlyUsedLedgers {
+        FrequentlyUsedLedgers {
+            ledgers: BTreeMap::new(),
+        }
+    }
+
+    pub fn to_usage(&self) -> BTreeMap<String, Usage> {
+        self.ledgers.clone()
+    }
+
+    fn touch(&mut self, key: types::KeyLedger, created: &chrono::DateTime<chrono::Utc>) {
+        let (cname, lname): (String, String) = key.into();
+        let usage = self
+            .ledgers
+            .entry(format!("{}:{}", cname, lname))
+            .or_default();
+        usage.count += 1;
+        usage.last_used = match usage.last_used.take() {
+            Some(last_used) if &last_used > created => Some(last_used),
+            _ => Some(created.clone()),
+        };
+    }
+}
+
+impl Reduce<types::JournalEntry> for FrequentlyUsedLedgers {
+    fn reduce(&mut self, doc: &types::JournalEntry) -> Result<()> {
+        for c in doc.creditors.iter() {
+            self.touch(c.ledger.clone(), &doc.created);
+        }
+        for d in doc.debitors.iter() {
+            self.touch(d.ledger.clone(), &doc.created);
+        }
+
+        Ok(())
+    }
+}
+
+// ranking function over completion candidates, candidates ordered Less
+// are offered first.
+pub type Rank = fn(&(String, Usage), &(String, Usage)) -> std::cmp::Ordering;
+
+// most used first, then most recently used, then alphabetical.
+pub fn rank_frequency(x: &(String, Usage), y: &(String, Usage)) -> std::cmp::Ordering {
+    y.1.count
+        .cmp(&x.1.count)
+        .then_with(|| y.1.last_used.cmp(&x.1.last_used))
+        .then_with(|| x.0.cmp(&y.0))
+}
+
+pub fn rank_alpha(x: &(String, Usage), y: &(String, Usage)) -> std::cmp::Ordering {
+    x.0.cmp(&y.0)
+}
+
+// complete ledger names by prefix, ranked by `rank`, which defaults to
+// rank_frequency().
+pub struct Completer {
+    candidates: Vec<(String, Usage)>,
+    rank: Rank,
+}
+
+impl Completer {
+    // candidates missing from `usage` are treated as never used.
+    pub fn new(names: Vec<String>, usage: &BTreeMap<String, Usage>) -> Completer {
+        let candidates = names
+            .into_iter()
+            .map(|name| {
+                let u = usage.get(&name).cloned().unwrap_or_default();
+                (name, u)
+            })
+            .collect();
+        Completer {
+            candidates,
+            rank: rank_frequency,
+        }
+    }
+
+    pub fn set_rank(&mut self, rank: Rank) -> &mut Self {
+        self.rank = rank;
+        self
+    }
+
+    // return candidates starting with `prefix`, ignoring case, in rank
+    // order.
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        let mut items: Vec<&(String, Usage)> = self
+            .candidates
+            .iter()
+            .filter(|(name, _)| name.to_lowercase().starts_with(&prefix))
+            .collect();
+        items.sort_by(|x, y| (self.rank)(x, y));
+        items.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "aggregates_test.rs"]
+mod aggregates_test;
@@ -0,0 +1,408 @@
+use super::*;
+use crate::mem_store::testing::{self, make_ledger};
+
+// groups are set directly, cash ledgers are grouped outside the default
+// chart of accounts.
+fn make_ledger_in(name: &str, groups: &str) -> types::Ledger {
+    let mut ledger = make_ledger(name);
+    ledger.groups = groups.split(',').map(|g| g.to_string()).collect();
+    ledger
+}
+
+fn make_entry(
+    date: (i32, u32, u32),
+    creditor: &str,
+    debitor: &str,
+    comm: (&str, f64),
+) -> types::JournalEntry {
+    let created = chrono::Utc.ymd(date.0, date.1, date.2).and_hms(10, 0, 0);
+    testing::make_entry("test", created, creditor, debitor, comm)
+}
+
+#[test]
+fn test_cash_flow() {
+    let ledgers = vec![
+        make_ledger_in("cash", "cash,assets"),
+        make_ledger_in("bank", "cash,assets"),
+        make_ledger_in("salary", "income"),
+        make_ledger_in("food", "expense"),
+    ];
+    let mut cf = CashFlow::from_group("inr".to_string(), &ledgers, "cash");
+    cf.set_rate("usd".to_string(), 80.0);
+
+    let entries = vec![
+        make_entry((2020, 1, 1), "salary", "bank", ("inr", 1000.0)),
+        make_entry((2020, 1, 5), "cash", "food", ("inr", 200.0)),
+        // transfer between cash ledgers is both an inflow and an outflow.
+        make_entry((2020, 1, 9), "bank", "cash", ("inr", 50.0)),
+        make_entry((2020, 2, 1), "salary", "bank", ("usd", 10.0)),
+        make_entry((2020, 2, 3), "bank", "food", ("inr", 300.0)),
+    ];
+    for entry in entries.iter() {
+        cf.reduce(entry).ok().unwrap();
+    }
+
+    let flows = cf.to_flows();
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0], ((2020, 1), 1050.0, 250.0, 800.0));
+    assert_eq!(flows[1], ((2020, 2), 800.0, 300.0, 500.0));
+
+    let entry = make_entry((2020, 3, 1), "salary", "bank", ("eur", 10.0));
+    match cf.reduce(&entry) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("eur"), "{}", msg),
+        _ => panic!("expected missing rate"),
+    }
+}
+
+#[test]
+fn test_cash_flow_rounding() {
+    let ledgers = vec![
+        make_ledger_in("cash", "cash"),
+        make_ledger_in("salary", "income"),
+    ];
+    // 0.25 usd at 0.5 lands exactly on 0.125 inr.
+    let entry = make_entry((2020, 1, 1), "salary", "cash", ("usd", 0.25));
+
+    let mut cf = CashFlow::from_group("inr".to_string(), &ledgers, "cash");
+    cf.set_rate("usd".to_string(), 0.5);
+    cf.reduce(&entry).ok().unwrap();
+    assert_eq!(cf.to_flows()[0].1, 0.13);
+
+    let mut cf = CashFlow::from_group("inr".to_string(), &ledgers, "cash");
+    cf.set_rate("usd".to_string(), 0.5)
+        .set_rounding(util::RoundingMode::HalfEven);
+    cf.reduce(&entry).ok().unwrap();
+    assert_eq!(cf.to_flows()[0].1, 0.12);
+}
+
+#[test]
+fn test_balances_as_of() {
+    let mut store = testing::make_store();
+    let usd: types::Commodity = ("usd".to_string(), 80.0).into();
+    store.put(usd).unwrap();
+
+    let entries = vec![
+        make_entry((2020, 1, 1), "salary", "bank", ("inr", 1000.0)),
+        make_entry((2020, 1, 5), "bank", "food", ("inr", 200.0)),
+        make_entry((2020, 1, 9), "salary", "bank", ("usd", 10.0)),
+        // after the snapshot date.
+        make_entry((2020, 2, 1), "salary", "bank", ("inr", 5000.0)),
+        make_entry((2020, 2, 3), "bank", "food", ("usd", 1.0)),
+    ];
+    for entry in entries.into_iter() {
+        store.put(entry).unwrap();
+    }
+
+    let date = chrono::Utc.ymd(2020, 1, 31).and_hms(23, 59, 59);
+    let balances: Vec<(String, f64)> = balances_as_of(&mut store, date, "inr")
+        .unwrap()
+        .into_iter()
+        .map(|(l, v)| (l.to_string(), v))
+        .collect();
+    assert_eq!(
+        balances,
+        vec![
+            ("ledger-acme-bank".to_string(), 1600.0),
+            ("ledger-acme-food".to_string(), 200.0),
+            ("ledger-acme-salary".to_string(), -1800.0),
+        ]
+    );
+
+    // entry on the snapshot date counts.
+    let date = chrono::Utc.ymd(2020, 1, 9).and_hms(10, 0, 0);
+    let balances = balances_as_of(&mut store, date, "inr").unwrap();
+    assert_eq!(balances[0].1, 1600.0);
+
+    let date = chrono::Utc.ymd(2019, 12, 31).and_hms(0, 0, 0);
+    assert!(balances_as_of(&mut store, date, "inr").unwrap().is_empty());
+
+    store.delete::<types::Commodity>("commodity-usd").unwrap();
+    let date = chrono::Utc.ymd(2020, 1, 31).and_hms(0, 0, 0);
+    match balances_as_of(&mut store, date, "inr") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("usd"), "{}", msg),
+        _ => panic!("expected missing rate"),
+    }
+}
+
+#[test]
+fn test_budget_variance() {
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut store = testing::make_store_with(w);
+    let usd: types::Commodity = ("usd".to_string(), 80.0).into();
+    store.put(usd).unwrap();
+
+    let food: types::KeyLedger = ("acme".to_string(), "food".to_string()).into();
+    let rent: types::KeyLedger = ("acme".to_string(), "rent".to_string()).into();
+    let travel: types::KeyLedger = ("acme".to_string(), "travel".to_string()).into();
+    for (ledger, amount) in vec![(&food, 500.0), (&rent, 1000.0), (&travel, 0.0)] {
+        let budget = types::Budget::new(ledger.clone(), (2020, 1), amount).unwrap();
+        store.put(budget).unwrap();
+    }
+    // budget for another month.
+    let budget = types::Budget::new(food.clone(), (2020, 2), 100.0).unwrap();
+    store.put(budget).unwrap();
+
+    let entries = vec![
+        make_entry((2020, 1, 2), "bank", "food", ("inr", 200.0)),
+        make_entry((2020, 1, 20), "bank", "food", ("usd", 1.0)),
+        make_entry((2020, 1, 31), "bank", "rent", ("inr", 1200.0)),
+        // outside the period.
+        make_entry((2020, 2, 1), "bank", "food", ("inr", 50.0)),
+    ];
+    for entry in entries.into_iter() {
+        store.put(entry).unwrap();
+    }
+
+    let rows = budget_variance(&mut store, (2020, 1)).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        rows[0],
+        BudgetRow {
+            ledger: food.to_string(),
+            budget: 500.0,
+            actual: 280.0,
+            variance: 220.0,
+            percent_used: 56.0,
+        }
+    );
+    // over budget.
+    assert_eq!(rows[1].ledger, rent.to_string());
+    assert_eq!(rows[1].actual, 1200.0);
+    assert_eq!(rows[1].variance, -200.0);
+    assert_eq!(rows[1].percent_used, 120.0);
+    // no budget, no spend.
+    assert_eq!(rows[2].ledger, travel.to_string());
+    assert_eq!((rows[2].actual, rows[2].percent_used), (0.0, 0.0));
+
+    assert_eq!(budget_variance(&mut store, (2020, 3)).unwrap(), vec![]);
+    assert!(types::Budget::new(food, (2020, 13), 1.0).is_err());
+}
+
+#[test]
+fn test_ledgers_for_company() {
+    let mut store = testing::make_store();
+
+    for name in ["food", "cash", "bank"].iter() {
+        store.put(make_ledger(name)).unwrap();
+    }
+    for name in ["cash", "payroll"].iter() {
+        let mut ledger = make_ledger(name);
+        ledger.company = "globex".to_string();
+        store.put(ledger).unwrap();
+    }
+
+    let names = |ledgers: Vec<types::Ledger>| -> Vec<String> {
+        ledgers.into_iter().map(|l| l.name).collect()
+    };
+    let ledgers = ledgers_for_company(&mut store, "acme").unwrap();
+    assert_eq!(names(ledgers), vec!["bank", "cash", "food"]);
+    let ledgers = ledgers_for_company(&mut store, "globex").unwrap();
+    assert_eq!(names(ledgers), vec!["cash", "payroll"]);
+    assert!(ledgers_for_company(&mut store, "initech").unwrap().is_empty());
+}
+
+#[test]
+fn test_ledger_codes() {
+    let mut store = testing::make_store();
+
+    for (name, code) in [("food", Some("5100")), ("cash", Some("1000")), ("bank", None)].iter() {
+        let mut ledger = make_ledger(name);
+        ledger.set_code(*code).unwrap();
+        store.put(ledger).unwrap();
+    }
+    let mut ledger = make_ledger("loan");
+    ledger.set_code(Some("200")).unwrap();
+    store.put(ledger).unwrap();
+    assert!(make_ledger("x").set_code(Some("10a")).is_err());
+    assert!(make_ledger("x").set_code(Some(" ")).is_err());
+
+    // codes are unique, re-putting a ledger keeps its own code.
+    let mut ledger = make_ledger("tax");
+    ledger.set_code(Some("1000")).unwrap();
+    match store.put(ledger) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("1000"), "{}", msg),
+        _ => panic!("expected duplicate code"),
+    }
+    let cash = ledger_by_code(&mut store, "1000").unwrap();
+    store.put(cash).unwrap();
+
+    assert_eq!(ledger_by_code(&mut store, "1000").unwrap().name, "cash");
+    assert_eq!(ledger_by_code(&mut store, " 5100 ").unwrap().name, "food");
+    match ledger_by_code(&mut store, "9999") {
+        Err(Error::KeyNotFound(_)) => (),
+        _ => panic!("expected unknown code"),
+    }
+    assert!(ledger_by_code(&mut store, "").is_err());
+
+    // numeric order, uncoded ledgers last.
+    let names: Vec<String> = ledgers_by_code(&mut store)
+        .unwrap()
+        .into_iter()
+        .map(|l| l.name)
+        .collect();
+    assert_eq!(names, vec!["loan", "cash", "food", "bank"]);
+}
+
+#[test]
+fn test_unreconciled() {
+    let mut store = testing::make_store();
+
+    let mut entries = vec![];
+    for day in 1..=4 {
+        let je = make_entry((2020, 6, day), "cash", "food", ("inr", 10.0));
+        store.put(je.clone()).unwrap();
+        entries.push(je);
+    }
+    // outside the period.
+    store
+        .put(make_entry((2020, 8, 1), "cash", "food", ("inr", 10.0)))
+        .unwrap();
+
+    // reconcile the 2nd and 3rd entries, then un-reconcile the 3rd.
+    for i in vec![1, 2, 2].into_iter() {
+        entries[i].toggle_reconciled();
+        store.put(entries[i].clone()).unwrap();
+    }
+
+    let period = (
+        chrono::Utc.ymd(2020, 6, 1).and_hms(0, 0, 0),
+        chrono::Utc.ymd(2020, 6, 30).and_hms(23, 59, 59),
+    );
+    let days: Vec<u32> = unreconciled(&mut store, period)
+        .unwrap()
+        .into_iter()
+        .map(|je| je.created.day())
+        .collect();
+    assert_eq!(days, vec![1, 3, 4]);
+}
+
+#[test]
+fn test_activity_heatmap() {
+    let at = |d: u32, h: u32| {
+        let created = chrono::Utc.ymd(2020, 6, d).and_hms(h, 30, 0);
+        types::JournalEntry::new("test".to_string(), created)
+    };
+    // 1-Jun-2020 is a Monday, 7-Jun-2020 a Sunday.
+    let entries = vec![at(1, 9), at(1, 9), at(1, 18), at(3, 0), at(7, 23)];
+
+    let mut heatmap = ActivityHeatmap::new(util::WeekStart::Monday);
+    for entry in entries.iter() {
+        heatmap.reduce(entry).unwrap();
+    }
+    let matrix = heatmap.matrix();
+    assert_eq!(matrix.len(), 7);
+    assert!(matrix.iter().all(|row| row.len() == 24));
+    assert_eq!(matrix[0][9], 2);
+    assert_eq!(matrix[0][18], 1);
+    assert_eq!(matrix[2][0], 1);
+    assert_eq!(matrix[6][23], 1);
+    let total: u32 = matrix.iter().flatten().sum();
+    assert_eq!(total, 5);
+
+    // weeks starting on sunday shift the rows.
+    let mut heatmap = ActivityHeatmap::new(util::WeekStart::Sunday);
+    for entry in entries.iter() {
+        heatmap.reduce(entry).unwrap();
+    }
+    let matrix = heatmap.matrix();
+    assert_eq!(matrix[0][23], 1);
+    assert_eq!(matrix[1][9], 2);
+}
+
+#[test]
+fn test_top_entries() {
+    let amounts = vec![40.0, 5.0, 120.0, 75.5, 0.5, 120.0, 60.0, 10.0];
+    let entries: Vec<types::JournalEntry> = amounts
+        .iter()
+        .enumerate()
+        .map(|(i, a)| make_entry((2020, 6, 1 + i as u32), "cash", "food", ("inr", *a)))
+        .collect();
+
+    let mut top = TopEntries::new(3);
+    for entry in entries.iter() {
+        top.reduce(entry).unwrap();
+        assert!(top.len() <= 3);
+    }
+    let items = top.top();
+    let values: Vec<f64> = items.iter().map(|(_, v)| *v).collect();
+    assert_eq!(values, vec![120.0, 120.0, 75.5]);
+    // equal amounts rank the later entry first.
+    assert_eq!(items[0].0.created, entries[5].created);
+    assert_eq!(items[1].0.created, entries[2].created);
+    assert_eq!(items[2].0.created, entries[3].created);
+
+    // fewer entries than N.
+    let mut top = TopEntries::new(10);
+    for entry in entries.iter().take(2) {
+        top.reduce(entry).unwrap();
+    }
+    let values: Vec<f64> = top.top().into_iter().map(|(_, v)| v).collect();
+    assert_eq!(values, vec![40.0, 5.0]);
+
+    let mut top = TopEntries::new(0);
+    top.reduce(&entries[0]).unwrap();
+    assert!(top.top().is_empty());
+}
+
+#[test]
+fn test_completer_ranking() {
+    let entries = vec![
+        make_entry((2020, 1, 1), "cash", "rent", ("usd", 10.0)),
+        make_entry((2020, 1, 2), "cash", "repairs", ("usd", 10.0)),
+        make_entry((2020, 1, 3), "cash", "rent", ("usd", 10.0)),
+        make_entry((2020, 1, 4), "cash", "rent", ("usd", 10.0)),
+    ];
+    let mut fl = FrequentlyUsedLedgers::new();
+    for entry in entries.iter() {
+        fl.reduce(entry).unwrap();
+    }
+    let usage = fl.to_usage();
+    assert_eq!(usage["acme:cash"].count, 4);
+    assert_eq!(usage["acme:rent"].count, 3);
+    assert_eq!(
+        usage["acme:rent"].last_used,
+        Some(chrono::Utc.ymd(2020, 1, 4).and_hms(10, 0, 0))
+    );
+
+    let names: Vec<String> = vec!["acme:refunds", "acme:repairs", "acme:rent", "acme:cash"]
+        .into_iter()
+        .map(|n| n.to_string())
+        .collect();
+    let mut completer = Completer::new(names, &usage);
+
+    // frequently used ledger ranks above a rarely used one, unused last.
+    assert_eq!(
+        completer.complete("acme:re"),
+        vec!["acme:rent", "acme:repairs", "acme:refunds"]
+    );
+    assert_eq!(completer.complete("ACME:CA"), vec!["acme:cash"]);
+    assert!(completer.complete("other").is_empty());
+
+    completer.set_rank(rank_alpha);
+    assert_eq!(
+        completer.complete("acme:re"),
+        vec!["acme:refunds", "acme:rent", "acme:repairs"]
+    );
+}
+
+#[test]
+fn test_recent_entries() {
+    let mut store = testing::make_store();
+
+    // spanning years, and one ahead of now that is not yet recent.
+    let now = chrono::Utc::now();
+    for (payee, days) in [("rent", 40), ("fuel", 1), ("tax", 400), ("later", -10)].iter() {
+        let created = now - chrono::Duration::days(*days);
+        let je = testing::make_entry(payee, created, "cash", "misc", ("inr", 1.0));
+        store.put(je).unwrap();
+    }
+
+    let payees = |entries: Vec<types::JournalEntry>| -> Vec<String> {
+        entries.into_iter().map(|e| e.payee).collect()
+    };
+    let entries = recent_entries(&mut store, 2).unwrap();
+    assert_eq!(payees(entries), vec!["fuel", "rent"]);
+    let entries = recent_entries(&mut store, 10).unwrap();
+    assert_eq!(payees(entries), vec!["fuel", "rent", "tax"]);
+    assert!(recent_entries(&mut store, 0).unwrap().is_empty());
+}
@@ -1,31 +1,98 @@
-use chrono;
+use atty;
+use chrono::{self, offset::TimeZone};
 use crossterm::{
     cursor,
     event::{self as ct_event, DisableMouseCapture, EnableMouseCapture, KeyCode},
     execute, queue,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 
 use std::{
+    collections::BTreeMap,
     ffi,
     io::{self, Write},
-    mem,
+    mem, path, time,
 };
 
 use crate::{
+    audit::{Audit, AuditAction},
+    command::{self, Command},
     event::{self, Event},
+    keymap::{Action, Keymap},
+    recent::Recent,
     term_elements as te,
     term_layers::{self as tl, Layer},
 };
 use ledger::{
-    core::{Error, Result, Store},
-    err_at, util,
+    aggregates,
+    core::{self, Durable, Error, Result, Store, Transaction},
+    err_at,
+    rates::{self, ManualRates},
+    types, util,
+    verify::Verifier,
 };
 
+// journal entries verified per idle poll, and the poll timeout.
+const VERIFY_CHUNK: usize = 64;
+const IDLE_POLL: time::Duration = time::Duration::from_millis(50);
+// events arriving within this window of each other, EG: key repeat, are
+// handled together with a single refresh, up to MAX_COALESCE events.
+const COALESCE_POLL: time::Duration = time::Duration::from_millis(8);
+const MAX_COALESCE: usize = 64;
+// values loaded between progress updates, while opening a workspace.
+const LOAD_PROGRESS: usize = 1024;
+// journal entries listed by the recent command.
+const RECENT_ENTRIES: usize = 50;
+// layers popped with cancel, remembered to re-enter with forward.
+const FORWARD_HISTORY: usize = 16;
+
+// headline banner while the workspace is open in safe mode.
+const SAFE_MODE_BANNER: &'static str = " SAFE MODE, read-only";
+
+// application options, from the command line.
+#[derive(Clone)]
+pub struct Config {
+    pub cursor_style: te::CursorStyle,
+    // leave mouse handling to the terminal, to select and copy text.
+    pub no_mouse: bool,
+    // editor for long notes, refer te::to_editor().
+    pub editor: String,
+    // first day of the week for calendars.
+    pub week_start: util::WeekStart,
+    // limit, in chars, for notes, refer types::MAX_NOTE_LEN.
+    pub max_note_len: usize,
+    // how amounts are shown.
+    pub amount_format: util::AmountFormat,
+    // options to open workspaces with.
+    pub options: core::Options,
+    // bounds for journal entry dates.
+    pub date_check: types::DateCheck,
+    // directory for recent workspaces and audit logs, None to keep
+    // neither, refer default_home().
+    pub home: Option<path::PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cursor_style: Default::default(),
+            no_mouse: false,
+            editor: Default::default(),
+            week_start: Default::default(),
+            max_note_len: types::MAX_NOTE_LEN,
+            amount_format: Default::default(),
+            options: Default::default(),
+            date_check: Default::default(),
+            home: None,
+        }
+    }
+}
+
 enum ViewFocus {
     Layer,
-    Cmd,
+    // command mode, remember the cursor to restore on leaving.
+    Cmd(Option<(u16, u16)>),
 }
 
 struct View<S>
@@ -36,32 +103,45 @@ where
     vp: te::Viewport,
     head: te::HeadLine,
     layers: Vec<Layer<S>>,
+    // popped layers, most recent last, cleared when a new layer is pushed.
+    forward: Vec<Layer<S>>,
     status: te::StatusLine,
     // cmd: te::CmdLine,
     focus: ViewFocus,
     cursor: Option<(u16, u16)>,
+    // whole view to be redrawn after handling the current event.
+    redraw: bool,
+    // number of refreshes after input, for tracing.
+    n_refresh: usize,
 }
 
 impl<S> View<S>
 where
     S: Store,
 {
-    fn new() -> Result<View<S>> {
-        let tm = err_at!(Fatal, Terminal::init())?;
+    fn new(config: &Config) -> Result<View<S>> {
+        let tm = err_at!(Fatal, Terminal::init(config))?;
+        Ok(View::from_terminal(tm))
+    }
+
+    fn from_terminal(tm: Terminal) -> View<S> {
         // adjust full screen for a head-line in top and status-line at bottom.
-        let vp = te::Viewport::new(1, 2, tm.rows - 2, tm.cols);
+        let vp = te::Viewport::new(1, 2, tm.rows.saturating_sub(2), tm.cols);
 
         debug!("App view-port {}", vp);
 
-        Ok(View {
+        View {
             tm,
             vp,
             head: Default::default(),
             layers: Default::default(),
+            forward: Default::default(),
             status: Default::default(),
             focus: ViewFocus::Layer,
             cursor: Some((1, 1)),
-        })
+            redraw: false,
+            n_refresh: 0,
+        }
     }
 
     #[inline]
@@ -75,9 +155,25 @@ where
     S: Store,
 {
     dir: ffi::OsString,
+    // directory holding one or more workspaces, to switch between.
+    parent: ffi::OsString,
     view: View<S>,
-    listeners: Vec<event::Tx>,
+    publisher: event::Publisher,
+    keymap: Keymap,
     store: Option<S>,
+    clipboard: te::Clipboard,
+    // background integrity scan of the open workspace.
+    verifier: Option<Verifier>,
+    // user actions on the open workspace.
+    audit: Audit,
+    home: Option<path::PathBuf>,
+    // editor for long notes, refer Config::editor.
+    editor: String,
+    week_start: util::WeekStart,
+    max_note_len: usize,
+    amount_format: util::AmountFormat,
+    options: core::Options,
+    date_check: types::DateCheck,
     date: chrono::Date<chrono::Local>,
     period: (chrono::Date<chrono::Local>, chrono::Date<chrono::Local>),
 }
@@ -110,34 +206,47 @@ impl<S> Application<S>
 where
     S: Store,
 {
-    pub fn run(dir: &ffi::OsStr) -> Result<()> {
-        let mut app = Application {
-            dir: dir.to_os_string(),
-            view: View::new()?,
-            listeners: Default::default(),
-            store: Default::default(),
-            date: chrono::Local::now().date(),
-            period: util::date_to_period(chrono::Local::now().date()),
-        };
-        app.view.head = {
-            let vp = te::Viewport::new(1, 1, 1, app.view.tm.cols);
-            te::HeadLine::new(&mut app, vp)?
-        };
-        app.view.status = {
-            let vp = te::Viewport::new(1, app.view.tm.rows, 1, app.view.tm.cols);
-            te::StatusLine::new(&mut app, vp)?
-        };
+    pub fn run(dir: &ffi::OsStr, config: Config) -> Result<()> {
+        let mut app: Application<S> = Application::new(dir, View::new(&config)?, Keymap::load()?)?;
+        app.options = config.options.clone();
+        app.date_check = config.date_check.clone();
+        app.home = config.home.clone();
+        app.editor = config.editor.clone();
+        app.week_start = config.week_start;
+        app.max_note_len = config.max_note_len;
+        app.amount_format = config.amount_format;
 
-        match S::open(dir) {
-            Ok(store) => {
+        // `dir` can be a workspace, or a parent directory of workspaces.
+        let workspaces = S::list_workspaces(dir).unwrap_or_default();
+        match app.open_store(dir) {
+            Ok(mut store) => {
                 info!("Open workspace dir:{:?}", dir);
+                app.verifier = Verifier::new(&mut store).ok();
+                let w = store.to_workspace()?;
+                app.audit = app.to_audit(&w.name);
+                app.view.head.set_banner(to_banner(&store));
                 app.store = Some(store);
+                touch_recent(app.home.as_deref(), dir);
                 app.view.layers = vec![
                     //
                     Layer::OpenCompany(tl::OpenCompany::new(&mut app)?)
                 ];
                 Ok(())
             }
+            Err(_) if !workspaces.is_empty() => {
+                info!("Pick workspace parent:{:?}", dir);
+                app.parent = dir.to_os_string();
+                let recent = match &app.home {
+                    Some(home) => Recent::load(home).unwrap_or_default(),
+                    None => Recent::default(),
+                };
+                let workspaces = recent.merge(workspaces);
+                app.view.layers = vec![
+                    Layer::OpenCompany(tl::OpenCompany::new(&mut app)?),
+                    Layer::PickWorkspace(tl::PickWorkspace::new(&mut app, workspaces)?),
+                ];
+                Ok(())
+            }
             Err(Error::NotFound(_)) => {
                 info!("New workspace dir:{:?}", dir);
                 app.view.layers = vec![
@@ -152,6 +261,60 @@ where
         app.event_loop()
     }
 
+    // application without a terminal, rendering into a buffer, so that
+    // layers can be driven with synthetic events.
+    #[cfg(test)]
+    pub fn headless(store: S) -> Result<(Application<S>, OutBuffer)> {
+        let out = OutBuffer::default();
+        let tm = Terminal {
+            out: Box::new(out.clone()),
+            cols: HEADLESS_SIZE.0,
+            rows: HEADLESS_SIZE.1,
+            raw: false,
+            mouse: true,
+            cursor_style: Default::default(),
+        };
+        let dir: &ffi::OsStr = "headless".as_ref();
+        let mut app = Application::new(dir, View::from_terminal(tm), Keymap::default())?;
+        app.store = Some(store);
+        Ok((app, out))
+    }
+
+    fn new(dir: &ffi::OsStr, view: View<S>, keymap: Keymap) -> Result<Application<S>> {
+        let mut app = Application {
+            dir: dir.to_os_string(),
+            parent: match path::Path::new(dir).parent() {
+                Some(parent) => parent.as_os_str().to_os_string(),
+                None => dir.to_os_string(),
+            },
+            view,
+            publisher: Default::default(),
+            keymap,
+            store: Default::default(),
+            clipboard: Default::default(),
+            verifier: None,
+            audit: Default::default(),
+            home: None,
+            editor: Default::default(),
+            week_start: Default::default(),
+            max_note_len: types::MAX_NOTE_LEN,
+            amount_format: Default::default(),
+            options: Default::default(),
+            date_check: Default::default(),
+            date: chrono::Local::now().date(),
+            period: util::date_to_period(chrono::Local::now().date()),
+        };
+        app.view.head = {
+            let vp = te::Viewport::new(1, 1, 1, app.view.tm.cols);
+            te::HeadLine::new(&mut app, vp)?
+        };
+        app.view.status = {
+            let vp = te::Viewport::new(1, app.view.tm.rows, 1, app.view.tm.cols);
+            te::StatusLine::new(&mut app, vp)?
+        };
+        Ok(app)
+    }
+
     fn event_loop(mut self) -> Result<()> {
         self.view.status.log("");
         self.refresh(true /*force*/)?.render()?;
@@ -166,50 +329,645 @@ where
 
         loop {
             self.show_cursor()?;
+            if !self.poll_idle()? {
+                continue;
+            }
             let evnt: Event = err_at!(Fatal, ct_event::read())?.into();
 
             trace!("Event-{:?}", evnt);
 
-            let evnt = match evnt {
-                Event::Resize { .. } => None,
-                evnt => match self.view.layers.pop() {
-                    Some(mut layer) => {
-                        let evnt = layer.handle_event(&mut self, evnt.clone())?;
-                        self.view.layers.push(layer);
-                        evnt
-                    },
-                    None => Some(evnt)
+            let next = || -> Result<Option<Event>> {
+                match err_at!(Fatal, ct_event::poll(COALESCE_POLL))? {
+                    true => {
+                        let evnt: Event = err_at!(Fatal, ct_event::read())?.into();
+                        trace!("Event-{:?}", evnt);
+                        Ok(Some(evnt))
+                    }
+                    false => Ok(None),
                 }
             };
+            if self.handle_inputs(evnt, next)? {
+                break Ok(());
+            }
+        }
+    }
 
-            if let Some(evnt) = evnt {
-                let m = evnt.to_modifiers();
-                match evnt.to_key_code() {
-                    Some(KeyCode::Char('q')) if m.is_empty() => break Ok(()),
-                    _ => {
-                        self.handle_event(evnt)?;
-                    }
-                }
+    // dispatch an input event to command mode or the top layer, and
+    // refresh the view. Return true to quit the application.
+    fn handle_input(&mut self, evnt: Event) -> Result<bool> {
+        self.handle_inputs(evnt, || Ok(None))
+    }
+
+    // dispatch `evnt`, and events that follow it from `next` until it
+    // returns None, then refresh the view once. Return true to quit the
+    // application.
+    fn handle_inputs<F>(&mut self, evnt: Event, mut next: F) -> Result<bool>
+    where
+        F: FnMut() -> Result<Option<Event>>,
+    {
+        let mut evnt = Some(evnt);
+        let mut n = 0;
+        while let Some(e) = evnt.take() {
+            if self.dispatch_input(e)? {
+                return Ok(true);
             }
+            n += 1;
+            if n < MAX_COALESCE {
+                evnt = next()?;
+            }
+        }
 
-            err_at!(Fatal, execute!(self.view.tm.stdout, cursor::Hide))?;
+        if let Some(store) = self.store.as_ref() {
+            self.view.status.set_modified(store.is_modified());
+        }
+
+        err_at!(Fatal, execute!(self.view.tm.out, cursor::Hide))?;
+        if mem::replace(&mut self.view.redraw, false) {
+            self.refresh(true /*force*/)?.render()?;
+        } else {
             self.refresh(false /*force*/)?;
-            err_at!(Fatal, self.view.tm.stdout.flush())?;
         }
+        err_at!(Fatal, self.view.tm.out.flush())?;
+        self.view.n_refresh += 1;
+        trace!("refresh {} after {} events", self.view.n_refresh, n);
+
+        Ok(false)
+    }
+
+    // dispatch an input event, without refreshing the view. Return true
+    // to quit the application.
+    fn dispatch_input(&mut self, evnt: Event) -> Result<bool> {
+        let evnt = match evnt {
+            Event::Resize { .. } => None,
+            evnt if evnt.is_mouse() && !self.view.tm.mouse => None,
+            evnt if self.is_cmd_mode() => self.handle_cmd_event(evnt)?,
+            evnt => match self.view.layers.pop() {
+                Some(mut layer) => {
+                    let evnt = layer.handle_event(self, evnt.clone())?;
+                    self.view.layers.push(layer);
+                    evnt
+                }
+                None => Some(evnt),
+            },
+        };
+
+        if let Some(evnt) = evnt {
+            match self.to_action(&evnt) {
+                Some(Action::Quit) => return Ok(true),
+                _ => {
+                    self.handle_event(evnt)?;
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    // verify the workspace in chunks while there is no user input, return
+    // true when an event is ready to be read.
+    fn poll_idle(&mut self) -> Result<bool> {
+        let mut verifier = match self.verifier.take() {
+            Some(verifier) if !verifier.is_done() => verifier,
+            verifier => {
+                self.verifier = verifier;
+                return Ok(true);
+            }
+        };
+        if err_at!(Fatal, ct_event::poll(IDLE_POLL))? {
+            self.verifier = Some(verifier);
+            return Ok(true);
+        }
+
+        match verifier.step(self.to_store()?, VERIFY_CHUNK) {
+            Ok(true) => {
+                let msg = match verifier.to_issues().len() {
+                    0 => format!("verified {} entries", verifier.to_entry_count()),
+                    n => format!("{} issues found, see :issues", n),
+                };
+                self.view.status.log(&msg);
+                self.refresh(false /*force*/)?;
+                err_at!(Fatal, self.view.tm.out.flush())?;
+                self.verifier = Some(verifier);
+            }
+            Ok(false) => self.verifier = Some(verifier),
+            Err(err) => warn!("verify {}", err),
+        }
+
+        Ok(false)
     }
 
     fn handle_event(&mut self, evnt: Event) -> Result<Option<Event>> {
-        let m = evnt.to_modifiers();
-        match evnt.to_key_code() {
-            Some(KeyCode::Esc) if m.is_empty() && self.view.layers.len() > 1 => {
-                self.view.layers.pop();
+        if let Event::OpenWorkspace(dir) = &evnt {
+            if let Err(err) = self.switch_workspace(dir.as_os_str()) {
+                self.view.status.log(&format!("{}", err));
+            }
+            return Ok(None);
+        }
+        if let Event::CreateWorkspace = &evnt {
+            if let Err(err) = self.create_workspace() {
+                self.view.status.log(&format!("{}", err));
+            }
+            return Ok(None);
+        }
+        if let Event::SubmitEntry = &evnt {
+            match self.submit_entry() {
+                Ok(msg) => self.view.status.log(&msg),
+                Err(err) => self.view.status.log(&format!("{}", err)),
+            }
+            return Ok(None);
+        }
+
+        let res = match &evnt {
+            Event::OpenEntry(key) => Some(self.open_entry(key)),
+            Event::OpenLedger(key) => Some(self.open_ledger(key.clone())),
+            Event::Date(date) => Some(self.pick_date(date.clone())),
+            Event::SaveTemplate(key) => Some(self.ask_template_name(key)),
+            Event::Answer(text) => Some(self.answer(text)),
+            _ => None,
+        };
+        if let Some(res) = res {
+            if let Err(err) = res {
+                self.view.status.log(&format!("{}", err));
+            }
+            return Ok(None);
+        }
+
+        match self.to_action(&evnt) {
+            Some(Action::Cancel) if self.view.layers.len() > 1 => {
+                if let Some(mut layer) = self.view.layers.pop() {
+                    layer.leave(self)?;
+                    self.view.forward.push(layer);
+                    if self.view.forward.len() > FORWARD_HISTORY {
+                        self.view.forward.remove(0);
+                    }
+                }
                 self.refresh(true /*force*/)?.render()?;
                 Ok(None)
             }
+            Some(Action::Forward) => {
+                match self.view.forward.pop() {
+                    Some(layer) => self.enter_layer(layer)?,
+                    None => self.view.status.log("no forward history"),
+                }
+                Ok(None)
+            }
+            Some(Action::Undo) => {
+                match self.to_store().and_then(|store| store.undo()) {
+                    Ok(true) => self.view.status.log("undone last change"),
+                    Ok(false) => self.view.status.log("nothing to undo"),
+                    Err(err) => self.view.status.log(&format!("{}", err)),
+                }
+                Ok(None)
+            }
+            // forms submit with the same key, elsewhere it saves.
+            Some(Action::Submit) => {
+                match self.do_command(Command::Commit) {
+                    Ok(_) => self.view.status.log("saved"),
+                    Err(err) => self.view.status.log(&format!("{}", err)),
+                }
+                Ok(None)
+            }
+            Some(Action::Command) => {
+                self.view.status.start_cmd()?;
+                self.view.focus = ViewFocus::Cmd(self.view.cursor);
+                self.view.cursor = self.view.status.to_cmd_cursor();
+                Ok(None)
+            }
             _ => Ok(Some(evnt)),
         }
     }
 
+    // in command mode all key events are captured by the status line,
+    // Enter runs the command and Esc abandons it.
+    fn handle_cmd_event(&mut self, evnt: Event) -> Result<Option<Event>> {
+        match evnt.to_key_code() {
+            Some(KeyCode::Enter) => {
+                let text = self.leave_cmd().unwrap_or_default();
+                let res = Command::parse(&text).and_then(|cmd| self.do_command(cmd));
+                match res {
+                    Ok(msg) => self.view.status.log(&msg),
+                    Err(err) => self.view.status.log(&format!("{}", err)),
+                }
+            }
+            _ if self.to_action(&evnt) == Some(Action::Cancel) => {
+                self.leave_cmd();
+                self.view.status.log("");
+            }
+            _ => {
+                let mut status = mem::replace(&mut self.view.status, Default::default());
+                let res = status.handle_event(self, evnt);
+                self.view.status = status;
+                res?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_cmd_mode(&self) -> bool {
+        match self.view.focus {
+            ViewFocus::Cmd(_) => true,
+            ViewFocus::Layer => false,
+        }
+    }
+
+    fn leave_cmd(&mut self) -> Option<String> {
+        if let ViewFocus::Cmd(cursor) = self.view.focus {
+            self.view.cursor = cursor;
+        }
+        self.view.focus = ViewFocus::Layer;
+        self.view.status.take_cmd()
+    }
+
+    fn do_command(&mut self, cmd: Command) -> Result<String> {
+        match &cmd {
+            Command::Commit => {
+                self.to_store()?.commit()?;
+                self.record_audit(AuditAction::Commit)?;
+            }
+            Command::Push => {
+                let status = self.to_store()?.push()?;
+                if status.is_transferred() {
+                    self.record_audit(AuditAction::Push)?;
+                }
+                return Ok(format!("push {}", status));
+            }
+            Command::Pull => {
+                let status = self.to_store_for_edit()?.pull()?;
+                if status.is_transferred() {
+                    self.record_audit(AuditAction::Pull)?;
+                }
+                return Ok(format!("pull {}", status));
+            }
+            Command::NewEntry => {
+                let legs = tl::EntryLegs::new(&self.to_store()?.to_workspace()?);
+                let layer = tl::EntryForm::new(self, self.date.naive_local(), legs)?;
+                self.push_layer(Layer::EntryForm(layer))?;
+            }
+            Command::DefaultLedger(ledger) => {
+                let mut w = self.to_store()?.to_workspace()?;
+                w.set_default_ledger(ledger.clone().map(types::KeyLedger::from));
+                self.put_value(w)?;
+            }
+            Command::Goto(Some(date)) => self.goto(*date)?,
+            Command::Goto(None) => {
+                let layer = tl::Prompt::new(self, tl::Question::Goto)?;
+                self.push_layer(Layer::Prompt(layer))?;
+            }
+            Command::Workspaces => {
+                let workspaces = S::list_workspaces(&self.parent)?;
+                if workspaces.is_empty() {
+                    let msg = format!("no workspaces under {:?}", self.parent);
+                    err_at!(NotFound, msg: msg)?;
+                }
+                let layer = tl::PickWorkspace::new(self, workspaces)?;
+                self.push_layer(Layer::PickWorkspace(layer))?;
+            }
+            Command::Issues => match &self.verifier {
+                Some(verifier) if verifier.is_done() => {
+                    let issues = verifier.to_issues();
+                    for issue in issues.iter() {
+                        warn!("verify {}", issue);
+                    }
+                    if !issues.is_empty() {
+                        return Ok(issues.join("; "));
+                    }
+                }
+                Some(_) => err_at!(InvalidInput, msg: format!("verify in progress"))?,
+                None => err_at!(NotFound, msg: format!("no workspace verified"))?,
+            },
+            Command::UpdateRates(quotes) => {
+                let store = self.to_store_for_edit()?;
+                let base = store.to_workspace()?.to_base_commodity();
+                let mut provider = ManualRates::new();
+                for (name, rate) in quotes.iter() {
+                    provider.set_rate(name, &base, *rate);
+                }
+                let names: Vec<String> = quotes.iter().map(|(name, _)| name.clone()).collect();
+                let on = chrono::Local::today().naive_local();
+                let updated = rates::update_rates(store, &provider, &names, on)?;
+                let n = updated.len();
+                self.put_all(updated)?;
+                return Ok(format!("{} rates updated", n));
+            }
+            Command::Entries => {
+                let entries = self.to_period_entries()?;
+                let layer = tl::EntryBrowser::new(self, entries)?;
+                self.push_layer(Layer::EntryBrowser(layer))?;
+            }
+            Command::Ledgers => {
+                let layer = tl::Navigator::new(self)?;
+                self.push_layer(Layer::Navigator(layer))?;
+            }
+            Command::Recent => {
+                let entries = aggregates::recent_entries(self.to_store()?, RECENT_ENTRIES)?;
+                let layer = tl::EntryBrowser::new(self, entries)?;
+                self.push_layer(Layer::EntryBrowser(layer))?;
+            }
+            Command::Calendar => {
+                let layer = tl::Calendar::new(self, self.date.naive_local())?;
+                self.push_layer(Layer::Calendar(layer))?;
+            }
+            Command::Template(name) => {
+                let key: types::KeyTemplate = (name.clone(),).into();
+                let template: types::Template = self.to_store()?.get(&key.to_string())?;
+                return self.fill_template(template, BTreeMap::new());
+            }
+        };
+
+        Ok(format!("{} ok", cmd))
+    }
+
+    // put `values` to the open workspace in a single transaction.
+    fn put_all<V>(&mut self, values: Vec<V>) -> Result<()>
+    where
+        V: Durable,
+    {
+        let store = match self.store.take() {
+            Some(store) => store,
+            None => err_at!(NotFound, msg: format!("no workspace open"))?,
+        };
+        let mut txn = store.begin()?;
+        let mut puts = vec![];
+        let res = values.into_iter().try_for_each(|value| {
+            puts.push((txn.put(value.clone())?, value));
+            Ok(())
+        });
+        self.store = Some(txn.end()?);
+        res?;
+
+        for (old, value) in puts.iter() {
+            self.audit_put(old.as_ref(), value);
+        }
+        Ok(())
+    }
+
+    // record a put in the audit log, refer Audit::record_put(). Failing to
+    // record is logged and does not fail the put.
+    fn audit_put<V>(&self, old: Option<&V>, value: &V)
+    where
+        V: Durable,
+    {
+        if let Err(err) = self.audit.record_put(old, value) {
+            warn!("audit put {}", err);
+        }
+    }
+
+    // record `action` on the open workspace, failing to record is logged
+    // and does not fail the action.
+    fn record_audit(&mut self, action: AuditAction) -> Result<()> {
+        let name = self.to_store()?.to_workspace()?.name;
+        if let Err(err) = self.audit.record(action, &format!("workspace {}", name)) {
+            warn!("audit {} {}", action, err);
+        }
+        Ok(())
+    }
+
+    // journal entries created within the current period.
+    fn to_period_entries(&mut self) -> Result<Vec<types::JournalEntry>> {
+        let (from, to) = self.period.clone();
+        let from = from.and_hms(0, 0, 0).with_timezone(&chrono::Utc);
+        let to = to.and_hms(23, 59, 59).with_timezone(&chrono::Utc);
+        let mut entries = vec![];
+        for entry in self.to_store()?.iter_journal(from, to)? {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    fn open_entry(&mut self, key: &str) -> Result<()> {
+        let entry: types::JournalEntry = self.to_store()?.get(key)?;
+        let layer = tl::EntryDetail::new(self, &entry)?;
+        self.push_layer(Layer::EntryDetail(layer))
+    }
+
+    // date picked from the calendar, which is closed.
+    fn pick_date(&mut self, date: chrono::Date<chrono::Local>) -> Result<()> {
+        if let Some(Layer::Calendar(_)) = self.view.layers.last() {
+            self.close_layer()?;
+        }
+        self.set_date(date)?;
+        self.view.status.log(&format!("date {}", date.format(command::DATE_FORMAT)));
+        self.refresh(true /*force*/)?.render()?;
+        Ok(())
+    }
+
+    fn ask_template_name(&mut self, key: &str) -> Result<()> {
+        let entry: types::JournalEntry = self.to_store()?.get(key)?;
+        let layer = tl::Prompt::new(self, tl::Question::TemplateName(entry))?;
+        self.push_layer(Layer::Prompt(layer))
+    }
+
+    // text entered in the prompt, which is closed once the answer is
+    // accepted. On error the prompt is left open to try again.
+    fn answer(&mut self, text: &str) -> Result<()> {
+        let question = match self.view.layers.last() {
+            Some(Layer::Prompt(layer)) => layer.to_question(),
+            _ => return Ok(()),
+        };
+        let msg = match question {
+            tl::Question::Goto => {
+                let date = util::parse_date_flexible(text)?;
+                self.close_layer()?;
+                self.goto(date)?;
+                format!("goto {}", date.format(command::DATE_FORMAT))
+            }
+            tl::Question::TemplateName(entry) => {
+                let template = types::Template::from_entry(text, &entry)?;
+                self.put_value(template)?;
+                self.close_layer()?;
+                format!("saved template {}", text)
+            }
+            tl::Question::Placeholder {
+                template,
+                mut values,
+                name,
+            } => {
+                template.check_placeholder(&name, text)?;
+                self.close_layer()?;
+                values.insert(name, text.to_string());
+                self.fill_template(template, values)?
+            }
+        };
+
+        self.view.status.log(&msg);
+        self.refresh(true /*force*/)?.render()?;
+        Ok(())
+    }
+
+    // add a journal entry from `template`, prompting for its placeholders
+    // one at a time, `values` are the placeholders answered so far.
+    fn fill_template(
+        &mut self,
+        template: types::Template,
+        values: BTreeMap<String, String>,
+    ) -> Result<String> {
+        let next = template
+            .to_placeholders()
+            .into_iter()
+            .find(|name| !values.contains_key(name));
+        match next {
+            Some(name) => {
+                let msg = format!("{} for {}", name, template.name);
+                let question = tl::Question::Placeholder {
+                    template,
+                    values,
+                    name,
+                };
+                let layer = tl::Prompt::new(self, question)?;
+                self.push_layer(Layer::Prompt(layer))?;
+                Ok(msg)
+            }
+            None => {
+                let prompt = |name: &str| match values.get(name) {
+                    Some(value) => Ok(value.clone()),
+                    None => err_at!(NotFound, msg: format!("placeholder {}", name)),
+                };
+                let entry = template.instantiate(chrono::Utc::now(), prompt)?;
+                entry.validate_note(self.max_note_len)?;
+                let warning = self.check_entry_date(&entry)?;
+                self.put_value(entry.clone())?;
+                Ok(added_msg(&entry, warning))
+            }
+        }
+    }
+
+    // add the journal entry filled in the form on top and close the form,
+    // on error the form is left open.
+    fn submit_entry(&mut self) -> Result<String> {
+        let base = self.to_store()?.to_workspace()?.to_base_commodity();
+        let entry = match self.view.layers.last() {
+            Some(Layer::EntryForm(layer)) => layer.to_entry(&base)?,
+            _ => err_at!(NotFound, msg: format!("no entry form"))?,
+        };
+        entry.validate_note(self.max_note_len)?;
+        let warning = self.check_entry_date(&entry)?;
+        self.put_value(entry.clone())?;
+        self.close_layer()?;
+        Ok(added_msg(&entry, warning))
+    }
+
+    // pop the top layer, not to be re-entered, and focus the one below.
+    fn close_layer(&mut self) -> Result<()> {
+        if let Some(mut layer) = self.view.layers.pop() {
+            layer.leave(self)?;
+        }
+        if let Some(mut top) = self.view.layers.pop() {
+            top.focus(self)?;
+            self.view.layers.push(top);
+        }
+        Ok(())
+    }
+
+    // jump to the ledger's detail, Esc returns to the layer below.
+    fn open_ledger(&mut self, key: types::KeyLedger) -> Result<()> {
+        let entries = self.to_period_entries()?;
+        let layer = tl::LedgerDetail::new(self, key, &entries)?;
+        self.push_layer(Layer::LedgerDetail(layer))
+    }
+
+    // push a new layer, forgetting layers that could be re-entered.
+    fn push_layer(&mut self, layer: Layer<S>) -> Result<()> {
+        self.view.forward.clear();
+        self.enter_layer(layer)
+    }
+
+    fn enter_layer(&mut self, mut layer: Layer<S>) -> Result<()> {
+        if let Some(mut top) = self.view.layers.pop() {
+            top.leave(self)?;
+            self.view.layers.push(top);
+        }
+        layer.focus(self)?;
+        self.view.layers.push(layer);
+        self.refresh(true /*force*/)?.render()?;
+
+        Ok(())
+    }
+
+    // tear down the current store and layers, and open the workspace
+    // in `dir`. On failure the current workspace is left as is.
+    fn switch_workspace(&mut self, dir: &ffi::OsStr) -> Result<()> {
+        // workspace is locked while open, re-opening it shall fail.
+        if self.store.is_some() && self.dir == dir {
+            self.view.status.log(&format!("workspace {:?}", self.dir));
+            return Ok(());
+        }
+        let store = self.open_store(dir)?;
+        info!("Switch workspace dir:{:?}", dir);
+        self.enter_workspace(dir, store)
+    }
+
+    // create the workspace filled in the form on top, in the application's
+    // directory, with its base commodity. On error the form is left open.
+    fn create_workspace(&mut self) -> Result<()> {
+        let (w, commodity) = match self.view.layers.last() {
+            Some(Layer::NewWorkspace(layer)) => layer.to_values()?,
+            _ => return Ok(()),
+        };
+        let dir = self.dir.clone();
+        let mut store = S::create_with(&dir, w, self.options.clone())?;
+        store.put(commodity.clone())?;
+        store.commit()?;
+        info!("Create workspace dir:{:?}", dir);
+
+        self.enter_workspace(&dir, store)?;
+        self.audit_put(None, &commodity);
+        Ok(())
+    }
+
+    // make `store`, in `dir`, the open workspace and start afresh from the
+    // company layer.
+    fn enter_workspace(&mut self, dir: &ffi::OsStr, mut store: S) -> Result<()> {
+        self.verifier = Verifier::new(&mut store).ok();
+        let w = store.to_workspace()?;
+        self.audit = self.to_audit(&w.name);
+        self.view.head.set_banner(to_banner(&store));
+
+        if let Some(mut top) = self.view.layers.pop() {
+            top.leave(self)?;
+        }
+        self.view.layers.clear();
+        self.view.forward.clear();
+        self.store = Some(store);
+        self.dir = dir.to_os_string();
+        touch_recent(self.home.as_deref(), dir);
+
+        let layer = Layer::OpenCompany(tl::OpenCompany::new(self)?);
+        self.push_layer(layer)?;
+        self.view.status.log(&format!("workspace {:?}", self.dir));
+
+        Ok(())
+    }
+
+    // open workspace in `dir` with application options, stores loading
+    // values on open report progress in the status line.
+    fn open_store(&mut self, dir: &ffi::OsStr) -> Result<S> {
+        let opts = self.options.clone();
+        let mut callback = |n: usize| {
+            self.view.status.log(&format!("loading {} values", n));
+            let res = self.refresh(false).and_then(|app| app.render());
+            if let Err(err) = res {
+                warn!("loading progress {}", err);
+            }
+        };
+        S::open_with_progress(dir, opts, Some((LOAD_PROGRESS, &mut callback)))
+    }
+
+    fn to_store(&mut self) -> Result<&mut S> {
+        match self.store.as_mut() {
+            Some(store) => Ok(store),
+            None => err_at!(NotFound, msg: format!("no workspace open")),
+        }
+    }
+
+    // store for an action that changes it, changes made after this can be
+    // reverted with undo.
+    fn to_store_for_edit(&mut self) -> Result<&mut S> {
+        let store = self.to_store()?;
+        store.mark_undo()?;
+        Ok(store)
+    }
+
     fn refresh(&mut self, force: bool) -> Result<&mut Self> {
         let mut head = mem::replace(&mut self.view.head, Default::default());
         head.refresh(self, force)?;
@@ -229,19 +987,19 @@ where
     }
 
     fn render(&mut self) -> Result<&mut Self> {
-        err_at!(Fatal, queue!(self.view.tm.stdout, self.view.head))?;
+        err_at!(Fatal, queue!(self.view.tm.out, self.view.head))?;
 
         match self.view.layers.pop() {
             Some(layer) => {
-                err_at!(Fatal, queue!(self.view.tm.stdout, layer))?;
+                err_at!(Fatal, queue!(self.view.tm.out, layer))?;
                 self.view.layers.push(layer);
             }
             None => (),
         }
 
-        err_at!(Fatal, queue!(self.view.tm.stdout, self.view.status))?;
+        err_at!(Fatal, queue!(self.view.tm.out, self.view.status))?;
 
-        err_at!(Fatal, self.view.tm.stdout.flush())?;
+        err_at!(Fatal, self.view.tm.out.flush())?;
 
         Ok(self)
     }
@@ -257,8 +1015,29 @@ where
     }
 
     #[inline]
-    pub fn as_mut_stdout(&mut self) -> &mut io::Stdout {
-        &mut self.view.tm.stdout
+    pub fn to_week_start(&self) -> util::WeekStart {
+        self.week_start
+    }
+
+    #[inline]
+    pub fn to_max_note_len(&self) -> usize {
+        self.max_note_len
+    }
+
+    #[inline]
+    pub fn to_amount_format(&self) -> util::AmountFormat {
+        self.amount_format
+    }
+
+    #[inline]
+    pub fn to_action(&self, evnt: &Event) -> Option<Action> {
+        self.keymap.to_action(evnt)
+    }
+
+    // elements render into this, the terminal or a buffer when headless.
+    #[inline]
+    pub fn as_mut_out(&mut self) -> &mut dyn Write {
+        &mut self.view.tm.out
     }
 
     #[inline]
@@ -285,19 +1064,110 @@ where
         Ok(())
     }
 
+    #[inline]
+    pub fn as_mut_clipboard(&mut self) -> &mut te::Clipboard {
+        &mut self.clipboard
+    }
+
+    #[inline]
+    pub fn to_clipboard(&self) -> te::Clipboard {
+        self.clipboard.clone()
+    }
+
+    // suspend the terminal, edit `text` in te::to_editor() and resume,
+    // return the edited text.
+    pub fn edit_external(&mut self, text: &str) -> Result<String> {
+        let editor = te::to_editor(&self.editor);
+        info!("edit external {:?}", editor);
+
+        self.view.tm.suspend()?;
+        let res = edit_with(&editor, text);
+        self.view.tm.resume()?;
+        self.view.redraw = true;
+
+        res
+    }
+
+    // redraw the whole view after the current event, EG: when a layer
+    // reflows its elements.
+    pub fn set_redraw(&mut self) {
+        self.view.redraw = true;
+    }
+
+    // put `value` to the open workspace, as a change that can be undone.
+    pub fn put_value<V>(&mut self, value: V) -> Result<Option<V>>
+    where
+        V: Durable,
+    {
+        let old = self.to_store_for_edit()?.put(value.clone())?;
+        self.audit_put(old.as_ref(), &value);
+        Ok(old)
+    }
+
+    // delete value by `key` from the open workspace, as a change that can
+    // be undone.
+    pub fn delete_value<V>(&mut self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let value: V = self.to_store_for_edit()?.delete(key)?;
+        if let Err(err) = self.audit.record_delete(&value) {
+            warn!("audit delete {}", err);
+        }
+        Ok(value)
+    }
+
+    pub fn log_status(&mut self, msg: &str) {
+        self.view.status.log(msg)
+    }
+
+    // audit log for workspace `name`, kept nowhere without a home.
+    fn to_audit(&self, name: &str) -> Audit {
+        match &self.home {
+            Some(home) => Audit::for_workspace(home, name),
+            None => Audit::default(),
+        }
+    }
+
+    // on submitting `entry`, warn in the status line about a likely
+    // mistyped date, or fail when such dates are rejected. Return the
+    // warning, if any.
+    pub fn check_entry_date(&mut self, entry: &types::JournalEntry) -> Result<Option<String>> {
+        let w = self.to_store()?.to_workspace()?;
+        let warning = entry.validate_date(&w, chrono::Utc::now(), &self.date_check)?;
+        if let Some(msg) = &warning {
+            warn!("{}", msg);
+            self.view.status.log(msg);
+        }
+        Ok(warning)
+    }
+
+    // show the cursor position of the focused edit element in the
+    // status line, None to clear it.
+    pub fn set_edit_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        self.view.status.set_cursor(cursor)
+    }
+
+    pub fn to_edit_cursor(&self) -> Option<(usize, usize)> {
+        self.view.status.to_cursor()
+    }
+
     pub fn subscribe(&mut self, tx: event::Tx) {
-        self.listeners.push(tx)
+        self.publisher.subscribe(tx)
     }
 
     pub fn publish(&mut self, evnt: Event) -> Result<()> {
-        let mut listeners = vec![];
-        for mut tx in self.listeners.drain(..) {
-            if let Ok(_) = tx.send(evnt.clone()) {
-                listeners.push(tx)
+        self.publisher.publish(evnt)
+    }
+
+    fn goto(&mut self, date: chrono::NaiveDate) -> Result<()> {
+        match chrono::Local.from_local_date(&date).single() {
+            Some(date) => {
+                self.set_date(date)?;
+                Ok(())
             }
+            None => err_at!(InvalidDate, msg: format!("{}", date)),
         }
-        self.listeners = listeners;
-        Ok(())
     }
 
     #[inline]
@@ -330,13 +1200,13 @@ where
             Some((col, row)) => err_at!(
                 Fatal,
                 execute!(
-                    self.view.tm.stdout,
-                    cursor::MoveTo(col - 1, row - 1),
-                    cursor::EnableBlinking,
+                    self.view.tm.out,
+                    te::move_to(col, row),
+                    self.view.tm.cursor_style,
                     cursor::Show,
                 )
             )?,
-            None => err_at!(Fatal, execute!(self.view.tm.stdout, cursor::Hide,))?,
+            None => err_at!(Fatal, execute!(self.view.tm.out, cursor::Hide,))?,
         }
 
         Ok(())
@@ -344,39 +1214,273 @@ where
 }
 
 struct Terminal {
-    stdout: io::Stdout,
+    out: Box<dyn Write>,
     cols: u16,
     rows: u16,
+    // terminal is in raw mode and alternate screen, restore on drop.
+    raw: bool,
+    // mouse events are captured, refer Config::no_mouse.
+    mouse: bool,
+    cursor_style: te::CursorStyle,
+}
+
+// terminal size for headless applications.
+#[cfg(test)]
+const HEADLESS_SIZE: (u16, u16) = (100, 30);
+
+// rendered output of a headless application.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct OutBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for OutBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl OutBuffer {
+    pub fn to_text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.borrow_mut().clear()
+    }
+}
+
+// empty in-memory workspace to run headless applications on.
+#[cfg(test)]
+pub fn make_store() -> ledger::mem_store::MemStore {
+    make_store_with(types::Workspace::new("test".to_string()))
+}
+
+#[cfg(test)]
+pub fn make_store_with(w: types::Workspace) -> ledger::mem_store::MemStore {
+    let dir: &ffi::OsStr = "mem".as_ref();
+    ledger::mem_store::MemStore::create(dir, w).unwrap()
+}
+
+// entry moving `value` inr from `creditor` to `debitor`, both ledgers
+// are under company `acme`.
+#[cfg(test)]
+pub fn make_entry(
+    payee: &str,
+    created: chrono::DateTime<chrono::Utc>,
+    creditor: &str,
+    debitor: &str,
+    value: f64,
+) -> types::JournalEntry {
+    let mut je = types::JournalEntry::new(payee.to_string(), created);
+    let comm = ("inr".to_string(), value);
+    je.add_debitor("acme".to_string(), debitor.to_string(), comm.clone())
+        .unwrap();
+    je.add_creditor("acme".to_string(), creditor.to_string(), comm)
+        .unwrap();
+    je
+}
+
+// synthetic key press, without and with modifiers.
+#[cfg(test)]
+pub fn key(code: KeyCode) -> Event {
+    key_with(code, ct_event::KeyModifiers::empty())
+}
+
+#[cfg(test)]
+pub fn key_with(code: KeyCode, modifiers: ct_event::KeyModifiers) -> Event {
+    Event::Key { code, modifiers }
+}
+
+// application's own directory, `~/.ledger47`, None if there is no home
+// directory.
+pub fn default_home() -> Option<path::PathBuf> {
+    dirs::home_dir().map(|mut loc| {
+        loc.push(".ledger47");
+        loc
+    })
+}
+
+// remember `dir` in recently opened workspaces under `home`, failing to
+// persist the list shall not fail the open.
+fn touch_recent(home: Option<&path::Path>, dir: &ffi::OsStr) {
+    let home = match home {
+        Some(home) => home,
+        None => return,
+    };
+    let mut recent = Recent::load(home).unwrap_or_default();
+    recent.touch(path::Path::new(dir), chrono::Utc::now());
+    if let Err(err) = recent.save(home) {
+        warn!("recent workspaces {}", err);
+    }
+}
+
+// status message for an added `entry`, keeping the date warning that
+// would otherwise be overwritten.
+fn added_msg(entry: &types::JournalEntry, warning: Option<String>) -> String {
+    match warning {
+        Some(warning) => format!("added {}, {}", entry.payee, warning),
+        None => format!("added {}", entry.payee),
+    }
+}
+
+// headline banner for the open workspace, empty unless it is read-only.
+fn to_banner<S>(store: &S) -> &'static str
+where
+    S: Store,
+{
+    match store.is_read_only() {
+        true => SAFE_MODE_BANNER,
+        false => "",
+    }
+}
+
+// run `editor`, command followed by optional arguments, on a temporary
+// file seeded with `text`, and return the edited content. A trailing
+// newline added by the editor is dropped.
+fn edit_with(editor: &str, text: &str) -> Result<String> {
+    let mut args = editor.split_whitespace();
+    let cmd = match args.next() {
+        Some(cmd) => cmd,
+        None => err_at!(InvalidInput, msg: format!("no editor"))?,
+    };
+
+    let loc = create_temp_file(text)?;
+
+    let res = std::process::Command::new(cmd).args(args).arg(&loc).status();
+    let data = match res {
+        Ok(status) if status.success() => std::fs::read_to_string(&loc),
+        Ok(status) => Err(io::Error::new(io::ErrorKind::Other, status.to_string())),
+        Err(err) => Err(err),
+    };
+    std::fs::remove_file(&loc).ok();
+
+    let mut data = err_at!(IOError, data, format!("editor {:?}", editor))?;
+    if data.ends_with('\n') && !text.ends_with('\n') {
+        data.pop();
+    }
+    Ok(data)
+}
+
+// create a fresh temporary file, readable only by the user, with an
+// unguessable name and seeded with `text`. Refuse to reuse an existing
+// file, so that another user can't plant one at the same path.
+fn create_temp_file(text: &str) -> Result<path::PathBuf> {
+    let mut loc = std::env::temp_dir();
+    loc.push(format!("ledger47-note-{}.txt", uuid::Uuid::new_v4()));
+
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut fd = err_at!(IOError, opts.open(&loc), format!("{:?}", loc))?;
+    err_at!(IOError, fd.write_all(text.as_bytes()), format!("{:?}", loc))?;
+
+    Ok(loc)
+}
+
+// TUI needs both stdin and stdout to be a terminal, when piped or run
+// under CI, refuse to launch.
+fn check_tty(stdin: bool, stdout: bool) -> Result<()> {
+    match (stdin, stdout) {
+        (true, true) => Ok(()),
+        (false, _) => err_at!(Fatal, msg: format!("stdin is not a terminal, try --export")),
+        (_, false) => err_at!(Fatal, msg: format!("stdout is not a terminal, try --export")),
+    }
 }
 
 impl Terminal {
-    fn init() -> Result<Terminal> {
+    fn init(config: &Config) -> Result<Terminal> {
+        check_tty(
+            atty::is(atty::Stream::Stdin),
+            atty::is(atty::Stream::Stdout),
+        )?;
+
         let mut stdout = io::stdout();
+        let mouse = !config.no_mouse;
         err_at!(Fatal, terminal::enable_raw_mode())?;
-        err_at!(
-            Fatal,
-            execute!(
-                stdout,
-                EnterAlternateScreen,
-                EnableMouseCapture,
-                cursor::Hide
-            )
-        )?;
+        if let Err(err) = init_screen(&mut stdout, mouse) {
+            // leave the terminal as we found it.
+            terminal::disable_raw_mode().ok();
+            err_at!(Fatal, msg: format!("unsupported terminal {}, try --export", err))?;
+        }
 
         let (cols, rows) = err_at!(Fatal, terminal::size())?;
-        Ok(Terminal { stdout, cols, rows })
+        Ok(Terminal {
+            out: Box::new(stdout),
+            cols,
+            rows,
+            raw: true,
+            mouse,
+            cursor_style: config.cursor_style,
+        })
     }
+
+    // hand the terminal over to a subprocess, refer resume().
+    fn suspend(&mut self) -> Result<()> {
+        if self.raw {
+            err_at!(Fatal, leave_screen(&mut self.out, self.mouse))?;
+            err_at!(Fatal, terminal::disable_raw_mode())?;
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        if self.raw {
+            err_at!(Fatal, terminal::enable_raw_mode())?;
+            err_at!(Fatal, init_screen(&mut self.out, self.mouse))?;
+        }
+        Ok(())
+    }
+}
+
+// switch to alternate screen and hide the cursor, capture mouse events
+// only if `mouse`.
+fn init_screen<W>(out: &mut W, mouse: bool) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    execute!(out, EnterAlternateScreen)?;
+    if mouse {
+        execute!(out, EnableMouseCapture)?;
+    }
+    execute!(out, cursor::Hide)
+}
+
+// undo init_screen().
+fn leave_screen<W>(out: &mut W, mouse: bool) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    execute!(out, LeaveAlternateScreen)?;
+    if mouse {
+        execute!(out, DisableMouseCapture)?;
+    }
+    execute!(out, cursor::Show)
 }
 
 impl Drop for Terminal {
     fn drop(&mut self) {
-        execute!(
-            self.stdout,
-            LeaveAlternateScreen,
-            DisableMouseCapture,
-            cursor::Show
-        )
-        .unwrap();
+        if !self.raw {
+            return;
+        }
+        if self.cursor_style != te::CursorStyle::Default {
+            // back to terminal's own cursor shape.
+            write!(self.out, "\x1b[0 q").ok();
+        }
+        leave_screen(&mut self.out, self.mouse).unwrap();
         terminal::disable_raw_mode().unwrap();
     }
 }
+
+#[cfg(test)]
+#[path = "app_test.rs"]
+mod app_test;
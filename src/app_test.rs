@@ -0,0 +1,1032 @@
+use super::*;
+
+#[test]
+fn test_check_tty() {
+    assert!(check_tty(true, true).is_ok());
+    match check_tty(true, false) {
+        Err(Error::Fatal(msg)) => {
+            assert!(msg.contains("stdout"), "{}", msg);
+            assert!(msg.contains("--export"), "{}", msg);
+        }
+        _ => panic!("expected fatal error"),
+    }
+    match check_tty(false, true) {
+        Err(Error::Fatal(msg)) => assert!(msg.contains("stdin"), "{}", msg),
+        _ => panic!("expected fatal error"),
+    }
+}
+
+#[test]
+fn test_headless_new_workspace_tab() {
+    let store = make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+    let layer = tl::NewWorkspace::new(&mut app).unwrap();
+    app.push_layer(Layer::NewWorkspace(layer)).unwrap();
+
+    let text = out.to_text();
+    assert!(text.contains("Create new workspace"), "{}", text);
+    assert!(text.contains("Name    :"), "{}", text);
+    // workspace name is focused first.
+    assert_eq!(app.view.cursor, Some((6, 5)));
+
+    let tab = key(KeyCode::Tab);
+    // journal layout.
+    assert_eq!(app.handle_input(tab.clone()).unwrap(), false);
+    assert_eq!(app.view.cursor, Some((6, 6)));
+    // commodity name, symbol, aliases, tags and notes.
+    for row in vec![9, 11, 13, 15, 17].into_iter() {
+        assert_eq!(app.handle_input(tab.clone()).unwrap(), false);
+        assert_eq!(app.view.cursor, Some((18, row)));
+    }
+    // notes take tabs as content, back-tab leaves to tags.
+    let backtab = key(KeyCode::BackTab);
+    out.clear();
+    app.handle_input(backtab).unwrap();
+    assert_eq!(app.view.cursor, Some((18, 15)));
+    assert!(!out.to_text().is_empty());
+
+    // esc leaves the fields, then quit.
+    assert_eq!(app.handle_input(key(KeyCode::Esc)).unwrap(), false);
+    assert_eq!(app.view.cursor, None);
+    assert_eq!(app.handle_input(key(KeyCode::Char('q'))).unwrap(), true);
+}
+
+#[test]
+fn test_headless_paste_tabular() {
+    use crossterm::event::KeyModifiers;
+
+    let store = make_store();
+    let (mut app, out) = Application::headless(store).unwrap();
+    let layer = tl::NewWorkspace::new(&mut app).unwrap();
+    app.push_layer(Layer::NewWorkspace(layer)).unwrap();
+
+    let tab = key(KeyCode::Tab);
+    app.handle_input(tab.clone()).unwrap();
+    app.handle_input(tab).unwrap();
+    assert_eq!(app.view.cursor, Some((18, 9)));
+
+    // commodity name, symbol and aliases from a spreadsheet row.
+    app.as_mut_clipboard().copy("yen\t¥\tjpy\n".to_string());
+    let paste = key_with(KeyCode::Char('v'), KeyModifiers::CONTROL);
+    app.handle_input(paste).unwrap();
+    assert_eq!(app.view.cursor.map(|(_, row)| row), Some(13));
+    let text = out.to_text();
+    assert!(text.contains("yen"), "{}", text);
+    assert!(text.contains("jpy"), "{}", text);
+}
+
+#[test]
+fn test_headless_layer_forward() {
+    use crossterm::event::KeyModifiers;
+
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    for _ in 0..3 {
+        let layer = tl::EntryBrowser::new(&mut app, vec![]).unwrap();
+        app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+    }
+
+    let esc = key(KeyCode::Esc);
+    let forward = key_with(KeyCode::Right, KeyModifiers::ALT);
+
+    // the bottom layer is never popped.
+    for _ in 0..3 {
+        app.handle_input(esc.clone()).unwrap();
+    }
+    assert_eq!((app.view.layers.len(), app.view.forward.len()), (1, 2));
+
+    app.handle_input(forward.clone()).unwrap();
+    assert_eq!((app.view.layers.len(), app.view.forward.len()), (2, 1));
+    app.handle_input(forward.clone()).unwrap();
+    assert_eq!((app.view.layers.len(), app.view.forward.len()), (3, 0));
+    // nothing to go forward to.
+    app.handle_input(forward.clone()).unwrap();
+    assert_eq!(app.view.layers.len(), 3);
+
+    // pushing a new layer drops the forward history.
+    app.handle_input(esc.clone()).unwrap();
+    assert_eq!(app.view.forward.len(), 1);
+    let layer = tl::EntryBrowser::new(&mut app, vec![]).unwrap();
+    app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+    assert!(app.view.forward.is_empty());
+
+    // history is bounded.
+    for _ in 0..(FORWARD_HISTORY + 2) {
+        let layer = tl::EntryBrowser::new(&mut app, vec![]).unwrap();
+        app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+    }
+    for _ in 0..(FORWARD_HISTORY + 2) {
+        app.handle_input(esc.clone()).unwrap();
+    }
+    assert_eq!(app.view.forward.len(), FORWARD_HISTORY);
+}
+
+#[test]
+fn test_headless_leg_to_ledger() {
+    let mut store = make_store();
+    let je = make_entry("grocer", chrono::Utc::now(), "cash", "food", 10.0);
+    store.put(je.clone()).unwrap();
+
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let layer = tl::EntryBrowser::new(&mut app, vec![je]).unwrap();
+    app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::EntryDetail(_)) => (),
+        _ => panic!("expected entry detail"),
+    }
+
+    // debit legs are listed first, move down to the credit leg.
+    app.handle_input(key(KeyCode::Down)).unwrap();
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    let cash: types::KeyLedger = ("acme".to_string(), "cash".to_string()).into();
+    match app.view.layers.last() {
+        Some(Layer::LedgerDetail(layer)) => {
+            assert_eq!(layer.to_key().to_string(), cash.to_string())
+        }
+        _ => panic!("expected ledger detail"),
+    }
+    assert_eq!(app.view.layers.len(), 3);
+
+    // Esc restores the entry view.
+    app.handle_input(key(KeyCode::Esc)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::EntryDetail(_)) => (),
+        _ => panic!("expected entry detail"),
+    }
+}
+
+#[test]
+fn test_headless_reconcile() {
+    use crossterm::event::KeyModifiers;
+
+    let mut store = make_store();
+    let je = make_entry("grocer", chrono::Utc::now(), "cash", "food", 10.0);
+    store.put(je.clone()).unwrap();
+    let key = je.to_key();
+
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let layer = tl::EntryBrowser::new(&mut app, vec![je]).unwrap();
+    app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+
+    let ctrl_r = key_with(KeyCode::Char('r'), KeyModifiers::CONTROL);
+    // selected entry is marked, and un-marked, in the store.
+    app.handle_input(ctrl_r.clone()).unwrap();
+    let entry: types::JournalEntry = app.as_mut().get(&key).unwrap();
+    assert!(entry.reconciled);
+    app.handle_input(ctrl_r).unwrap();
+    let entry: types::JournalEntry = app.as_mut().get(&key).unwrap();
+    assert!(!entry.reconciled);
+}
+
+#[test]
+fn test_headless_calendar() {
+    let store = make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+    app.set_date(chrono::Local.ymd(2020, 7, 15)).unwrap();
+    app.do_command(Command::Calendar).unwrap();
+    let text = out.to_text();
+    assert!(text.contains("July 2020"), "{}", text);
+    assert!(text.contains("[15]"), "{}", text);
+
+    // a week ahead, a day back, then pick the date.
+    app.handle_input(key(KeyCode::Down)).unwrap();
+    app.handle_input(key(KeyCode::Left)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::Calendar(layer)) => {
+            assert_eq!(layer.to_date(), chrono::NaiveDate::from_ymd(2020, 7, 21))
+        }
+        _ => panic!("expected calendar"),
+    }
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    assert_eq!(app.date, chrono::Local.ymd(2020, 7, 21));
+    assert!(app.view.layers.is_empty());
+
+    // weeks start on the configured day.
+    assert!(text.contains(" Mo  Tu  We "), "{}", text);
+    app.week_start = util::WeekStart::Sunday;
+    out.clear();
+    app.do_command(Command::Calendar).unwrap();
+    let text = out.to_text();
+    assert!(text.contains(" Su  Mo  Tu "), "{}", text);
+}
+
+#[test]
+fn test_headless_recent_repeat() {
+    use crossterm::event::KeyModifiers;
+
+    let mut store = make_store();
+    let created = chrono::Utc::now() - chrono::Duration::days(30);
+    let je = make_entry("rent", created, "cash", "rent", 10.0);
+    store.put(je).unwrap();
+
+    let (mut app, out) = Application::headless(store).unwrap();
+    app.do_command(Command::Recent).unwrap();
+    let text = out.to_text();
+    assert!(text.contains("rent"), "{}", text);
+
+    // repeat the selected entry, dated today.
+    let key = key_with(KeyCode::Char('r'), KeyModifiers::ALT);
+    app.handle_input(key).unwrap();
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].payee, "rent");
+    assert_eq!(entries[0].created.date(), chrono::Utc::today());
+}
+
+#[test]
+fn test_headless_save_template() {
+    use crossterm::event::KeyModifiers;
+
+    let mut store = make_store();
+    let je = make_entry("landlord", chrono::Utc::now(), "bank", "rent", 1200.0);
+    store.put(je.clone()).unwrap();
+
+    let (mut app, out) = Application::headless(store).unwrap();
+    app.open_entry(&je.to_key()).unwrap();
+
+    app.handle_input(key_with(KeyCode::Char('s'), KeyModifiers::ALT))
+
+        .unwrap();
+    assert!(out.to_text().contains("Save landlord as template"));
+
+    // empty name is refused, the prompt stays open.
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::Prompt(_)) => (),
+        _ => panic!("expected prompt"),
+    }
+    for ch in "rent".chars() {
+        app.handle_input(key(KeyCode::Char(ch))).unwrap();
+    }
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::EntryDetail(_)) => (),
+        _ => panic!("expected entry detail"),
+    }
+
+    // instantiated later, the template reproduces the entry's legs.
+    let template: types::Template = app.as_mut().get("template-rent").unwrap();
+    let entry = template.to_entry(chrono::Utc::now());
+    assert_eq!(entry.payee, "landlord");
+    assert_eq!(entry.debitors[0].ledger.to_string(), je.debitors[0].ledger.to_string());
+    assert_eq!(entry.debitors[0].commodity.value, 1200.0);
+    assert_eq!(entry.creditors[0].ledger.to_string(), je.creditors[0].ledger.to_string());
+    assert_eq!(entry.creditors[0].commodity.value, 1200.0);
+}
+
+#[test]
+fn test_headless_entry_form() {
+    use crossterm::event::KeyModifiers;
+    use ledger::mem_store::MemStore;
+
+    let store = make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+    app.do_command(Command::NewEntry).unwrap();
+    let text = out.to_text();
+    assert!(text.contains("New journal entry"), "{}", text);
+    assert!(text.contains("balanced"), "{}", text);
+
+    let tab = key(KeyCode::Tab);
+    let fill = |app: &mut Application<MemStore>, fields: Vec<&str>| {
+        for field in fields.into_iter() {
+            for ch in field.chars() {
+                app.handle_input(key(KeyCode::Char(ch))).unwrap();
+            }
+            app.handle_input(tab.clone()).unwrap();
+        }
+    };
+    // skip quick entry, payee, date as pre-filled, and the debit leg.
+    fill(&mut app, vec!["", "grocer", "", "acme:food", "inr"]);
+    for ch in "10".chars() {
+        app.handle_input(key(KeyCode::Char(ch))).unwrap();
+    }
+    // footer shows what the credit legs need to balance.
+    let text = out.to_text();
+    assert!(text.contains("remaining inr 10.00 Cr"), "{}", text);
+
+    // add a debit row and remove it while empty, focus moves to credit.
+    let ctrl = |ch| key_with(KeyCode::Char(ch), KeyModifiers::CONTROL);
+    let row = app.view.cursor.map(|(_, row)| row).unwrap();
+    app.handle_input(ctrl('n')).unwrap();
+    app.handle_input(tab.clone()).unwrap();
+    assert_eq!(app.view.cursor.map(|(_, row)| row), Some(row + 1));
+    app.handle_input(ctrl('d')).unwrap();
+    assert_eq!(app.view.cursor.map(|(_, row)| row), Some(row + 3));
+    out.clear();
+    fill(&mut app, vec!["acme:cash", "inr", "10"]);
+    let text = out.to_text();
+    assert!(text.contains("balanced"), "{}", text);
+
+    app.handle_input(ctrl('s')).unwrap();
+    if let Some(Layer::EntryForm(_)) = app.view.layers.last() {
+        panic!("expected entry form to close");
+    }
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].payee, "grocer");
+    assert_eq!(entries[0].debitors.len(), 1);
+    assert_eq!(entries[0].creditors.len(), 1);
+    assert!(entries[0].is_balanced());
+}
+
+#[test]
+fn test_headless_quick_entry() {
+    use ledger::mem_store::MemStore;
+
+    let store = make_store();
+    let (mut app, out) = Application::headless(store).unwrap();
+    app.do_command(Command::NewEntry).unwrap();
+
+    let typ = |app: &mut Application<MemStore>, text: &str| {
+        for ch in text.chars() {
+            app.handle_input(key(KeyCode::Char(ch))).unwrap();
+        }
+        app.handle_input(key(KeyCode::Enter)).unwrap();
+    };
+
+    // malformed account, the form stays open.
+    typ(&mut app, "rent | checking -1200 inr | acme:rent 1200 inr");
+    let text = out.to_text();
+    assert!(text.contains("quick entry account"), "{}", text);
+    match app.view.layers.last() {
+        Some(Layer::EntryForm(_)) => (),
+        _ => panic!("expected entry form"),
+    }
+    app.close_layer().unwrap();
+
+    // well-formed entry is added on enter.
+    app.do_command(Command::NewEntry).unwrap();
+    typ(&mut app, "rent | acme:checking -1200 inr | acme:rent 1200 inr");
+    if let Some(Layer::EntryForm(_)) = app.view.layers.last() {
+        panic!("expected entry form to close");
+    }
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].payee, "rent");
+    assert_eq!(entries[0].creditors[0].commodity.value, 1200.0);
+}
+
+#[test]
+fn test_headless_insert_today() {
+    use crossterm::event::KeyModifiers;
+
+    let store = make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+
+    let alt_t = key_with(KeyCode::Char('t'), KeyModifiers::ALT);
+    let today = chrono::Local::today();
+    let text_today = today.format(command::DATE_FORMAT).to_string();
+
+    // entry form date field, after quick entry and payee.
+    let date = chrono::NaiveDate::from_ymd(2020, 7, 21);
+    app.do_command(Command::Goto(Some(date))).unwrap();
+    app.do_command(Command::NewEntry).unwrap();
+    for _ in 0..2 {
+        app.handle_input(key(KeyCode::Tab)).unwrap();
+    }
+    out.clear();
+    app.handle_input(alt_t.clone()).unwrap();
+    let text = out.to_text();
+    assert!(text.contains(&text_today), "{}", text);
+    app.close_layer().unwrap();
+
+    // goto prompt takes today's date on alt-t.
+    app.do_command(Command::Goto(None)).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::Prompt(_)) => (),
+        _ => panic!("expected prompt"),
+    }
+    app.handle_input(alt_t).unwrap();
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    assert!(app.view.layers.is_empty());
+    assert_eq!(app.date, today);
+}
+
+#[test]
+fn test_headless_entry_form_default_ledger() {
+    use crossterm::event::KeyModifiers;
+
+    let store = make_store();
+    let (mut app, out) = Application::headless(store).unwrap();
+
+    // unset default leaves the credit leg blank.
+    app.do_command(Command::NewEntry).unwrap();
+    assert!(!out.to_text().contains("acme:checking"));
+    app.close_layer().unwrap();
+
+    let ledger = ("acme".to_string(), "checking".to_string());
+    app.do_command(Command::DefaultLedger(Some(ledger))).unwrap();
+    app.do_command(Command::NewEntry).unwrap();
+    let text = out.to_text();
+    assert!(text.contains("acme:checking"), "{}", text);
+
+    let tab = key(KeyCode::Tab);
+    let fields = vec!["", "grocer", "", "acme:food", "inr", "10", "", "inr", "10"];
+    for field in fields.into_iter() {
+        for ch in field.chars() {
+            app.handle_input(key(KeyCode::Char(ch))).unwrap();
+        }
+        app.handle_input(tab.clone()).unwrap();
+    }
+    app.handle_input(key_with(KeyCode::Char('s'), KeyModifiers::CONTROL))
+        .unwrap();
+
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    let ledger: types::KeyLedger = ("acme".to_string(), "checking".to_string()).into();
+    assert_eq!(entries[0].creditors[0].ledger.to_string(), ledger.to_string());
+}
+
+#[test]
+fn test_headless_move_leg() {
+    use crossterm::event::KeyModifiers;
+    use ledger::mem_store::MemStore;
+
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    app.do_command(Command::NewEntry).unwrap();
+
+    let tab = key(KeyCode::Tab);
+    let fill = |app: &mut Application<MemStore>, fields: Vec<&str>| {
+        for field in fields.into_iter() {
+            for ch in field.chars() {
+                app.handle_input(key(KeyCode::Char(ch))).unwrap();
+            }
+            app.handle_input(tab.clone()).unwrap();
+        }
+    };
+    fill(&mut app, vec!["", "landlord", "", "acme:food", "inr", "10"]);
+    app.handle_input(key(KeyCode::BackTab)).unwrap();
+    app.handle_input(key_with(KeyCode::Char('n'), KeyModifiers::CONTROL))
+        .unwrap();
+    app.handle_input(tab.clone()).unwrap();
+    fill(&mut app, vec!["acme:rent", "inr"]);
+    for ch in "20".chars() {
+        app.handle_input(key(KeyCode::Char(ch))).unwrap();
+    }
+
+    // second debit row moves up, focus follows it.
+    let row = app.view.cursor.map(|(_, row)| row).unwrap();
+    app.handle_input(key_with(KeyCode::Up, KeyModifiers::ALT))
+        .unwrap();
+    assert_eq!(app.view.cursor.map(|(_, row)| row), Some(row - 1));
+    app.handle_input(key_with(KeyCode::Up, KeyModifiers::ALT))
+        .unwrap();
+    assert_eq!(app.view.cursor.map(|(_, row)| row), Some(row - 1));
+    app.handle_input(tab.clone()).unwrap();
+    app.handle_input(tab.clone()).unwrap();
+    app.handle_input(tab.clone()).unwrap();
+    app.handle_input(tab.clone()).unwrap();
+    fill(&mut app, vec!["acme:cash", "inr", "30"]);
+    app.handle_input(key_with(KeyCode::Char('s'), KeyModifiers::CONTROL))
+        .unwrap();
+
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    let values: Vec<f64> = entries[0].debitors.iter().map(|d| d.commodity.value).collect();
+    assert_eq!(values, vec![20.0, 10.0]);
+
+    // in entry detail, legs move and the entry is saved in that order.
+    let layer = tl::EntryDetail::new(&mut app, &entries[0]).unwrap();
+    app.push_layer(Layer::EntryDetail(layer)).unwrap();
+    app.handle_input(key_with(KeyCode::Down, KeyModifiers::ALT))
+        .unwrap();
+    let entry: types::JournalEntry = app.as_mut().get(&entries[0].to_key()).unwrap();
+    let values: Vec<f64> = entry.debitors.iter().map(|d| d.commodity.value).collect();
+    assert_eq!(values, vec![10.0, 20.0]);
+}
+
+#[test]
+fn test_headless_template_placeholders() {
+    use ledger::mem_store::MemStore;
+
+    let mut store = make_store();
+    let mut je = make_entry("electricity", chrono::Utc::now(), "bank", "utility", 1.0);
+    let mut template = types::Template::from_entry("power", &je).unwrap();
+    template.set_amount_placeholder(0, "{amount}").unwrap();
+    template.set_amount_placeholder(1, "{amount}").unwrap();
+    store.put(template).unwrap();
+
+    let (mut app, out) = Application::headless(store).unwrap();
+    let msg = app.do_command(Command::Template("power".to_string())).unwrap();
+    assert_eq!(msg, "amount for power");
+    assert!(out.to_text().contains("Template power"));
+
+    let type_text = |app: &mut Application<MemStore>, text: &str| {
+        for ch in text.chars() {
+            app.handle_input(key(KeyCode::Char(ch))).unwrap();
+        }
+        app.handle_input(key(KeyCode::Enter)).unwrap();
+    };
+    // amount must be a number, the prompt stays open.
+    type_text(&mut app, "lots");
+    match app.view.layers.last() {
+        Some(Layer::Prompt(_)) => (),
+        _ => panic!("expected prompt"),
+    }
+    for _ in 0..4 {
+        app.handle_input(key(KeyCode::Backspace)).unwrap();
+    }
+    type_text(&mut app, "1250.5");
+    assert!(app.view.layers.is_empty());
+
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].payee, "electricity");
+    assert_eq!(entries[0].debitors[0].commodity.value, 1250.5);
+    assert_eq!(entries[0].creditors[0].commodity.value, 1250.5);
+
+    // notes are limited as configured.
+    je.note = "monthly electricity bill".to_string();
+    let template = types::Template::from_entry("bill", &je).unwrap();
+    app.max_note_len = 10;
+    assert!(app.fill_template(template, BTreeMap::new()).is_err());
+    assert_eq!(
+        aggregates::recent_entries(app.as_mut(), 10).unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn test_headless_navigator() {
+    use ledger::mem_store::MemStore;
+    use std::convert::TryInto;
+
+    let mut store = make_store();
+    let s = |s: &str| s.to_string();
+    for name in ["acme", "globex"].iter() {
+        let company: types::Company = (s(name), s("2020-01-01T00:00:00Z"), s(""), s(""), s(""))
+            .try_into()
+            .unwrap();
+        store.put(company).unwrap();
+    }
+    for (company, name) in [("acme", "cash"), ("acme", "food"), ("globex", "payroll")].iter() {
+        let ledger: types::Ledger = (
+            s(name),
+            s("2020-01-01T00:00:00Z"),
+            s(company),
+            s(""),
+            s(""),
+            s(""),
+            s(""),
+        )
+            .try_into()
+            .unwrap();
+        store.put(ledger).unwrap();
+    }
+
+    let (mut app, out) = Application::headless(store).unwrap();
+    app.do_command(Command::Ledgers).unwrap();
+    // ledger rows are their one line summary.
+    let text = out.to_text();
+    assert!(text.contains("acme:cash"), "{}", text);
+
+    let ledgers = |app: &Application<MemStore>| match app.view.layers.last() {
+        Some(Layer::Navigator(layer)) => {
+            let keys = layer.to_ledgers().into_iter().map(|k| k.to_string());
+            (layer.to_company(), keys.collect::<Vec<String>>())
+        }
+        _ => panic!("expected navigator"),
+    };
+
+    // first company is selected on open.
+    assert_eq!(
+        ledgers(&app),
+        (Some(s("acme")), vec![s("ledger-acme-cash"), s("ledger-acme-food")])
+    );
+
+    // moving the selection in the left pane repopulates the right pane.
+    app.handle_input(key(KeyCode::Down)).unwrap();
+    assert_eq!(ledgers(&app), (Some(s("globex")), vec![s("ledger-globex-payroll")]));
+    app.handle_input(key(KeyCode::Up)).unwrap();
+    assert_eq!(ledgers(&app).0, Some(s("acme")));
+
+    // Right moves focus to the ledgers, Down no longer changes company.
+    app.handle_input(key(KeyCode::Right)).unwrap();
+    app.handle_input(key(KeyCode::Down)).unwrap();
+    assert_eq!(ledgers(&app).0, Some(s("acme")));
+    app.handle_input(key(KeyCode::Enter)).unwrap();
+    let food: types::KeyLedger = (s("acme"), s("food")).into();
+    match app.view.layers.last() {
+        Some(Layer::LedgerDetail(layer)) => {
+            assert_eq!(layer.to_key().to_string(), food.to_string())
+        }
+        _ => panic!("expected ledger detail"),
+    }
+
+    // back in the navigator, Left returns focus to the companies.
+    app.handle_input(key(KeyCode::Esc)).unwrap();
+    app.handle_input(key(KeyCode::Left)).unwrap();
+    app.handle_input(key(KeyCode::Down)).unwrap();
+    assert_eq!(ledgers(&app).0, Some(s("globex")));
+}
+
+#[test]
+fn test_headless_entry_display_currency() {
+    use crossterm::event::KeyModifiers;
+    use ledger::{core::Durable, mem_store::MemStore};
+
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut store = make_store_with(w);
+    for (name, value) in [("inr", 1.0), ("usd", 75.0)].iter() {
+        store.put(types::Commodity::from((name.to_string(), *value))).unwrap();
+    }
+    let mut je = make_entry("travel", chrono::Utc::now(), "cash", "trip", 10.0);
+    for leg in je.debitors.iter_mut() {
+        leg.commodity.name = "usd".to_string();
+    }
+    for leg in je.creditors.iter_mut() {
+        leg.commodity.name = "usd".to_string();
+    }
+    store.put(je.clone()).unwrap();
+
+    let (mut app, mut out) = Application::headless(store).unwrap();
+    let layer = tl::EntryDetail::new(&mut app, &je).unwrap();
+    app.push_layer(Layer::EntryDetail(layer)).unwrap();
+    let text = out.to_text();
+    assert!(text.contains("10.00 usd Dr"), "{}", text);
+
+    let alt_c = key_with(KeyCode::Char('c'), KeyModifiers::ALT);
+    let to_currency = |app: &Application<MemStore>| match app.view.layers.last() {
+        Some(Layer::EntryDetail(layer)) => layer.to_currency(),
+        _ => panic!("expected entry detail"),
+    };
+
+    // commodities are cycled by name, inr first.
+    out.clear();
+    app.handle_input(alt_c.clone()).unwrap();
+    assert_eq!(to_currency(&app), Some("inr".to_string()));
+    let text = out.to_text();
+    assert!(text.contains("750.00 inr Dr"), "{}", text);
+    assert!(text.contains("750.00 inr Cr"), "{}", text);
+
+    app.handle_input(alt_c.clone()).unwrap();
+    assert_eq!(to_currency(&app), Some("usd".to_string()));
+    out.clear();
+    app.handle_input(alt_c.clone()).unwrap();
+    assert_eq!(to_currency(&app), None);
+    assert!(out.to_text().contains("10.00 usd Dr"));
+
+    // stored entry is left as is.
+    let stored: types::JournalEntry = app.as_mut().get(&je.to_key()).unwrap();
+    assert_eq!(stored.debitors[0].commodity.name, "usd");
+    assert_eq!(stored.debitors[0].commodity.value, 10.0);
+}
+
+#[test]
+fn test_headless_entry_date_warning() {
+    use chrono::Datelike;
+
+    let mut w = types::Workspace::new("test".to_string());
+    w.set_created(chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0));
+    let store = make_store_with(w);
+    let (mut app, out) = Application::headless(store).unwrap();
+
+    let now = chrono::Utc::now();
+    let typo = now.with_year(now.year() + 180).unwrap();
+    let far = types::JournalEntry::new("grocer".to_string(), typo);
+    app.check_entry_date(&far).unwrap();
+    app.refresh(true /*force*/).unwrap().render().unwrap();
+    let text = out.to_text();
+    assert!(text.contains("days ahead"), "{}", text);
+
+    let normal = types::JournalEntry::new("grocer".to_string(), now);
+    assert_eq!(app.check_entry_date(&normal).unwrap(), None);
+
+    // back-dated entries are rejected, until the books start earlier.
+    let old = types::JournalEntry::new("grocer".to_string(), chrono::Utc.ymd(2019, 6, 1).and_hms(0, 0, 0));
+    app.date_check.reject = true;
+    assert!(app.check_entry_date(&old).is_err());
+    app.date_check.not_before = Some(chrono::NaiveDate::from_ymd(2019, 4, 1));
+    app.check_entry_date(&old).unwrap();
+}
+
+#[test]
+fn test_headless_entry_form_date_check() {
+    use ledger::mem_store::MemStore;
+
+    let mut w = types::Workspace::new("test".to_string());
+    w.set_created(chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0));
+    let store = make_store_with(w);
+    let (mut app, out) = Application::headless(store).unwrap();
+
+    let typ = |app: &mut Application<MemStore>, text: &str| {
+        for ch in text.chars() {
+            app.handle_input(key(KeyCode::Char(ch))).unwrap();
+        }
+        app.handle_input(key(KeyCode::Enter)).unwrap();
+    };
+    let quick = "rent | acme:checking -1200 inr | acme:rent 1200 inr";
+
+    // back-dated entry is rejected, the form stays open.
+    let date = chrono::NaiveDate::from_ymd(2019, 6, 1);
+    app.do_command(Command::Goto(Some(date))).unwrap();
+    app.date_check.reject = true;
+    app.do_command(Command::NewEntry).unwrap();
+    typ(&mut app, quick);
+    let text = out.to_text();
+    assert!(text.contains("before workspace created"), "{}", text);
+    match app.view.layers.last() {
+        Some(Layer::EntryForm(_)) => (),
+        _ => panic!("expected entry form"),
+    }
+    app.close_layer().unwrap();
+
+    // only warned otherwise, the entry is added.
+    app.date_check.reject = false;
+    app.do_command(Command::NewEntry).unwrap();
+    typ(&mut app, quick);
+    assert!(app.view.layers.is_empty());
+    let text = out.to_text();
+    assert!(text.contains("added rent, entry date 2019-06-01"), "{}", text);
+    let entries = aggregates::recent_entries(app.as_mut(), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+}
+
+#[test]
+fn test_headless_save() {
+    use crossterm::event::KeyModifiers;
+
+    let store = make_store();
+    let (mut app, out) = Application::headless(store).unwrap();
+
+    let save = key_with(KeyCode::Char('s'), KeyModifiers::CONTROL);
+    assert_eq!(app.handle_input(save).unwrap(), false);
+    app.refresh(true /*force*/).unwrap().render().unwrap();
+    let text = out.to_text();
+    assert!(text.contains("saved"), "{}", text);
+}
+
+#[test]
+fn test_init_screen_mouse() {
+    use crossterm::Command;
+
+    let enable = EnableMouseCapture.ansi_code().to_string();
+    let disable = DisableMouseCapture.ansi_code().to_string();
+
+    let mut out: Vec<u8> = vec![];
+    init_screen(&mut out, false /*mouse*/).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains(&EnterAlternateScreen.ansi_code().to_string()));
+    assert!(!text.contains(enable.as_str()));
+
+    let mut out: Vec<u8> = vec![];
+    leave_screen(&mut out, false /*mouse*/).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains(&LeaveAlternateScreen.ansi_code().to_string()));
+    assert!(!text.contains(disable.as_str()));
+
+    let mut out: Vec<u8> = vec![];
+    init_screen(&mut out, true /*mouse*/).unwrap();
+    assert!(String::from_utf8(out).unwrap().contains(enable.as_str()));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_external() {
+    use crossterm::event::KeyModifiers;
+    use std::os::unix::fs::PermissionsExt;
+
+    // fake editor, appends a line to the file it is given.
+    let mut script = std::env::temp_dir();
+    script.push("ledger47-test-editor.sh");
+    std::fs::write(&script, "#!/bin/sh\nprintf ' and eggs\\n' >> \"$1\"\n").unwrap();
+    let perms = std::fs::Permissions::from_mode(0o755);
+    std::fs::set_permissions(&script, perms).unwrap();
+    let editor = script.to_str().unwrap().to_string();
+
+    assert_eq!(edit_with(&editor, "milk").unwrap(), "milk and eggs");
+    assert!(edit_with("", "milk").is_err());
+    assert!(edit_with("false", "milk").is_err());
+    assert_eq!(te::to_editor("nano"), "nano");
+
+    // temporary files are private and never reused.
+    let loc = create_temp_file("milk").unwrap();
+    let mode = std::fs::metadata(&loc).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    let other = create_temp_file("milk").unwrap();
+    assert_ne!(other, loc);
+    std::fs::remove_file(&loc).unwrap();
+    std::fs::remove_file(&other).unwrap();
+
+    // round trip through the edit box.
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+
+    app.editor = editor.clone();
+    let vp = te::Viewport::new(1, 2, 4, 40);
+    let mut em = te::Element::EditBox(te::EditBox::new(&mut app, vp).unwrap());
+    em.set_content("bread").unwrap();
+    let evnt = key_with(KeyCode::Char('e'), KeyModifiers::CONTROL);
+    assert!(em.handle_event(&mut app, evnt).unwrap().is_none());
+    assert_eq!(em.to_content().unwrap(), "bread and eggs");
+    assert!(app.view.redraw);
+}
+
+#[test]
+fn test_headless_coalesce_refresh() {
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let layer = tl::NewWorkspace::new(&mut app).unwrap();
+    app.push_layer(Layer::NewWorkspace(layer)).unwrap();
+
+    let char_key = |ch| key(KeyCode::Char(ch));
+
+    // events within the window are handled with a single refresh.
+    let n_refresh = app.view.n_refresh;
+    let mut evnts = "ersonal".chars().map(char_key);
+    let quit = app
+        .handle_inputs(char_key('p'), || Ok(evnts.next()))
+        .unwrap();
+    assert_eq!(quit, false);
+    assert_eq!(app.view.n_refresh, n_refresh + 1);
+    assert_eq!(app.view.cursor, Some((14, 5)));
+
+    // each event handled alone refreshes.
+    app.handle_input(char_key('s')).unwrap();
+    app.handle_input(char_key('t')).unwrap();
+    assert_eq!(app.view.n_refresh, n_refresh + 3);
+
+    // too many events refresh before the rest are read.
+    let mut evnts = (0..MAX_COALESCE * 2).map(|_| char_key('x'));
+    app.handle_inputs(char_key('x'), || Ok(evnts.next()))
+        .unwrap();
+    assert_eq!(app.view.n_refresh, n_refresh + 4);
+    assert_eq!(evnts.count(), MAX_COALESCE + 1);
+}
+
+#[test]
+fn test_headless_update_rates() {
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut store = make_store_with(w);
+    for (name, value) in [("inr", 1.0), ("usd", 0.0), ("eur", 90.0)].iter() {
+        let value: types::Commodity = (name.to_string(), *value).into();
+        store.put(value).unwrap();
+    }
+
+    // quoted commodities are updated, zero value or not, others left as is.
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let cmd = Command::parse("update rates usd=82.5").unwrap();
+    assert_eq!(app.do_command(cmd).unwrap(), "1 rates updated");
+    let usd: types::Commodity = app.as_mut().get("commodity-usd").unwrap();
+    assert_eq!(usd.value, 82.5);
+    let eur: types::Commodity = app.as_mut().get("commodity-eur").unwrap();
+    assert_eq!(eur.value, 90.0);
+
+    // unknown commodity fails without updating any.
+    let cmd = Command::parse("update rates eur=91 chf=80").unwrap();
+    assert!(app.do_command(cmd).is_err());
+    let eur: types::Commodity = app.as_mut().get("commodity-eur").unwrap();
+    assert_eq!(eur.value, 90.0);
+}
+
+#[test]
+fn test_headless_audit_commit() {
+    let mut loc = std::env::temp_dir();
+    loc.push("ledger47-test");
+    loc.push("test_headless_audit_commit");
+    std::fs::remove_dir_all(&loc).ok();
+    loc.push("audit.log");
+
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    app.audit = Audit::from_file(&loc);
+
+    assert_eq!(app.do_command(Command::Commit).unwrap(), "commit ok");
+    let msg = app.do_command(Command::Push).unwrap();
+    assert_eq!(msg, "push already up to date");
+    // nothing was transferred, push is not recorded.
+    let text = std::fs::read_to_string(&loc).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].ends_with(" commit workspace test"), "{}", lines[0]);
+}
+
+#[test]
+fn test_headless_audit_entries() {
+    use crossterm::event::KeyModifiers;
+
+    let mut loc = std::env::temp_dir();
+    loc.push("ledger47-test");
+    loc.push("test_headless_audit_entries");
+    std::fs::remove_dir_all(&loc).ok();
+    loc.push("audit.log");
+
+    let mut store = make_store();
+    let je = make_entry("grocer", chrono::Utc::now(), "cash", "food", 10.0);
+    store.put(je.clone()).unwrap();
+
+    let (mut app, _out) = Application::headless(store).unwrap();
+    app.audit = Audit::from_file(&loc);
+    let layer = tl::EntryBrowser::new(&mut app, vec![je.clone()]).unwrap();
+    app.push_layer(Layer::EntryBrowser(layer)).unwrap();
+
+    // reconcile, repeat, and delete the repeated entry listed first.
+    app.handle_input(key_with(KeyCode::Char('r'), KeyModifiers::CONTROL))
+        .unwrap();
+    app.handle_input(key_with(KeyCode::Char('r'), KeyModifiers::ALT))
+        .unwrap();
+    app.handle_input(key_with(KeyCode::Char('d'), KeyModifiers::CONTROL))
+        .unwrap();
+    let entry: types::JournalEntry = app.as_mut().get(&je.to_key()).unwrap();
+    assert!(entry.reconciled);
+    assert_eq!(
+        aggregates::recent_entries(app.as_mut(), 10).unwrap().len(),
+        1
+    );
+
+    let text = std::fs::read_to_string(&loc).unwrap();
+    let actions: Vec<&str> = text.lines().map(|l| l.split(' ').nth(1).unwrap()).collect();
+    assert_eq!(actions, vec!["entry-edited", "entry-added", "entry-deleted"]);
+}
+
+#[test]
+fn test_headless_create_workspace() {
+    use crossterm::event::KeyModifiers;
+
+    let name = "test_headless_create_workspace";
+    let mut home = std::env::temp_dir();
+    home.push("ledger47-test");
+    home.push(name);
+    std::fs::remove_dir_all(&home).ok();
+
+    let store = make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    app.home = Some(home.clone());
+    let layer = tl::NewWorkspace::new(&mut app).unwrap();
+    app.push_layer(Layer::NewWorkspace(layer)).unwrap();
+
+    let ctrl_s = key_with(KeyCode::Char('s'), KeyModifiers::CONTROL);
+    // workspace name is required, the form stays open.
+    app.handle_input(ctrl_s.clone()).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::NewWorkspace(_)) => (),
+        _ => panic!("expected new workspace form"),
+    }
+
+    let tab = key(KeyCode::Tab);
+    for (i, text) in vec![name, "month-log", "yen", "¥"].into_iter().enumerate() {
+        if i > 0 {
+            app.handle_input(tab.clone()).unwrap();
+        }
+        for ch in text.chars() {
+            app.handle_input(key(KeyCode::Char(ch))).unwrap();
+        }
+    }
+    app.handle_input(ctrl_s).unwrap();
+    match app.view.layers.last() {
+        Some(Layer::OpenCompany(_)) => (),
+        _ => panic!("expected open company"),
+    }
+
+    let w = app.as_mut().to_workspace().unwrap();
+    assert_eq!(w.name, name);
+    assert_eq!(w.to_base_commodity(), "yen");
+    assert_eq!(w.journal_layout, "month-log");
+    let yen: types::Commodity = app.as_mut().get("commodity-yen").unwrap();
+    assert_eq!(yen.symbol, "¥");
+
+    // base commodity is audited as created, workspace is remembered.
+    let text = std::fs::read_to_string(home.join(name).join("audit.log")).unwrap();
+    assert!(text.contains(" commodity-created yen"), "{}", text);
+    assert!(home.join("recent.json").exists());
+    std::fs::remove_dir_all(&home).ok();
+}
+
+#[test]
+fn test_safe_mode_banner() {
+    use ledger::db_files::Db;
+
+    let mut loc = std::env::temp_dir();
+    loc.push("ledger47-test");
+    loc.push("test_safe_mode_banner");
+    std::fs::remove_dir_all(&loc).ok();
+    std::fs::create_dir_all(&loc).unwrap();
+    let dir = loc.as_os_str();
+
+    let w = types::Workspace::new("test".to_string());
+    let db = Db::create(dir, w).unwrap();
+    assert_eq!(to_banner(&db), "");
+    std::mem::drop(db);
+
+    let opts = core::Options {
+        safe: true,
+        ..Default::default()
+    };
+    let db = Db::open_with(dir, opts).unwrap();
+    assert_eq!(to_banner(&db), SAFE_MODE_BANNER);
+}
+
+#[test]
+fn test_show_cursor_style() {
+    let store = make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+
+    app.view.tm.cursor_style = te::CursorStyle::SteadyBar;
+    app.view.cursor = Some((1, 1));
+    out.clear();
+    app.show_cursor().unwrap();
+    assert!(out.to_text().contains("\x1b[6 q"));
+}
@@ -0,0 +1,116 @@
+use chrono;
+
+use std::{fmt, fs, io::Write, path, result};
+
+use ledger::{
+    core::{Durable, Error, Result},
+    err_at,
+};
+
+// significant user actions, recorded in the audit log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    EntryAdded,
+    EntryEdited,
+    EntryDeleted,
+    CommodityCreated,
+    Commit,
+    Push,
+    Pull,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            AuditAction::EntryAdded => write!(f, "entry-added"),
+            AuditAction::EntryEdited => write!(f, "entry-edited"),
+            AuditAction::EntryDeleted => write!(f, "entry-deleted"),
+            AuditAction::CommodityCreated => write!(f, "commodity-created"),
+            AuditAction::Commit => write!(f, "commit"),
+            AuditAction::Push => write!(f, "push"),
+            AuditAction::Pull => write!(f, "pull"),
+        }
+    }
+}
+
+// append-only, human readable log of user actions, one line per action,
+// EG: `2020-06-01T10:00:00+00:00 entry-added 01-Jun-20 grocer 100.00 inr`.
+// Kept apart from the debug log in `<home>/<workspace>/audit.log`, refer
+// app::default_home().
+#[derive(Clone, Default)]
+pub struct Audit {
+    // None when there is no place to log, actions are not recorded.
+    loc: Option<path::PathBuf>,
+}
+
+impl Audit {
+    pub fn for_workspace(home: &path::Path, name: &str) -> Audit {
+        let mut loc = home.to_path_buf();
+        loc.push(name);
+        loc.push("audit.log");
+        Audit { loc: Some(loc) }
+    }
+
+    pub fn from_file(loc: &path::Path) -> Audit {
+        Audit {
+            loc: Some(loc.to_path_buf()),
+        }
+    }
+
+    pub fn record(&self, action: AuditAction, summary: &str) -> Result<()> {
+        self.record_at(chrono::Utc::now(), action, summary)
+    }
+
+    pub fn record_at(
+        &self,
+        at: chrono::DateTime<chrono::Utc>,
+        action: AuditAction,
+        summary: &str,
+    ) -> Result<()> {
+        let loc = match &self.loc {
+            Some(loc) => loc,
+            None => return Ok(()),
+        };
+        if let Some(parent) = loc.parent() {
+            err_at!(IOError, fs::create_dir_all(parent), format!("{:?}", parent))?;
+        }
+
+        let summary = summary.replace(|ch| ch == '\r' || ch == '\n', " ");
+        let line = format!("{} {} {}\n", at.to_rfc3339(), action, summary.trim());
+        let mut fd = err_at!(
+            IOError,
+            fs::OpenOptions::new().append(true).create(true).open(loc),
+            format!("{:?}", loc)
+        )?;
+        err_at!(IOError, fd.write_all(line.as_bytes()), format!("{:?}", loc))
+    }
+
+    // record a put of journal entry or commodity, `old` is the value it
+    // replaced if any. Other types are not audited.
+    pub fn record_put<V>(&self, old: Option<&V>, value: &V) -> Result<()>
+    where
+        V: Durable,
+    {
+        let action = match (value.to_type().as_str(), old) {
+            ("journalentry", None) => AuditAction::EntryAdded,
+            ("journalentry", Some(_)) => AuditAction::EntryEdited,
+            ("commodity", None) => AuditAction::CommodityCreated,
+            _ => return Ok(()),
+        };
+        self.record(action, &value.summary())
+    }
+
+    pub fn record_delete<V>(&self, value: &V) -> Result<()>
+    where
+        V: Durable,
+    {
+        match value.to_type().as_str() {
+            "journalentry" => self.record(AuditAction::EntryDeleted, &value.summary()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "audit_test.rs"]
+mod audit_test;
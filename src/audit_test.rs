@@ -0,0 +1,65 @@
+use chrono::offset::TimeZone;
+
+use ledger::types;
+
+use super::*;
+
+fn make_loc(name: &str) -> path::PathBuf {
+    let mut loc = std::env::temp_dir();
+    loc.push("ledger47-test");
+    loc.push(name);
+    fs::remove_dir_all(&loc).ok();
+    loc.push("audit.log");
+    loc
+}
+
+fn read_lines(loc: &path::Path) -> Vec<String> {
+    let text = fs::read_to_string(loc).unwrap();
+    text.lines().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn test_audit_record() {
+    let loc = make_loc("test_audit_record");
+    let audit = Audit::from_file(&loc);
+
+    let at = chrono::Utc.ymd(2020, 6, 1).and_hms(10, 0, 0);
+    audit.record_at(at, AuditAction::Push, "workspace\ntest").unwrap();
+    audit.record_at(at, AuditAction::Commit, "workspace test").unwrap();
+    assert_eq!(
+        read_lines(&loc),
+        vec![
+            "2020-06-01T10:00:00+00:00 push workspace test",
+            "2020-06-01T10:00:00+00:00 commit workspace test",
+        ]
+    );
+
+    // puts of entries and new commodities are audited, others are not.
+    let mut je = types::JournalEntry::new("grocer".to_string(), at);
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("inr".to_string(), 100.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "food".to_string(), ("inr".to_string(), 100.0))
+        .unwrap();
+    audit.record_put(None, &je).unwrap();
+    audit.record_put(Some(&je), &je).unwrap();
+    audit.record_delete(&je).unwrap();
+    let inr: types::Commodity = ("inr".to_string(), 1.0).into();
+    audit.record_put(None, &inr).unwrap();
+    audit.record_put(Some(&inr), &inr).unwrap();
+    audit.record_put(None, &types::Company::default()).unwrap();
+
+    let lines = read_lines(&loc);
+    assert_eq!(lines.len(), 6);
+    let actions: Vec<&str> = lines[2..]
+        .iter()
+        .map(|l| l.split(' ').nth(1).unwrap())
+        .collect();
+    assert_eq!(
+        actions,
+        vec!["entry-added", "entry-edited", "entry-deleted", "commodity-created"]
+    );
+    assert!(lines[2].ends_with(&je.summary()), "{}", lines[2]);
+
+    // no location, nothing is recorded.
+    Audit::default().record(AuditAction::Pull, "ignored").unwrap();
+}
@@ -1,48 +1,45 @@
 use llrb_index::Llrb;
 
-use std::ffi;
+use std::{collections::BTreeMap, ffi, path};
 
-use crate::{types, core::{Result, Transaction, Durable, Store}};
+use crate::{
+    types,
+    core::{Result, Transaction, Durable, Options, Progress, Store, SyncStatus},
+};
 
-struct Cache<S> where S: Store {
+// store wrapper, holding metadata and journal entries in memory, loaded
+// when the store is opened. Writes go through to the wrapped store.
+pub struct Cache<S> where S: Store {
     db: S,
-
-    commodities: Llrb<String, types::Commodity>,
-    companies: Llrb<String, types::Company>,
-    ledgers: Llrb<String, types::Ledger>,
-    entries: Llrb<String, types::JournalEntry>,
+    index: Index,
 }
 
 impl<S> Store for Cache<S> where S: Store {
     type Txn = CacheTransaction<S>;
 
-    fn create(dir: &ffi::OsStr, w: types::Workspace) -> Result<Self> {
-        let db = S::create(dir, w)?;
-        let mut c = Cache {
-            db,
-
-            commodities: Llrb::new("cache-commodities"),
-            companies: Llrb::new("cache-companies"),
-            ledgers: Llrb::new("cache-ledgers"),
-            entries: Llrb::new("cache-entries"),
-        };
-
-        c.load()?;
+    fn create_with(dir: &ffi::OsStr, w: types::Workspace, opts: Options) -> Result<Self> {
+        let db = S::create_with(dir, w, opts)?;
+        let mut c = Cache::new(db);
+        c.load(None)?;
         Ok(c)
     }
 
-    fn open(dir: &ffi::OsStr) -> Result<Self> {
-        let db = S::open(dir)?;
-        let mut c = Cache {
-            db,
+    fn list_workspaces(parent: &ffi::OsStr) -> Result<Vec<(String, path::PathBuf)>> {
+        S::list_workspaces(parent)
+    }
 
-            commodities: Llrb::new("cache-commodities"),
-            companies: Llrb::new("cache-companies"),
-            ledgers: Llrb::new("cache-ledgers"),
-            entries: Llrb::new("cache-entries"),
-        };
+    fn open_with(dir: &ffi::OsStr, opts: Options) -> Result<Self> {
+        Self::open_with_progress(dir, opts, None)
+    }
 
-        c.load()?;
+    fn open_with_progress(
+        dir: &ffi::OsStr,
+        opts: Options,
+        progress: Option<Progress>,
+    ) -> Result<Self> {
+        let db = S::open_with(dir, opts)?;
+        let mut c = Cache::new(db);
+        c.load(progress)?;
         Ok(c)
     }
 
@@ -50,7 +47,9 @@ impl<S> Store for Cache<S> where S: Store {
     where
         V: Durable
     {
-        self.db.put(value)
+        let old_value = self.db.put(value.clone())?;
+        self.index.set(&value)?;
+        Ok(old_value)
     }
 
     fn get<V>(&mut self, key: &str) -> Result<V>
@@ -64,7 +63,9 @@ impl<S> Store for Cache<S> where S: Store {
     where
         V: Durable
     {
-        self.db.delete(key)
+        let value: V = self.db.delete(key)?;
+        self.index.remove(&value.to_type(), key);
+        Ok(value)
     }
 
     fn iter<V>(&mut self) -> Result<Box<dyn Iterator<Item = Result<V>>>>
@@ -83,57 +84,201 @@ impl<S> Store for Cache<S> where S: Store {
         self.db.iter_journal(from, to)
     }
 
+    // entries are lent out from memory, without going to the wrapped store.
+    fn for_each_journal<F>(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&types::JournalEntry)
+    {
+        let mut entries: Vec<&types::JournalEntry> = self
+            .index
+            .entries
+            .values()
+            .filter(|e| e.created >= from && e.created <= to)
+            .collect();
+        entries.sort();
+
+        entries.into_iter().for_each(|e| f(e));
+        Ok(())
+    }
+
+    fn to_workspace(&mut self) -> Result<types::Workspace> {
+        self.db.to_workspace()
+    }
+
     fn commit(&mut self) -> Result<()> {
-        todo!()
+        self.db.commit()
+    }
+
+    fn is_modified(&self) -> bool {
+        self.db.is_modified()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.db.is_read_only()
+    }
+
+    fn mark_undo(&mut self) -> Result<()> {
+        self.db.mark_undo()
     }
 
-    fn pull(&mut self) -> Result<()> {
-        todo!()
+    // values reverted by undo are loaded afresh.
+    fn undo(&mut self) -> Result<bool> {
+        let undone = self.db.undo()?;
+        if undone {
+            self.reload()?;
+        }
+        Ok(undone)
     }
 
-    fn push(&mut self) -> Result<()> {
-        todo!()
+    // values pulled in are loaded afresh.
+    fn pull(&mut self) -> Result<SyncStatus> {
+        let status = self.db.pull()?;
+        if status.is_transferred() {
+            self.reload()?;
+        }
+        Ok(status)
+    }
+
+    fn push(&mut self) -> Result<SyncStatus> {
+        self.db.push()
     }
 
     fn begin(self) -> Result<Self::Txn> {
-        todo!()
+        Ok(CacheTransaction {
+            txn: self.db.begin()?,
+            index: self.index,
+        })
     }
 }
 
 impl<S> Cache<S> where S: Store {
-    fn load(&mut self) -> Result<()> {
+    pub fn new(db: S) -> Cache<S> {
+        Cache {
+            db,
+            index: Index::new(),
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.index = Index::new();
+        self.load(None)
+    }
+
+    fn load(&mut self, mut progress: Option<Progress>) -> Result<()> {
         let commodities = self.iter::<types::Commodity>()?;
         let companies = self.iter::<types::Company>()?;
         let ledgers = self.iter::<types::Ledger>()?;
         let entries = {
-            let from = chrono::Utc::now();
-            let to: chrono::DateTime<chrono::Utc> = chrono::Local::now().into();
+            let from = chrono::MIN_DATE.and_hms(0, 0, 0);
+            let to = chrono::MAX_DATE.and_hms(23, 59, 59);
             self.iter_journal(from, to)?
         };
 
+        let mut n = 0;
+        let mut tick = || {
+            n += 1;
+            match progress.as_mut() {
+                Some((every, callback)) if *every > 0 && n % *every == 0 => callback(n),
+                _ => (),
+            }
+        };
+
+        let index = &mut self.index;
         for c in commodities {
             let c = c?;
-            self.commodities.set(c.to_key(), c);
+            index.commodities.set(c.to_key(), c);
+            tick();
         }
         for c in companies {
             let c = c?;
-            self.companies.set(c.to_key(), c);
+            index.companies.set(c.to_key(), c);
+            tick();
         }
         for l in ledgers {
             let l = l?;
-            self.ledgers.set(l.to_key(), l);
+            index.ledgers.set(l.to_key(), l);
+            tick();
         }
         for e in entries {
             let e = e?;
-            self.entries.set(e.to_key(), e);
+            index.entries.insert(e.to_key(), e);
+            tick();
         }
 
         Ok(())
     }
 }
 
+// values held in memory, by key. Journal entries are lent out by
+// reference, refer for_each_journal().
+struct Index {
+    commodities: Llrb<String, types::Commodity>,
+    companies: Llrb<String, types::Company>,
+    ledgers: Llrb<String, types::Ledger>,
+    entries: BTreeMap<String, types::JournalEntry>,
+}
+
+impl Index {
+    fn new() -> Index {
+        Index {
+            commodities: Llrb::new("cache-commodities"),
+            companies: Llrb::new("cache-companies"),
+            ledgers: Llrb::new("cache-ledgers"),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    // values of other types are not held in memory.
+    fn set<V>(&mut self, value: &V) -> Result<()> where V: Durable {
+        let (key, data) = (value.to_key(), value.encode()?);
+        match value.to_type().as_str() {
+            "commodity" => {
+                let mut c = types::Commodity::default();
+                c.decode(&data)?;
+                self.commodities.set(key, c);
+            }
+            "company" => {
+                let mut c = types::Company::default();
+                c.decode(&data)?;
+                self.companies.set(key, c);
+            }
+            "ledger" => {
+                let mut l = types::Ledger::default();
+                l.decode(&data)?;
+                self.ledgers.set(key, l);
+            }
+            "journalentry" => {
+                let mut e = types::JournalEntry::default();
+                e.decode(&data)?;
+                self.entries.insert(key, e);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, typ: &str, key: &str) {
+        let key = key.to_string();
+        match typ {
+            "commodity" => { self.commodities.delete(&key); }
+            "company" => { self.companies.delete(&key); }
+            "ledger" => { self.ledgers.delete(&key); }
+            "journalentry" => { self.entries.remove(&key); }
+            _ => (),
+        }
+    }
+}
+
+// transaction on the wrapped store, the in-memory values follow its
+// writes and are handed back to the cache on end.
 pub struct CacheTransaction<S> where S: Store {
-    db: Cache<S>,
+    txn: S::Txn,
+    index: Index,
 }
 
 impl<S> Transaction<Cache<S>> for CacheTransaction<S> where S: Store {
@@ -141,28 +286,32 @@ impl<S> Transaction<Cache<S>> for CacheTransaction<S> where S: Store {
     where
         V: Durable,
     {
-        self.db.put(value)
+        let old_value = self.txn.put(value.clone())?;
+        self.index.set(&value)?;
+        Ok(old_value)
     }
 
     fn get<V>(&mut self, key: &str) -> Result<V>
     where
         V: Durable,
     {
-        self.db.get(key)
+        self.txn.get(key)
     }
 
     fn delete<V>(&mut self, key: &str) -> Result<V>
     where
         V: Durable,
     {
-        self.db.delete(key)
+        let value: V = self.txn.delete(key)?;
+        self.index.remove(&value.to_type(), key);
+        Ok(value)
     }
 
     fn iter<V>(&mut self) -> Result<Box<dyn Iterator<Item = Result<V>>>>
     where
         V: 'static + Durable,
     {
-        self.db.iter()
+        self.txn.iter()
     }
 
     fn iter_journal(
@@ -170,10 +319,17 @@ impl<S> Transaction<Cache<S>> for CacheTransaction<S> where S: Store {
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>> {
-        self.db.iter_journal(from, to)
+        self.txn.iter_journal(from, to)
     }
 
     fn end(self) -> Result<Cache<S>> {
-        Ok(self.db)
+        Ok(Cache {
+            db: self.txn.end()?,
+            index: self.index,
+        })
     }
 }
+
+#[cfg(test)]
+#[path = "cache_test.rs"]
+mod cache_test;
@@ -0,0 +1,140 @@
+use super::*;
+use crate::mem_store::testing;
+
+#[test]
+fn test_cache_load_progress() {
+    let mut store = testing::make_store();
+    for i in 0..7 {
+        let value: types::Commodity = (format!("c{}", i), i as f64).into();
+        store.put(value).unwrap();
+    }
+
+    let mut counts = vec![];
+    let mut callback = |n: usize| counts.push(n);
+    let mut cache = Cache::new(store);
+    cache.load(Some((2, &mut callback))).unwrap();
+    assert_eq!(counts, vec![2, 4, 6]);
+    assert_eq!(cache.index.commodities.len(), 7);
+
+    // no progress for zero `every`.
+    let mut calls = 0;
+    let mut callback = |_: usize| calls += 1;
+    cache.load(Some((0, &mut callback))).unwrap();
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn test_cache_write_through() {
+    use chrono::TimeZone;
+
+    let mut store = testing::make_store();
+    let created = chrono::Utc.ymd(2001, 1, 1).and_hms(9, 0, 0);
+    store.put(types::JournalEntry::new("rent".to_string(), created)).unwrap();
+
+    // entries are loaded irrespective of their date.
+    let mut cache = Cache::new(store);
+    cache.load(None).unwrap();
+    assert_eq!(cache.index.entries.len(), 1);
+
+    let value: types::Commodity = ("usd".to_string(), 1.0).into();
+    cache.put(value).unwrap();
+    assert_eq!(cache.index.commodities.len(), 1);
+    cache.delete::<types::Commodity>("commodity-usd").unwrap();
+    assert_eq!(cache.index.commodities.len(), 0);
+
+    let mut txn = cache.begin().unwrap();
+    let value: types::Commodity = ("inr".to_string(), 1.0).into();
+    txn.put(value).unwrap();
+    let cache = txn.end().unwrap();
+    assert_eq!(cache.index.commodities.len(), 1);
+}
+
+// begin and end through the Store and Transaction traits, the
+// transaction is consumed by end and the store is handed back.
+fn txn_round_trip<S>(store: S, name: &str) -> S
+where
+    S: Store,
+{
+    let mut txn = store.begin().unwrap();
+    let value: types::Commodity = (name.to_string(), 1.0).into();
+    txn.put(value).unwrap();
+    let mut store = txn.end().unwrap();
+    let value: types::Commodity = store.get(&format!("commodity-{}", name)).unwrap();
+    assert_eq!(value.name, name);
+    store
+}
+
+#[test]
+fn test_transaction_begin_end() {
+    let store = testing::make_store();
+    let store = txn_round_trip(store, "usd");
+
+    txn_round_trip(Cache::new(store), "inr");
+}
+
+#[test]
+fn test_for_each_journal() {
+    use chrono::TimeZone;
+
+    let mut store = testing::make_store();
+    for (payee, day) in [("rent", 3), ("grocer", 1), ("fuel", 20), ("cafe", 9)].iter() {
+        let created = chrono::Utc.ymd(2020, 6, *day).and_hms(9, 0, 0);
+        let je = testing::make_entry(payee, created, "cash", "misc", ("inr", 10.0));
+        store.put(je).unwrap();
+    }
+    let key = {
+        let created = chrono::Utc.ymd(2020, 6, 9).and_hms(9, 0, 0);
+        let entries: Vec<types::JournalEntry> = store
+            .iter_journal(created, created)
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect();
+        entries[0].to_key()
+    };
+    store.delete::<types::JournalEntry>(&key).unwrap();
+
+    let from = chrono::Utc.ymd(2020, 6, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2020, 6, 10).and_hms(0, 0, 0);
+    let expected: Vec<String> = store
+        .iter_journal(from, to)
+        .unwrap()
+        .map(|e| e.unwrap().payee)
+        .collect();
+    assert_eq!(expected, vec!["grocer".to_string(), "rent".to_string()]);
+
+    // entries are lent to the callback, in the same order as iter_journal.
+    let mut payees = vec![];
+    let mut total = 0.0;
+    store
+        .for_each_journal(from, to, |e: &types::JournalEntry| {
+            payees.push(e.payee.clone());
+            total += e.debitors[0].commodity.value;
+        })
+        .unwrap();
+    assert_eq!(payees, expected);
+    assert_eq!(total, 20.0);
+
+    let mut cache = Cache::new(store);
+    cache.load(None).unwrap();
+    let mut seen = vec![];
+    cache
+        .for_each_journal(from, to, |e| seen.push(e.payee.clone()))
+        .unwrap();
+    assert_eq!(seen, expected);
+
+    // cache lends the entries it holds, without cloning them.
+    let mut lent = vec![];
+    cache
+        .for_each_journal(from, to, |e: &types::JournalEntry| {
+            lent.push(e as *const types::JournalEntry)
+        })
+        .unwrap();
+    let held: Vec<*const types::JournalEntry> = cache
+        .index
+        .entries
+        .values()
+        .map(|e| e as *const types::JournalEntry)
+        .collect();
+    assert_eq!(lent.len(), 2);
+    assert!(lent.iter().all(|e| held.contains(e)));
+}
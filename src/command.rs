@@ -0,0 +1,151 @@
+use chrono;
+
+use std::{fmt, result};
+
+use ledger::{
+    core::{Error, Result},
+    err_at, util,
+};
+
+// display format for dates in command line, EG: `goto 01-Apr-24`, refer
+// util::parse_date_flexible() for accepted input.
+pub const DATE_FORMAT: &'static str = "%d-%b-%y";
+
+// commands typed in the status line after a `:`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Commit,
+    Push,
+    Pull,
+    NewEntry,
+    Goto(Option<chrono::NaiveDate>),
+    Workspaces,
+    Issues,
+    Entries,
+    Ledgers,
+    Calendar,
+    Recent,
+    // (commodity, rate) quotes, value of one unit in the base commodity.
+    UpdateRates(Vec<(String, f64)>),
+    // add a journal entry from the named template.
+    Template(String),
+    // (company, ledger) pre-filled in the first credit leg of new
+    // entries, None to clear it.
+    DefaultLedger(Option<(String, String)>),
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            Command::Commit => write!(f, "commit"),
+            Command::Push => write!(f, "push"),
+            Command::Pull => write!(f, "pull"),
+            Command::NewEntry => write!(f, "new entry"),
+            Command::Goto(Some(date)) => write!(f, "goto {}", date.format(DATE_FORMAT)),
+            Command::Goto(None) => write!(f, "goto"),
+            Command::Workspaces => write!(f, "workspaces"),
+            Command::Issues => write!(f, "issues"),
+            Command::Entries => write!(f, "entries"),
+            Command::Ledgers => write!(f, "ledgers"),
+            Command::Calendar => write!(f, "calendar"),
+            Command::Recent => write!(f, "recent"),
+            Command::UpdateRates(quotes) => {
+                write!(f, "update rates")?;
+                for (name, rate) in quotes.iter() {
+                    write!(f, " {}={}", name, rate)?;
+                }
+                Ok(())
+            }
+            Command::Template(name) => write!(f, "template {}", name),
+            Command::DefaultLedger(Some((company, ledger))) => {
+                write!(f, "default ledger {}:{}", company, ledger)
+            }
+            Command::DefaultLedger(None) => write!(f, "default ledger"),
+        }
+    }
+}
+
+impl Command {
+    // parse command text, without the leading `:`.
+    pub fn parse(text: &str) -> Result<Command> {
+        let args: Vec<&str> = text.split_whitespace().collect();
+        match args.as_slice() {
+            [] => err_at!(InvalidInput, msg: format!("empty command")),
+            ["commit"] => Ok(Command::Commit),
+            ["push"] => Ok(Command::Push),
+            ["pull"] => Ok(Command::Pull),
+            ["workspaces"] => Ok(Command::Workspaces),
+            ["issues"] => Ok(Command::Issues),
+            ["entries"] => Ok(Command::Entries),
+            ["ledgers"] => Ok(Command::Ledgers),
+            ["calendar"] => Ok(Command::Calendar),
+            ["recent"] => Ok(Command::Recent),
+            ["new", "entry"] => Ok(Command::NewEntry),
+            ["update", "rates"] => {
+                err_at!(InvalidInput, msg: format!("update rates expects name=rate"))
+            }
+            ["update", "rates", quotes @ ..] => Ok(Command::UpdateRates(parse_quotes(quotes)?)),
+            ["goto", date] => Ok(Command::Goto(Some(util::parse_date_flexible(date)?))),
+            ["goto"] => Ok(Command::Goto(None)),
+            ["template", name] => Ok(Command::Template(name.to_string())),
+            ["template"] => err_at!(InvalidInput, msg: format!("template expects a name")),
+            ["default", "ledger"] => Ok(Command::DefaultLedger(None)),
+            ["default", "ledger", name] => Ok(Command::DefaultLedger(Some(parse_ledger(name)?))),
+            ["default"] | ["default", _] => {
+                err_at!(InvalidInput, msg: format!("default expects `ledger`"))
+            }
+            ["new"] | ["new", _] => err_at!(InvalidInput, msg: format!("new expects `entry`")),
+            ["update"] | ["update", _] => {
+                err_at!(InvalidInput, msg: format!("update expects `rates`"))
+            }
+            [cmd @ "commit", ..]
+            | [cmd @ "push", ..]
+            | [cmd @ "pull", ..]
+            | [cmd @ "new", ..]
+            | [cmd @ "update", ..]
+            | [cmd @ "goto", ..]
+            | [cmd @ "workspaces", ..]
+            | [cmd @ "issues", ..]
+            | [cmd @ "entries", ..]
+            | [cmd @ "ledgers", ..]
+            | [cmd @ "calendar", ..]
+            | [cmd @ "recent", ..]
+            | [cmd @ "template", ..]
+            | [cmd @ "default", ..] => {
+                let msg = format!("too many arguments for {:?}", cmd);
+                err_at!(InvalidInput, msg: msg)
+            }
+            [cmd, ..] => err_at!(InvalidInput, msg: format!("unknown command {:?}", cmd)),
+        }
+    }
+}
+
+// parse `company:ledger`, EG: `acme:checking`.
+fn parse_ledger(arg: &str) -> Result<(String, String)> {
+    let valid = |s: &str| !s.is_empty() && util::str_as_anuh(s);
+    match arg.split(':').collect::<Vec<&str>>().as_slice() {
+        [company, ledger] if valid(company) && valid(ledger) => {
+            Ok((company.to_string(), ledger.to_string()))
+        }
+        _ => err_at!(InvalidInput, msg: format!("bad ledger {:?}, expects company:ledger", arg)),
+    }
+}
+
+// parse `name=rate` quotes, EG: `usd=82.5`.
+fn parse_quotes(args: &[&str]) -> Result<Vec<(String, f64)>> {
+    let mut quotes = vec![];
+    for arg in args.iter() {
+        let mut parts = arg.splitn(2, '=');
+        let (name, rate) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let rate: f64 = match rate.parse() {
+            Ok(rate) if !name.is_empty() && rate > 0.0 => rate,
+            _ => err_at!(InvalidInput, msg: format!("bad rate {:?}, expects name=rate", arg))?,
+        };
+        quotes.push((name.to_string(), rate));
+    }
+    Ok(quotes)
+}
+
+#[cfg(test)]
+#[path = "command_test.rs"]
+mod command_test;
@@ -0,0 +1,81 @@
+use super::*;
+
+#[test]
+fn test_command_parse() {
+    assert_eq!(Command::parse("commit").unwrap(), Command::Commit);
+    assert_eq!(Command::parse("  push ").unwrap(), Command::Push);
+    assert_eq!(Command::parse("pull").unwrap(), Command::Pull);
+    assert_eq!(Command::parse("new  entry").unwrap(), Command::NewEntry);
+    assert_eq!(Command::parse("workspaces").unwrap(), Command::Workspaces);
+    assert_eq!(Command::parse("issues").unwrap(), Command::Issues);
+    assert_eq!(Command::parse("entries").unwrap(), Command::Entries);
+    assert_eq!(Command::parse("ledgers").unwrap(), Command::Ledgers);
+    assert_eq!(Command::parse("calendar").unwrap(), Command::Calendar);
+    assert_eq!(Command::parse("recent").unwrap(), Command::Recent);
+    let cmd = Command::parse("template rent").unwrap();
+    assert_eq!(cmd, Command::Template("rent".to_string()));
+    assert_eq!(cmd.to_string(), "template rent");
+    assert!(Command::parse("template").is_err());
+    let cmd = Command::parse("default ledger acme:checking").unwrap();
+    let ledger = ("acme".to_string(), "checking".to_string());
+    assert_eq!(cmd, Command::DefaultLedger(Some(ledger)));
+    assert_eq!(cmd.to_string(), "default ledger acme:checking");
+    assert_eq!(Command::parse("default ledger").unwrap(), Command::DefaultLedger(None));
+    assert!(Command::parse("default ledger checking").is_err());
+    assert!(Command::parse("default ledger acme:").is_err());
+    let cmd = Command::parse("update rates usd=82.5 eur=90").unwrap();
+    let quotes = vec![("usd".to_string(), 82.5), ("eur".to_string(), 90.0)];
+    assert_eq!(cmd, Command::UpdateRates(quotes));
+    assert_eq!(cmd.to_string(), "update rates usd=82.5 eur=90");
+
+    let date = chrono::NaiveDate::from_ymd(2024, 4, 1);
+    let cmd = Command::parse("goto 01-Apr-24").unwrap();
+    assert_eq!(cmd, Command::Goto(Some(date)));
+    assert_eq!(cmd.to_string(), "goto 01-Apr-24");
+    let date = Some(date);
+    assert_eq!(Command::parse("goto 2024-04-01").unwrap(), Command::Goto(date));
+    assert_eq!(Command::parse("goto 01/04/2024").unwrap(), Command::Goto(date));
+    let cmd = Command::parse("goto").unwrap();
+    assert_eq!(cmd, Command::Goto(None));
+    assert_eq!(cmd.to_string(), "goto");
+}
+
+#[test]
+fn test_command_parse_bad() {
+    match Command::parse("") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("empty"), "{}", msg),
+        _ => panic!("expected empty command"),
+    }
+    match Command::parse("fetch") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("unknown"), "{}", msg),
+        _ => panic!("expected unknown command"),
+    }
+    match Command::parse("commit now") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("too many"), "{}", msg),
+        _ => panic!("expected too many arguments"),
+    }
+    match Command::parse("new ledger") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("entry"), "{}", msg),
+        _ => panic!("expected new entry"),
+    }
+    match Command::parse("update ledgers") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("rates"), "{}", msg),
+        _ => panic!("expected update rates"),
+    }
+    match Command::parse("update rates") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("name=rate"), "{}", msg),
+        _ => panic!("expected missing quotes"),
+    }
+    match Command::parse("update rates usd=abc") {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("usd=abc"), "{}", msg),
+        _ => panic!("expected bad rate"),
+    }
+    match Command::parse("goto tomorrow") {
+        Err(Error::InvalidDate(_)) => (),
+        _ => panic!("expected invalid date"),
+    }
+    match Command::parse("goto 31-Feb-24") {
+        Err(Error::InvalidDate(_)) => (),
+        _ => panic!("expected invalid date"),
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ffi, fmt, result};
+use std::{ffi, fmt, path, result};
 
 use crate::types;
 
@@ -10,18 +10,64 @@ pub trait Durable: Default + Clone {
     fn to_type(&self) -> String;
     // a unique key across all values of any type.
     fn to_key(&self) -> String;
+    // one line description of the value, for lists and pickers.
+    fn summary(&self) -> String {
+        self.to_key()
+    }
     // serialize data-value or report-value that can be persisted.
     fn encode(&self) -> Result<String>;
     // de-serialize data-value or report-value from bytes.
     fn decode(&mut self, from: &str) -> Result<()>;
 }
 
+// typed key, that can only be used to fetch values of type `Value`.
+pub trait TypedKey: fmt::Display {
+    type Value: Durable;
+}
+
+// options a store is created or opened with, for the lifetime of the
+// store.
+#[derive(Clone, Default)]
+pub struct Options {
+    // allow ledger groups outside the workspace's chart of accounts.
+    pub custom_groups: bool,
+    // open read-only, without resetting, migrating or committing.
+    pub safe: bool,
+    // commit after every put and delete, else on explicit commit.
+    pub autocommit: bool,
+}
+
+// progress callback for loading a store, (every, callback). Callback is
+// invoked with the number of records loaded so far, once every `every`
+// records.
+pub type Progress<'a> = (usize, &'a mut dyn FnMut(usize));
+
 pub trait Store: Sized {
     type Txn: Transaction<Self>;
 
-    fn create(dir: &ffi::OsStr, w: types::Workspace) -> Result<Self>;
+    fn create(dir: &ffi::OsStr, w: types::Workspace) -> Result<Self> {
+        Self::create_with(dir, w, Options::default())
+    }
+
+    fn create_with(dir: &ffi::OsStr, w: types::Workspace, opts: Options) -> Result<Self>;
+
+    fn open(dir: &ffi::OsStr) -> Result<Self> {
+        Self::open_with(dir, Options::default())
+    }
+
+    fn open_with(dir: &ffi::OsStr, opts: Options) -> Result<Self>;
 
-    fn open(dir: &ffi::OsStr) -> Result<Self>;
+    // same as open_with(), stores loading values on open report progress.
+    fn open_with_progress(
+        dir: &ffi::OsStr,
+        opts: Options,
+        _progress: Option<Progress>,
+    ) -> Result<Self> {
+        Self::open_with(dir, opts)
+    }
+
+    // list workspaces, as (name, dir), found under `parent` directory.
+    fn list_workspaces(parent: &ffi::OsStr) -> Result<Vec<(String, path::PathBuf)>>;
 
     fn put<V>(&mut self, value: V) -> Result<Option<V>>
     where
@@ -31,6 +77,14 @@ pub trait Store: Sized {
     where
         V: Durable;
 
+    // same as get(), but value's type is implied by the key's type.
+    fn get_by<K>(&mut self, key: &K) -> Result<K::Value>
+    where
+        K: TypedKey,
+    {
+        self.get(&key.to_string())
+    }
+
     fn delete<V>(&mut self, key: &str) -> Result<V>
     where
         V: Durable;
@@ -45,12 +99,55 @@ pub trait Store: Sized {
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>>;
 
+    // same as iter_journal(), but entries are passed to `f` by reference.
+    // Stores holding entries in memory pass them without cloning, others
+    // fall back on iter_journal().
+    fn for_each_journal<F>(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&types::JournalEntry),
+    {
+        for entry in self.iter_journal(from, to)? {
+            f(&entry?);
+        }
+        Ok(())
+    }
+
+    fn to_workspace(&mut self) -> Result<types::Workspace>;
+
     fn commit(&mut self) -> Result<()>;
 
-    fn pull(&mut self) -> Result<()>;
+    // whether there are puts or deletes yet to be committed.
+    fn is_modified(&self) -> bool {
+        false
+    }
+
+    // whether writes are refused, EG: workspace opened in safe mode.
+    fn is_read_only(&self) -> bool {
+        false
+    }
 
-    fn push(&mut self) -> Result<()>;
+    // remember the store's current state, so that changes made after it
+    // can be reverted with undo(). Stores without history ignore this.
+    fn mark_undo(&mut self) -> Result<()> {
+        Ok(())
+    }
 
+    // revert to the state of the last mark_undo(), return false if there
+    // is nothing to undo.
+    fn undo(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn pull(&mut self) -> Result<SyncStatus>;
+
+    fn push(&mut self) -> Result<SyncStatus>;
+
+    // begin consumes the store, which is handed back by Transaction::end.
     fn begin(self) -> Result<Self::Txn>;
 }
 
@@ -66,6 +163,13 @@ where
     where
         V: Durable;
 
+    fn get_by<K>(&mut self, key: &K) -> Result<K::Value>
+    where
+        K: TypedKey,
+    {
+        self.get(&key.to_string())
+    }
+
     fn delete<V>(&mut self, key: &str) -> Result<V>
     where
         V: Durable;
@@ -80,14 +184,49 @@ where
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>>;
 
+    // end consumes the transaction and returns the store it began on.
     fn end(self) -> Result<S>;
 }
 
+// outcome of a pull or push, for the user to see what was transferred.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncStatus {
+    // number of commits transferred, or that would have been when there
+    // are conflicts.
+    pub commits: usize,
+    // pull moved HEAD forward without a merge commit, push always does.
+    pub fast_forward: bool,
+    // files in conflict, nothing is transferred when not empty.
+    pub conflicts: Vec<String>,
+}
+
+impl fmt::Display for SyncStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let plural = |n: usize, noun: &str| match n {
+            1 => format!("1 {}", noun),
+            n => format!("{} {}s", n, noun),
+        };
+        match (self.conflicts.len(), self.commits, self.fast_forward) {
+            (0, 0, _) => write!(f, "already up to date"),
+            (0, n, true) => write!(f, "{}, fast-forward", plural(n, "commit")),
+            (0, n, false) => write!(f, "{}, merged", plural(n, "commit")),
+            (n, _, _) => write!(f, "{}, {}", plural(n, "conflict"), self.conflicts.join(", ")),
+        }
+    }
+}
+
+impl SyncStatus {
+    // whether any commit moved between the local and remote stores.
+    pub fn is_transferred(&self) -> bool {
+        self.conflicts.is_empty() && self.commits > 0
+    }
+}
+
 pub trait Reduce<T> {
     fn reduce(&mut self, doc: &T) -> Result<()>;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Error {
     KeyNotFound(String),
     Fatal(String),
@@ -1,16 +1,118 @@
 use chrono::{self, offset::TimeZone, Datelike};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use git2;
-use log::trace;
-
-use std::{ffi, fs, path};
+use jsondata::Json;
+use log::{debug, trace, warn};
+use tar;
+
+use std::{
+    ffi, fmt, fs,
+    io::{Read, Write},
+    path, result,
+    str::FromStr,
+    time,
+};
 
 use crate::{
-    core::{Durable, Error, Result, Store, Transaction},
-    types,
+    aggregates,
+    core::{Durable, Error, Options, Result, Store, SyncStatus, Transaction},
+    types, util,
 };
 
 // TODO: add git description.
 
+// run git operation `op` and log its timing and outcome at debug level,
+// `oids` describes a successful outcome, EG:
+// `git op=commit elapsed=1.2ms oids=3f2a..->9c1e..` or
+// `git op=push elapsed=3.4ms err=...`.
+fn git_op<T, F, O>(op: &str, f: F, oids: O) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+    O: FnOnce(&T) -> String,
+{
+    let start = time::Instant::now();
+    let res = f();
+    let elapsed = start.elapsed();
+    match &res {
+        Ok(val) => debug!("git op={} elapsed={:?} oids={}", op, elapsed, oids(val)),
+        Err(err) => debug!("git op={} elapsed={:?} err={}", op, elapsed, err),
+    }
+    res
+}
+
+// squash commits in `repo`, refer Db::compact(). Return the old and new
+// HEAD, None if there was nothing to squash.
+fn compact_repo(
+    repo: &git2::Repository,
+    keep_recent: usize,
+) -> Result<Option<(git2::Oid, git2::Oid)>> {
+    let mut walk = err_at!(IOError, repo.revwalk(), format!("git revwalk"))?;
+    err_at!(IOError, walk.push_head(), format!("git push_head"))?;
+    err_at!(
+        IOError,
+        walk.simplify_first_parent(),
+        format!("git first_parent")
+    )?;
+    let mut commits = vec![];
+    for oid in walk {
+        let oid = err_at!(IOError, oid, format!("git revwalk"))?;
+        commits.push(err_at!(
+            IOError,
+            repo.find_commit(oid),
+            format!("git find_commit")
+        )?);
+    }
+    // newest first, nothing to squash.
+    if commits.len() <= (keep_recent + 1) {
+        return Ok(None);
+    }
+
+    let base = &commits[keep_recent];
+    let tree = err_at!(IOError, base.tree(), format!("git tree"))?;
+    let message = format!("compact {} commits", commits.len() - keep_recent);
+    let mut tip_oid = err_at!(
+        IOError,
+        repo.commit(
+            None, /*update_ref*/
+            &base.author(),
+            &base.committer(),
+            &message,
+            &tree,
+            &[],
+        ),
+        format!("git commit")
+    )?;
+    for commit in commits[..keep_recent].iter().rev() {
+        let parent = err_at!(
+            IOError,
+            repo.find_commit(tip_oid),
+            format!("git find_commit")
+        )?;
+        let tree = err_at!(IOError, commit.tree(), format!("git tree"))?;
+        tip_oid = err_at!(
+            IOError,
+            repo.commit(
+                None, /*update_ref*/
+                &commit.author(),
+                &commit.committer(),
+                commit.message().unwrap_or(""),
+                &tree,
+                &[&parent],
+            ),
+            format!("git commit")
+        )?;
+    }
+
+    let mut head = err_at!(IOError, repo.head(), format!("git head"))?;
+    err_at!(
+        IOError,
+        head.set_target(tip_oid, &message),
+        format!("git set_target")
+    )?;
+
+    Ok(Some((commits[0].id(), tip_oid)))
+}
+
 #[derive(Clone)]
 pub struct FileLoc(ffi::OsString);
 
@@ -69,8 +171,20 @@ impl FileLoc {
     where
         V: Durable,
     {
-        let old_value = self.to_value().ok();
-        err_at!(IOError, fs::write(&self.0, value.encode()?.as_bytes()))?;
+        let old_value: Option<V> = self.to_value().ok();
+        let data = value.encode()?;
+
+        // touch-only updates, where nothing but the `updated` timestamp
+        // differs, are not written back to disk.
+        if let Some(old_value) = &old_value {
+            if is_touch_only(&old_value.encode()?, &data)? {
+                trace!("skip touch-only put {:?}", self.0);
+                return Ok(Some(old_value.clone()));
+            }
+        }
+
+        let data = with_final_newline(&data);
+        err_at!(IOError, fs::write(&self.0, data.as_bytes()))?;
 
         Ok(old_value)
     }
@@ -100,8 +214,12 @@ impl FileLoc {
         let mut value: V = Default::default();
         let typ = value.to_type();
         let data = err_at!(IOError, fs::read(&self.0), typ)?;
-        let s = err_at!(InvalidJson, std::str::from_utf8(&data), typ)?;
-        value.decode(s)?;
+        let s = err_at!(
+            InvalidFile,
+            std::str::from_utf8(&data),
+            format!("{} not utf8 {:?}", typ, self.0)
+        )?;
+        value.decode(s.trim_end())?;
         Ok(value)
     }
 }
@@ -112,14 +230,313 @@ impl From<FileLoc> for ffi::OsString {
     }
 }
 
+// list workspaces under `parent`, each workspace is a sub-directory
+// with a `workspace.json` file. Sorted by workspace name.
+pub fn list_workspaces(parent: &ffi::OsStr) -> Result<Vec<(String, path::PathBuf)>> {
+    let mut workspaces = vec![];
+    for entry in err_at!(IOError, fs::read_dir(parent), format!("{:?}", parent))? {
+        let dir = err_at!(IOError, entry)?.path();
+        let file_loc = FileLoc::from_key(dir.as_os_str(), "workspace");
+        if dir.is_dir() && path::Path::new(&file_loc.0).is_file() {
+            let w: types::Workspace = file_loc.to_value()?;
+            workspaces.push((w.name, dir));
+        }
+    }
+    workspaces.sort();
+
+    Ok(workspaces)
+}
+
+// summary of a git commit, refer Db::file_history().
+#[derive(Clone, Debug)]
+pub struct CommitInfo {
+    pub oid: String,
+    pub author: String,
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub message: String,
+}
+
+impl<'a> From<&git2::Commit<'a>> for CommitInfo {
+    fn from(commit: &git2::Commit<'a>) -> CommitInfo {
+        CommitInfo {
+            oid: commit.id().to_string(),
+            author: commit.author().name().unwrap_or("").to_string(),
+            time: chrono::Utc.timestamp(commit.time().seconds(), 0),
+            message: commit.message().unwrap_or("").to_string(),
+        }
+    }
+}
+
+// step that transforms a workspace's data on upgrading to `to_version()`.
+// Versions that only add fields need no step, missing fields take their
+// default on decode, refer types::workspace_fields(). Db::migrate() bumps
+// the version and commits.
+pub trait Migration {
+    fn to_version(&self) -> u64;
+
+    fn migrate(&self, db: &mut Db) -> Result<()>;
+}
+
+// registered migrations, in version order.
+pub fn migrations() -> Vec<Box<dyn Migration>> {
+    vec![Box::new(AddWorkspaceCreated)]
+}
+
+// version 5, workspace.json carries `created`, the time of the first
+// commit for existing workspaces.
+struct AddWorkspaceCreated;
+
+impl Migration for AddWorkspaceCreated {
+    fn to_version(&self) -> u64 {
+        5
+    }
+
+    fn migrate(&self, db: &mut Db) -> Result<()> {
+        let created = {
+            let repo = db.repo()?;
+            let mut walk = err_at!(IOError, repo.revwalk(), format!("git revwalk"))?;
+            err_at!(IOError, walk.push_head(), format!("git push_head"))?;
+            let sorting = git2::Sort::TIME | git2::Sort::REVERSE;
+            err_at!(IOError, walk.set_sorting(sorting), format!("git sorting"))?;
+
+            let first = match walk.next() {
+                Some(oid) => err_at!(IOError, oid, format!("git revwalk"))?,
+                None => return Ok(()),
+            };
+            let commit = err_at!(IOError, repo.find_commit(first), format!("git find_commit"))?;
+            chrono::Utc.timestamp(commit.time().seconds(), 0)
+        };
+        db.w.set_created(created);
+        // workspace is re-written after bump.
+        Ok(())
+    }
+}
+
+// undo marks remembered for a session, older marks are dropped.
+pub const UNDO_DEPTH: usize = 64;
+
+// advisory lock file, held while a workspace is open.
+pub const LOCK_FILE: &'static str = ".ledger47.lock";
+
+// lock on workspace directory, the lock file is created exclusively and
+// removed when the lock is dropped. Lock file records its holder as
+// `pid@host`, a lock left behind by a dead process on this host is
+// reclaimed.
+pub struct WorkspaceLock(path::PathBuf);
+
+impl WorkspaceLock {
+    fn acquire(dir: &ffi::OsStr) -> Result<WorkspaceLock> {
+        let mut loc = path::PathBuf::from(dir);
+        loc.push(LOCK_FILE);
+        let holder = format!("{}@{}", std::process::id(), to_hostname());
+        let res = match Self::create(&loc, &holder) {
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists && Self::is_stale(&loc) => {
+                warn!("reclaim stale lock {:?}", loc);
+                fs::remove_file(&loc).ok();
+                Self::create(&loc, &holder)
+            }
+            res => res,
+        };
+        match res {
+            Ok(_) => Ok(WorkspaceLock(loc)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                let holder = fs::read_to_string(&loc).unwrap_or_default();
+                let msg = format!(
+                    "workspace already open {:?}, held by {:?}, lock {:?}",
+                    dir,
+                    holder.trim(),
+                    loc
+                );
+                err_at!(IOError, msg: msg)
+            }
+            Err(err) => err_at!(IOError, msg: format!("{:?} {}", loc, err)),
+        }
+    }
+
+    fn create(loc: &path::Path, holder: &str) -> std::io::Result<()> {
+        let mut fd = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(loc)?;
+        let res = fd.write_all(holder.as_bytes());
+        if res.is_err() {
+            fs::remove_file(loc).ok();
+        }
+        res
+    }
+
+    // lock held by a process that no longer runs on this host. Locks
+    // from other hosts, or without a holder, are never stale.
+    fn is_stale(loc: &path::Path) -> bool {
+        let data = match fs::read_to_string(loc) {
+            Ok(data) => data,
+            Err(_) => return false,
+        };
+        let mut parts = data.trim().splitn(2, '@');
+        let pid = parts.next().and_then(|pid| pid.parse::<u32>().ok());
+        match (pid, parts.next()) {
+            (Some(pid), Some(host)) if host == to_hostname() => !is_process_alive(pid),
+            _ => false,
+        }
+    }
+}
+
+fn to_hostname() -> String {
+    match fs::read_to_string("/etc/hostname") {
+        Ok(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_process_alive(pid: u32) -> bool {
+    path::Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+// without a way to probe, every holder is assumed to be alive.
+#[cfg(not(target_os = "linux"))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+// layout of journal entries under the workspace's `journal/` directory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JournalLayout {
+    // one file per entry, `journal/YYYY/M/D/<key>.json`.
+    Files,
+    // one append-only NDJSON log per month, `journal/YYYY/MM.ndjson`.
+    MonthLog,
+}
+
+impl Default for JournalLayout {
+    fn default() -> JournalLayout {
+        JournalLayout::Files
+    }
+}
+
+impl fmt::Display for JournalLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            JournalLayout::Files => write!(f, "files"),
+            JournalLayout::MonthLog => write!(f, "month-log"),
+        }
+    }
+}
+
+impl FromStr for JournalLayout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<JournalLayout> {
+        match s.trim() {
+            "files" => Ok(JournalLayout::Files),
+            "month-log" => Ok(JournalLayout::MonthLog),
+            _ => err_at!(InvalidInput, msg: format!("invalid journal layout {:?}", s)),
+        }
+    }
+}
+
 pub struct Db {
     dir: ffi::OsString,
     w: types::Workspace,
     repo: Option<git2::Repository>,
     remotes: Vec<git2::Repository>,
+    // number of files touched while in batch mode.
+    batch: Option<usize>,
+    lock: Option<WorkspaceLock>,
+    // uncommitted puts or deletes.
+    modified: bool,
+    // keys of journal entries put since the last commit.
+    staged: Vec<String>,
+    journal_layout: JournalLayout,
+    // commits to reset to on undo, most recent last.
+    undo: Vec<git2::Oid>,
+    opts: Options,
+}
+
+// outcome of pulling from one remote, `oid` is the commit to move HEAD
+// to, None when up to date or in conflict.
+#[derive(Default)]
+struct Pulled {
+    oid: Option<git2::Oid>,
+    commits: usize,
+    fast_forward: bool,
+    conflicts: Vec<String>,
 }
 
 impl Db {
+    // Run `f` in batch mode, where commits are deferred and the store
+    // is committed once, after `f` returns. Return the number of files
+    // put or deleted within the batch.
+    pub fn batch<F>(&mut self, f: F) -> Result<usize>
+    where
+        F: FnOnce(&mut Db) -> Result<()>,
+    {
+        self.batch = Some(0);
+        let res = f(self);
+        let n = self.batch.take().unwrap_or(0);
+        res?;
+
+        let (oh_oid, nh_oid) = self.do_commit(&format!("batch commit {} files", n))?;
+        trace!("git batch-commit {}->{} files:{}", oh_oid, nh_oid, n);
+
+        Ok(n)
+    }
+
+    pub fn set_autocommit(&mut self, autocommit: bool) -> &mut Self {
+        self.opts.autocommit = autocommit;
+        self
+    }
+
+    // delete commodity `name`, refuse if it is the workspace's base
+    // commodity or is referenced by any journal entry, unless `force`.
+    pub fn delete_commodity(&mut self, name: &str, force: bool) -> Result<types::Commodity> {
+        if !force {
+            let mut refs = vec![];
+            if self.w.to_base_commodity() == name {
+                refs.push(format!("workspace {}", self.w.name));
+            }
+            // every date, and years archived out of the journal.
+            let from = chrono::MIN_DATE.and_hms(0, 0, 0);
+            let to = chrono::MAX_DATE.and_hms(23, 59, 59);
+            let archived = self.to_archived_entries()?.into_iter().map(Ok);
+            for je in self.iter_journal(from, to)?.chain(archived) {
+                let je = je?;
+                let mut names = je
+                    .creditors
+                    .iter()
+                    .map(|c| &c.commodity.name)
+                    .chain(je.debitors.iter().map(|d| &d.commodity.name));
+                if names.any(|n| n == name) {
+                    refs.push(je.to_key());
+                }
+            }
+            if !refs.is_empty() {
+                let msg = format!("commodity {} referenced by {}", name, refs.join(", "));
+                err_at!(InvalidInput, msg: msg)?;
+            }
+        }
+
+        let ck: types::KeyCommodity = (name.to_string(),).into();
+        self.delete(&ck.to_string())
+    }
+
+    // commit policy, applied after every put and delete. Batches commit
+    // once at the end, and transactions are committed by their caller.
+    fn after_write(&mut self) -> Result<()> {
+        if self.opts.autocommit && self.batch.is_none() && self.w.txn_uuid == 0 {
+            self.do_commit("auto commit")?;
+        } else {
+            self.modified = true;
+        }
+        Ok(())
+    }
+
     pub fn to_metadata_dir(&self) -> MetadataDir {
         let mut pp = path::PathBuf::new();
         pp.push(&self.dir);
@@ -127,6 +544,13 @@ impl Db {
         MetadataDir(pp.into_os_string())
     }
 
+    pub fn to_reports_dir(&self) -> ReportsDir {
+        let mut pp = path::PathBuf::new();
+        pp.push(&self.dir);
+        pp.push("reports");
+        ReportsDir(pp.into_os_string())
+    }
+
     pub fn to_journal_dir(&self) -> JournalDir {
         let mut pp = path::PathBuf::new();
         pp.push(path::Path::new(&self.dir));
@@ -134,70 +558,654 @@ impl Db {
         JournalDir(pp.into_os_string())
     }
 
-    fn get_head_commit(&self) -> Result<git2::Commit> {
-        let old_head_oid = err_at!(
-            IOError,
-            self.repo.as_ref().unwrap().refname_to_id("HEAD"),
-            format!("git refname_to_id")
-        )?;
-        let parent = err_at!(
+    pub fn to_journal_log(&self) -> JournalLog {
+        let mut pp = path::PathBuf::new();
+        pp.push(path::Path::new(&self.dir));
+        pp.push("journal");
+        JournalLog(pp.into_os_string())
+    }
+
+    // return every revision of the file at `path`, oldest first, along
+    // with the commit that introduced it. `path` is either relative to
+    // the workspace directory or an absolute path under it. Commits that
+    // leave the file unchanged are skipped.
+    pub fn file_history(&self, path: &ffi::OsStr) -> Result<Vec<(CommitInfo, String)>> {
+        let repo = self.repo()?;
+        let path = {
+            let path = path::Path::new(path);
+            path.strip_prefix(&self.dir).unwrap_or(path).to_path_buf()
+        };
+
+        let mut walk = err_at!(IOError, repo.revwalk(), format!("git revwalk"))?;
+        err_at!(IOError, walk.push_head(), format!("git push_head"))?;
+        err_at!(
             IOError,
-            self.repo.as_ref().unwrap().find_commit(old_head_oid),
-            format!("git find_commit")
+            walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE),
+            format!("git set_sorting")
         )?;
 
-        Ok(parent)
+        let mut history: Vec<(CommitInfo, String)> = vec![];
+        for oid in walk {
+            let oid = err_at!(IOError, oid, format!("git revwalk"))?;
+            let commit = err_at!(IOError, repo.find_commit(oid), format!("git find_commit"))?;
+            let tree = err_at!(IOError, commit.tree(), format!("git tree"))?;
+            let entry = match tree.get_path(&path) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            let object = err_at!(IOError, entry.to_object(repo), format!("git to_object"))?;
+            let content = match object.as_blob() {
+                Some(blob) => err_at!(
+                    InvalidFile,
+                    std::str::from_utf8(blob.content()),
+                    format!("not utf8 {:?}@{}", path, oid)
+                )?
+                .to_string(),
+                None => err_at!(InvalidFile, msg: format!("not a file {:?}", path))?,
+            };
+
+            match history.last() {
+                Some((_, last)) if last == &content => (),
+                _ => history.push(((&commit).into(), content)),
+            }
+        }
+
+        Ok(history)
     }
 
-    fn do_commit(&mut self, message: &str) -> Result<(git2::Oid, git2::Oid)> {
-        // stage the changes.
-        let mut index = err_at!(
+    // squash all but the most recent `keep_recent` commits into a single
+    // base commit, the recent commits are replayed on top of it with their
+    // tree, author, committer and message intact. Working tree and index
+    // are left untouched.
+    pub fn compact(&mut self, keep_recent: usize) -> Result<()> {
+        self.check_writable()?;
+        let repo = self.repo()?;
+
+        let oids = |squashed: &Option<(git2::Oid, git2::Oid)>| match squashed {
+            Some((old, new)) => format!("{}->{}", old, new),
+            None => "-".to_string(),
+        };
+        git_op("compact", || compact_repo(repo, keep_recent), oids)?;
+
+        Ok(())
+    }
+
+    // bundle journal entries for `year` into `journal/YYYY.tar.gz` and
+    // remove the year's directory. Archived years are not iterated until
+    // restored, refer restore_year().
+    pub fn archive_year(&mut self, year: i32) -> Result<()> {
+        self.check_writable()?;
+        let (year_dir, bundle) = self.to_year_bundle(year);
+        if !year_dir.is_dir() {
+            err_at!(NotFound, msg: format!("journal year {}", year))?;
+        } else if bundle.exists() {
+            err_at!(InvalidInput, msg: format!("journal year {} already archived", year))?;
+        }
+
+        let file = err_at!(IOError, fs::File::create(&bundle), format!("{:?}", bundle))?;
+        let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        err_at!(IOError, tar.append_dir_all(year.to_string(), &year_dir))?;
+        let gz = err_at!(IOError, tar.into_inner())?;
+        err_at!(IOError, gz.finish())?;
+        err_at!(IOError, fs::remove_dir_all(&year_dir))?;
+        trace!("archive journal year {} into {:?}", year, bundle);
+
+        self.after_write()
+    }
+
+    // unpack `journal/YYYY.tar.gz`, archived by archive_year().
+    pub fn restore_year(&mut self, year: i32) -> Result<()> {
+        self.check_writable()?;
+        let (year_dir, bundle) = self.to_year_bundle(year);
+        if !bundle.is_file() {
+            err_at!(NotFound, msg: format!("journal archive {}", year))?;
+        } else if year_dir.exists() {
+            err_at!(InvalidInput, msg: format!("journal year {} not archived", year))?;
+        }
+
+        let file = err_at!(IOError, fs::File::open(&bundle), format!("{:?}", bundle))?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        err_at!(IOError, archive.unpack(&self.to_journal_root()))?;
+        err_at!(IOError, fs::remove_file(&bundle))?;
+        trace!("restore journal year {} from {:?}", year, bundle);
+
+        self.after_write()
+    }
+
+    // journal entries in years archived by archive_year().
+    pub fn to_archived_entries(&self) -> Result<Vec<types::JournalEntry>> {
+        let mut entries = vec![];
+        for year in to_dir_numbers::<i32>(&self.to_journal_root(), ".tar.gz")?.into_iter() {
+            let (_, bundle) = self.to_year_bundle(year);
+            let file = err_at!(IOError, fs::File::open(&bundle), format!("{:?}", bundle))?;
+            let mut archive = tar::Archive::new(GzDecoder::new(file));
+            for item in err_at!(IOError, archive.entries(), format!("{:?}", bundle))? {
+                let mut item = err_at!(IOError, item, format!("{:?}", bundle))?;
+                if !item.header().entry_type().is_file() {
+                    continue;
+                }
+                let mut data = String::default();
+                err_at!(IOError, item.read_to_string(&mut data), format!("{:?}", bundle))?;
+                // one entry per file, or one entry per line of a month log.
+                for line in data.lines().filter(|line| !line.trim().is_empty()) {
+                    let mut je = types::JournalEntry::default();
+                    je.decode(line.trim_end())?;
+                    entries.push(je);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    // archive every journal year older than the latest `keep_years`,
+    // return the archived years.
+    pub fn archive_before(&mut self, keep_years: i32) -> Result<Vec<i32>> {
+        let till = chrono::Utc::today().year() - keep_years;
+        let years = to_journal_years(&self.to_journal_root(), i32::MIN, till - 1)?;
+        for year in years.iter() {
+            self.archive_year(*year)?;
+        }
+        Ok(years)
+    }
+
+    // both layouts keep a year's entries under `journal/YYYY/`, as day
+    // files or as month logs.
+    fn to_journal_root(&self) -> ffi::OsString {
+        match self.journal_layout {
+            JournalLayout::Files => self.to_journal_dir().0,
+            JournalLayout::MonthLog => self.to_journal_log().0,
+        }
+    }
+
+    fn to_year_bundle(&self, year: i32) -> (path::PathBuf, path::PathBuf) {
+        let journal_dir = path::PathBuf::from(self.to_journal_root());
+        (
+            journal_dir.join(year.to_string()),
+            journal_dir.join(format!("{}.tar.gz", year)),
+        )
+    }
+
+    // apply pending migrations in order, bump the workspace version to
+    // this build's and commit once. Fail if the workspace is newer than
+    // this build.
+    pub fn migrate(&mut self) -> Result<()> {
+        let (from, to) = (self.w.version, types::WORKSPACE_VERSION);
+        if from > to {
+            let msg = format!("workspace version {} newer than {}", from, to);
+            err_at!(InvalidFile, msg: msg)?;
+        } else if from == to {
+            return Ok(());
+        }
+
+        for m in migrations().into_iter() {
+            if m.to_version() <= from {
+                continue;
+            }
+            trace!("migrate workspace v{}->v{}", from, m.to_version());
+            m.migrate(self)?;
+        }
+        self.w.version = to;
+        self.put(self.w.clone())?;
+        self.do_commit(&format!("migrate workspace v{}->v{}", from, self.w.version))?;
+
+        Ok(())
+    }
+
+    // open workspace read-only, without taking the lock, resetting broken
+    // transactions, migrating or committing, so that a repository left
+    // dirty or conflicted can be inspected before repair. Every write
+    // fails, refer to_repo_problems() for what is wrong.
+    pub fn open_safe(dir: &ffi::OsStr) -> Result<Db> {
+        let opts = Options {
+            safe: true,
+            ..Options::default()
+        };
+        Db::open_with(dir, opts)
+    }
+
+    fn do_open(dir: &ffi::OsStr, opts: Options) -> Result<Db> {
+        let safe = opts.safe;
+        let w_dir = path::Path::new(dir);
+        if w_dir.exists() {
+            // safe mode leaves the directory untouched, not even a lock.
+            let lock = match safe {
+                true => None,
+                false => Some(WorkspaceLock::acquire(dir)?),
+            };
+            let file_loc = FileLoc::from_key(dir, "workspace");
+            let w: types::Workspace = file_loc.to_value()?;
+
+            let repo = err_at!(
+                IOError,
+                git2::Repository::open(dir),
+                format!("can't open git repository: {:?}", dir)
+            )?;
+
+            let mut remotes = vec![];
+            for remote in w.remotes.iter() {
+                let remote: &ffi::OsStr = remote.as_ref();
+                remotes.push(err_at!(
+                    IOError,
+                    git2::Repository::open(remote),
+                    format!("can't open remote git repository: {:?}", remote)
+                )?);
+            }
+
+            let journal_layout: JournalLayout = w.journal_layout.parse()?;
+            let mut db = Db {
+                dir: w_dir.as_os_str().to_os_string(),
+                w,
+                repo: Some(repo),
+                remotes,
+                batch: None,
+                lock,
+                modified: false,
+                staged: vec![],
+                journal_layout,
+                undo: vec![],
+                // opening commits once, refer below.
+                opts: Options {
+                    autocommit: false,
+                    ..opts.clone()
+                },
+            };
+
+            if safe {
+                for problem in db.to_repo_problems()?.iter() {
+                    warn!("safe mode, {:?} {}", dir, problem);
+                }
+                return Ok(db);
+            }
+
+            // check for broken transactions.
+            {
+                let head_commit = db.get_head_commit()?;
+                if head_commit.message().unwrap().starts_with("txn commit") {
+                    let parent = err_at!(
+                        //
+                        IOError, head_commit.parent(0), format!("git parent")
+                    )?;
+                    let mut cob = git2::build::CheckoutBuilder::new();
+                    cob.force();
+                    err_at!(
+                        IOError,
+                        db.repo()?.reset(
+                            parent.as_object(),
+                            git2::ResetType::Hard,
+                            Some(&mut cob)
+                        ),
+                        format!("git reset")
+                    )?;
+                }
+            }
+
+            db.migrate()?;
+
+            // base commodity must be defined in metadata.
+            if !db.w.commodity.is_empty() {
+                let key = db.w.commodity.clone();
+                if let Err(err) = db.get::<types::Commodity>(&key) {
+                    let msg = format!("base commodity {:?}, {}", key, err);
+                    err_at!(NotFound, msg: msg)?;
+                }
+            }
+
+            db.w.set_txn_uuid(0);
+            db.put(db.w.clone())?;
+            db.do_commit("user commit")?;
+            db.opts.autocommit = opts.autocommit;
+
+            Ok(db)
+        } else {
+            err_at!(NotFound, msg: format!("dir:{:?}", dir))?
+        }
+    }
+
+    // describe the state a previous run could have left the repository
+    // in, empty if it is clean.
+    pub fn to_repo_problems(&self) -> Result<Vec<String>> {
+        let repo = self.repo()?;
+        let mut problems = vec![];
+
+        match repo.state() {
+            git2::RepositoryState::Clean => (),
+            state => problems.push(format!("repository state {:?}", state)),
+        }
+        let index = err_at!(IOError, repo.index(), format!("git index"))?;
+        if index.has_conflicts() {
+            problems.push("conflicts in index".to_string());
+        }
+        if let Ok(head) = self.get_head_commit() {
+            if head.message().unwrap_or("").starts_with("txn commit") {
+                problems.push("broken transaction".to_string());
+            }
+        }
+
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true);
+        let statuses = err_at!(IOError, repo.statuses(Some(&mut opts)), format!("git status"))?;
+        let n = statuses
+            .iter()
+            .filter(|st| st.path() != Some(LOCK_FILE))
+            .count();
+        if n > 0 {
+            problems.push(format!("{} uncommitted files", n));
+        }
+
+        Ok(problems)
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        match self.is_read_only() {
+            true => err_at!(NoEdit, msg: format!("read-only, safe mode {:?}", self.dir)),
+            false => Ok(()),
+        }
+    }
+
+    // fetch HEAD of remote at `off` and work out how it merges with our
+    // HEAD. The merge, if any, is committed but HEAD is left for the
+    // caller to move to the returned oid.
+    fn pull_remote(&self, off: usize) -> Result<Pulled> {
+        let repo = self.repo()?;
+        let loc = &self.w.remotes[off];
+
+        let (refname, theirs_oid) = {
+            let head = err_at!(IOError, self.remotes[off].head(), format!("git head {}", loc))?;
+            match (head.name(), head.target()) {
+                (Some(name), Some(oid)) => (name.to_string(), oid),
+                _ => err_at!(IOError, msg: format!("git head {}", loc))?,
+            }
+        };
+        let mut remote = err_at!(IOError, repo.remote_anonymous(loc), format!("git remote"))?;
+        let fetch = || {
+            err_at!(
+                IOError,
+                remote.fetch(&[refname.as_str()], None, None),
+                format!("git fetch {}", loc)
+            )
+        };
+        git_op("fetch", fetch, |_| theirs_oid.to_string())?;
+
+        let theirs = err_at!(
             IOError,
-            self.repo.as_ref().unwrap().index(),
-            format!("git error")
+            repo.find_annotated_commit(theirs_oid),
+            format!("git find_annotated_commit")
         )?;
-        err_at!(
+        let (analysis, _) = err_at!(
             IOError,
-            index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None),
-            format!("git add_all")
+            repo.merge_analysis(&[&theirs]),
+            format!("git merge_analysis")
         )?;
-        let oid = err_at!(IOError, index.write_tree(), format!("git write"))?;
+        if analysis.is_up_to_date() {
+            return Ok(Pulled::default());
+        }
 
-        // commit the staged changs.
-        let tree = err_at!(
+        let head = self.get_head_commit()?;
+        let (commits, _) = err_at!(
             IOError,
-            self.repo.as_ref().unwrap().find_tree(oid),
-            format!("git find_tree")
+            repo.graph_ahead_behind(theirs_oid, head.id()),
+            format!("git graph_ahead_behind")
         )?;
+        if analysis.is_fast_forward() {
+            return Ok(Pulled {
+                oid: Some(theirs_oid),
+                commits,
+                fast_forward: true,
+                conflicts: vec![],
+            });
+        }
+
+        let theirs = err_at!(IOError, repo.find_commit(theirs_oid), format!("git find_commit"))?;
+        let merge = || {
+            err_at!(
+                IOError,
+                repo.merge_commits(&head, &theirs, None),
+                format!("git merge_commits")
+            )
+        };
+        let mut index = git_op("merge", merge, |index: &git2::Index| {
+            let conflicts = match index.has_conflicts() {
+                true => " conflicts",
+                false => "",
+            };
+            format!("{}+{}{}", head.id(), theirs_oid, conflicts)
+        })?;
+        if index.has_conflicts() {
+            let mut conflicts = vec![];
+            for conflict in err_at!(IOError, index.conflicts(), format!("git conflicts"))? {
+                let conflict = err_at!(IOError, conflict, format!("git conflicts"))?;
+                let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+                if let Some(entry) = entry {
+                    conflicts.push(String::from_utf8_lossy(&entry.path).to_string());
+                }
+            }
+            return Ok(Pulled {
+                oid: None,
+                commits,
+                fast_forward: false,
+                conflicts,
+            });
+        }
+
+        let tree_oid = err_at!(IOError, index.write_tree_to(repo), format!("git write"))?;
+        let tree = err_at!(IOError, repo.find_tree(tree_oid), format!("git find_tree"))?;
+        let signature = err_at!(IOError, repo.signature(), format!("git signature"))?;
+        let commit = || {
+            err_at!(
+                IOError,
+                repo.commit(
+                    None, /*update_ref*/
+                    &signature,
+                    &signature,
+                    &format!("merge {}", loc),
+                    &tree,
+                    &[&head, &theirs],
+                ),
+                format!("git commit")
+            )
+        };
+        let oid = git_op("commit", commit, |oid| format!("{}+{}->{}", head.id(), theirs_oid, oid))?;
+
+        Ok(Pulled {
+            oid: Some(oid),
+            commits,
+            fast_forward: false,
+            conflicts: vec![],
+        })
+    }
+
+    fn push_remote(&self, off: usize) -> Result<SyncStatus> {
+        let repo = self.repo()?;
+        let loc = &self.w.remotes[off];
+
+        let (refname, oid) = {
+            let head = err_at!(IOError, repo.head(), format!("git head"))?;
+            match (head.name(), head.target()) {
+                (Some(name), Some(oid)) => (name.to_string(), oid),
+                _ => err_at!(IOError, msg: format!("git head"))?,
+            }
+        };
+        // commits on the remote we have not seen fail the push.
+        let (commits, behind) = match self.remotes[off].refname_to_id(&refname) {
+            Ok(theirs) if repo.find_commit(theirs).is_ok() => err_at!(
+                IOError,
+                repo.graph_ahead_behind(oid, theirs),
+                format!("git graph_ahead_behind")
+            )?,
+            Ok(_) => (0, 1),
+            Err(_) => {
+                let mut walk = err_at!(IOError, repo.revwalk(), format!("git revwalk"))?;
+                err_at!(IOError, walk.push(oid), format!("git revwalk"))?;
+                (walk.count(), 0)
+            }
+        };
+        if behind > 0 {
+            return Ok(SyncStatus {
+                commits: 0,
+                fast_forward: false,
+                conflicts: vec![format!("{} has changes, pull first", loc)],
+            });
+        } else if commits == 0 {
+            return Ok(SyncStatus::default());
+        }
+
+        let mut remote = err_at!(IOError, repo.remote_anonymous(loc), format!("git remote"))?;
+        let refspec = format!("{}:{}", refname, refname);
+        let push = || {
+            let res = remote.push(&[refspec.as_str()], None);
+            err_at!(IOError, res, format!("git push {}", loc))
+        };
+        git_op("push", push, |_| oid.to_string())?;
+
+        Ok(SyncStatus {
+            commits,
+            fast_forward: true,
+            conflicts: vec![],
+        })
+    }
+
+    // hard reset HEAD and the work tree to commit `oid`, and reload the
+    // workspace from it.
+    fn reset_to(&mut self, oid: git2::Oid) -> Result<()> {
+        {
+            let repo = self.repo()?;
+            let commit = err_at!(IOError, repo.find_commit(oid), format!("git find_commit"))?;
+            let mut cob = git2::build::CheckoutBuilder::new();
+            cob.force();
+            let reset = || {
+                let res = repo.reset(commit.as_object(), git2::ResetType::Hard, Some(&mut cob));
+                err_at!(IOError, res, format!("git reset"))
+            };
+            git_op("reset", reset, |_| oid.to_string())?;
+        }
+        self.w = FileLoc::from_key(&self.dir, "workspace").to_value()?;
+        self.modified = false;
+        self.staged.clear();
+
+        Ok(())
+    }
+
+    fn repo(&self) -> Result<&git2::Repository> {
+        match self.repo.as_ref() {
+            Some(repo) => Ok(repo),
+            None => err_at!(Fatal, msg: format!("no git repository for {:?}", self.dir)),
+        }
+    }
+
+    fn get_head_commit(&self) -> Result<git2::Commit> {
+        let repo = self.repo()?;
         let old_head_oid = err_at!(
             IOError,
-            self.repo.as_ref().unwrap().refname_to_id("HEAD"),
+            repo.refname_to_id("HEAD"),
             format!("git refname_to_id")
         )?;
         let parent = err_at!(
             IOError,
-            self.repo.as_ref().unwrap().find_commit(old_head_oid),
+            repo.find_commit(old_head_oid),
             format!("git find_commit")
         )?;
-        let signature = err_at!(
+
+        Ok(parent)
+    }
+
+    // with strict balance, fail if any journal entry put since the last
+    // commit is unbalanced. Entries deleted or archived since are skipped.
+    fn check_staged(&mut self) -> Result<()> {
+        if !self.w.strict_balance {
+            return Ok(());
+        }
+
+        let mut keys = vec![];
+        for key in self.staged.clone().into_iter() {
+            match self.get::<types::JournalEntry>(&key) {
+                Ok(entry) if !entry.is_balanced() => keys.push(key),
+                _ => (),
+            }
+        }
+
+        match keys.len() {
+            0 => Ok(()),
+            _ => err_at!(InvalidInput, msg: format!("unbalanced {}", keys.join(", "))),
+        }
+    }
+
+    fn do_commit(&mut self, message: &str) -> Result<(git2::Oid, git2::Oid)> {
+        self.check_writable()?;
+        self.check_staged()?;
+
+        let oids = self.git_commit(message)?;
+        self.modified = false;
+        self.staged.clear();
+
+        Ok(oids)
+    }
+
+    // stage the work tree and commit it on HEAD, return the old and new
+    // HEAD, both are the same when the tree is unchanged.
+    fn git_commit(&self, message: &str) -> Result<(git2::Oid, git2::Oid)> {
+        let repo = self.repo()?;
+
+        // stage the changes, lock file is never committed.
+        let mut index = err_at!(IOError, repo.index(), format!("git error"))?;
+        let mut skip_lock = |path: &path::Path, _: &[u8]| -> i32 {
+            if path == path::Path::new(LOCK_FILE) {
+                1
+            } else {
+                0
+            }
+        };
+        err_at!(
             IOError,
-            self.repo.as_ref().unwrap().signature(),
-            format!("git signature")
+            index.add_all(
+                ["*"].iter(),
+                git2::IndexAddOption::DEFAULT,
+                Some(&mut skip_lock)
+            ),
+            format!("git add_all")
         )?;
-        let new_head_oid = err_at!(
+        // files removed from the work tree, EG: archived journal years,
+        // are removed from the index as well.
+        err_at!(
             IOError,
-            self.repo.as_ref().unwrap().commit(
-                Some("HEAD"), /*update_ref*/
-                &signature,   /*author*/
-                &signature,   /*committer*/
-                message,
-                &tree,
-                &[&parent],
-            ),
-            format!("git commit")
+            index.update_all(["*"].iter(), None),
+            format!("git update_all")
         )?;
+        let oid = err_at!(IOError, index.write_tree(), format!("git write"))?;
 
-        trace!("git commit {}->{}", old_head_oid, new_head_oid);
+        // commit the staged changs.
+        let tree = err_at!(IOError, repo.find_tree(oid), format!("git find_tree"))?;
+        // a freshly initialised repository has no HEAD to parent on.
+        let (old_head_oid, parents) = match repo.refname_to_id("HEAD") {
+            Ok(old_head_oid) => {
+                let parent = err_at!(
+                    IOError,
+                    repo.find_commit(old_head_oid),
+                    format!("git find_commit")
+                )?;
+                (old_head_oid, vec![parent])
+            }
+            Err(_) => (git2::Oid::zero(), vec![]),
+        };
+        // nothing to commit, EG: touch-only puts, leave HEAD as it is.
+        if parents.iter().any(|parent| parent.tree_id() == oid) {
+            return Ok((old_head_oid, old_head_oid));
+        }
+        let signature = err_at!(IOError, repo.signature(), format!("git signature"))?;
+        let commit = || {
+            err_at!(
+                IOError,
+                repo.commit(
+                    Some("HEAD"), /*update_ref*/
+                    &signature,   /*author*/
+                    &signature,   /*committer*/
+                    message,
+                    &tree,
+                    &parents.iter().collect::<Vec<&git2::Commit>>(),
+                ),
+                format!("git commit")
+            )
+        };
+        let new_head_oid = git_op("commit", commit, |oid| format!("{}->{}", old_head_oid, oid))?;
 
         Ok((old_head_oid, new_head_oid))
     }
@@ -206,12 +1214,13 @@ impl Db {
 impl Store for Db {
     type Txn = DbTransaction;
 
-    fn create(dir: &ffi::OsStr, w: types::Workspace) -> Result<Db> {
+    fn create_with(dir: &ffi::OsStr, w: types::Workspace, opts: Options) -> Result<Db> {
         let repo = err_at!(
             IOError,
             git2::Repository::init(dir),
             format!("can't initialise git repository: {:?}", dir)
         )?;
+        let lock = WorkspaceLock::acquire(dir)?;
 
         let mut remotes = vec![];
         for remote in w.remotes.iter() {
@@ -223,11 +1232,23 @@ impl Store for Db {
             )?);
         }
 
+        let journal_layout: JournalLayout = w.journal_layout.parse()?;
         let mut db = Db {
             dir: dir.to_os_string(),
             w,
             repo: Some(repo),
             remotes,
+            batch: None,
+            lock: Some(lock),
+            modified: false,
+            staged: vec![],
+            journal_layout,
+            undo: vec![],
+            // creating commits once, refer below.
+            opts: Options {
+                autocommit: false,
+                ..opts.clone()
+            },
         };
         err_at!(IOError, fs::create_dir_all(&dir))?;
         err_at!(IOError, fs::create_dir_all(&db.to_metadata_dir().0))?;
@@ -236,87 +1257,113 @@ impl Store for Db {
         let file_loc = FileLoc::from_key(&dir, "workspace");
         file_loc.put(db.w.clone())?;
 
+        // base commodity must be a valid commodity key, seed its metadata
+        // so that a freshly created workspace can be opened.
+        if !db.w.commodity.is_empty() {
+            let key = db.w.commodity.clone();
+            let name = db.w.to_base_commodity();
+            if !key.starts_with("commodity-") || !util::str_as_anuh(&name) {
+                err_at!(InvalidInput, msg: format!("base commodity {:?}", key))?;
+            }
+            let meta_dir = db.to_metadata_dir();
+            if meta_dir.get::<types::Commodity>(&key).is_err() {
+                let value: types::Commodity = (name, 1.0).into();
+                meta_dir.put(value)?;
+            }
+        }
+
         db.do_commit("user commit")?;
+        db.opts.autocommit = opts.autocommit;
 
         Ok(db)
     }
 
-    fn open(dir: &ffi::OsStr) -> Result<Db> {
-        let w_dir = path::Path::new(dir);
-        if w_dir.exists() {
-            let file_loc = FileLoc::from_key(dir, "workspace");
-            let w: types::Workspace = file_loc.to_value()?;
-
-            let repo = err_at!(
-                IOError,
-                git2::Repository::open(dir),
-                format!("can't open git repository: {:?}", dir)
-            )?;
-
-            let mut remotes = vec![];
-            for remote in w.remotes.iter() {
-                let remote: &ffi::OsStr = remote.as_ref();
-                remotes.push(err_at!(
-                    IOError,
-                    git2::Repository::open(remote),
-                    format!("can't open remote git repository: {:?}", remote)
-                )?);
-            }
-
-            let mut db = Db {
-                dir: w_dir.as_os_str().to_os_string(),
-                w,
-                repo: Some(repo),
-                remotes,
-            };
+    fn open_with(dir: &ffi::OsStr, opts: Options) -> Result<Db> {
+        Db::do_open(dir, opts)
+    }
 
-            // check for broken transactions.
-            {
-                let head_commit = db.get_head_commit()?;
-                if head_commit.message().unwrap().starts_with("txn commit") {
-                    let parent = err_at!(
-                        //
-                        IOError, head_commit.parent(0), format!("git parent")
-                    )?;
-                    let mut cob = git2::build::CheckoutBuilder::new();
-                    cob.force();
-                    err_at!(
-                        IOError,
-                        db.repo.as_ref().unwrap().reset(
-                            parent.as_object(),
-                            git2::ResetType::Hard,
-                            Some(&mut cob)
-                        ),
-                        format!("git reset")
-                    )?;
-                }
-            }
+    // opened in safe mode, refer Db::open_safe().
+    fn is_read_only(&self) -> bool {
+        self.opts.safe
+    }
 
-            db.w.set_txn_uuid(0);
-            db.put(db.w.clone())?;
-            db.do_commit("user commit")?;
+    // uncommitted changes are committed, so that they are part of what is
+    // undone, refer undo().
+    fn mark_undo(&mut self) -> Result<()> {
+        self.check_writable()?;
+        if self.modified {
+            self.do_commit("user commit")?;
+        }
+        let oid = self.get_head_commit()?.id();
+        self.undo.push(oid);
+        if self.undo.len() > UNDO_DEPTH {
+            self.undo.remove(0);
+        }
+        Ok(())
+    }
 
-            Ok(db)
-        } else {
-            err_at!(NotFound, msg: format!("dir:{:?}", dir))?
+    // changes since the mark are committed before resetting to it, so that
+    // files they added are removed as well.
+    fn undo(&mut self) -> Result<bool> {
+        self.check_writable()?;
+        let oid = match self.undo.pop() {
+            Some(oid) => oid,
+            None => return Ok(false),
+        };
+        if self.modified {
+            self.do_commit("user commit")?;
         }
+
+        self.reset_to(oid)?;
+        trace!("undo to {}", oid);
+
+        Ok(true)
+    }
+
+    fn list_workspaces(parent: &ffi::OsStr) -> Result<Vec<(String, path::PathBuf)>> {
+        list_workspaces(parent)
     }
 
     fn put<V>(&mut self, value: V) -> Result<Option<V>>
     where
         V: Durable,
     {
-        match value.to_type().as_str() {
-            "company" | "commodity" | "ledger" => {
+        self.check_writable()?;
+        if let Some(n) = self.batch.as_mut() {
+            *n += 1;
+        }
+
+        if value.to_type() == "ledger" {
+            let mut ledger = types::Ledger::default();
+            ledger.decode(&value.encode()?)?;
+            if !self.opts.custom_groups {
+                self.w.check_groups(&ledger.groups)?;
+            }
+            aggregates::check_ledger_code(self, &ledger)?;
+        }
+
+        let old_value = match value.to_type().as_str() {
+            "workspace" => FileLoc::from_key(&self.dir, "workspace").put(value),
+            "company" | "commodity" | "ledger" | "budget" | "template" => {
                 let meta_dir = self.to_metadata_dir();
                 meta_dir.put(value)
             }
+            "report" => {
+                let reports_dir = self.to_reports_dir();
+                reports_dir.put(value)
+            }
             "journalentry" => {
-                let jrn_dir = self.to_journal_dir();
-                jrn_dir.put(value)
+                self.staged.push(value.to_key());
+                match self.journal_layout {
+                    JournalLayout::Files => self.to_journal_dir().put(value),
+                    JournalLayout::MonthLog => self.to_journal_log().put(value),
+                }
             }
             _ => err_at!(Fatal, msg: format!("unreachable"))?,
-        }
+        }?;
+        self.after_write()?;
+
+        Ok(old_value)
     }
 
     fn get<V>(&mut self, key: &str) -> Result<V>
@@ -326,14 +1373,18 @@ impl Store for Db {
         let value: V = Default::default();
 
         match value.to_type().as_str() {
-            "company" | "commodity" | "ledger" => {
+            "company" | "commodity" | "ledger" | "budget" | "template" => {
                 let meta_dir = self.to_metadata_dir();
                 meta_dir.get(key)
             }
-            "journalentry" => {
-                let jrn_dir = self.to_journal_dir();
-                jrn_dir.get(key)
+            "report" => {
+                let reports_dir = self.to_reports_dir();
+                reports_dir.get(key)
             }
+            "journalentry" => match self.journal_layout {
+                JournalLayout::Files => self.to_journal_dir().get(key),
+                JournalLayout::MonthLog => self.to_journal_log().get(key),
+            },
             _ => err_at!(Fatal, msg: format!("unreachable"))?,
         }
     }
@@ -342,27 +1393,41 @@ impl Store for Db {
     where
         V: Durable,
     {
+        self.check_writable()?;
+        if let Some(n) = self.batch.as_mut() {
+            *n += 1;
+        }
+
         let value: V = Default::default();
 
-        match value.to_type().as_str() {
-            "company" | "commodity" | "ledger" => {
+        let value = match value.to_type().as_str() {
+            "company" | "commodity" | "ledger" | "budget" | "template" => {
                 let meta_dir = self.to_metadata_dir();
                 meta_dir.delete(key)
             }
-            "journalentry" => {
-                let jrn_dir = self.to_journal_dir();
-                jrn_dir.delete(key)
+            "report" => {
+                let reports_dir = self.to_reports_dir();
+                reports_dir.delete(key)
             }
+            "journalentry" => match self.journal_layout {
+                JournalLayout::Files => self.to_journal_dir().delete(key),
+                JournalLayout::MonthLog => self.to_journal_log().delete(key),
+            },
             _ => err_at!(Fatal, msg: format!("unreachable"))?,
-        }
+        }?;
+        self.after_write()?;
+
+        Ok(value)
     }
 
     fn iter<V>(&mut self) -> Result<Box<dyn Iterator<Item = Result<V>>>>
     where
         V: 'static + Durable,
     {
-        let iter = self.to_metadata_dir().iter()?;
-        Ok(Box::new(iter))
+        match V::default().to_type().as_str() {
+            "report" => Ok(Box::new(self.to_reports_dir().iter()?)),
+            _ => Ok(Box::new(self.to_metadata_dir().iter()?)),
+        }
     }
 
     fn iter_journal(
@@ -370,21 +1435,74 @@ impl Store for Db {
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>> {
-        let iter = self.to_journal_dir().iter(from, to)?;
-        Ok(Box::new(iter))
+        match self.journal_layout {
+            JournalLayout::Files => Ok(Box::new(self.to_journal_dir().iter(from, to)?)),
+            JournalLayout::MonthLog => Ok(Box::new(self.to_journal_log().iter(from, to)?)),
+        }
+    }
+
+    fn to_workspace(&mut self) -> Result<types::Workspace> {
+        Ok(self.w.clone())
     }
 
     fn commit(&mut self) -> Result<()> {
-        self.do_commit("user commit")?;
+        if self.batch.is_none() {
+            self.do_commit("user commit")?;
+        }
         Ok(())
     }
 
-    fn pull(&mut self) -> Result<()> {
-        todo!()
+    fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    // pull from remotes in order, stop at the first remote whose changes
+    // conflict, leaving the store as it was before that remote.
+    fn pull(&mut self) -> Result<SyncStatus> {
+        self.check_writable()?;
+        if self.modified {
+            self.do_commit("user commit")?;
+        }
+
+        let mut status = SyncStatus::default();
+        for off in 0..self.remotes.len() {
+            let st = self.pull_remote(off)?;
+            if !st.conflicts.is_empty() {
+                status.conflicts = st.conflicts;
+                break;
+            }
+            if let Some(oid) = st.oid {
+                self.reset_to(oid)?;
+                // any merge commit makes the whole pull a merge.
+                status.fast_forward =
+                    st.fast_forward && (status.commits == 0 || status.fast_forward);
+                status.commits += st.commits;
+            }
+        }
+
+        Ok(status)
     }
 
-    fn push(&mut self) -> Result<()> {
-        todo!()
+    // push HEAD's branch to remotes in order, stop at the first remote
+    // that has commits not pulled yet.
+    fn push(&mut self) -> Result<SyncStatus> {
+        self.check_writable()?;
+        if self.modified {
+            self.do_commit("user commit")?;
+        }
+
+        let mut status = SyncStatus::default();
+        for off in 0..self.remotes.len() {
+            let st = self.push_remote(off)?;
+            if !st.conflicts.is_empty() {
+                status.conflicts = st.conflicts;
+                break;
+            }
+            status.commits += st.commits;
+            status.fast_forward |= st.fast_forward;
+        }
+
+        Ok(status)
     }
 
     fn begin(mut self) -> Result<DbTransaction> {
@@ -450,22 +1568,15 @@ impl Transaction<Db> for DbTransaction {
 
     fn end(mut self) -> Result<Db> {
         {
+            let repo = self.db.repo()?;
             let object = err_at!(
                 IOError,
-                self.db
-                    .repo
-                    .as_ref()
-                    .unwrap()
-                    .find_object(self.old_head_oid, None),
+                repo.find_object(self.old_head_oid, None),
                 format!("git find_object")
             )?;
             err_at!(
                 IOError,
-                self.db
-                    .repo
-                    .as_ref()
-                    .unwrap()
-                    .reset(&object, git2::ResetType::Mixed, None),
+                repo.reset(&object, git2::ResetType::Mixed, None),
                 format!("git reset")
             )?;
             trace!(
@@ -485,7 +1596,7 @@ impl Transaction<Db> for DbTransaction {
 pub struct MetadataDir(ffi::OsString);
 
 impl MetadataDir {
-    const TYPES: [&'static str; 3] = ["company", "commodity", "ledger"];
+    const TYPES: [&'static str; 5] = ["company", "commodity", "ledger", "budget", "template"];
 
     pub fn put<V>(&self, value: V) -> Result<Option<V>>
     where
@@ -534,12 +1645,96 @@ impl MetadataDir {
     where
         V: Durable,
     {
+        // file names are `<type>-<name>.json`, skip other types.
+        let prefix = format!("{}-", V::default().to_type());
+
         let mut dfs = vec![];
         let dir = &self.0;
         let es = err_at!(IOError, fs::read_dir(dir), format!("{:?}", dir))?;
         for item in es {
             let item = err_at!(IOError, item, format!("{:?}", self.0))?;
-            dfs.push(Ok(FileLoc::new(&self.0, &item.file_name()).to_value()?));
+            let file_name = item.file_name();
+            match file_name.to_str() {
+                Some(name) if is_value_file(name, &prefix) => (),
+                _ => continue,
+            }
+            dfs.push(Ok(FileLoc::new(&self.0, &file_name).to_value()?));
+        }
+
+        Ok(dfs.into_iter())
+    }
+}
+
+// generated reports, created on first put, files are named by report
+// key, `report-<name>-<generated>.json`.
+pub struct ReportsDir(ffi::OsString);
+
+impl ReportsDir {
+    const TYPES: [&'static str; 1] = ["report"];
+
+    pub fn put<V>(&self, value: V) -> Result<Option<V>>
+    where
+        V: Durable,
+    {
+        let typ = value.to_type();
+        if !Self::TYPES.contains(&typ.as_str()) {
+            err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
+        }
+
+        err_at!(IOError, fs::create_dir_all(&self.0), format!("{:?}", self.0))?;
+        let file_loc = FileLoc::from_value(&self.0, &value);
+        file_loc.put(value)
+    }
+
+    pub fn get<V>(&self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let value: V = Default::default();
+
+        let typ = value.to_type();
+        if !Self::TYPES.contains(&typ.as_str()) {
+            err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
+        }
+
+        let file_loc = FileLoc::from_key(&self.0, key);
+        file_loc.get()
+    }
+
+    pub fn delete<V>(&self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let value: V = Default::default();
+
+        let typ = value.to_type();
+        if !Self::TYPES.contains(&typ.as_str()) {
+            err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
+        }
+
+        let file_loc = FileLoc::from_key(&self.0, key);
+        file_loc.delete()
+    }
+
+    pub fn iter<V>(&self) -> Result<std::vec::IntoIter<Result<V>>>
+    where
+        V: Durable,
+    {
+        let mut dfs = vec![];
+        if !path::Path::new(&self.0).exists() {
+            return Ok(dfs.into_iter());
+        }
+
+        let prefix = format!("{}-", V::default().to_type());
+        let es = err_at!(IOError, fs::read_dir(&self.0), format!("{:?}", self.0))?;
+        for item in es {
+            let item = err_at!(IOError, item, format!("{:?}", self.0))?;
+            let file_name = item.file_name();
+            match file_name.to_str() {
+                Some(name) if is_value_file(name, &prefix) => (),
+                _ => continue,
+            }
+            dfs.push(Ok(FileLoc::new(&self.0, &file_name).to_value()?));
         }
 
         Ok(dfs.into_iter())
@@ -549,7 +1744,7 @@ impl MetadataDir {
 pub struct JournalDir(ffi::OsString);
 
 impl JournalDir {
-    const TYPES: [&'static str; 3] = ["company", "commodity", "ledger"];
+    const TYPES: [&'static str; 1] = ["journalentry"];
 
     fn put<V>(&self, value: V) -> Result<Option<V>>
     where
@@ -578,7 +1773,7 @@ impl JournalDir {
             err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
         }
 
-        let file_loc = FileLoc::from_key(&self.0, key);
+        let file_loc = FileLoc::from_journal_key(&self.0, key)?;
         file_loc.get()
     }
 
@@ -593,7 +1788,7 @@ impl JournalDir {
             err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
         }
 
-        let file_loc = FileLoc::from_key(&self.0, key);
+        let file_loc = FileLoc::from_journal_key(&self.0, key)?;
         file_loc.delete()
     }
 
@@ -602,7 +1797,222 @@ impl JournalDir {
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<IterTransaction> {
-        Ok(IterTransaction::new(self.0.clone(), from, to))
+        IterTransaction::new(self.0.clone(), from, to)
+    }
+}
+
+// journal entries as one log per month, every line in the log is an
+// encoded entry. New entries are appended, updates and deletes rewrite
+// the month's log.
+pub struct JournalLog(ffi::OsString);
+
+impl JournalLog {
+    fn put<V>(&self, value: V) -> Result<Option<V>>
+    where
+        V: Durable,
+    {
+        let typ = value.to_type();
+        if typ != "journalentry" {
+            err_at!(Fatal, msg: format!("invalid type:{}", typ))?;
+        }
+
+        let key = value.to_key();
+        let log_loc = self.to_log_loc(&key)?;
+        let mut values: Vec<V> = read_log(&log_loc)?;
+        match values.iter().position(|v| v.to_key() == key) {
+            Some(off) => {
+                let old_value = values[off].clone();
+                if is_touch_only(&old_value.encode()?, &value.encode()?)? {
+                    trace!("skip touch-only put {:?}", log_loc);
+                    return Ok(Some(old_value));
+                }
+                values[off] = value;
+                write_log(&log_loc, &values)?;
+                Ok(Some(old_value))
+            }
+            None => {
+                FileLoc(log_loc.clone()).create_dir_all()?;
+                let mut fd = err_at!(
+                    IOError,
+                    fs::OpenOptions::new().create(true).append(true).open(&log_loc),
+                    format!("{:?}", log_loc)
+                )?;
+                let data = with_final_newline(&value.encode()?);
+                err_at!(IOError, fd.write_all(data.as_bytes()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn get<V>(&self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let log_loc = self.to_log_loc(key)?;
+        let values: Vec<V> = read_log(&log_loc)?;
+        match values.into_iter().find(|v| v.to_key() == key) {
+            Some(value) => Ok(value),
+            None => err_at!(NotFound, msg: format!("{} in {:?}", key, log_loc)),
+        }
+    }
+
+    pub fn delete<V>(&self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let log_loc = self.to_log_loc(key)?;
+        let mut values: Vec<V> = read_log(&log_loc)?;
+        match values.iter().position(|v| v.to_key() == key) {
+            Some(off) => {
+                let value = values.remove(off);
+                write_log(&log_loc, &values)?;
+                Ok(value)
+            }
+            None => err_at!(NotFound, msg: format!("{} in {:?}", key, log_loc)),
+        }
+    }
+
+    fn iter(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<IterJournalLog> {
+        IterJournalLog::new(self.0.clone(), from, to)
+    }
+
+    // journal keys start with `year-month-day`.
+    fn to_log_loc(&self, key: &str) -> Result<ffi::OsString> {
+        let parts: Vec<&str> = key.split("-").collect();
+        let (year, month) = match parts.as_slice() {
+            [y, m, _, ..] => (y.parse::<i32>().ok(), m.parse::<u32>().ok()),
+            _ => (None, None),
+        };
+        match (year, month) {
+            (Some(year), Some(month)) => Ok(to_month_log(&self.0, year, month)),
+            _ => err_at!(InvalidFile, msg: format!("journalkey:{}", key)),
+        }
+    }
+}
+
+fn to_month_log(journal_dir: &ffi::OsStr, year: i32, month: u32) -> ffi::OsString {
+    let mut pp = path::PathBuf::new();
+    pp.push(journal_dir);
+    pp.push(&year.to_string());
+    pp.push(&format!("{:02}.ndjson", month));
+    pp.into_os_string()
+}
+
+// values in a month's log, in the order they were appended. Missing log
+// is an empty month.
+fn read_log<V>(log_loc: &ffi::OsStr) -> Result<Vec<V>>
+where
+    V: Durable,
+{
+    if !path::Path::new(log_loc).exists() {
+        return Ok(vec![]);
+    }
+
+    let data = err_at!(IOError, fs::read(log_loc), format!("{:?}", log_loc))?;
+    let s = err_at!(
+        InvalidFile,
+        std::str::from_utf8(&data),
+        format!("not utf8 {:?}", log_loc)
+    )?;
+    let mut values = vec![];
+    for line in s.lines().filter(|line| !line.trim().is_empty()) {
+        let mut value: V = Default::default();
+        value.decode(line.trim_end())?;
+        values.push(value);
+    }
+    Ok(values)
+}
+
+// rewrite a month's log, removing it once the last value is deleted.
+fn write_log<V>(log_loc: &ffi::OsStr, values: &[V]) -> Result<()>
+where
+    V: Durable,
+{
+    if values.is_empty() {
+        err_at!(IOError, fs::remove_file(log_loc))?;
+        return Ok(());
+    }
+
+    let mut data = String::default();
+    for value in values.iter() {
+        data.push_str(&with_final_newline(&value.encode()?));
+    }
+    err_at!(IOError, fs::write(log_loc, data.as_bytes()))?;
+    Ok(())
+}
+
+// iterate over journal logs, a month at a time, for entries created
+// between `from` and `to`. Entries within a month are sorted.
+struct IterJournalLog {
+    journal_dir: ffi::OsString,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    months: Vec<(i32, u32)>,
+    entries: Vec<types::JournalEntry>,
+    done: bool,
+}
+
+impl IterJournalLog {
+    fn new(
+        journal_dir: ffi::OsString,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<IterJournalLog> {
+        let mut months = vec![];
+        for year in to_journal_years(&journal_dir, from.year(), to.year())? {
+            let year_dir = to_year_dir(&journal_dir, year);
+            let (first, last) = to_month_range(year, &from.date(), &to.date());
+            for month in to_dir_numbers::<u32>(&year_dir, ".ndjson")?.into_iter() {
+                if month >= first && month <= last {
+                    months.push((year, month));
+                }
+            }
+        }
+
+        Ok(IterJournalLog {
+            journal_dir,
+            from,
+            to,
+            months,
+            entries: vec![],
+            done: false,
+        })
+    }
+}
+
+impl Iterator for IterJournalLog {
+    type Item = Result<types::JournalEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.len() {
+                _ if self.done => break None,
+                0 if self.months.len() == 0 => break None,
+                0 => {
+                    let (year, month) = self.months.remove(0);
+                    let log_loc = to_month_log(&self.journal_dir, year, month);
+                    match read_log::<types::JournalEntry>(&log_loc) {
+                        Ok(entries) => {
+                            let (from, to) = (self.from, self.to);
+                            self.entries = entries
+                                .into_iter()
+                                .filter(|e| e.created >= from && e.created <= to)
+                                .collect();
+                            self.entries.sort();
+                        }
+                        Err(err) => {
+                            self.done = true;
+                            break Some(Err(err));
+                        }
+                    }
+                }
+                _ => break Some(Ok(self.entries.remove(0))),
+            }
+        }
     }
 }
 
@@ -618,14 +2028,14 @@ impl IterTransaction {
         dir: ffi::OsString,
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
-    ) -> IterTransaction {
-        let iter = JournalYears::new(dir, from.date());
-        IterTransaction {
+    ) -> Result<IterTransaction> {
+        let iter = JournalYears::new(dir, from.date(), to.date())?;
+        Ok(IterTransaction {
             from: from,
             to: to,
             iter,
             done: false,
-        }
+        })
     }
 }
 
@@ -633,7 +2043,7 @@ impl Iterator for IterTransaction {
     type Item = Result<types::JournalEntry>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.done {
+        if !self.done {
             loop {
                 match self.iter.next() {
                     Some(res) => match res {
@@ -664,6 +2074,7 @@ where
 {
     journal_dir: ffi::OsString,
     from: chrono::Date<chrono::Utc>,
+    to: chrono::Date<chrono::Utc>,
     years: Vec<i32>,
     year: Option<JournalYear<V>>,
 }
@@ -672,13 +2083,19 @@ impl<V> JournalYears<V>
 where
     V: Ord + Durable,
 {
-    fn new(journal_dir: ffi::OsString, from: chrono::Date<chrono::Utc>) -> JournalYears<V> {
-        JournalYears {
+    fn new(
+        journal_dir: ffi::OsString,
+        from: chrono::Date<chrono::Utc>,
+        to: chrono::Date<chrono::Utc>,
+    ) -> Result<JournalYears<V>> {
+        let years = to_journal_years(&journal_dir, from.year(), to.year())?;
+        Ok(JournalYears {
             journal_dir,
             from,
-            years: (from.year()..=chrono::Utc::today().year()).collect(),
+            to,
+            years,
             year: Default::default(),
-        }
+        })
     }
 }
 
@@ -700,9 +2117,16 @@ where
                 },
                 None if self.years.len() == 0 => break None,
                 None => {
-                    let from = self.from.with_year(self.years.remove(0)).unwrap();
-                    let jy = JournalYear::new(self.journal_dir.clone(), from);
-                    self.year = Some(jy);
+                    // only the first and last years are partial.
+                    let year = self.years.remove(0);
+                    let months = to_month_range(year, &self.from, &self.to);
+                    match JournalYear::new(&self.journal_dir, year, months) {
+                        Ok(jy) => self.year = Some(jy),
+                        Err(err) => {
+                            self.years.clear();
+                            break Some(Err(err));
+                        }
+                    }
                 }
             }
         }
@@ -714,6 +2138,7 @@ where
     V: Ord + Durable,
 {
     year_dir: ffi::OsString,
+    year: i32,
     months: Vec<u32>,
     month: Option<JournalMonth<V>>,
 }
@@ -722,19 +2147,23 @@ impl<V> JournalYear<V>
 where
     V: Ord + Durable,
 {
-    fn new(journal_dir: ffi::OsString, from: chrono::Date<chrono::Utc>) -> JournalYear<V> {
-        let year_dir = {
-            let mut pp = path::PathBuf::new();
-            pp.push(path::Path::new(&journal_dir));
-            pp.push(&from.year().to_string());
-            pp.into_os_string()
-        };
-
-        JournalYear {
+    fn new(
+        journal_dir: &ffi::OsStr,
+        year: i32,
+        (first, last): (u32, u32),
+    ) -> Result<JournalYear<V>> {
+        let year_dir = to_year_dir(journal_dir, year);
+        let months: Vec<u32> = to_dir_numbers::<u32>(&year_dir, "")?
+            .into_iter()
+            .filter(|m| *m >= first && *m <= last)
+            .collect();
+
+        Ok(JournalYear {
             year_dir,
-            months: (from.month()..=chrono::Utc::today().month()).collect(),
+            year,
+            months,
             month: Default::default(),
-        }
+        })
     }
 }
 
@@ -757,7 +2186,7 @@ where
                 None if self.months.len() == 0 => break None,
                 None => {
                     let month = self.months.remove(0);
-                    let jm = JournalMonth::new(self.year_dir.clone(), month);
+                    let jm = JournalMonth::new(self.year_dir.clone(), self.year, month);
                     self.month = Some(jm);
                 }
             }
@@ -778,7 +2207,7 @@ impl<V> JournalMonth<V>
 where
     V: Ord + Durable,
 {
-    fn new(year_dir: ffi::OsString, month: u32) -> JournalMonth<V> {
+    fn new(year_dir: ffi::OsString, year: i32, month: u32) -> JournalMonth<V> {
         let month_dir = {
             let mut pp = path::PathBuf::new();
             pp.push(path::Path::new(&year_dir));
@@ -787,7 +2216,7 @@ where
         };
         JournalMonth {
             month_dir,
-            days: (1..32).collect(),
+            days: days_in_month(year, month).iter().map(|d| d.day()).collect(),
             day: Default::default(),
         }
     }
@@ -869,7 +2298,7 @@ where
                 let data = fs::read(&file_loc).ok()?;
                 let from = std::str::from_utf8(&data).ok()?;
                 let mut value: V = Default::default();
-                value.decode(from).ok()?;
+                value.decode(from.trim_end()).ok()?;
                 Some(value)
             }
             None => None,
@@ -891,16 +2320,96 @@ where
     }
 }
 
+// value files are named `<type>-<name>.json`, where `prefix` is `<type>-`.
+// Other files, like editor backups, are skipped.
+fn is_value_file(file_name: &str, prefix: &str) -> bool {
+    file_name.starts_with(prefix) && file_name.ends_with(".json")
+}
+
+fn to_year_dir(journal_dir: &ffi::OsStr, year: i32) -> ffi::OsString {
+    let mut pp = path::PathBuf::new();
+    pp.push(journal_dir);
+    pp.push(&year.to_string());
+    pp.into_os_string()
+}
+
+// years between `from` and `to` that have journal entries on disk,
+// archived years are skipped.
+fn to_journal_years(journal_dir: &ffi::OsStr, from: i32, to: i32) -> Result<Vec<i32>> {
+    let years = to_dir_numbers::<i32>(journal_dir, "")?;
+    Ok(years.into_iter().filter(|y| *y >= from && *y <= to).collect())
+}
+
+// months of `year` that fall between `from` and `to`, as (first, last).
+fn to_month_range(
+    year: i32,
+    from: &chrono::Date<chrono::Utc>,
+    to: &chrono::Date<chrono::Utc>,
+) -> (u32, u32) {
+    let first = if year == from.year() { from.month() } else { 1 };
+    let last = if year == to.year() { to.month() } else { 12 };
+    (first, last)
+}
+
+// names under `dir` that are numbers followed by `suffix`, in ascending
+// order. Missing directory has none.
+fn to_dir_numbers<T>(dir: &ffi::OsStr, suffix: &str) -> Result<Vec<T>>
+where
+    T: FromStr + Ord,
+{
+    if !path::Path::new(dir).exists() {
+        return Ok(vec![]);
+    }
+
+    let mut numbers = vec![];
+    for item in err_at!(IOError, fs::read_dir(dir), format!("{:?}", dir))? {
+        let item = err_at!(IOError, item)?;
+        let file_name = item.file_name();
+        let number = match file_name.to_str() {
+            Some(name) if name.ends_with(suffix) => {
+                name[..(name.len() - suffix.len())].parse::<T>().ok()
+            }
+            _ => None,
+        };
+        if let Some(number) = number {
+            numbers.push(number);
+        }
+    }
+    numbers.sort();
+
+    Ok(numbers)
+}
+
+// stored json ends with exactly one newline, keeping git diffs clean.
+fn with_final_newline(data: &str) -> String {
+    let mut data = data.trim_end_matches(&['\r', '\n'][..]).to_string();
+    data.push('\n');
+    data
+}
+
+// compare two encoded values ignoring their `updated` timestamp.
+fn is_touch_only(old: &str, new: &str) -> Result<bool> {
+    let mut old: Json = err_at!(InvalidJson, old.parse())?;
+    let mut new: Json = err_at!(InvalidJson, new.parse())?;
+    old.delete("/updated").ok();
+    new.delete("/updated").ok();
+    Ok(old == new)
+}
+
 fn days_in_month(year: i32, month: u32) -> Vec<chrono::Date<chrono::Utc>> {
     let mut start_date = chrono::Utc.ymd(year, month, 1);
     let mut dates = vec![];
     loop {
         dates.push(start_date);
         match start_date.succ_opt() {
-            Some(next_date) => {
+            Some(next_date) if next_date.month() == month => {
                 start_date = next_date;
             }
-            None => break dates,
+            _ => break dates,
         }
     }
 }
+
+#[cfg(test)]
+#[path = "db_files_test.rs"]
+mod db_files_test;
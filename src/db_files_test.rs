@@ -0,0 +1,1085 @@
+use super::*;
+use crate::mem_store::testing;
+
+use std::{
+    convert::TryInto,
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Mutex, Once,
+    },
+    thread, time,
+};
+
+fn make_test_dir(name: &str) -> ffi::OsString {
+    let mut pp = std::env::temp_dir();
+    pp.push("ledger47-test");
+    pp.push(name);
+    fs::remove_dir_all(&pp).ok();
+    fs::create_dir_all(&pp).unwrap();
+    pp.into_os_string()
+}
+
+#[test]
+fn test_file_loc_touch_only() {
+    let dir = make_test_dir("test_file_loc_touch_only");
+
+    let value: types::Commodity = ("inr".to_string(), 10.0).into();
+    let file_loc = FileLoc::from_value(&dir, &value);
+    assert!(file_loc.put(value.clone()).unwrap().is_none());
+    let mtime = fs::metadata(&file_loc.0).unwrap().modified().unwrap();
+
+    thread::sleep(time::Duration::from_millis(20));
+
+    // same content, fresh `updated` timestamp.
+    let value: types::Commodity = ("inr".to_string(), 10.0).into();
+    assert!(file_loc.put(value).unwrap().is_some());
+    let new_mtime = fs::metadata(&file_loc.0).unwrap().modified().unwrap();
+    assert_eq!(mtime, new_mtime);
+
+    thread::sleep(time::Duration::from_millis(20));
+
+    let value: types::Commodity = ("inr".to_string(), 20.0).into();
+    file_loc.put(value).unwrap();
+    let new_mtime = fs::metadata(&file_loc.0).unwrap().modified().unwrap();
+    assert!(new_mtime > mtime);
+}
+
+#[test]
+fn test_db_touch_only_commit() {
+    let dir = make_test_dir("test_db_touch_only_commit");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    db.put(types::Commodity::from(("inr".to_string(), 10.0))).unwrap();
+    db.commit().unwrap();
+    let n_commits = count_commits(&db);
+
+    // same content, fresh `updated` timestamp.
+    db.put(types::Commodity::from(("inr".to_string(), 10.0))).unwrap();
+    db.commit().unwrap();
+    assert!(!db.is_modified());
+    assert_eq!(count_commits(&db), n_commits);
+
+    db.put(types::Commodity::from(("inr".to_string(), 20.0))).unwrap();
+    db.commit().unwrap();
+    assert_eq!(count_commits(&db), n_commits + 1);
+}
+
+#[test]
+fn test_file_loc_final_newline() {
+    let dir = make_test_dir("test_file_loc_final_newline");
+
+    let value: types::Commodity = ("inr".to_string(), 10.0).into();
+    let file_loc = FileLoc::from_value(&dir, &value);
+    file_loc.put(value.clone()).unwrap();
+
+    let data = String::from_utf8(fs::read(&file_loc.0).unwrap()).unwrap();
+    assert!(data.ends_with("}\n"), "{:?}", data);
+    assert!(!data.ends_with("\n\n"), "{:?}", data);
+    let out: types::Commodity = file_loc.get().unwrap();
+    assert_eq!(out.value, 10.0);
+
+    // files written without, or with extra, newlines still decode.
+    let trimmed = data.trim_end().to_string();
+    for data in vec![trimmed.clone(), trimmed + "\r\n\n"].into_iter() {
+        fs::write(&file_loc.0, data).unwrap();
+        let out: types::Commodity = file_loc.get().unwrap();
+        assert_eq!(out.name, "inr");
+    }
+
+    assert_eq!(with_final_newline("{}"), "{}\n");
+    assert_eq!(with_final_newline("{}\n\n"), "{}\n");
+}
+
+fn count_commits(db: &Db) -> usize {
+    let repo = db.repo.as_ref().unwrap();
+    let mut walk = repo.revwalk().unwrap();
+    walk.push_head().unwrap();
+    walk.count()
+}
+
+#[test]
+fn test_db_batch() {
+    let dir = make_test_dir("test_db_batch");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    let n_commits = count_commits(&db);
+
+    let n = db
+        .batch(|db| {
+            for i in 0..100 {
+                let value: types::Commodity = (format!("c{}", i), i as f64).into();
+                db.put(value)?;
+                db.commit()?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(n, 100);
+    assert_eq!(count_commits(&db), n_commits + 1);
+}
+
+#[test]
+fn test_db_autocommit() {
+    let dir = make_test_dir("test_db_autocommit");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    db.set_autocommit(true);
+    let n_commits = count_commits(&db);
+
+    for i in 0..3 {
+        let value: types::Commodity = (format!("c{}", i), i as f64).into();
+        db.put(value).unwrap();
+        assert!(!db.is_modified());
+    }
+    assert_eq!(count_commits(&db), n_commits + 3);
+
+    db.set_autocommit(false);
+    let value: types::Commodity = ("c3".to_string(), 3.0).into();
+    db.put(value).unwrap();
+    db.delete::<types::Commodity>("commodity-c0").unwrap();
+    assert!(db.is_modified());
+    assert_eq!(count_commits(&db), n_commits + 3);
+
+    db.commit().unwrap();
+    assert!(!db.is_modified());
+    assert_eq!(count_commits(&db), n_commits + 4);
+    std::mem::drop(db);
+
+    // policy is an option the store is opened with.
+    let opts = Options {
+        autocommit: true,
+        ..Options::default()
+    };
+    let mut db = Db::open_with(&dir, opts).unwrap();
+    let n_commits = count_commits(&db);
+    let value: types::Commodity = ("c4".to_string(), 4.0).into();
+    db.put(value).unwrap();
+    assert!(!db.is_modified());
+    assert_eq!(count_commits(&db), n_commits + 1);
+}
+
+#[test]
+fn test_db_without_repo() {
+    let dir = make_test_dir("test_db_without_repo");
+    let mut db = Db {
+        dir: dir.clone(),
+        w: types::Workspace::new("test".to_string()),
+        repo: None,
+        remotes: vec![],
+        batch: None,
+        lock: None,
+        modified: false,
+        staged: vec![],
+        journal_layout: JournalLayout::Files,
+        undo: vec![],
+        opts: Options::default(),
+    };
+
+    match db.get_head_commit() {
+        Err(Error::Fatal(_)) => (),
+        _ => panic!("expected fatal error"),
+    }
+    match db.do_commit("user commit") {
+        Err(Error::Fatal(_)) => (),
+        _ => panic!("expected fatal error"),
+    }
+    match db.commit() {
+        Err(Error::Fatal(_)) => (),
+        _ => panic!("expected fatal error"),
+    }
+}
+
+#[test]
+fn test_db_open_base_commodity() {
+    let dir = make_test_dir("test_db_open_base_commodity");
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    let value: types::Commodity = ("inr".to_string(), 1.0).into();
+    db.put(value).unwrap();
+    db.commit().unwrap();
+    std::mem::drop(db);
+
+    let mut db = Db::open(&dir).ok().unwrap();
+    db.delete::<types::Commodity>("commodity-inr").ok().unwrap();
+    db.commit().unwrap();
+    std::mem::drop(db);
+
+    match Db::open(&dir) {
+        Err(Error::NotFound(msg)) => assert!(msg.contains("commodity-inr"), "{}", msg),
+        _ => panic!("expected base commodity not found"),
+    }
+}
+
+#[test]
+fn test_db_create_base_commodity() {
+    let dir = make_test_dir("test_db_create_base_commodity");
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-usd".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    let value: types::Commodity = db.get("commodity-usd").unwrap();
+    assert_eq!(value.name, "usd");
+    std::mem::drop(db);
+    Db::open(&dir).unwrap();
+
+    let dir = make_test_dir("test_db_create_base_commodity_invalid");
+    let w = types::Workspace::new("test".to_string()).set_commodity("ledger-usd".to_string());
+    match Db::create(&dir, w) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("ledger-usd"), "{}", msg),
+        _ => panic!("expected invalid base commodity"),
+    }
+}
+
+#[test]
+fn test_db_ledger_groups() {
+    let make_ledger = |groups: Vec<&str>| types::Ledger {
+        name: "cash".to_string(),
+        company: "acme".to_string(),
+        groups: groups.into_iter().map(|g| g.to_string()).collect(),
+        ..Default::default()
+    };
+
+    let dir = make_test_dir("test_db_ledger_groups");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    db.put(make_ledger(vec!["asset"])).unwrap();
+    match db.put(make_ledger(vec!["asset", "petty"])) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("petty"), "{}", msg),
+        _ => panic!("expected unknown group"),
+    }
+    std::mem::drop(db);
+
+    let opts = Options {
+        custom_groups: true,
+        ..Options::default()
+    };
+    let mut db = Db::open_with(&dir, opts).unwrap();
+    db.put(make_ledger(vec!["asset", "petty"])).unwrap();
+}
+
+#[test]
+fn test_db_open_safe() {
+    let dir = make_test_dir("test_db_open_safe");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    db.put(types::Commodity::from(("inr".to_string(), 1.0))).unwrap();
+    db.commit().unwrap();
+
+    // simulate a run that died within a transaction, with uncommitted files.
+    let mut txn = db.begin().unwrap();
+    txn.put(types::Commodity::from(("usd".to_string(), 75.0))).unwrap();
+    std::mem::drop(txn);
+
+    let head = || {
+        let repo = git2::Repository::open(&dir).unwrap();
+        repo.refname_to_id("HEAD").unwrap()
+    };
+    let old_head = head();
+
+    let mut db = Db::open_safe(&dir).unwrap();
+    assert!(db.is_read_only());
+    let problems = db.to_repo_problems().unwrap();
+    assert!(problems.contains(&"broken transaction".to_string()), "{:?}", problems);
+    assert!(problems.iter().any(|p| p.ends_with("uncommitted files")), "{:?}", problems);
+
+    // entries can be inspected, but nothing is written.
+    let usd: types::Commodity = db.get("commodity-usd").unwrap();
+    assert_eq!(usd.value, 75.0);
+    match db.put(types::Commodity::from(("eur".to_string(), 90.0))) {
+        Err(Error::NoEdit(_)) => (),
+        _ => panic!("expected read-only"),
+    }
+    assert!(db.delete::<types::Commodity>("commodity-usd").is_err());
+    assert!(db.commit().is_err());
+    // archiving moves journal years, refused before looking for them.
+    match db.archive_year(2020) {
+        Err(Error::NoEdit(_)) => (),
+        _ => panic!("expected read-only"),
+    }
+    match db.restore_year(2020) {
+        Err(Error::NoEdit(_)) => (),
+        _ => panic!("expected read-only"),
+    }
+    assert!(!path::Path::new(&dir).join(LOCK_FILE).exists());
+    std::mem::drop(db);
+
+    assert_eq!(head(), old_head);
+
+    // regular open resets the broken transaction.
+    let db = Db::open(&dir).unwrap();
+    assert!(!db.is_read_only());
+    assert_ne!(head(), old_head);
+}
+
+#[test]
+fn test_db_undo() {
+    let dir = make_test_dir("test_db_undo");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    assert_eq!(db.undo().unwrap(), false);
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 10.0));
+    let key = je.to_key();
+
+    // undo an add, committed or not.
+    db.mark_undo().unwrap();
+    db.put(je.clone()).unwrap();
+    assert!(db.get::<types::JournalEntry>(&key).is_ok());
+    assert_eq!(db.undo().unwrap(), true);
+    assert!(db.get::<types::JournalEntry>(&key).is_err());
+    assert!(!db.is_modified());
+
+    db.mark_undo().unwrap();
+    db.put(je.clone()).unwrap();
+    db.commit().unwrap();
+    db.mark_undo().unwrap();
+    let mut edited = je.clone();
+    edited.payee = "market".to_string();
+    db.put(edited).unwrap();
+
+    // undo the edit, then the add.
+    assert_eq!(db.undo().unwrap(), true);
+    let entry: types::JournalEntry = db.get(&key).unwrap();
+    assert_eq!(entry.payee, "grocer");
+    assert_eq!(db.undo().unwrap(), true);
+    assert!(db.get::<types::JournalEntry>(&key).is_err());
+    assert_eq!(db.undo().unwrap(), false);
+}
+
+#[test]
+fn test_db_pull() {
+    let origin = make_test_dir("test_db_pull_origin");
+    let dir = make_test_dir("test_db_pull");
+    let w = types::Workspace::new("test".to_string());
+    let mut od = Db::create(&origin, w).unwrap();
+
+    git2::Repository::clone(origin.to_str().unwrap(), &dir).unwrap();
+    let file_loc = FileLoc::from_key(&dir, "workspace");
+    let mut w: types::Workspace = file_loc.to_value().unwrap();
+    w.add_remote(origin.to_str().unwrap().to_string());
+    file_loc.put(w).unwrap();
+    let mut db = Db::open(&dir).unwrap();
+
+    let status = db.pull().unwrap();
+    assert_eq!(status, SyncStatus::default());
+    assert_eq!(status.to_string(), "already up to date");
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 10.0));
+    let key = je.to_key();
+    od.put(je.clone()).unwrap();
+    od.commit().unwrap();
+
+    // opening committed the remote in workspace, so this is a merge.
+    let status = db.pull().unwrap();
+    assert_eq!(status.commits, 1);
+    assert_eq!(status.fast_forward, false);
+    assert!(status.conflicts.is_empty());
+    assert_eq!(status.to_string(), "1 commit, merged");
+    assert!(db.get::<types::JournalEntry>(&key).is_ok());
+    assert_eq!(db.to_workspace().unwrap().remotes.len(), 1);
+    assert_eq!(db.pull().unwrap().to_string(), "already up to date");
+
+    // both sides edit the same entry, nothing is pulled.
+    let mut edited = je.clone();
+    edited.payee = "market".to_string();
+    od.put(edited).unwrap();
+    od.commit().unwrap();
+    let mut edited = je.clone();
+    edited.payee = "bazaar".to_string();
+    db.put(edited).unwrap();
+    db.commit().unwrap();
+
+    let status = db.pull().unwrap();
+    assert_eq!(status.conflicts.len(), 1, "{:?}", status.conflicts);
+    assert!(status.to_string().starts_with("1 conflict, "), "{}", status);
+    let entry: types::JournalEntry = db.get(&key).unwrap();
+    assert_eq!(entry.payee, "bazaar");
+
+    // nor can we push over the remote's commits.
+    let status = db.push().unwrap();
+    assert_eq!(status.commits, 0);
+    assert!(status.conflicts[0].contains("pull first"), "{:?}", status);
+}
+
+#[test]
+fn test_db_push_pull_fast_forward() {
+    let seed = make_test_dir("test_db_push_pull_seed");
+    let origin = make_test_dir("test_db_push_pull_origin");
+    let (dir1, dir2) = (make_test_dir("test_db_push_1"), make_test_dir("test_db_pull_2"));
+    let w = types::Workspace::new("test".to_string());
+    std::mem::drop(Db::create(&seed, w).unwrap());
+    git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(seed.to_str().unwrap(), path::Path::new(&origin))
+        .unwrap();
+
+    git2::Repository::clone(origin.to_str().unwrap(), &dir1).unwrap();
+    let file_loc = FileLoc::from_key(&dir1, "workspace");
+    let mut w: types::Workspace = file_loc.to_value().unwrap();
+    w.add_remote(origin.to_str().unwrap().to_string());
+    file_loc.put(w).unwrap();
+    let mut db1 = Db::open(&dir1).unwrap();
+
+    // the remote, added on open, is pushed to the bare origin.
+    let status = db1.push().unwrap();
+    assert_eq!(status.commits, 1);
+    assert!(status.conflicts.is_empty());
+    assert_eq!(status.to_string(), "1 commit, fast-forward");
+    let theirs = git2::Repository::open(&origin).unwrap();
+    let head = db1.get_head_commit().unwrap().id();
+    assert_eq!(theirs.head().unwrap().target(), Some(head));
+    assert_eq!(db1.push().unwrap(), SyncStatus::default());
+
+    // second clone already has the remote, nothing to commit on open.
+    git2::Repository::clone(origin.to_str().unwrap(), &dir2).unwrap();
+    let mut db2 = Db::open(&dir2).unwrap();
+    assert_eq!(db2.get_head_commit().unwrap().id(), head);
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 10.0));
+    let key = je.to_key();
+    db1.put(je).unwrap();
+    db1.commit().unwrap();
+    assert_eq!(db1.push().unwrap().commits, 1);
+
+    let status = db2.pull().unwrap();
+    assert_eq!(status.commits, 1);
+    assert!(status.fast_forward);
+    assert!(status.conflicts.is_empty());
+    assert_eq!(status.to_string(), "1 commit, fast-forward");
+    assert!(db2.get::<types::JournalEntry>(&key).is_ok());
+    assert_eq!(
+        db2.get_head_commit().unwrap().id(),
+        db1.get_head_commit().unwrap().id()
+    );
+}
+
+// git timing logs, from every test in this binary, captured for
+// assertions. Only the latest CAPTURE_LIMIT lines are held.
+struct CaptureLog;
+
+const CAPTURE_LIMIT: usize = 1024;
+
+static CAPTURE_LOG: CaptureLog = CaptureLog;
+static CAPTURE_INIT: Once = Once::new();
+static CAPTURED: AtomicPtr<Mutex<Vec<String>>> = AtomicPtr::new(ptr::null_mut());
+
+impl log::Log for CaptureLog {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Debug
+    }
+
+    fn log(&self, record: &log::Record) {
+        let line = format!("{}", record.args());
+        if self.enabled(record.metadata()) && line.starts_with("git op=") {
+            let mut lines = to_captured().lock().unwrap();
+            if lines.len() >= CAPTURE_LIMIT {
+                lines.remove(0);
+            }
+            lines.push(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// buffer is allocated on first use, along with installing the logger.
+fn to_captured() -> &'static Mutex<Vec<String>> {
+    CAPTURE_INIT.call_once(|| {
+        let lines = Box::leak(Box::new(Mutex::new(vec![])));
+        CAPTURED.store(lines, Ordering::SeqCst);
+        log::set_logger(&CAPTURE_LOG).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+    });
+    unsafe { &*CAPTURED.load(Ordering::SeqCst) }
+}
+
+// only this test captures, so clearing can't race with another reader.
+#[test]
+fn test_db_git_timing() {
+    to_captured().lock().unwrap().clear();
+
+    let dir = make_test_dir("test_db_git_timing");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    db.put(types::Commodity::from(("inr".to_string(), 1.0))).unwrap();
+    let (old, new) = db.do_commit("user commit").unwrap();
+
+    // failed operations are logged as well.
+    let fail = || err_at!(IOError, msg: "boom");
+    let res: Result<()> = git_op("test-fail", fail, |_| unreachable!());
+    assert!(res.is_err());
+
+    let lines = to_captured().lock().unwrap().clone();
+    let oids = format!("oids={}->{}", old, new);
+    let commits: Vec<&String> = lines.iter().filter(|l| l.ends_with(&oids)).collect();
+    assert_eq!(commits.len(), 1, "{:?}", commits);
+    assert!(commits[0].starts_with("git op=commit elapsed="), "{}", commits[0]);
+
+    let fails: Vec<&String> = lines
+        .iter()
+        .filter(|l| l.starts_with("git op=test-fail "))
+        .collect();
+    assert_eq!(fails.len(), 1, "{:?}", fails);
+    assert!(fails[0].contains(" err=") && fails[0].contains("boom"), "{}", fails[0]);
+    assert!(lines.len() <= CAPTURE_LIMIT);
+}
+
+#[test]
+fn test_file_loc_invalid_utf8() {
+    let dir = make_test_dir("test_file_loc_invalid_utf8");
+    let file_loc = FileLoc::from_key(&dir, "commodity-inr");
+    fs::write(&file_loc.0, &[0x7b, 0xff, 0xfe, 0x7d]).unwrap();
+
+    match file_loc.to_value::<types::Commodity>() {
+        Err(Error::InvalidFile(msg)) => assert!(msg.contains("commodity-inr.json"), "{}", msg),
+        _ => panic!("expected invalid file"),
+    }
+
+    fs::write(&file_loc.0, "{\"name\": ").unwrap();
+    match file_loc.to_value::<types::Commodity>() {
+        Err(Error::InvalidJson(_)) => (),
+        _ => panic!("expected invalid json"),
+    }
+}
+
+#[test]
+fn test_db_file_history() {
+    let dir = make_test_dir("test_db_file_history");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let value: types::Commodity = ("inr".to_string(), 10.0).into();
+    db.put(value).unwrap();
+    db.commit().unwrap();
+    let value: types::Commodity = ("inr".to_string(), 20.0).into();
+    db.put(value).unwrap();
+    db.commit().unwrap();
+    // unrelated commit leaves the record's history untouched.
+    let value: types::Commodity = ("usd".to_string(), 1.0).into();
+    db.put(value).unwrap();
+    db.commit().unwrap();
+
+    let path: &ffi::OsStr = "metadata/commodity-inr.json".as_ref();
+    let history = db.file_history(path).unwrap();
+    assert_eq!(history.len(), 2);
+
+    let mut value: types::Commodity = Default::default();
+    value.decode(&history[0].1).unwrap();
+    assert_eq!(value.value, 10.0);
+    value.decode(&history[1].1).unwrap();
+    assert_eq!(value.value, 20.0);
+    assert!(history[0].0.time <= history[1].0.time);
+    assert_ne!(history[0].0.oid, history[1].0.oid);
+
+    let path = FileLoc::from_key(&db.to_metadata_dir().0, "commodity-inr");
+    assert_eq!(db.file_history(&path.0).unwrap().len(), 2);
+    let path: &ffi::OsStr = "metadata/commodity-xyz.json".as_ref();
+    assert!(db.file_history(path).unwrap().is_empty());
+}
+
+#[test]
+fn test_list_workspaces() {
+    let parent = make_test_dir("test_list_workspaces");
+    for name in ["personal", "business"].iter() {
+        let mut dir = path::PathBuf::from(&parent);
+        dir.push(name);
+        let w = types::Workspace::new(name.to_string());
+        Db::create(dir.as_os_str(), w).unwrap();
+    }
+    // neither a plain directory nor a file is a workspace.
+    let mut dir = path::PathBuf::from(&parent);
+    dir.push("scratch");
+    fs::create_dir_all(&dir).unwrap();
+    dir.push("notes.txt");
+    fs::write(&dir, "hello").unwrap();
+
+    let workspaces = list_workspaces(&parent).unwrap();
+    let names: Vec<&str> = workspaces.iter().map(|(n, _)| n.as_str()).collect();
+    assert_eq!(names, vec!["business", "personal"]);
+    for (name, dir) in workspaces.iter() {
+        assert!(dir.ends_with(name), "{:?}", dir);
+        assert!(Db::open(dir.as_os_str()).is_ok());
+    }
+
+    assert!(list_workspaces("/ledger47/no/such/dir".as_ref()).is_err());
+}
+
+#[test]
+fn test_db_compact() {
+    let dir = make_test_dir("test_db_compact");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let mut i = 0;
+    while count_commits(&db) < 10 {
+        let value: types::Commodity = (format!("c{}", i % 4), i as f64).into();
+        db.put(value).unwrap();
+        db.commit().unwrap();
+        i += 1;
+    }
+    let head_tree = db.get_head_commit().unwrap().tree_id();
+    let to_values = |db: &mut Db| -> Vec<(String, f64)> {
+        db.iter()
+            .unwrap()
+            .map(|v: Result<types::Commodity>| v.unwrap())
+            .map(|v| (v.name, v.value))
+            .collect()
+    };
+    let values = to_values(&mut db);
+
+    db.compact(3).unwrap();
+    assert_eq!(count_commits(&db), 4);
+    assert_eq!(db.get_head_commit().unwrap().tree_id(), head_tree);
+    assert_eq!(to_values(&mut db), values);
+
+    // already compact.
+    db.compact(3).unwrap();
+    assert_eq!(count_commits(&db), 4);
+}
+
+#[test]
+fn test_db_workspace_lock() {
+    let dir = make_test_dir("test_db_workspace_lock");
+    let w = types::Workspace::new("test".to_string());
+    let db = Db::create(&dir, w).unwrap();
+
+    match Db::open(&dir) {
+        Err(Error::IOError(msg)) => {
+            assert!(msg.contains("workspace already open"), "{}", msg);
+            assert!(msg.contains(LOCK_FILE), "{}", msg);
+        }
+        _ => panic!("expected workspace already open"),
+    }
+    // lock file is not part of the history.
+    let path: &ffi::OsStr = LOCK_FILE.as_ref();
+    assert!(db.file_history(path).unwrap().is_empty());
+
+    std::mem::drop(db);
+    let db = Db::open(&dir).ok().unwrap();
+    std::mem::drop(db);
+    Db::open(&dir).ok().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_db_workspace_lock_stale() {
+    let dir = make_test_dir("test_db_workspace_lock_stale");
+    let w = types::Workspace::new("test".to_string());
+    std::mem::drop(Db::create(&dir, w).unwrap());
+
+    // lock left behind by a process that is gone is reclaimed.
+    let loc = path::Path::new(&dir).join(LOCK_FILE);
+    fs::write(&loc, format!("{}@{}", u32::MAX, to_hostname())).unwrap();
+    let db = Db::open(&dir).ok().unwrap();
+    let holder = fs::read_to_string(&loc).unwrap();
+    assert_eq!(holder, format!("{}@{}", std::process::id(), to_hostname()));
+    std::mem::drop(db);
+
+    // lock from another host is left alone.
+    fs::write(&loc, format!("{}@elsewhere", u32::MAX)).unwrap();
+    match Db::open(&dir) {
+        Err(Error::IOError(msg)) => assert!(msg.contains("elsewhere"), "{}", msg),
+        _ => panic!("expected workspace already open"),
+    }
+}
+
+#[test]
+fn test_db_migrate() {
+    let dir = make_test_dir("test_db_migrate");
+    let w = types::Workspace::new("test".to_string());
+    let db = Db::create(&dir, w.clone()).unwrap();
+    std::mem::drop(db);
+
+    // rewind workspace.json to version 0.
+    let file_loc = FileLoc::from_key(&dir, "workspace");
+    fs::write(&file_loc.0, w.encode_as(0).unwrap()).unwrap();
+    assert_eq!(file_loc.to_value::<types::Workspace>().unwrap().version, 0);
+
+    let mut db = Db::open(&dir).ok().unwrap();
+    assert_eq!(db.to_workspace().unwrap().version, types::WORKSPACE_VERSION);
+    let history = db.file_history("workspace.json".as_ref()).unwrap();
+    let msg = format!("migrate workspace v0->v{}", types::WORKSPACE_VERSION);
+    assert!(history.iter().any(|(c, _)| c.message == msg));
+    std::mem::drop(db);
+
+    // persisted, already migrated, created as of the first commit.
+    let w: types::Workspace = file_loc.to_value().unwrap();
+    assert_eq!(w.version, types::WORKSPACE_VERSION);
+    assert!(w.created <= chrono::Utc::now());
+    assert!(w.created.timestamp() < w.updated.timestamp() + 1);
+    let mut db = Db::open(&dir).ok().unwrap();
+    db.migrate().unwrap();
+}
+
+#[test]
+fn test_db_migrate_default_ledger() {
+    let dir = make_test_dir("test_db_migrate_default_ledger");
+    let w = types::Workspace::new("test".to_string());
+    let db = Db::create(&dir, w.clone()).unwrap();
+    std::mem::drop(db);
+
+    let file_loc = FileLoc::from_key(&dir, "workspace");
+    fs::write(&file_loc.0, w.encode_as(1).unwrap()).unwrap();
+    assert_eq!(file_loc.to_value::<types::Workspace>().unwrap().version, 1);
+
+    let mut db = Db::open(&dir).ok().unwrap();
+    let w = db.to_workspace().unwrap();
+    assert_eq!(w.version, types::WORKSPACE_VERSION);
+    assert!(w.to_default_ledger().is_none());
+    let history = db.file_history("workspace.json".as_ref()).unwrap();
+    let msg = format!("migrate workspace v1->v{}", types::WORKSPACE_VERSION);
+    assert!(history.iter().any(|(c, _)| c.message == msg));
+}
+
+#[test]
+fn test_dir_numbers() {
+    let dir = make_test_dir("test_dir_numbers");
+    for name in ["2021", "2019", "2018.tar.gz", "notes"].iter() {
+        let mut pp = path::PathBuf::from(&dir);
+        pp.push(name);
+        fs::create_dir_all(&pp).unwrap();
+    }
+    assert_eq!(to_dir_numbers::<i32>(&dir, "").unwrap(), vec![2019, 2021]);
+    assert_eq!(to_journal_years(&dir, 2020, 2030).unwrap(), vec![2021]);
+
+    let mut pp = path::PathBuf::from(&dir);
+    pp.push("missing");
+    assert!(to_dir_numbers::<i32>(pp.as_os_str(), "").unwrap().is_empty());
+}
+
+#[test]
+fn test_journal_month_days() {
+    assert_eq!(days_in_month(2020, 2).len(), 29);
+    assert_eq!(days_in_month(2021, 2).len(), 28);
+    assert_eq!(days_in_month(2021, 4).len(), 30);
+    assert_eq!(days_in_month(2021, 12).len(), 31);
+
+    let dir: ffi::OsString = "journal".into();
+    let jm: JournalMonth<types::JournalEntry> = JournalMonth::new(dir.clone(), 2020, 2);
+    assert_eq!(jm.days, (1..=29).collect::<Vec<u32>>());
+    let jm: JournalMonth<types::JournalEntry> = JournalMonth::new(dir, 2021, 2);
+    assert_eq!(jm.days.last(), Some(&28));
+}
+
+#[test]
+fn test_db_journal_month_log() {
+    let dir = make_test_dir("test_db_journal_month_log");
+    let mut w = types::Workspace::new("test".to_string());
+    w.set_journal_layout(&JournalLayout::MonthLog.to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let make_entry = |payee: &str, created: chrono::DateTime<chrono::Utc>| {
+        let je = testing::make_entry(payee, created, "bank", "food", ("inr", 10.0));
+        je
+    };
+    let e1 = make_entry("grocer", chrono::Utc.ymd(2020, 5, 20).and_hms(9, 0, 0));
+    let e2 = make_entry("baker", chrono::Utc.ymd(2020, 5, 2).and_hms(9, 0, 0));
+    let e3 = make_entry("cafe", chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0));
+    for je in vec![e1.clone(), e2.clone(), e3.clone()].into_iter() {
+        assert!(db.put(je).unwrap().is_none());
+    }
+
+    // entries of a month are lines in one log.
+    let log_loc = to_month_log(&db.to_journal_log().0, 2020, 5);
+    let data = fs::read_to_string(&log_loc).unwrap();
+    assert_eq!(data.lines().count(), 2);
+    assert!(data.ends_with('\n'));
+
+    let je: types::JournalEntry = db.get(&e2.to_key()).unwrap();
+    assert_eq!(je.payee, "baker");
+
+    let mut e1_upd = e1.clone();
+    e1_upd.payee = "market".to_string();
+    let old = db.put(e1_upd).unwrap().unwrap();
+    assert_eq!(old.payee, "grocer");
+    let je: types::JournalEntry = db.get(&e1.to_key()).unwrap();
+    assert_eq!(je.payee, "market");
+    assert_eq!(fs::read_to_string(&log_loc).unwrap().lines().count(), 2);
+
+    let from = chrono::Utc.ymd(2020, 5, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2020, 6, 30).and_hms(23, 59, 59);
+    let payees: Vec<String> = db
+        .iter_journal(from, to)
+        .unwrap()
+        .map(|je| je.unwrap().payee)
+        .collect();
+    assert_eq!(payees, vec!["baker", "market", "cafe"]);
+
+    let je: types::JournalEntry = db.delete(&e2.to_key()).unwrap();
+    assert_eq!(je.payee, "baker");
+    assert!(db.get::<types::JournalEntry>(&e2.to_key()).is_err());
+    assert!(db.delete::<types::JournalEntry>(&e2.to_key()).is_err());
+
+    // deleting the last entry of a month removes its log.
+    db.delete::<types::JournalEntry>(&e3.to_key()).unwrap();
+    assert!(!path::Path::new(&to_month_log(&db.to_journal_log().0, 2020, 6)).exists());
+    assert_eq!(db.iter_journal(from, to).unwrap().count(), 1);
+
+    // layout is persisted, reopening sees the same entries, including
+    // those dated after today.
+    let later = chrono::Utc::now() + chrono::Duration::days(400);
+    db.put(make_entry("rent", later)).unwrap();
+    db.commit().unwrap();
+    std::mem::drop(db);
+    let mut db = Db::open(&dir).unwrap();
+    assert_eq!(db.journal_layout, JournalLayout::MonthLog);
+    let to = later + chrono::Duration::days(400);
+    let payees: Vec<String> = db
+        .iter_journal(from, to)
+        .unwrap()
+        .map(|je| je.unwrap().payee)
+        .collect();
+    assert_eq!(payees, vec!["market", "rent"]);
+}
+
+#[test]
+fn test_db_iter_mixed_types() {
+    let dir = make_test_dir("test_db_iter_mixed_types");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let created = chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let s = |s: &str| s.to_string();
+    for name in ["acme", "globex"].iter() {
+        let company: types::Company = (s(name), s("2020-01-01T00:00:00Z"), s(""), s(""), s(""))
+            .try_into()
+            .unwrap();
+        db.put(company).unwrap();
+    }
+    for i in 0..3 {
+        let value: types::Commodity = (format!("c{}", i), i as f64).into();
+        db.put(value).unwrap();
+    }
+    db.put(testing::make_ledger("cash")).unwrap();
+    // stray files in metadata, like editor backups, are skipped.
+    let mut backup = path::PathBuf::from(&db.to_metadata_dir().0);
+    backup.push("company-acme.json~");
+    fs::write(&backup, "not json").unwrap();
+
+    let companies: Vec<types::Company> = db.iter().unwrap().map(|v| v.unwrap()).collect();
+    let names: Vec<&str> = companies.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"acme") && names.contains(&"globex"));
+    assert_eq!(db.iter::<types::Commodity>().unwrap().count(), 3);
+    assert_eq!(db.iter::<types::Ledger>().unwrap().count(), 1);
+    assert_eq!(db.iter::<types::Budget>().unwrap().count(), 0);
+
+    // journal entries, one file each, are kept apart from metadata.
+    let je = testing::make_entry("grocer", created, "cash", "food", ("c0", 1.0));
+    db.put(je.clone()).unwrap();
+    let out: types::JournalEntry = db.get(&je.to_key()).unwrap();
+    assert_eq!(out.payee, "grocer");
+    assert_eq!(db.iter::<types::Company>().unwrap().count(), 2);
+    db.delete::<types::JournalEntry>(&je.to_key()).unwrap();
+    assert!(db.get::<types::JournalEntry>(&je.to_key()).is_err());
+}
+
+#[test]
+fn test_db_iter_journal_years() {
+    let dir = make_test_dir("test_db_iter_journal_years");
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let dates = [(2019, 3, 10), (2019, 8, 1), (2020, 2, 14), (2021, 11, 30)];
+    for (y, m, d) in dates.iter() {
+        let created = chrono::Utc.ymd(*y, *m, *d).and_hms(9, 0, 0);
+        let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 1.0));
+        db.put(je).unwrap();
+    }
+
+    // only the first year starts from `from`, later years are walked from
+    // january through december.
+    let from = chrono::Utc.ymd(2019, 6, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2021, 12, 31).and_hms(23, 59, 59);
+    let days: Vec<(i32, u32)> = db
+        .iter_journal(from, to)
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.created.year(), e.created.month())
+        })
+        .collect();
+    assert_eq!(days, vec![(2019, 8), (2020, 2), (2021, 11)]);
+}
+
+#[test]
+fn test_db_delete_commodity() {
+    let dir = make_test_dir("test_db_delete_commodity");
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+    for name in ["inr", "usd", "eur", "gbp", "chf"].iter() {
+        let value: types::Commodity = (name.to_string(), 1.0).into();
+        db.put(value).unwrap();
+    }
+    let make_entry = |created: chrono::DateTime<chrono::Utc>, comm: &str| {
+        let je = testing::make_entry("grocer", created, "cash", "food", (comm, 1.0));
+        je
+    };
+    let je = make_entry(chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0), "usd");
+    db.put(je.clone()).unwrap();
+    // dated after today, and archived.
+    let later = make_entry(chrono::Utc::now() + chrono::Duration::days(400), "chf");
+    db.put(later.clone()).unwrap();
+    let old = make_entry(chrono::Utc.ymd(2018, 3, 1).and_hms(0, 0, 0), "gbp");
+    db.put(old.clone()).unwrap();
+    db.archive_year(2018).unwrap();
+
+    // referenced commodities are protected.
+    for (name, key) in vec![("usd", je.to_key()), ("chf", later.to_key()), ("gbp", old.to_key())] {
+        match db.delete_commodity(name, false) {
+            Err(Error::InvalidInput(msg)) => assert!(msg.contains(&key), "{}", msg),
+            _ => panic!("expected {} to be protected", name),
+        }
+    }
+    match db.delete_commodity("inr", false) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("workspace test"), "{}", msg),
+        _ => panic!("expected inr to be protected"),
+    }
+    assert!(db.get::<types::Commodity>("commodity-usd").is_ok());
+
+    // unreferenced commodity deletes cleanly.
+    let value = db.delete_commodity("eur", false).unwrap();
+    assert_eq!(value.name, "eur");
+    assert!(db.get::<types::Commodity>("commodity-eur").is_err());
+
+    // force skips the reference check.
+    db.delete_commodity("usd", true).unwrap();
+    assert!(db.get::<types::Commodity>("commodity-usd").is_err());
+}
+
+#[test]
+fn test_db_strict_balance() {
+    let dir = make_test_dir("test_db_strict_balance");
+    let mut w = types::Workspace::new("test".to_string());
+    w.set_strict_balance(true);
+    let mut db = Db::create(&dir, w).unwrap();
+    let n_commits = count_commits(&db);
+
+    // unbalanced entry, bypassing validation, is refused on commit.
+    let created = chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let mut je = types::JournalEntry::new("grocer".to_string(), created);
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("inr".to_string(), 1.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "food".to_string(), ("inr".to_string(), 2.0))
+        .unwrap();
+    assert!(!je.is_balanced());
+    db.put(je.clone()).unwrap();
+    match db.commit() {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains(&je.to_key()), "{}", msg),
+        _ => panic!("expected unbalanced entry to be refused"),
+    }
+    assert!(db.is_modified());
+    assert_eq!(count_commits(&db), n_commits);
+
+    // commits once the entry is balanced.
+    je.debitors[0].commodity.value = 1.0;
+    db.put(je.clone()).unwrap();
+    db.commit().unwrap();
+    assert_eq!(count_commits(&db), n_commits + 1);
+
+    // disabled, unbalanced entries commit as before.
+    db.w.set_strict_balance(false);
+    je.debitors[0].commodity.value = 2.0;
+    db.put(je.clone()).unwrap();
+    db.commit().unwrap();
+    assert_eq!(count_commits(&db), n_commits + 2);
+}
+
+#[test]
+fn test_db_archive_year() {
+    for layout in vec![JournalLayout::Files, JournalLayout::MonthLog].into_iter() {
+        do_test_db_archive_year(layout)
+    }
+}
+
+fn do_test_db_archive_year(layout: JournalLayout) {
+    let dir = make_test_dir(&format!("test_db_archive_year_{:?}", layout));
+    let mut w = types::Workspace::new("test".to_string());
+    w.set_journal_layout(&layout.to_string());
+    let mut db = Db::create(&dir, w).unwrap();
+
+    let make_entry = |payee: &str, created: chrono::DateTime<chrono::Utc>| {
+        let je = testing::make_entry(payee, created, "bank", "food", ("inr", 10.0));
+        je
+    };
+    let entries = vec![
+        make_entry("grocer", chrono::Utc.ymd(2018, 3, 1).and_hms(9, 0, 0)),
+        make_entry("baker", chrono::Utc.ymd(2018, 11, 2).and_hms(9, 0, 0)),
+        make_entry("cafe", chrono::Utc.ymd(2019, 6, 1).and_hms(9, 0, 0)),
+    ];
+    for je in entries.iter() {
+        db.put(je.clone()).unwrap();
+    }
+    db.commit().unwrap();
+
+    let from = chrono::Utc.ymd(2000, 1, 1).and_hms(0, 0, 0);
+    let payees = |db: &mut Db| -> Vec<String> {
+        db.iter_journal(from, chrono::Utc::now())
+            .unwrap()
+            .map(|je| je.unwrap().payee)
+            .collect()
+    };
+    assert_eq!(payees(&mut db).len(), 3);
+
+    // archived year is excluded from iteration.
+    db.archive_year(2018).unwrap();
+    let (year_dir, bundle) = db.to_year_bundle(2018);
+    assert!(!year_dir.exists() && bundle.is_file());
+    assert_eq!(payees(&mut db), vec!["cafe".to_string()]);
+    assert!(db.get::<types::JournalEntry>(&entries[0].to_key()).is_err());
+    assert!(db.archive_year(2018).is_err());
+    assert!(db.restore_year(2019).is_err());
+    db.commit().unwrap();
+
+    // removal is committed, a hard reset doesn't bring the year back.
+    let head_oid = {
+        let head = db.get_head_commit().unwrap();
+        let tree = head.tree().unwrap();
+        assert!(tree.get_path(path::Path::new("journal/2018")).is_err());
+        assert!(tree.get_path(path::Path::new("journal/2018.tar.gz")).is_ok());
+        head.id()
+    };
+    db.reset_to(head_oid).unwrap();
+    assert!(!year_dir.exists() && bundle.is_file());
+
+    // restored entries are back.
+    db.restore_year(2018).unwrap();
+    assert!(year_dir.is_dir() && !bundle.exists());
+    let mut names = payees(&mut db);
+    names.sort();
+    assert_eq!(names, vec!["baker", "cafe", "grocer"]);
+    let je: types::JournalEntry = db.get(&entries[1].to_key()).unwrap();
+    assert_eq!(je.payee, "baker");
+
+    // retention archives years older than the latest `keep_years`.
+    let keep = chrono::Utc::today().year() - 2019;
+    assert_eq!(db.archive_before(keep).unwrap(), vec![2018]);
+    assert_eq!(payees(&mut db), vec!["cafe".to_string()]);
+}
+
+#[test]
+fn test_db_cache_undo() {
+    use crate::cache::Cache;
+
+    let dir = make_test_dir("test_db_cache_undo");
+    let w = types::Workspace::new("test".to_string());
+    let mut cache: Cache<Db> = Cache::create(&dir, w).unwrap();
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 10.0));
+    let (from, to) = (created, created);
+
+    // undo through the cache drops the entry from memory as well.
+    cache.mark_undo().unwrap();
+    cache.put(je).unwrap();
+    let mut n = 0;
+    cache.for_each_journal(from, to, |_| n += 1).unwrap();
+    assert_eq!(n, 1);
+    assert_eq!(cache.undo().unwrap(), true);
+    let mut n = 0;
+    cache.for_each_journal(from, to, |_| n += 1).unwrap();
+    assert_eq!(n, 0);
+}
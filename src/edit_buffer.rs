@@ -1,6 +1,7 @@
 use crossterm::event::{KeyCode, KeyModifiers};
 use log::trace;
 use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthChar;
 
 use std::{cmp, io};
@@ -157,6 +158,13 @@ impl Buffer {
         self.as_ref().to_string()
     }
 
+    // return the logical (col, row) of the cursor, zero based.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let row = self.buf.char_to_line(self.cursor);
+        let col = self.cursor - self.buf.line_to_char(row);
+        (col, row)
+    }
+
     pub fn view_lines(&self, from: usize) -> Vec<String> {
         self.as_ref()
             .lines_at(from)
@@ -164,6 +172,14 @@ impl Buffer {
             .collect()
     }
 
+    // insert `text` at the cursor and move the cursor past it, return
+    // the new (col, row) of the cursor.
+    pub fn insert_str(&mut self, text: &str) -> (usize, usize) {
+        let cursr = self.cursor;
+        self.buf.insert(cursr, text);
+        self.update_cursor(cursr + text.chars().count())
+    }
+
     pub fn handle_event(&mut self, evnt: Event) -> Result<EditRes> {
         use EditEvent::{BackTab, Backspace, Char, Delete, Down, End, Enter};
         use EditEvent::{Esc, Home, Insert, Left, Noop, PageDown, PageUp};
@@ -182,7 +198,7 @@ impl Buffer {
             }
             Backspace if cursr == 0 => ((0, 0), None),
             Backspace => {
-                let new_cursor = cursr - 1;
+                let new_cursor = prev_grapheme(&self.buf, cursr);
                 self.buf.remove(new_cursor..cursr);
                 (self.update_cursor(new_cursor), None)
             }
@@ -247,8 +263,10 @@ impl Buffer {
                 (self.update_cursor(cursr + 1), None)
             }
             Delete => {
-                if cursr < line_last_char(&self.buf, cursr) {
-                    self.buf.remove(cursr..(cursr + 1));
+                let last_char = line_last_char(&self.buf, cursr);
+                if cursr < last_char {
+                    let end = cmp::min(next_grapheme(&self.buf, cursr), last_char);
+                    self.buf.remove(cursr..end);
                 }
                 (self.update_cursor(cursr), None)
             }
@@ -292,6 +310,34 @@ impl Buffer {
     }
 }
 
+// char index of the grapheme-cluster boundary before `cursor`, so that
+// combining marks and multi-codepoint emoji are removed as one unit.
+fn prev_grapheme(buf: &Rope, cursor: usize) -> usize {
+    let line_idx = buf.char_to_line(cursor);
+    // include the previous line, to handle line breaks like "\r\n".
+    let from = buf.line_to_char(line_idx.saturating_sub(1));
+    let s = buf.slice(from..cursor).to_string();
+    match s.grapheme_indices(true).next_back() {
+        Some((off, _)) => from + s[..off].chars().count(),
+        None => cursor,
+    }
+}
+
+// char index of the grapheme-cluster boundary after `cursor`.
+fn next_grapheme(buf: &Rope, cursor: usize) -> usize {
+    let line_idx = buf.char_to_line(cursor);
+    let till = if (line_idx + 1) < buf.len_lines() {
+        buf.line_to_char(line_idx + 1)
+    } else {
+        buf.len_chars()
+    };
+    let s = buf.slice(cursor..till).to_string();
+    match s.graphemes(true).next() {
+        Some(g) => cursor + g.chars().count(),
+        None => cursor,
+    }
+}
+
 fn line_last_char(buf: &Rope, cursor: usize) -> usize {
     let line_idx = buf.char_to_line(cursor);
     let start_idx = buf.line_to_char(line_idx);
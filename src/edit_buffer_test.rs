@@ -19,3 +19,75 @@ fn test_rope_line_len() {
     let line = buf.line(buf.char_to_line(0));
     assert_eq!(12, line.len_chars());
 }
+
+#[test]
+fn test_cursor_position() {
+    let key = |code| Event::Key {
+        code,
+        modifiers: KeyModifiers::empty(),
+    };
+
+    let s = "hello world\nhow are you".to_string();
+    let mut buf = Buffer::from_reader(s.as_bytes()).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (0, 0));
+
+    for _ in 0..3 {
+        buf.handle_event(key(KeyCode::Right)).ok().unwrap();
+    }
+    assert_eq!(buf.cursor_position(), (3, 0));
+    buf.handle_event(key(KeyCode::Down)).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (3, 1));
+    buf.handle_event(key(KeyCode::End)).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (11, 1));
+    buf.handle_event(key(KeyCode::Home)).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (0, 1));
+    buf.handle_event(key(KeyCode::Up)).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (0, 0));
+    buf.handle_event(key(KeyCode::Char('x'))).ok().unwrap();
+    assert_eq!(buf.cursor_position(), (1, 0));
+}
+
+#[test]
+fn test_grapheme_backspace_delete() {
+    let key = |code| Event::Key {
+        code,
+        modifiers: KeyModifiers::empty(),
+    };
+
+    // flag emoji is two regional-indicator codepoints.
+    let s = "a\u{1F1EE}\u{1F1F3}b".to_string();
+    let mut buf = Buffer::from_reader(s.as_bytes()).ok().unwrap();
+    buf.handle_event(key(KeyCode::End)).ok().unwrap();
+    buf.handle_event(key(KeyCode::Backspace)).ok().unwrap();
+    assert_eq!(buf.to_string(), "a\u{1F1EE}\u{1F1F3}");
+    buf.handle_event(key(KeyCode::Backspace)).ok().unwrap();
+    assert_eq!(buf.to_string(), "a");
+    assert_eq!(buf.cursor_position(), (1, 0));
+
+    // `e` followed by a combining acute accent.
+    let s = "cafe\u{301}\nx".to_string();
+    let mut buf = Buffer::from_reader(s.as_bytes()).ok().unwrap();
+    buf.handle_event(key(KeyCode::End)).ok().unwrap();
+    buf.handle_event(key(KeyCode::Backspace)).ok().unwrap();
+    assert_eq!(buf.to_string(), "caf\nx");
+    assert_eq!(buf.cursor_position(), (3, 0));
+
+    let s = "e\u{301}\u{1F1EE}\u{1F1F3}x".to_string();
+    let mut buf = Buffer::from_reader(s.as_bytes()).ok().unwrap();
+    buf.handle_event(key(KeyCode::Delete)).ok().unwrap();
+    assert_eq!(buf.to_string(), "\u{1F1EE}\u{1F1F3}x");
+    buf.handle_event(key(KeyCode::Delete)).ok().unwrap();
+    assert_eq!(buf.to_string(), "x");
+    assert_eq!(buf.cursor_position(), (0, 0));
+
+    // buffer is still editable.
+    buf.handle_event(key(KeyCode::Char('y'))).ok().unwrap();
+    assert_eq!(buf.to_string(), "yx");
+
+    // backspace at line start joins lines.
+    let s = "ab\ncd".to_string();
+    let mut buf = Buffer::from_reader(s.as_bytes()).ok().unwrap();
+    buf.handle_event(key(KeyCode::Down)).ok().unwrap();
+    buf.handle_event(key(KeyCode::Backspace)).ok().unwrap();
+    assert_eq!(buf.to_string(), "abcd");
+}
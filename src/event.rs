@@ -1,10 +1,10 @@
 use crossterm::event::{self, KeyCode, KeyModifiers, MouseButton};
 
-use std::{fmt, result, sync::mpsc};
+use std::{fmt, path, result, sync::mpsc};
 
 use ledger::{
     core::{Error, Result},
-    err_at,
+    err_at, types,
 };
 
 #[derive(Clone, Debug)]
@@ -53,6 +53,20 @@ pub enum Event {
         from: chrono::Date<chrono::Local>,
         to: chrono::Date<chrono::Local>,
     },
+    OpenWorkspace(path::PathBuf),
+    // create the workspace filled in the new workspace form.
+    CreateWorkspace,
+    // add the journal entry filled in the entry form.
+    SubmitEntry,
+    // open the journal entry, by its key, in entry detail.
+    OpenEntry(String),
+    // open the ledger's detail, EG: from a leg in entry detail.
+    OpenLedger(types::KeyLedger),
+    // prompt for a name to save the journal entry, by its key, as a
+    // template.
+    SaveTemplate(String),
+    // text entered in a prompt.
+    Answer(String),
 }
 
 impl Event {
@@ -68,6 +82,14 @@ impl Event {
         }
     }
 
+    pub fn is_mouse(&self) -> bool {
+        match self {
+            Event::MouseDown { .. } | Event::MouseUp { .. } | Event::MouseDrag { .. } => true,
+            Event::MouseScrollDown { .. } | Event::MouseScrollUp { .. } => true,
+            _ => false,
+        }
+    }
+
     pub fn to_key_code(&self) -> Option<KeyCode> {
         match self {
             Event::Key { code, .. } => Some(code.clone()),
@@ -183,6 +205,13 @@ impl fmt::Display for Event {
             ),
             Event::Date(date) => write!(f, "date {}", date),
             Event::Period { from, to } => write!(f, "period from:{} to:{}", from, to),
+            Event::OpenWorkspace(dir) => write!(f, "open workspace {:?}", dir),
+            Event::CreateWorkspace => write!(f, "create workspace"),
+            Event::SubmitEntry => write!(f, "submit entry"),
+            Event::OpenEntry(key) => write!(f, "open entry {}", key),
+            Event::OpenLedger(key) => write!(f, "open ledger {}", key),
+            Event::SaveTemplate(key) => write!(f, "save template {}", key),
+            Event::Answer(text) => write!(f, "answer {}", text),
         }
     }
 }
@@ -213,3 +242,52 @@ impl Tx {
         Ok(())
     }
 }
+
+// Publish application events, like `Date` and `Period`, to all subscribed
+// elements.
+#[derive(Default)]
+pub struct Publisher {
+    listeners: Vec<Tx>,
+}
+
+impl Publisher {
+    pub fn subscribe(&mut self, tx: Tx) {
+        self.listeners.push(tx)
+    }
+
+    // Send `evnt` to every subscriber, dropping the ones that have
+    // disconnected.
+    pub fn publish(&mut self, evnt: Event) -> Result<()> {
+        let mut listeners = vec![];
+        for mut tx in self.listeners.drain(..) {
+            if let Ok(_) = tx.send(evnt.clone()) {
+                listeners.push(tx)
+            }
+        }
+        self.listeners = listeners;
+        Ok(())
+    }
+}
+
+// Drain all pending events on a subscription, calling `f` for each one.
+// Return true if `f` returned true for any of the events, that is, the
+// subscriber needs a refresh.
+pub fn drain<F>(rx: &mpsc::Receiver<Event>, mut f: F) -> Result<bool>
+where
+    F: FnMut(Event) -> bool,
+{
+    let mut refresh = false;
+    loop {
+        match rx.try_recv() {
+            Ok(evnt) => refresh = f(evnt) || refresh,
+            Err(mpsc::TryRecvError::Empty) => break Ok(refresh),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                break err_at!(IOError, msg: format!("drain"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "event_test.rs"]
+mod event_test;
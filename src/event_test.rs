@@ -0,0 +1,44 @@
+use chrono::TimeZone;
+
+use super::*;
+
+#[test]
+fn test_publish_period() {
+    let mut publisher: Publisher = Default::default();
+    let (tx1, rx1) = Tx::new();
+    let (tx2, rx2) = Tx::new();
+    publisher.subscribe(tx1);
+    publisher.subscribe(tx2);
+
+    let from = chrono::Local.ymd(2020, 4, 1);
+    let to = chrono::Local.ymd(2021, 3, 31);
+    publisher.publish(Event::Period { from, to }).unwrap();
+
+    for rx in [rx1, rx2].iter() {
+        let mut periods = vec![];
+        let refresh = drain(rx, |evnt| match evnt {
+            Event::Period { from, to } => {
+                periods.push((from, to));
+                true
+            }
+            _ => false,
+        })
+        .unwrap();
+        assert!(refresh);
+        assert_eq!(periods, vec![(from, to)]);
+    }
+}
+
+#[test]
+fn test_publish_disconnected() {
+    let mut publisher: Publisher = Default::default();
+    let (tx1, rx1) = Tx::new();
+    let (tx2, rx2) = Tx::new();
+    publisher.subscribe(tx1);
+    publisher.subscribe(tx2);
+    std::mem::drop(rx1);
+
+    publisher.publish(Event::Submit).unwrap();
+    assert_eq!(publisher.listeners.len(), 1);
+    assert_eq!(drain(&rx2, |_| false).unwrap(), false);
+}
@@ -0,0 +1,246 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use dirs;
+
+use std::{collections::HashMap, fmt, fs, path, result};
+
+use crate::event::Event;
+use ledger::{
+    core::{Error, Result},
+    err_at,
+};
+
+// logical actions, translated from raw key events via Keymap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    Cancel,
+    NextField,
+    PrevField,
+    Submit,
+    MoveUp,
+    MoveDown,
+    Command,
+    AddRow,
+    RemoveRow,
+    Copy,
+    Paste,
+    Reconcile,
+    Forward,
+    SortDate,
+    SortPayee,
+    SortAmount,
+    EditExternal,
+    InsertToday,
+    DisplayCurrency,
+    Undo,
+    Repeat,
+    SaveTemplate,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            Action::Quit => write!(f, "quit"),
+            Action::Cancel => write!(f, "cancel"),
+            Action::NextField => write!(f, "next-field"),
+            Action::PrevField => write!(f, "prev-field"),
+            Action::Submit => write!(f, "submit"),
+            Action::MoveUp => write!(f, "move-up"),
+            Action::MoveDown => write!(f, "move-down"),
+            Action::Command => write!(f, "command"),
+            Action::AddRow => write!(f, "add-row"),
+            Action::RemoveRow => write!(f, "remove-row"),
+            Action::Copy => write!(f, "copy"),
+            Action::Paste => write!(f, "paste"),
+            Action::Reconcile => write!(f, "reconcile"),
+            Action::Forward => write!(f, "forward"),
+            Action::SortDate => write!(f, "sort-date"),
+            Action::SortPayee => write!(f, "sort-payee"),
+            Action::SortAmount => write!(f, "sort-amount"),
+            Action::EditExternal => write!(f, "edit-external"),
+            Action::InsertToday => write!(f, "insert-today"),
+            Action::DisplayCurrency => write!(f, "display-currency"),
+            Action::Undo => write!(f, "undo"),
+            Action::Repeat => write!(f, "repeat"),
+            Action::SaveTemplate => write!(f, "save-template"),
+        }
+    }
+}
+
+impl Action {
+    fn from_name(name: &str) -> Result<Action> {
+        match name {
+            "quit" => Ok(Action::Quit),
+            "cancel" => Ok(Action::Cancel),
+            "next-field" => Ok(Action::NextField),
+            "prev-field" => Ok(Action::PrevField),
+            "submit" => Ok(Action::Submit),
+            "move-up" => Ok(Action::MoveUp),
+            "move-down" => Ok(Action::MoveDown),
+            "command" => Ok(Action::Command),
+            "add-row" => Ok(Action::AddRow),
+            "remove-row" => Ok(Action::RemoveRow),
+            "copy" => Ok(Action::Copy),
+            "paste" => Ok(Action::Paste),
+            "reconcile" => Ok(Action::Reconcile),
+            "forward" => Ok(Action::Forward),
+            "sort-date" => Ok(Action::SortDate),
+            "sort-payee" => Ok(Action::SortPayee),
+            "sort-amount" => Ok(Action::SortAmount),
+            "edit-external" => Ok(Action::EditExternal),
+            "insert-today" => Ok(Action::InsertToday),
+            "display-currency" => Ok(Action::DisplayCurrency),
+            "undo" => Ok(Action::Undo),
+            "repeat" => Ok(Action::Repeat),
+            "save-template" => Ok(Action::SaveTemplate),
+            _ => err_at!(InvalidInput, msg: format!("invalid action {:?}", name)),
+        }
+    }
+}
+
+// translate key events into actions. Defaults can be overridden by
+// `~/.ledger47/keymap`, where each line is of the form
+//
+//      <key> = <action>
+//
+// EG: `ctrl-n = next-field`, `ctrl-p = prev-field`.
+#[derive(Clone)]
+pub struct Keymap {
+    keys: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let none = KeyModifiers::empty();
+
+        let mut keys = HashMap::new();
+        keys.insert((KeyCode::Char('q'), none), Action::Quit);
+        keys.insert((KeyCode::Esc, none), Action::Cancel);
+        keys.insert((KeyCode::Enter, none), Action::NextField);
+        keys.insert((KeyCode::Tab, none), Action::NextField);
+        keys.insert((KeyCode::BackTab, none), Action::PrevField);
+        keys.insert((KeyCode::BackTab, KeyModifiers::SHIFT), Action::PrevField);
+        keys.insert((KeyCode::Char('s'), KeyModifiers::CONTROL), Action::Submit);
+        keys.insert((KeyCode::Up, KeyModifiers::ALT), Action::MoveUp);
+        keys.insert((KeyCode::Down, KeyModifiers::ALT), Action::MoveDown);
+        keys.insert((KeyCode::Char(':'), none), Action::Command);
+        keys.insert((KeyCode::Char(':'), KeyModifiers::SHIFT), Action::Command);
+        keys.insert((KeyCode::Char('n'), KeyModifiers::CONTROL), Action::AddRow);
+        keys.insert((KeyCode::Char('d'), KeyModifiers::CONTROL), Action::RemoveRow);
+        keys.insert((KeyCode::Char('y'), KeyModifiers::CONTROL), Action::Copy);
+        keys.insert((KeyCode::Char('v'), KeyModifiers::CONTROL), Action::Paste);
+        keys.insert((KeyCode::Char('r'), KeyModifiers::CONTROL), Action::Reconcile);
+        keys.insert((KeyCode::Right, KeyModifiers::ALT), Action::Forward);
+        keys.insert((KeyCode::Char('d'), KeyModifiers::ALT), Action::SortDate);
+        keys.insert((KeyCode::Char('p'), KeyModifiers::ALT), Action::SortPayee);
+        keys.insert((KeyCode::Char('a'), KeyModifiers::ALT), Action::SortAmount);
+        keys.insert((KeyCode::Char('e'), KeyModifiers::CONTROL), Action::EditExternal);
+        keys.insert((KeyCode::Char('t'), KeyModifiers::ALT), Action::InsertToday);
+        keys.insert((KeyCode::Char('c'), KeyModifiers::ALT), Action::DisplayCurrency);
+        keys.insert((KeyCode::Char('z'), KeyModifiers::CONTROL), Action::Undo);
+        keys.insert((KeyCode::Char('r'), KeyModifiers::ALT), Action::Repeat);
+        keys.insert((KeyCode::Char('s'), KeyModifiers::ALT), Action::SaveTemplate);
+
+        Keymap { keys }
+    }
+}
+
+impl Keymap {
+    // load keymap from user's config, fall back to defaults if there
+    // is no config file.
+    pub fn load() -> Result<Keymap> {
+        let loc = match dirs::home_dir() {
+            Some(mut loc) => {
+                loc.push(".ledger47");
+                loc.push("keymap");
+                loc
+            }
+            None => return Ok(Default::default()),
+        };
+
+        if path::Path::new(&loc).exists() {
+            let data = err_at!(IOError, fs::read(&loc), format!("{:?}", loc))?;
+            let text = err_at!(InvalidInput, std::str::from_utf8(&data))?;
+            Keymap::from_config(text)
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    // apply key mappings from config text on top of the defaults.
+    pub fn from_config(text: &str) -> Result<Keymap> {
+        let mut keymap: Keymap = Default::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split('=').map(|s| s.trim()).collect::<Vec<&str>>().as_slice() {
+                [key, action] => {
+                    let key = parse_key(key)?;
+                    let action = Action::from_name(action)?;
+                    keymap.keys.insert(key, action);
+                }
+                _ => err_at!(InvalidInput, msg: format!("invalid keymap {:?}", line))?,
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn to_action(&self, evnt: &Event) -> Option<Action> {
+        match evnt {
+            Event::Key { code, modifiers } => self.keys.get(&(*code, *modifiers)).cloned(),
+            _ => None,
+        }
+    }
+}
+
+// parse key specification like `q`, `esc`, `ctrl-n`, `alt-f1`.
+fn parse_key(spec: &str) -> Result<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::empty();
+    let mut parts: Vec<&str> = spec.split('-').collect();
+    let key = match parts.pop() {
+        Some("") if spec.ends_with('-') => "-",
+        Some(key) => key,
+        None => err_at!(InvalidInput, msg: format!("invalid key {:?}", spec))?,
+    };
+    for part in parts.into_iter().filter(|p| !p.is_empty()) {
+        match part {
+            "ctrl" => modifiers.insert(KeyModifiers::CONTROL),
+            "alt" => modifiers.insert(KeyModifiers::ALT),
+            "shift" => modifiers.insert(KeyModifiers::SHIFT),
+            _ => err_at!(InvalidInput, msg: format!("invalid modifier {:?}", part))?,
+        }
+    }
+
+    let code = match key {
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        key if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap()),
+        key if key.starts_with('f') => {
+            let n: u8 = err_at!(InvalidInput, key[1..].parse(), format!("key {:?}", spec))?;
+            KeyCode::F(n)
+        }
+        _ => err_at!(InvalidInput, msg: format!("invalid key {:?}", spec))?,
+    };
+
+    Ok((code, modifiers))
+}
+
+#[cfg(test)]
+#[path = "keymap_test.rs"]
+mod keymap_test;
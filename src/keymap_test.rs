@@ -0,0 +1,77 @@
+use super::*;
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+    Event::Key { code, modifiers }
+}
+
+#[test]
+fn test_keymap_default() {
+    let keymap: Keymap = Default::default();
+    let none = KeyModifiers::empty();
+
+    let evnt = key(KeyCode::Char('q'), none);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Quit));
+    let evnt = key(KeyCode::Tab, none);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::NextField));
+    let evnt = key(KeyCode::Up, KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::MoveUp));
+    let evnt = key(KeyCode::Down, KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::MoveDown));
+    let evnt = key(KeyCode::Char(':'), none);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Command));
+    let evnt = key(KeyCode::Char('n'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::AddRow));
+    let evnt = key(KeyCode::Char('d'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::RemoveRow));
+    let evnt = key(KeyCode::Char('y'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Copy));
+    let evnt = key(KeyCode::Char('v'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Paste));
+    let evnt = key(KeyCode::Char('r'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Reconcile));
+    let evnt = key(KeyCode::Right, KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Forward));
+    let evnt = key(KeyCode::Char('d'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::SortDate));
+    let evnt = key(KeyCode::Char('p'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::SortPayee));
+    let evnt = key(KeyCode::Char('a'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::SortAmount));
+    let evnt = key(KeyCode::Char('e'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::EditExternal));
+    let evnt = key(KeyCode::Char('t'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::InsertToday));
+    let evnt = key(KeyCode::Char('c'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::DisplayCurrency));
+    let evnt = key(KeyCode::Char('z'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Undo));
+    let evnt = key(KeyCode::Char('r'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Repeat));
+    let evnt = key(KeyCode::Char('s'), KeyModifiers::ALT);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::SaveTemplate));
+    let evnt = key(KeyCode::Char('x'), none);
+    assert_eq!(keymap.to_action(&evnt), None);
+}
+
+#[test]
+fn test_keymap_config() {
+    let text = "# emacs style\nctrl-n = next-field\nctrl-p = prev-field\nctrl-x = quit\n";
+    let keymap = Keymap::from_config(text).unwrap();
+    let none = KeyModifiers::empty();
+
+    let evnt = key(KeyCode::Char('n'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::NextField));
+    let evnt = key(KeyCode::Char('p'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::PrevField));
+    let evnt = key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Quit));
+
+    // unmapped keys fall back to defaults.
+    let evnt = key(KeyCode::Esc, none);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Cancel));
+    let evnt = key(KeyCode::Char('q'), none);
+    assert_eq!(keymap.to_action(&evnt), Some(Action::Quit));
+
+    assert!(Keymap::from_config("ctrl-n = jump").is_err());
+    assert!(Keymap::from_config("hyper-n = quit").is_err());
+}
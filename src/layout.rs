@@ -0,0 +1,143 @@
+// layout arithmetic over terminal cells. Elements can be laid out in
+// degenerate viewports, zero sized or at the terminal origin, so all of it
+// saturates: such elements render clipped instead of panicking.
+
+// `a - b` cells, zero when `b` exceeds `a`.
+#[inline]
+pub fn sub(a: u16, b: u16) -> u16 {
+    a.saturating_sub(b)
+}
+
+// `off` cells after `origin`, clipped at the last addressable cell.
+#[inline]
+pub fn add(origin: u16, off: u16) -> u16 {
+    origin.saturating_add(off)
+}
+
+// last cell of a span of `len` cells starting at `origin`. For zero
+// `len` this is the cell before `origin`.
+#[inline]
+pub fn last(origin: u16, len: u16) -> u16 {
+    sub(add(origin, len), 1)
+}
+
+// cells left in `width` after `used` cells, zero when over-filled.
+#[inline]
+pub fn remaining(width: u16, used: usize) -> usize {
+    (width as usize).saturating_sub(used)
+}
+
+// left and right padding to center `t_width` cells within `width`.
+// Text wider than `width` gets no padding.
+pub fn center(width: u16, t_width: u16) -> (u16, u16) {
+    let pad = sub(width, t_width);
+    let left = pad / 2;
+    (left, pad - left)
+}
+
+// split `width` cells into a left and a right pane, the right pane is
+// `right` cells wide but never more than half of `width`.
+pub fn split(width: u16, right: u16) -> (u16, u16) {
+    let right = std::cmp::min(right, width / 2);
+    (sub(width, right), right)
+}
+
+// width spec for a list column. A column takes at least `min` cells and
+// grows upto `max` cells, zero `max` being unbounded, in proportion to
+// its `flex`. Columns with zero `flex` stay at `min`. When `min` of all
+// columns does not fit, columns with lower `priority` are hidden first.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Column {
+    pub min: u16,
+    pub max: u16,
+    pub flex: u16,
+    pub priority: u16,
+}
+
+impl Column {
+    pub fn fixed(width: u16, priority: u16) -> Column {
+        Column {
+            min: width,
+            max: width,
+            flex: 0,
+            priority,
+        }
+    }
+
+    pub fn flex(min: u16, max: u16, flex: u16, priority: u16) -> Column {
+        Column {
+            min,
+            max,
+            flex,
+            priority,
+        }
+    }
+
+    fn can_grow(&self, width: u16) -> bool {
+        self.flex > 0 && (self.max == 0 || width < self.max)
+    }
+}
+
+// solve widths for `columns` laid out in `width` cells, with a one cell
+// gap between visible columns. Hidden columns get zero width. Among
+// columns of equal priority the right most is hidden first.
+pub fn solve_columns(width: u16, columns: &[Column]) -> Vec<u16> {
+    let mut visible: Vec<bool> = columns.iter().map(|c| c.min > 0 || c.flex > 0).collect();
+
+    let required = |visible: &[bool]| -> usize {
+        let mins = columns.iter().zip(visible).filter(|(_, v)| **v).map(|(c, _)| c.min as usize);
+        let n = visible.iter().filter(|v| **v).count();
+        mins.sum::<usize>() + n.saturating_sub(1)
+    };
+    while required(&visible) > (width as usize) {
+        let victim = columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| visible[*i])
+            .min_by_key(|(i, c)| (c.priority, std::cmp::Reverse(*i)))
+            .map(|(i, _)| i);
+        match victim {
+            Some(i) => visible[i] = false,
+            None => break,
+        }
+    }
+
+    let mut widths: Vec<u16> = columns
+        .iter()
+        .zip(visible.iter())
+        .map(|(c, v)| if *v { c.min } else { 0 })
+        .collect();
+
+    let mut leftover = remaining(width, required(&visible));
+    while leftover > 0 {
+        let growable: Vec<usize> = (0..columns.len())
+            .filter(|i| visible[*i] && columns[*i].can_grow(widths[*i]))
+            .collect();
+        let total_flex: usize = growable.iter().map(|i| columns[*i].flex as usize).sum();
+        if total_flex == 0 {
+            break;
+        }
+
+        let mut given = 0;
+        for i in growable.into_iter() {
+            let share = std::cmp::max(leftover * (columns[i].flex as usize) / total_flex, 1);
+            let share = std::cmp::min(share, leftover - given);
+            let share = match columns[i].max {
+                0 => share,
+                max => std::cmp::min(share, (max - widths[i]) as usize),
+            };
+            widths[i] += share as u16;
+            given += share;
+            if given == leftover {
+                break;
+            }
+        }
+        leftover -= given;
+    }
+
+    widths
+}
+
+#[cfg(test)]
+#[path = "layout_test.rs"]
+mod layout_test;
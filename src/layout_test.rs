@@ -0,0 +1,54 @@
+use super::*;
+
+#[test]
+fn test_layout() {
+    assert_eq!(sub(10, 3), 7);
+    assert_eq!(sub(3, 10), 0);
+    assert_eq!(add(10, 3), 13);
+    assert_eq!(add(u16::MAX, 3), u16::MAX);
+
+    assert_eq!(last(5, 4), 8);
+    assert_eq!(last(5, 0), 4);
+    assert_eq!(last(0, 0), 0);
+
+    assert_eq!(remaining(10, 4), 6);
+    assert_eq!(remaining(4, 10), 0);
+
+    assert_eq!(center(10, 4), (3, 3));
+    assert_eq!(center(10, 5), (2, 3));
+    assert_eq!(center(4, 10), (0, 0));
+    assert_eq!(center(0, 0), (0, 0));
+}
+
+#[test]
+fn test_layout_split() {
+    assert_eq!(split(100, 30), (70, 30));
+    assert_eq!(split(40, 30), (20, 20));
+    assert_eq!(split(1, 30), (1, 0));
+    assert_eq!(split(0, 30), (0, 0));
+}
+
+#[test]
+fn test_solve_columns() {
+    // date, payee, note and amount.
+    let columns = vec![
+        Column::fixed(9, 3),
+        Column::flex(10, 0, 2, 3),
+        Column::flex(10, 40, 1, 1),
+        Column::fixed(12, 2),
+    ];
+
+    // wide terminal, all columns shown, flex columns fill the rest.
+    let widths = solve_columns(100, &columns);
+    assert_eq!(widths, vec![9, 48, 28, 12]);
+    assert_eq!(widths.iter().sum::<u16>() + 3, 100);
+
+    // note column stops growing at its max.
+    let widths = solve_columns(200, &columns);
+    assert_eq!(widths, vec![9, 136, 40, 12]);
+
+    // narrow terminal, low priority columns are dropped.
+    assert_eq!(solve_columns(40, &columns), vec![9, 17, 0, 12]);
+    assert_eq!(solve_columns(20, &columns), vec![9, 10, 0, 0]);
+    assert_eq!(solve_columns(5, &columns), vec![0, 0, 0, 0]);
+}
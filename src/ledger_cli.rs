@@ -0,0 +1,386 @@
+use chrono::{self, offset::TimeZone};
+use log::warn;
+
+use std::io;
+
+use crate::{
+    core::{Error, Result, Store},
+    types, util,
+};
+
+// beancount directives that share the `<date> <keyword>` layout with
+// transactions, they are not imported.
+const DIRECTIVES: [&'static str; 11] = [
+    "open", "close", "balance", "price", "pad", "note", "document", "commodity", "event",
+    "custom", "query",
+];
+
+// import journal entries from a subset of ledger-cli and beancount
+// formats, and commit them to `store`. Return the number of entries
+// imported. Nothing is imported if any line fails to parse, if an
+// entry's date is rejected by `check`, refer JournalEntry::validate_date(),
+// or if any entry fails to be written.
+pub fn import_ledger_format<S, R>(store: &mut S, mut r: R, check: &types::DateCheck) -> Result<usize>
+where
+    S: Store,
+    R: io::Read,
+{
+    let mut text = String::default();
+    err_at!(IOError, r.read_to_string(&mut text))?;
+
+    let entries = parse_ledger_format(&text)?;
+    let (w, now) = (store.to_workspace()?, chrono::Utc::now());
+    for entry in entries.iter() {
+        if let Some(msg) = entry.validate_date(&w, now, check)? {
+            warn!("import {}", msg);
+        }
+    }
+
+    // entries written before a failed put are reverted.
+    store.mark_undo()?;
+    let n = entries.len();
+    for entry in entries.into_iter() {
+        if let Err(err) = store.put(entry) {
+            store.undo()?;
+            return Err(err);
+        }
+    }
+    store.commit()?;
+
+    Ok(n)
+}
+
+// write journal entries between `from` and `to` as ledger-cli
+// transaction blocks, refer to_ledger_format().
+pub fn export_ledger_format<S, W>(
+    store: &mut S,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    w: &mut W,
+) -> Result<()>
+where
+    S: Store,
+    W: io::Write,
+{
+    let mut entries = vec![];
+    for entry in store.iter_journal(from, to)? {
+        entries.push(entry?);
+    }
+
+    let output = to_ledger_format(&entries);
+    err_at!(IOError, w.write_all(output.as_bytes()))?;
+    Ok(())
+}
+
+// one transaction block per entry, debits followed by credits. Ledger
+// `food` under company `expenses` is written as account `Expenses:Food`.
+// Amounts are not localized, so that they can be imported back.
+pub fn to_ledger_format(entries: &[types::JournalEntry]) -> String {
+    let mut s = String::default();
+    for entry in entries.iter() {
+        s.push_str(&format!(
+            "{} {}\n",
+            entry.created.format("%Y/%m/%d"),
+            entry.payee
+        ));
+        let legs = entry
+            .debitors
+            .iter()
+            .map(|d| (&d.ledger, &d.commodity, 1.0))
+            .chain(entry.creditors.iter().map(|c| (&c.ledger, &c.commodity, -1.0)));
+        for (ledger, commodity, sign) in legs {
+            s.push_str(&format!(
+                "    {:<32}  {:>14} {}\n",
+                to_account(ledger),
+                util::NumberLocale::Plain.format(sign * commodity.value),
+                commodity.name.to_uppercase()
+            ));
+        }
+        s.push_str("\n");
+    }
+    s
+}
+
+fn to_account(ledger: &types::KeyLedger) -> String {
+    let (cname, lname): (String, String) = ledger.clone().into();
+    format!("{}:{}", capitalize(&cname), capitalize(&lname))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(ch) => ch.to_uppercase().chain(chars).collect(),
+        None => String::default(),
+    }
+}
+
+// parse transaction blocks, each made of a header line
+//
+//      2020-01-05 * "payee" "narration"
+//      2020/01/05 payee
+//
+// followed by indented posting lines, `<account>  <amount> <commodity>`.
+// Positive amounts are debits and negative amounts are credits, atmost
+// one posting can leave out its amount to balance the transaction.
+// Accounts are mapped to company and ledger, `Expenses:Food:Snacks` is
+// ledger `food_snacks` under company `expenses`.
+pub fn parse_ledger_format(text: &str) -> Result<Vec<types::JournalEntry>> {
+    let mut entries = vec![];
+    let mut errors = vec![];
+    let mut txn: Option<Txn> = None;
+    // skip indented lines following a header that failed to parse.
+    let mut skip = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let lineno = i + 1;
+        // blank line ends a transaction, comments don't.
+        if line.trim().is_empty() {
+            finish_txn(txn.take(), &mut entries, &mut errors);
+            skip = false;
+            continue;
+        }
+        let line = match line.find(';') {
+            Some(n) => &line[..n],
+            None => line,
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+
+        if indented {
+            match txn.as_mut() {
+                Some(txn) => match parse_posting(line) {
+                    Ok(Some(posting)) => txn.postings.push((lineno, posting)),
+                    Ok(None) => (),
+                    Err(err) => errors.push(format!("line {}: {}", lineno, err)),
+                },
+                None if skip => (),
+                None => errors.push(format!("line {}: posting outside transaction", lineno)),
+            }
+            continue;
+        }
+
+        finish_txn(txn.take(), &mut entries, &mut errors);
+        match parse_header(line) {
+            Ok((date, payee)) => {
+                txn = Some(Txn {
+                    lineno,
+                    date,
+                    payee,
+                    postings: vec![],
+                });
+                skip = false;
+            }
+            Err(err) => {
+                errors.push(format!("line {}: {}", lineno, err));
+                skip = true;
+            }
+        }
+    }
+    finish_txn(txn.take(), &mut entries, &mut errors);
+
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        err_at!(InvalidInput, msg: errors.join("; "))
+    }
+}
+
+// transaction block in the middle of parsing.
+struct Txn {
+    lineno: usize,
+    date: chrono::NaiveDate,
+    payee: String,
+    // (lineno, posting)
+    postings: Vec<(usize, Posting)>,
+}
+
+struct Posting {
+    company: String,
+    ledger: String,
+    // (commodity, value), None for elided amount.
+    amount: Option<(String, f64)>,
+}
+
+fn finish_txn(txn: Option<Txn>, entries: &mut Vec<types::JournalEntry>, errors: &mut Vec<String>) {
+    if let Some(txn) = txn {
+        let lineno = txn.lineno;
+        match to_entry(txn) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => errors.push(format!("line {}: {}", lineno, err)),
+        }
+    }
+}
+
+fn to_entry(txn: Txn) -> Result<types::JournalEntry> {
+    if txn.postings.len() < 2 {
+        err_at!(InvalidInput, msg: format!("transaction needs atleast two postings"))?;
+    }
+
+    // fill in the elided amount.
+    let mut elided = None;
+    let mut totals: Vec<(String, f64)> = vec![];
+    for (i, (lineno, posting)) in txn.postings.iter().enumerate() {
+        match &posting.amount {
+            Some((name, value)) => match totals.iter_mut().find(|(n, _)| n == name) {
+                Some(total) => total.1 += value,
+                None => totals.push((name.clone(), *value)),
+            },
+            None if elided.is_none() => elided = Some(i),
+            None => err_at!(InvalidInput, msg: format!("line {} second elided amount", lineno))?,
+        }
+    }
+    let mut postings: Vec<Posting> = txn.postings.into_iter().map(|(_, p)| p).collect();
+    if let Some(i) = elided {
+        match totals.as_slice() {
+            [(name, total)] => postings[i].amount = Some((name.clone(), -total)),
+            _ => err_at!(InvalidInput, msg: format!("can't balance elided amount"))?,
+        }
+    }
+
+    let created = chrono::Utc.from_utc_date(&txn.date).and_hms(0, 0, 0);
+    let mut entry = types::JournalEntry::new(txn.payee, created);
+    for posting in postings.into_iter() {
+        let (name, value) = posting.amount.unwrap();
+        if value < 0.0 {
+            entry.add_creditor(posting.company, posting.ledger, (name, -value))?;
+        } else {
+            entry.add_debitor(posting.company, posting.ledger, (name, value))?;
+        }
+    }
+    entry.validate()?;
+
+    Ok(entry)
+}
+
+fn parse_header(line: &str) -> Result<(chrono::NaiveDate, String)> {
+    let line = line.trim();
+    let (date, rest) = match line.find(char::is_whitespace) {
+        Some(n) => (&line[..n], line[n..].trim()),
+        None => (line, ""),
+    };
+    let date = ["%Y-%m-%d", "%Y/%m/%d"]
+        .iter()
+        .filter_map(|f| chrono::NaiveDate::parse_from_str(date, f).ok())
+        .next();
+    let date = match date {
+        Some(date) => date,
+        None => {
+            let word = line.split_whitespace().next().unwrap_or("");
+            err_at!(InvalidInput, msg: format!("unparseable directive {:?}", word))?
+        }
+    };
+
+    let word = rest.split_whitespace().next().unwrap_or("");
+    if DIRECTIVES.contains(&word) {
+        err_at!(InvalidInput, msg: format!("unparseable directive {:?}", word))?;
+    }
+
+    // optional flag and code.
+    let mut rest = rest;
+    if rest.starts_with('*') || rest.starts_with('!') {
+        rest = rest[1..].trim_start();
+    }
+    if rest.starts_with('(') {
+        rest = match rest.find(')') {
+            Some(n) => rest[(n + 1)..].trim_start(),
+            None => err_at!(InvalidInput, msg: format!("unterminated code"))?,
+        };
+    }
+
+    // beancount quotes payee and narration, payee is the first of them.
+    let payee = if rest.starts_with('"') {
+        match rest[1..].find('"') {
+            Some(n) => rest[1..(n + 1)].to_string(),
+            None => err_at!(InvalidInput, msg: format!("unterminated payee"))?,
+        }
+    } else {
+        rest.to_string()
+    };
+
+    Ok((date, payee))
+}
+
+// return None for metadata lines, `key: value`.
+fn parse_posting(line: &str) -> Result<Option<Posting>> {
+    let line = line.trim();
+    // account is separated from amount by a tab or two spaces, beancount
+    // accounts have no spaces.
+    let n = match (line.find('\t'), line.find("  ")) {
+        (Some(x), Some(y)) => Some(std::cmp::min(x, y)),
+        (Some(x), None) | (None, Some(x)) => Some(x),
+        (None, None) => line.find(' '),
+    };
+    let (account, amount) = match n {
+        Some(n) => (&line[..n], line[n..].trim()),
+        None => (line, ""),
+    };
+    if account.ends_with(':') {
+        return Ok(None);
+    }
+
+    let (company, ledger) = parse_account(account)?;
+    let amount = match amount {
+        "" => None,
+        amount => Some(parse_amount(amount)?),
+    };
+
+    Ok(Some(Posting {
+        company,
+        ledger,
+        amount,
+    }))
+}
+
+fn parse_account(account: &str) -> Result<(String, String)> {
+    let parts: Vec<String> = account
+        .split(':')
+        .map(|s| {
+            let s = s.trim().to_lowercase();
+            s.replace(|ch: char| ch == '-' || ch == ' ', "_")
+        })
+        .collect();
+    match parts.as_slice() {
+        [company, ledger, ..] if !company.is_empty() && !ledger.is_empty() => {
+            let ledger = parts[1..].join("_");
+            if util::str_as_anuh(company) && util::str_as_anuh(&ledger) {
+                Ok((company.clone(), ledger))
+            } else {
+                err_at!(InvalidInput, msg: format!("invalid account {:?}", account))
+            }
+        }
+        _ => err_at!(InvalidInput, msg: format!("account {:?} needs a company", account)),
+    }
+}
+
+// `-100.00 INR` or `INR -100.00`.
+fn parse_amount(amount: &str) -> Result<(String, f64)> {
+    if amount.contains('@') || amount.contains('{') {
+        err_at!(InvalidInput, msg: format!("prices not supported {:?}", amount))?;
+    }
+
+    let tokens: Vec<&str> = amount.split_whitespace().collect();
+    let (name, value) = match tokens.as_slice() {
+        [x, y] => match (parse_number(x), parse_number(y)) {
+            (Some(value), None) => (y.to_string(), value),
+            (None, Some(value)) => (x.to_string(), value),
+            _ => err_at!(InvalidInput, msg: format!("invalid amount {:?}", amount))?,
+        },
+        _ => err_at!(InvalidInput, msg: format!("amount {:?} needs a commodity", amount))?,
+    };
+    let name = name.to_lowercase();
+    if !util::str_as_anuh(&name) {
+        err_at!(InvalidInput, msg: format!("invalid commodity {:?}", name))?;
+    }
+
+    Ok((name, value))
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.replace(',', "").parse().ok()
+}
+
+#[cfg(test)]
+#[path = "ledger_cli_test.rs"]
+mod ledger_cli_test;
@@ -0,0 +1,173 @@
+use chrono::Datelike;
+
+use super::*;
+use crate::{core::Durable, mem_store::testing};
+
+const SAMPLE: &'static str = "\
+; groceries, ledger-cli style
+2020/05/01 * (101) Grocer
+    Expenses:Food:Snacks        120.50 INR  ; chips
+    Assets:Cash
+
+2020-05-02 * \"Cafe\" \"coffee with team\"
+  Expenses:Food  INR 50.00
+  Assets:Bank-Account  -50.00 INR
+";
+
+#[test]
+fn test_parse_ledger_format() {
+    let entries = parse_ledger_format(SAMPLE).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let je = &entries[0];
+    assert_eq!(je.payee, "Grocer");
+    assert_eq!(
+        (je.created.year(), je.created.month(), je.created.day()),
+        (2020, 5, 1)
+    );
+    assert_eq!(je.debitors.len(), 1);
+    assert_eq!(je.debitors[0].ledger.to_string(), "ledger-expenses-food_snacks");
+    assert_eq!(je.debitors[0].commodity.name, "inr");
+    assert_eq!(je.debitors[0].commodity.value, 120.5);
+    // elided amount balances the transaction.
+    assert_eq!(je.creditors.len(), 1);
+    assert_eq!(je.creditors[0].ledger.to_string(), "ledger-assets-cash");
+    assert_eq!(je.creditors[0].commodity.value, 120.5);
+
+    let je = &entries[1];
+    assert_eq!(je.payee, "Cafe");
+    assert_eq!(je.debitors[0].ledger.to_string(), "ledger-expenses-food");
+    assert_eq!(je.debitors[0].commodity.value, 50.0);
+    assert_eq!(je.creditors[0].ledger.to_string(), "ledger-assets-bank_account");
+    assert_eq!(je.creditors[0].commodity.value, 50.0);
+}
+
+#[test]
+fn test_parse_ledger_format_errors() {
+    let text = "\
+2020-01-01 open Assets:Cash INR
+  meta: \"ignored\"
+
+option \"title\" \"books\"
+
+2020-05-01 Grocer
+  Expenses:Food  10.00 INR
+  Assets:Cash  -5.00 INR
+
+2020-05-02 Cafe
+  Expenses:Food  10.00 INR @ 2 USD
+  Assets:Cash
+";
+    match parse_ledger_format(text) {
+        Err(Error::InvalidInput(msg)) => {
+            assert!(msg.contains("line 1: "), "{}", msg);
+            assert!(msg.contains("unparseable directive \"open\""), "{}", msg);
+            assert!(msg.contains("line 4: "), "{}", msg);
+            assert!(msg.contains("unparseable directive \"option\""), "{}", msg);
+            assert!(msg.contains("line 6: "), "{}", msg);
+            assert!(msg.contains("imbalance"), "{}", msg);
+            assert!(msg.contains("line 11: "), "{}", msg);
+            assert!(!msg.contains("line 2: "), "{}", msg);
+        }
+        _ => panic!("expected invalid input"),
+    }
+}
+
+#[test]
+fn test_import_ledger_format() {
+    let mut store = testing::make_store();
+    let from = chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2020, 12, 31).and_hms(0, 0, 0);
+
+    // entries dated before the workspace was created are rejected,
+    // unless the books are configured to start earlier.
+    let mut check = types::DateCheck::default();
+    check.reject = true;
+    match import_ledger_format(&mut store, SAMPLE.as_bytes(), &check) {
+        Err(Error::InvalidDate(msg)) => assert!(msg.contains("2020-05-01"), "{}", msg),
+        res => panic!("expected rejected date {:?}", res),
+    }
+    assert_eq!(store.iter_journal(from, to).unwrap().count(), 0);
+    check.not_before = Some(chrono::NaiveDate::from_ymd(2020, 1, 1));
+
+    let n = import_ledger_format(&mut store, SAMPLE.as_bytes(), &check).unwrap();
+    assert_eq!(n, 2);
+    assert_eq!(store.iter_journal(from, to).unwrap().count(), 2);
+
+    // nothing is imported on error.
+    let text = "2020-06-01 Rent\n  Expenses:Rent  100 INR\n";
+    assert!(import_ledger_format(&mut store, text.as_bytes(), &check).is_err());
+    assert_eq!(store.iter_journal(from, to).unwrap().count(), 2);
+}
+
+#[test]
+fn test_import_ledger_format_partial() {
+    let mut pp = std::env::temp_dir();
+    pp.push("ledger47-test");
+    pp.push("test_import_ledger_format_partial");
+    std::fs::remove_dir_all(&pp).ok();
+    std::fs::create_dir_all(&pp).unwrap();
+    let dir = pp.into_os_string();
+    let w = types::Workspace::new("test".to_string());
+    let mut db = crate::db_files::Db::create(&dir, w).unwrap();
+
+    // a file in place of the second entry's day directory fails its put.
+    let key = parse_ledger_format(SAMPLE).unwrap()[1].to_key();
+    let mut day = std::path::PathBuf::from(&dir);
+    day.push("journal");
+    day.extend(key.split('-').take(3));
+    std::fs::create_dir_all(day.parent().unwrap()).unwrap();
+    std::fs::write(&day, "").unwrap();
+
+    let check = types::DateCheck::default();
+    assert!(import_ledger_format(&mut db, SAMPLE.as_bytes(), &check).is_err());
+    let from = chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2020, 12, 31).and_hms(0, 0, 0);
+    assert_eq!(db.iter_journal(from, to).unwrap().count(), 0);
+    assert!(!db.is_modified());
+}
+
+#[test]
+fn test_export_ledger_format() {
+    let mut store = testing::make_store();
+    let check = types::DateCheck::default();
+    import_ledger_format(&mut store, SAMPLE.as_bytes(), &check).unwrap();
+
+    let from = chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let to = chrono::Utc.ymd(2020, 12, 31).and_hms(0, 0, 0);
+    let mut out: Vec<u8> = vec![];
+    export_ledger_format(&mut store, from, to, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "2020/05/01 Grocer");
+    assert!(lines[1].starts_with("    Expenses:Food_snacks "), "{}", text);
+    assert!(lines[1].ends_with(" 120.50 INR"), "{}", text);
+    assert!(lines[2].starts_with("    Assets:Cash "), "{}", text);
+    assert!(lines[2].ends_with(" -120.50 INR"), "{}", text);
+    assert_eq!(lines[3], "");
+    assert_eq!(lines[4], "2020/05/02 Cafe");
+
+    // round trip, export of re-imported entries is identical.
+    let entries = parse_ledger_format(&text).unwrap();
+    assert_eq!(to_ledger_format(&entries), text);
+
+    let normalize = |text: &str| -> Vec<String> {
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .map(|l| l.to_lowercase())
+            .collect()
+    };
+    assert_eq!(
+        normalize(&text),
+        vec![
+            "2020/05/01 grocer",
+            "expenses:food_snacks 120.50 inr",
+            "assets:cash -120.50 inr",
+            "2020/05/02 cafe",
+            "expenses:food 50.00 inr",
+            "assets:bank_account -50.00 inr",
+        ]
+    );
+}
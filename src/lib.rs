@@ -4,4 +4,9 @@ pub mod cache;
 #[macro_use]
 pub mod util;
 pub mod db_files;
+pub mod ledger_cli;
+pub mod mem_store;
+pub mod rates;
+pub mod report;
 pub mod types;
+pub mod verify;
@@ -3,17 +3,22 @@ use log::error;
 use simplelog;
 use structopt::StructOpt;
 
-use std::{ffi, fs};
+use std::{ffi, fs, io};
 
 mod app;
+mod audit;
+mod command;
 mod edit_buffer;
 mod event;
+mod keymap;
+mod layout;
+mod recent;
 mod term_elements;
 mod term_layers;
 
 use ledger::{
-    core::{Error, Result},
-    db_files, err_at,
+    core::{self, Error, Result, Store},
+    cache, db_files, err_at, report, types, util,
 };
 
 // commands:
@@ -40,6 +45,116 @@ pub struct Opt {
 
     //#[structopt(long = "percentile", default_value = "99")]
     //percentile: String,
+    #[structopt(
+        long = "export",
+        help = "export report to stdout, trial-balance|statement|csv|ledger"
+    )]
+    export: Option<String>,
+
+    #[structopt(long = "from", help = "export from date, YYYY-MM-DD")]
+    from: Option<String>,
+
+    #[structopt(long = "to", help = "export till date, YYYY-MM-DD")]
+    to: Option<String>,
+
+    #[structopt(long = "hide-zero", help = "export without zero-balance ledgers")]
+    hide_zero: bool,
+
+    #[structopt(
+        long = "max-note-len",
+        default_value = "10000",
+        help = "maximum length, in chars, of journal-entry notes"
+    )]
+    max_note_len: usize,
+
+    #[structopt(
+        long = "rounding",
+        default_value = "half-up",
+        help = "rounding for converted and formatted amounts, half-up|half-even"
+    )]
+    rounding: util::RoundingMode,
+
+    #[structopt(
+        long = "amount-style",
+        default_value = "code",
+        help = "label amounts with commodity, code|symbol"
+    )]
+    amount_style: util::AmountStyle,
+
+    #[structopt(
+        long = "number-locale",
+        default_value = "plain",
+        help = "group and decimal separators for amounts, plain|us|eu|in"
+    )]
+    number_locale: util::NumberLocale,
+
+    #[structopt(
+        long = "symbol-range",
+        help = "chars commodity symbols are shown with, else code, ascii|bmp|any, default from locale"
+    )]
+    symbol_range: Option<util::SymbolRange>,
+
+    #[structopt(
+        long = "cursor-style",
+        default_value = "default",
+        help = "edit cursor, default|blinking-block|steady-block|blinking-underline|steady-underline|blinking-bar|steady-bar"
+    )]
+    cursor_style: term_elements::CursorStyle,
+
+    #[structopt(
+        long = "week-start",
+        default_value = "monday",
+        help = "first day of the week in calendars, monday|sunday"
+    )]
+    week_start: util::WeekStart,
+
+    #[structopt(long = "autocommit", help = "commit after every change")]
+    autocommit: bool,
+
+    #[structopt(
+        long = "max-future-days",
+        default_value = "365",
+        help = "warn about entries dated further ahead than this"
+    )]
+    max_future_days: usize,
+
+    #[structopt(
+        long = "reject-odd-dates",
+        help = "reject, instead of warning about, entries dated far ahead or before the workspace"
+    )]
+    reject_odd_dates: bool,
+
+    #[structopt(
+        long = "books-from",
+        help = "date the books start, to enter entries dated before the workspace was created"
+    )]
+    books_from: Option<String>,
+
+    #[structopt(
+        long = "safe",
+        help = "open workspace read-only, without resetting or committing"
+    )]
+    safe: bool,
+
+    #[structopt(
+        long = "allow-custom-groups",
+        help = "allow ledger groups outside the workspace's chart of accounts"
+    )]
+    allow_custom_groups: bool,
+
+    #[structopt(
+        long = "no-mouse",
+        help = "leave the mouse to the terminal, for selecting and copying text"
+    )]
+    no_mouse: bool,
+
+    #[structopt(
+        long = "editor",
+        default_value = "",
+        help = "editor for long notes, defaults to $EDITOR or vi"
+    )]
+    editor: String,
+
     #[structopt(short = "v", long = "verbose")]
     verbose: bool,
 
@@ -53,14 +168,87 @@ fn main() {
     match init_logger(&opts) {
         Ok(()) => (),
         Err(err) => {
-            println!("{}", err);
+            eprintln!("{}", err);
             std::process::exit(1);
         }
     }
+
+    if let Some(name) = &opts.export {
+        match export(&opts, name) {
+            Ok(()) => (),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let not_before = match opts.books_from.as_ref().map(|s| util::parse_date_flexible(s)) {
+        Some(Ok(date)) => Some(date),
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        None => None,
+    };
     let dir: &ffi::OsStr = opts.dir.as_ref();
-    match app::Application::<db_files::Db>::run(dir) {
+    let config = app::Config {
+        cursor_style: opts.cursor_style,
+        no_mouse: opts.no_mouse,
+        editor: opts.editor.clone(),
+        week_start: opts.week_start,
+        max_note_len: opts.max_note_len,
+        amount_format: to_amount_format(&opts),
+        options: core::Options {
+            custom_groups: opts.allow_custom_groups,
+            safe: opts.safe,
+            autocommit: opts.autocommit,
+        },
+        date_check: types::DateCheck {
+            max_future_days: opts.max_future_days,
+            not_before,
+            reject: opts.reject_odd_dates,
+        },
+        home: app::default_home(),
+    };
+    match app::Application::<cache::Cache<db_files::Db>>::run(dir, config) {
         Ok(()) => (),
-        Err(err) => error!("{}", err),
+        Err(err) => {
+            error!("{}", err);
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// run the report and print it on stdout, without launching the TUI.
+fn export(opts: &Opt, name: &str) -> Result<()> {
+    if !report::REPORTS.contains(&name) {
+        err_at!(InvalidInput, msg: format!("invalid report {:?}", name))?;
+    }
+
+    // read-only, exports run alongside an open TUI and never rewrite
+    // the workspace.
+    let dir: &ffi::OsStr = opts.dir.as_ref();
+    let mut store = db_files::Db::open_safe(dir)?;
+    let (from, to) = report::to_period(
+        opts.from.as_ref().map(|s| s.as_str()),
+        opts.to.as_ref().map(|s| s.as_str()),
+    )?;
+
+    let stdout = io::stdout();
+    let mut w = stdout.lock();
+    let fmt = to_amount_format(opts);
+    report::export(&mut store, name, from, to, opts.hide_zero, &fmt, &mut w)
+}
+
+fn to_amount_format(opts: &Opt) -> util::AmountFormat {
+    util::AmountFormat {
+        rounding: opts.rounding,
+        locale: opts.number_locale,
+        style: opts.amount_style,
+        symbols: opts.symbol_range.unwrap_or_else(util::SymbolRange::from_env),
     }
 }
 
@@ -83,7 +271,10 @@ fn init_logger(opts: &Opt) -> Result<()> {
     } else {
         simplelog::LevelFilter::Info
     };
-    println!("log level {}", level_filter);
+    // keep stdout clean for exported reports.
+    if opts.export.is_none() {
+        println!("log level {}", level_filter);
+    }
 
     let mut config = simplelog::ConfigBuilder::new();
     config
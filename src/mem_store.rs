@@ -0,0 +1,293 @@
+use std::{collections::BTreeMap, ffi, path};
+
+use crate::{
+    aggregates,
+    core::{Durable, Error, Options, Result, Store, SyncStatus, Transaction},
+    types,
+};
+
+// in-memory store, values are held in their encoded form, nothing is
+// persisted. Useful for testing and for scratch workspaces.
+pub struct MemStore {
+    // key -> (doc-type, encoded-value), for all but journal entries.
+    values: BTreeMap<String, (String, String)>,
+    // key -> journal entry, held decoded to be lent out by
+    // for_each_journal().
+    journal: BTreeMap<String, types::JournalEntry>,
+    opts: Options,
+}
+
+impl MemStore {
+    fn to_value<V>(&self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let mut value: V = Default::default();
+        match (self.values.get(key), self.journal.get(key)) {
+            (Some((typ, data)), _) if typ == &value.to_type() => {
+                value.decode(data)?;
+                Ok(value)
+            }
+            (_, Some(entry)) if value.to_type() == "journalentry" => {
+                value.decode(&entry.encode()?)?;
+                Ok(value)
+            }
+            _ => err_at!(KeyNotFound, msg: format!("{}", key)),
+        }
+    }
+
+    fn to_values<V>(&self) -> Result<Vec<V>>
+    where
+        V: Durable,
+    {
+        let typ = V::default().to_type();
+
+        let mut values = vec![];
+        if typ == "journalentry" {
+            for entry in self.journal.values() {
+                let mut value: V = Default::default();
+                value.decode(&entry.encode()?)?;
+                values.push(value);
+            }
+        }
+        for (t, data) in self.values.values() {
+            if t == &typ {
+                let mut value: V = Default::default();
+                value.decode(data)?;
+                values.push(value);
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+impl Store for MemStore {
+    type Txn = MemTransaction;
+
+    fn create_with(_dir: &ffi::OsStr, w: types::Workspace, opts: Options) -> Result<MemStore> {
+        let mut values = BTreeMap::new();
+        values.insert(w.to_key(), (w.to_type(), w.encode()?));
+
+        Ok(MemStore {
+            values,
+            journal: BTreeMap::new(),
+            opts,
+        })
+    }
+
+    fn open_with(dir: &ffi::OsStr, _opts: Options) -> Result<MemStore> {
+        err_at!(NotFound, msg: format!("dir:{:?}", dir))
+    }
+
+    fn list_workspaces(_parent: &ffi::OsStr) -> Result<Vec<(String, path::PathBuf)>> {
+        Ok(vec![])
+    }
+
+    fn put<V>(&mut self, value: V) -> Result<Option<V>>
+    where
+        V: Durable,
+    {
+        let key = value.to_key();
+        let old_value = self.to_value(&key).ok();
+        let data = value.encode()?;
+        if value.to_type() == "ledger" {
+            let mut ledger = types::Ledger::default();
+            ledger.decode(&data)?;
+            if !self.opts.custom_groups {
+                self.to_workspace()?.check_groups(&ledger.groups)?;
+            }
+            aggregates::check_ledger_code(self, &ledger)?;
+        }
+        if value.to_type() == "journalentry" {
+            let mut entry = types::JournalEntry::default();
+            entry.decode(&data)?;
+            self.journal.insert(key, entry);
+        } else {
+            self.values.insert(key, (value.to_type(), data));
+        }
+
+        Ok(old_value)
+    }
+
+    fn get<V>(&mut self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        self.to_value(key)
+    }
+
+    fn delete<V>(&mut self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        let value = self.to_value(key)?;
+        self.values.remove(key);
+        self.journal.remove(key);
+        Ok(value)
+    }
+
+    fn iter<V>(&mut self) -> Result<Box<dyn Iterator<Item = Result<V>>>>
+    where
+        V: 'static + Durable,
+    {
+        let values: Vec<Result<V>> = self.to_values()?.into_iter().map(Ok).collect();
+        Ok(Box::new(values.into_iter()))
+    }
+
+    fn iter_journal(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>> {
+        let mut entries: Vec<types::JournalEntry> = self
+            .journal
+            .values()
+            .filter(|e| e.created >= from && e.created <= to)
+            .cloned()
+            .collect();
+        entries.sort();
+
+        let entries: Vec<Result<types::JournalEntry>> = entries.into_iter().map(Ok).collect();
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn for_each_journal<F>(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&types::JournalEntry),
+    {
+        let mut entries: Vec<&types::JournalEntry> = self
+            .journal
+            .values()
+            .filter(|e| e.created >= from && e.created <= to)
+            .collect();
+        entries.sort();
+
+        entries.into_iter().for_each(|e| f(e));
+        Ok(())
+    }
+
+    fn to_workspace(&mut self) -> Result<types::Workspace> {
+        self.to_value("workspace")
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn pull(&mut self) -> Result<SyncStatus> {
+        Ok(SyncStatus::default())
+    }
+
+    fn push(&mut self) -> Result<SyncStatus> {
+        Ok(SyncStatus::default())
+    }
+
+    fn begin(self) -> Result<MemTransaction> {
+        Ok(MemTransaction { db: self })
+    }
+}
+
+pub struct MemTransaction {
+    db: MemStore,
+}
+
+impl Transaction<MemStore> for MemTransaction {
+    fn put<V>(&mut self, value: V) -> Result<Option<V>>
+    where
+        V: Durable,
+    {
+        self.db.put(value)
+    }
+
+    fn get<V>(&mut self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        self.db.get(key)
+    }
+
+    fn delete<V>(&mut self, key: &str) -> Result<V>
+    where
+        V: Durable,
+    {
+        self.db.delete(key)
+    }
+
+    fn iter<V>(&mut self) -> Result<Box<dyn Iterator<Item = Result<V>>>>
+    where
+        V: 'static + Durable,
+    {
+        self.db.iter()
+    }
+
+    fn iter_journal(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Box<dyn Iterator<Item = Result<types::JournalEntry>>>> {
+        self.db.iter_journal(from, to)
+    }
+
+    fn end(self) -> Result<MemStore> {
+        Ok(self.db)
+    }
+}
+
+// fixtures shared by unit tests, ledgers and journal entries are under
+// company `acme`.
+#[cfg(test)]
+pub mod testing {
+    use std::{convert::TryInto, ffi};
+
+    use super::MemStore;
+    use crate::{core::Store, types};
+
+    // empty in-memory workspace named `test`.
+    pub fn make_store() -> MemStore {
+        make_store_with(types::Workspace::new("test".to_string()))
+    }
+
+    pub fn make_store_with(w: types::Workspace) -> MemStore {
+        let dir: &ffi::OsStr = "mem".as_ref();
+        MemStore::create(dir, w).unwrap()
+    }
+
+    // ledger `name`, created on 2020-01-01, in no groups.
+    pub fn make_ledger(name: &str) -> types::Ledger {
+        (
+            name.to_string(),
+            "2020-01-01T00:00:00Z".to_string(),
+            "acme".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )
+            .try_into()
+            .unwrap()
+    }
+
+    // entry moving `comm`, as (commodity, value), from `creditor` to
+    // `debitor`.
+    pub fn make_entry(
+        payee: &str,
+        created: chrono::DateTime<chrono::Utc>,
+        creditor: &str,
+        debitor: &str,
+        comm: (&str, f64),
+    ) -> types::JournalEntry {
+        let mut je = types::JournalEntry::new(payee.to_string(), created);
+        let comm = (comm.0.to_string(), comm.1);
+        je.add_creditor("acme".to_string(), creditor.to_string(), comm.clone())
+            .unwrap();
+        je.add_debitor("acme".to_string(), debitor.to_string(), comm)
+            .unwrap();
+        je
+    }
+}
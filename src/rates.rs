@@ -0,0 +1,162 @@
+use chrono;
+
+use std::collections::BTreeMap;
+
+use crate::{
+    core::{Error, Result, Store},
+    types,
+};
+
+// source of exchange rates, value of one unit of commodity `from` in
+// commodity `to` on date `on`. Refer ManualRates for the default, other
+// providers, EG: over HTTP, can be plugged into update_rates().
+pub trait RateProvider {
+    fn rate(&self, from: &str, to: &str, on: chrono::NaiveDate) -> Result<f64>;
+}
+
+// rates set by hand, independent of date. A rate set for (from, to)
+// also answers (to, from) with its inverse, and rates of two commodities
+// to a common one answer their cross rate.
+#[derive(Clone, Default)]
+pub struct ManualRates {
+    table: BTreeMap<(String, String), f64>,
+}
+
+impl ManualRates {
+    pub fn new() -> ManualRates {
+        Default::default()
+    }
+
+    // rates of every commodity in the store to `base`, from its value.
+    pub fn from_store<S>(store: &mut S, base: &str) -> Result<ManualRates>
+    where
+        S: Store,
+    {
+        let mut rates = ManualRates::new();
+        for commodity in store.iter::<types::Commodity>()? {
+            let commodity = commodity?;
+            if commodity.name != base && commodity.value != 0.0 {
+                rates.set_rate(&commodity.name, base, commodity.value);
+            }
+        }
+        Ok(rates)
+    }
+
+    pub fn set_rate(&mut self, from: &str, to: &str, rate: f64) -> &mut Self {
+        self.table.insert((from.to_string(), to.to_string()), rate);
+        self
+    }
+}
+
+impl RateProvider for ManualRates {
+    fn rate(&self, from: &str, to: &str, _on: chrono::NaiveDate) -> Result<f64> {
+        if from == to {
+            return Ok(1.0);
+        }
+
+        let key = (from.to_string(), to.to_string());
+        let inv = (to.to_string(), from.to_string());
+        match (self.table.get(&key), self.table.get(&inv)) {
+            (Some(rate), _) => Ok(*rate),
+            (None, Some(rate)) if *rate != 0.0 => Ok(1.0 / rate),
+            _ => match self.to_cross_rate(from, to) {
+                Some(rate) => Ok(rate),
+                None => err_at!(InvalidInput, msg: format!("no rate for {} in {}", from, to)),
+            },
+        }
+    }
+}
+
+impl ManualRates {
+    // rate of `from` in `to` via a commodity both have a rate to.
+    fn to_cross_rate(&self, from: &str, to: &str) -> Option<f64> {
+        for ((f, pivot), rate) in self.table.iter() {
+            if f != from {
+                continue;
+            }
+            match self.table.get(&(to.to_string(), pivot.clone())) {
+                Some(to_rate) if *to_rate != 0.0 => return Some(rate / to_rate),
+                _ => (),
+            }
+        }
+        None
+    }
+}
+
+// copy of `entry` with every leg's amount converted into commodity `to`,
+// at the rate on the entry's date, for display. `entry` is left as is.
+pub fn convert_entry<P>(
+    entry: &types::JournalEntry,
+    to: &str,
+    provider: &P,
+) -> Result<types::JournalEntry>
+where
+    P: RateProvider,
+{
+    let on = entry.created.naive_utc().date();
+    let convert = |commodity: &mut types::Commodity| -> Result<()> {
+        if commodity.name != to {
+            commodity.value *= provider.rate(&commodity.name, to, on)?;
+            commodity.name = to.to_string();
+            commodity.symbol = Default::default();
+        }
+        Ok(())
+    };
+
+    let mut entry = entry.clone();
+    for c in entry.creditors.iter_mut() {
+        convert(&mut c.commodity)?;
+    }
+    for d in entry.debitors.iter_mut() {
+        convert(&mut d.commodity)?;
+    }
+    Ok(entry)
+}
+
+// commodities named in `names`, or every commodity other than the
+// workspace's base commodity when empty, with their value refreshed from
+// `provider` as of `on`. Return only those whose value changed, nothing
+// is put back to store, so that the caller can put them together.
+pub fn update_rates<S, P>(
+    store: &mut S,
+    provider: &P,
+    names: &[String],
+    on: chrono::NaiveDate,
+) -> Result<Vec<types::Commodity>>
+where
+    S: Store,
+    P: RateProvider,
+{
+    let base = store.to_workspace()?.to_base_commodity();
+
+    let mut commodities = vec![];
+    for commodity in store.iter::<types::Commodity>()? {
+        commodities.push(commodity?);
+    }
+    for name in names.iter() {
+        if !commodities.iter().any(|c| &c.name == name) {
+            err_at!(NotFound, msg: format!("commodity {}", name))?;
+        }
+    }
+
+    let mut updated = vec![];
+    for mut commodity in commodities.into_iter() {
+        if commodity.name == base {
+            continue;
+        } else if !names.is_empty() && !names.contains(&commodity.name) {
+            continue;
+        }
+        let rate = provider.rate(&commodity.name, &base, on)?;
+        if rate != commodity.value {
+            commodity.value = rate;
+            commodity.updated = chrono::Utc::now();
+            updated.push(commodity);
+        }
+    }
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+#[path = "rates_test.rs"]
+mod rates_test;
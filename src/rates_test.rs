@@ -0,0 +1,103 @@
+use super::*;
+use crate::{
+    core::Durable,
+    mem_store::{testing, MemStore},
+};
+
+// fixed quotes in inr, standing in for a provider over HTTP.
+struct StubProvider;
+
+impl RateProvider for StubProvider {
+    fn rate(&self, from: &str, to: &str, _on: chrono::NaiveDate) -> Result<f64> {
+        match (from, to) {
+            ("usd", "inr") => Ok(82.5),
+            ("eur", "inr") => Ok(90.0),
+            _ => err_at!(InvalidInput, msg: format!("{} {}", from, to)),
+        }
+    }
+}
+
+fn make_store() -> MemStore {
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-inr".to_string());
+    let mut store = testing::make_store_with(w);
+    for (name, value) in [("inr", 1.0), ("usd", 75.0), ("eur", 90.0)].iter() {
+        let value: types::Commodity = (name.to_string(), *value).into();
+        store.put(value).unwrap();
+    }
+    store
+}
+
+#[test]
+fn test_manual_rates() {
+    let on = chrono::NaiveDate::from_ymd(2020, 6, 1);
+    let mut rates = ManualRates::new();
+    rates.set_rate("usd", "inr", 80.0);
+    assert_eq!(rates.rate("usd", "inr", on).unwrap(), 80.0);
+    assert_eq!(rates.rate("inr", "usd", on).unwrap(), 1.0 / 80.0);
+    assert_eq!(rates.rate("eur", "eur", on).unwrap(), 1.0);
+    match rates.rate("eur", "inr", on) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("eur"), "{}", msg),
+        _ => panic!("expected no rate"),
+    }
+
+    let mut store = make_store();
+    let rates = ManualRates::from_store(&mut store, "inr").unwrap();
+    assert_eq!(rates.rate("usd", "inr", on).unwrap(), 75.0);
+    assert_eq!(rates.rate("eur", "inr", on).unwrap(), 90.0);
+
+    // manual rates from the store leave commodities as they are.
+    assert!(update_rates(&mut store, &rates, &[], on).unwrap().is_empty());
+}
+
+#[test]
+fn test_update_rates() {
+    let on = chrono::NaiveDate::from_ymd(2020, 6, 1);
+    let mut store = make_store();
+
+    let updated = update_rates(&mut store, &StubProvider, &[], on).unwrap();
+    let names: Vec<String> = updated.iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["usd".to_string()]);
+    assert_eq!(updated[0].value, 82.5);
+    // nothing is put back to store.
+    let usd: types::Commodity = store.get("commodity-usd").unwrap();
+    assert_eq!(usd.value, 75.0);
+
+    // provider failing for a commodity fails the update.
+    store.put(types::Commodity::from(("gbp".to_string(), 100.0))).unwrap();
+    assert!(update_rates(&mut store, &StubProvider, &[], on).is_err());
+
+    // only named commodities are looked up, zero value or not.
+    store.put(types::Commodity::from(("usd".to_string(), 0.0))).unwrap();
+    let names = vec!["usd".to_string()];
+    let updated = update_rates(&mut store, &StubProvider, &names, on).unwrap();
+    assert_eq!(updated.len(), 1);
+    assert_eq!(updated[0].value, 82.5);
+    let names = vec!["chf".to_string()];
+    assert!(update_rates(&mut store, &StubProvider, &names, on).is_err());
+}
+
+#[test]
+fn test_convert_entry() {
+    let je = testing::make_entry("travel", chrono::Utc::now(), "cash", "trip", ("usd", 10.0));
+    let stored = je.encode().unwrap();
+
+    let mut store = make_store();
+    let rates = ManualRates::from_store(&mut store, "inr").unwrap();
+
+    let shown = convert_entry(&je, "inr", &rates).unwrap();
+    assert_eq!(shown.creditors[0].commodity.name, "inr");
+    assert_eq!(shown.creditors[0].commodity.value, 750.0);
+    assert_eq!(shown.debitors[0].commodity.value, 750.0);
+    assert_eq!(shown.uuid, je.uuid);
+
+    // cross rate through the base commodity.
+    let shown = convert_entry(&je, "eur", &rates).unwrap();
+    assert_eq!(shown.debitors[0].commodity.name, "eur");
+    assert!((shown.debitors[0].commodity.value - 750.0 / 90.0).abs() < 1e-9);
+
+    assert!(convert_entry(&je, "gbp", &rates).is_err());
+
+    // the entry itself is left as is.
+    assert_eq!(je.encode().unwrap(), stored);
+    assert_eq!(je.creditors[0].commodity.name, "usd");
+}
@@ -0,0 +1,135 @@
+use chrono;
+use jsondata::{Json, JsonSerialize};
+
+use std::{
+    convert::{TryFrom, TryInto},
+    fs, path,
+};
+
+use ledger::{
+    core::{Error, Result},
+    err_at,
+};
+
+// number of workspaces remembered in `<home>/recent.json`, refer
+// app::default_home().
+pub const MAX_RECENT: usize = 8;
+const RECENT_FILE: &'static str = "recent.json";
+
+// on-disk form, `dirs[i]` was last opened at `opened[i]`.
+#[derive(Clone, Default, JsonSerialize)]
+struct RecentFile {
+    dirs: Vec<String>,
+    opened: Vec<String>,
+}
+
+// recently opened workspaces, most recent first.
+#[derive(Clone, Default)]
+pub struct Recent {
+    items: Vec<(path::PathBuf, chrono::DateTime<chrono::Utc>)>,
+}
+
+impl TryFrom<RecentFile> for Recent {
+    type Error = Error;
+
+    fn try_from(rf: RecentFile) -> Result<Recent> {
+        if rf.dirs.len() != rf.opened.len() {
+            err_at!(InvalidJson, msg: format!("recent dirs/opened mismatch"))?;
+        }
+
+        let mut items = vec![];
+        for (dir, opened) in rf.dirs.into_iter().zip(rf.opened.into_iter()) {
+            let opened = err_at!(
+                InvalidDate,
+                chrono::DateTime::parse_from_rfc3339(&opened),
+                format!("{:?}", opened)
+            )?;
+            items.push((dir.into(), opened.with_timezone(&chrono::Utc)));
+        }
+
+        Ok(Recent { items })
+    }
+}
+
+impl From<Recent> for RecentFile {
+    fn from(recent: Recent) -> RecentFile {
+        let mut rf: RecentFile = Default::default();
+        for (dir, opened) in recent.items.into_iter() {
+            rf.dirs.push(dir.to_string_lossy().to_string());
+            rf.opened.push(opened.to_rfc3339());
+        }
+        rf
+    }
+}
+
+impl Recent {
+    // load from `home`, empty list if there is no such file.
+    pub fn load(home: &path::Path) -> Result<Recent> {
+        Recent::from_file(&home.join(RECENT_FILE))
+    }
+
+    // save to `home`, creating it if missing.
+    pub fn save(&self, home: &path::Path) -> Result<()> {
+        self.to_file(&home.join(RECENT_FILE))
+    }
+
+    pub fn from_file(loc: &path::Path) -> Result<Recent> {
+        if !loc.exists() {
+            return Ok(Default::default());
+        }
+
+        let data = err_at!(IOError, fs::read(loc), format!("{:?}", loc))?;
+        let text = err_at!(InvalidInput, std::str::from_utf8(&data))?;
+        let jval: Json = err_at!(InvalidJson, text.parse())?;
+        let rf: RecentFile = err_at!(InvalidJson, jval.try_into())?;
+        rf.try_into()
+    }
+
+    pub fn to_file(&self, loc: &path::Path) -> Result<()> {
+        if let Some(parent) = loc.parent() {
+            err_at!(IOError, fs::create_dir_all(parent), format!("{:?}", parent))?;
+        }
+
+        let rf: RecentFile = self.clone().into();
+        let jval: Json = err_at!(ConvertFail, rf.try_into())?;
+        err_at!(IOError, fs::write(loc, jval.to_string()), format!("{:?}", loc))
+    }
+
+    // workspace at `dir` was opened at `now`, move it to the top.
+    pub fn touch(&mut self, dir: &path::Path, now: chrono::DateTime<chrono::Utc>) {
+        self.items.retain(|(d, _)| d != dir);
+        self.items.insert(0, (dir.to_path_buf(), now));
+        self.items.truncate(MAX_RECENT);
+    }
+
+    pub fn to_dirs(&self) -> Vec<path::PathBuf> {
+        self.items.iter().map(|(dir, _)| dir.clone()).collect()
+    }
+
+    // list for workspace picker, recent workspaces that still exist on
+    // disk, followed by the rest of `workspaces`.
+    pub fn merge(&self, workspaces: Vec<(String, path::PathBuf)>) -> Vec<(String, path::PathBuf)> {
+        let mut items: Vec<(String, path::PathBuf)> = self
+            .items
+            .iter()
+            .filter(|(dir, _)| dir.exists())
+            .map(|(dir, _)| {
+                let name = match dir.file_name() {
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => dir.to_string_lossy().to_string(),
+                };
+                (name, dir.clone())
+            })
+            .collect();
+        for (name, dir) in workspaces.into_iter() {
+            if !items.iter().any(|(_, d)| d == &dir) {
+                items.push((name, dir))
+            }
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+#[path = "recent_test.rs"]
+mod recent_test;
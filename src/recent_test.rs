@@ -0,0 +1,75 @@
+use chrono::offset::TimeZone;
+
+use super::*;
+
+#[test]
+fn test_recent_touch() {
+    let (a, b, c) = (
+        path::PathBuf::from("/data/a"),
+        path::PathBuf::from("/data/b"),
+        path::PathBuf::from("/data/c"),
+    );
+    let at = |secs: i64| chrono::Utc.timestamp(1_600_000_000 + secs, 0);
+
+    let mut recent: Recent = Default::default();
+    recent.touch(&a, at(1));
+    recent.touch(&b, at(2));
+    recent.touch(&c, at(3));
+    assert_eq!(recent.to_dirs(), vec![c.clone(), b.clone(), a.clone()]);
+
+    recent.touch(&a, at(4));
+    assert_eq!(recent.to_dirs(), vec![a.clone(), c.clone(), b.clone()]);
+
+    for i in 0..(MAX_RECENT * 2) {
+        let dir = path::PathBuf::from(format!("/data/x{}", i));
+        recent.touch(&dir, at(10 + i as i64));
+    }
+    assert_eq!(recent.to_dirs().len(), MAX_RECENT);
+}
+
+#[test]
+fn test_recent_file() {
+    let mut loc = std::env::temp_dir();
+    loc.push("ledger47-test");
+    loc.push("recent");
+    fs::remove_dir_all(&loc).ok();
+    loc.push("recent.json");
+
+    let recent = Recent::from_file(&loc).unwrap();
+    assert!(recent.to_dirs().is_empty());
+
+    let mut recent: Recent = Default::default();
+    let now = chrono::Utc.timestamp(1_600_000_000, 0);
+    for name in ["a", "b", "c", "b"].iter() {
+        recent.touch(&path::PathBuf::from(format!("/data/{}", name)), now);
+    }
+    recent.to_file(&loc).unwrap();
+
+    let loaded = Recent::from_file(&loc).unwrap();
+    let dirs: Vec<path::PathBuf> = ["b", "c", "a"]
+        .iter()
+        .map(|name| path::PathBuf::from(format!("/data/{}", name)))
+        .collect();
+    assert_eq!(loaded.to_dirs(), dirs);
+    assert_eq!(loaded.items[0].1, now);
+}
+
+#[test]
+fn test_recent_merge() {
+    let mut root = std::env::temp_dir();
+    root.push("ledger47-test");
+    root.push("recent-merge");
+    fs::remove_dir_all(&root).ok();
+    let (a, b, gone) = (root.join("a"), root.join("b"), root.join("gone"));
+    fs::create_dir_all(&a).unwrap();
+    fs::create_dir_all(&b).unwrap();
+
+    let mut recent: Recent = Default::default();
+    let now = chrono::Utc.timestamp(1_600_000_000, 0);
+    recent.touch(&gone, now);
+    recent.touch(&b, now);
+
+    let workspaces = vec![("a".to_string(), a.clone()), ("b".to_string(), b.clone())];
+    let items = recent.merge(workspaces);
+    assert_eq!(items, vec![("b".to_string(), b), ("a".to_string(), a)]);
+}
@@ -0,0 +1,245 @@
+use chrono::{self, offset::TimeZone};
+
+use std::{collections::BTreeMap, io};
+
+use crate::{
+    core::{Error, Reduce, Result, Store},
+    ledger_cli, types, util,
+};
+
+pub const REPORTS: [&'static str; 4] = ["trial-balance", "statement", "csv", "ledger"];
+
+// parse the export period, dates are in YYYY-MM-DD format. Missing dates
+// default to the current accounting period.
+pub fn to_period(
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+    let (def_from, def_to) = util::date_to_period(chrono::Utc::today());
+    let from = match from {
+        Some(from) => parse_date(from)?,
+        None => def_from,
+    };
+    let to = match to {
+        Some(to) => parse_date(to)?,
+        None => def_to,
+    };
+
+    Ok((from.and_hms(0, 0, 0), to.and_hms(23, 59, 59)))
+}
+
+fn parse_date(s: &str) -> Result<chrono::Date<chrono::Utc>> {
+    let date = err_at!(
+        InvalidDate,
+        chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d"),
+        format!("{:?}", s)
+    )?;
+    Ok(chrono::Utc.from_utc_date(&date))
+}
+
+// run the report named `name`, over journal entries between `from` and
+// `to`, and write its text output to `w`. Refer to_report() for
+// `hide_zero` and `fmt`.
+pub fn export<S, W>(
+    store: &mut S,
+    name: &str,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    hide_zero: bool,
+    fmt: &util::AmountFormat,
+    w: &mut W,
+) -> Result<()>
+where
+    S: Store,
+    W: io::Write,
+{
+    let report = to_report(store, name, from, to, hide_zero, fmt)?;
+    err_at!(IOError, w.write_all(report.output.as_bytes()))?;
+    Ok(())
+}
+
+// run the report named `name`, over journal entries between `from` and
+// `to`, and return it along with its parameters, so that it can be
+// persisted in `store`. With `hide_zero`, ledgers whose balance for the
+// period is zero are left out of the trial balance. Trial balance and
+// statement amounts are formatted with `fmt`.
+pub fn to_report<S>(
+    store: &mut S,
+    name: &str,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    hide_zero: bool,
+    fmt: &util::AmountFormat,
+) -> Result<types::Report>
+where
+    S: Store,
+{
+    let base = store.to_workspace()?.to_base_commodity();
+
+    let mut entries = vec![];
+    for entry in store.iter_journal(from, to)? {
+        entries.push(entry?);
+    }
+
+    let output = match name {
+        "trial-balance" => {
+            let mut tb = TrialBalance::new(base);
+            tb.set_hide_zero(hide_zero);
+            for entry in entries.iter() {
+                tb.reduce(entry)?;
+            }
+            tb.to_text(fmt)
+        }
+        "statement" => to_statement(&entries, fmt),
+        "csv" => to_csv(&entries),
+        "ledger" => ledger_cli::to_ledger_format(&entries),
+        _ => err_at!(InvalidInput, msg: format!("invalid report {:?}", name))?,
+    };
+
+    Ok(types::Report::new(name.to_string(), (from, to), output))
+}
+
+// net balance for every (ledger, commodity), debits are positive and
+// credits are negative. Totals are listed with the base commodity first.
+#[derive(Default)]
+pub struct TrialBalance {
+    base: String,
+    balances: BTreeMap<(String, String), f64>,
+    // leave out balances that net to zero.
+    hide_zero: bool,
+}
+
+impl Reduce<types::JournalEntry> for TrialBalance {
+    fn reduce(&mut self, doc: &types::JournalEntry) -> Result<()> {
+        for c in doc.creditors.iter() {
+            let key = (c.ledger.to_string(), c.commodity.name.clone());
+            *self.balances.entry(key).or_insert(0.0) -= c.commodity.value;
+        }
+        for d in doc.debitors.iter() {
+            let key = (d.ledger.to_string(), d.commodity.name.clone());
+            *self.balances.entry(key).or_insert(0.0) += d.commodity.value;
+        }
+
+        Ok(())
+    }
+}
+
+impl TrialBalance {
+    pub fn new(base: String) -> TrialBalance {
+        TrialBalance {
+            base,
+            balances: BTreeMap::new(),
+            hide_zero: false,
+        }
+    }
+
+    pub fn set_hide_zero(&mut self, hide_zero: bool) -> &mut Self {
+        self.hide_zero = hide_zero;
+        self
+    }
+
+    pub fn to_base(&self) -> String {
+        self.base.clone()
+    }
+
+    pub fn to_balances(&self) -> Vec<(String, String, f64)> {
+        self.iter_balances()
+            .map(|((l, c), v)| (l.clone(), c.clone(), *v))
+            .collect()
+    }
+
+    fn iter_balances(&self) -> impl Iterator<Item = (&(String, String), &f64)> {
+        let hide_zero = self.hide_zero;
+        self.balances
+            .iter()
+            .filter(move |(_, v)| !hide_zero || v.abs() >= types::BALANCE_EPSILON)
+    }
+
+    fn to_text(&self, fmt: &util::AmountFormat) -> String {
+        let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+        let mut s = String::default();
+        for ((ledger, commodity), value) in self.iter_balances() {
+            let amount = fmt.format_amount(*value);
+            s.push_str(&format!("{:<32} {:<8} {:>14}\n", ledger, commodity, amount));
+            *totals.entry(commodity.clone()).or_insert(0.0) += value;
+        }
+        if let Some(value) = totals.remove(&self.base) {
+            let amount = fmt.format_amount(value);
+            s.push_str(&format!("{:<32} {:<8} {:>14}\n", "total", self.base, amount));
+        }
+        for (commodity, value) in totals.iter() {
+            let amount = fmt.format_amount(*value);
+            s.push_str(&format!("{:<32} {:<8} {:>14}\n", "total", commodity, amount));
+        }
+        s
+    }
+}
+
+fn to_statement(entries: &[types::JournalEntry], fmt: &util::AmountFormat) -> String {
+    let mut s = String::default();
+    for entry in entries.iter() {
+        s.push_str(&format!(
+            "{} {}\n",
+            entry.created.format("%Y-%m-%d"),
+            entry.payee
+        ));
+        for d in entry.debitors.iter() {
+            s.push_str(&format!(
+                "    {:<32} {:<8} {:>14} Dr\n",
+                d.ledger.to_string(),
+                d.commodity.name,
+                fmt.format_amount(d.commodity.value)
+            ));
+        }
+        for c in entry.creditors.iter() {
+            s.push_str(&format!(
+                "    {:<32} {:<8} {:>14} Cr\n",
+                c.ledger.to_string(),
+                c.commodity.name,
+                fmt.format_amount(c.commodity.value)
+            ));
+        }
+    }
+    s
+}
+
+fn to_csv(entries: &[types::JournalEntry]) -> String {
+    let mut s = "date,payee,ledger,commodity,debit,credit\n".to_string();
+    for entry in entries.iter() {
+        let date = entry.created.format("%Y-%m-%d").to_string();
+        let payee = csv_field(&entry.payee);
+        for d in entry.debitors.iter() {
+            s.push_str(&format!(
+                "{},{},{},{},{},\n",
+                date,
+                payee,
+                csv_field(&d.ledger.to_string()),
+                csv_field(&d.commodity.name),
+                d.commodity.value
+            ));
+        }
+        for c in entry.creditors.iter() {
+            s.push_str(&format!(
+                "{},{},{},{},,{}\n",
+                date,
+                payee,
+                csv_field(&c.ledger.to_string()),
+                csv_field(&c.commodity.name),
+                c.commodity.value
+            ));
+        }
+    }
+    s
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+#[path = "report_test.rs"]
+mod report_test;
@@ -0,0 +1,162 @@
+use super::*;
+use crate::{
+    core::Durable,
+    mem_store::{testing, MemStore},
+};
+
+fn make_store() -> MemStore {
+    let mut store = testing::make_store();
+
+    let created = chrono::Utc.ymd(2020, 5, 1).and_hms(10, 0, 0);
+    let je = testing::make_entry("grocer", created, "cash", "food", ("inr", 100.0));
+    store.put(je).unwrap();
+
+    let created = chrono::Utc.ymd(2020, 5, 2).and_hms(10, 0, 0);
+    let je = testing::make_entry("cafe, downtown", created, "cash", "food", ("inr", 50.5));
+    store.put(je).unwrap();
+
+    store
+}
+
+fn do_export(name: &str) -> Result<String> {
+    let mut store = make_store();
+    let (from, to) = to_period(Some("2020-04-01"), Some("2021-03-31"))?;
+    let (fmt, mut out): (util::AmountFormat, Vec<u8>) = Default::default();
+    export(&mut store, name, from, to, false, &fmt, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_export_trial_balance() {
+    let out = do_export("trial-balance").ok().unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", out);
+    assert!(lines[0].starts_with("ledger-acme-cash"), "{}", out);
+    assert!(lines[0].ends_with("-150.50"), "{}", out);
+    assert!(lines[1].starts_with("ledger-acme-food"), "{}", out);
+    assert!(lines[1].ends_with("150.50"), "{}", out);
+    assert!(lines[2].starts_with("total"), "{}", out);
+    assert!(lines[2].ends_with("0.00"), "{}", out);
+}
+
+#[test]
+fn test_export_statement() {
+    let out = do_export("statement").ok().unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 6, "{}", out);
+    assert_eq!(lines[0], "2020-05-01 grocer");
+    assert!(lines[1].ends_with("100.00 Dr"), "{}", out);
+    assert!(lines[2].ends_with("100.00 Cr"), "{}", out);
+    assert_eq!(lines[3], "2020-05-02 cafe, downtown");
+}
+
+#[test]
+fn test_export_csv() {
+    let out = do_export("csv").ok().unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", out);
+    assert_eq!(lines[0], "date,payee,ledger,commodity,debit,credit");
+    assert_eq!(lines[1], "2020-05-01,grocer,ledger-acme-food,inr,100,");
+    assert_eq!(lines[2], "2020-05-01,grocer,ledger-acme-cash,inr,,100");
+    assert_eq!(
+        lines[3],
+        "2020-05-02,\"cafe, downtown\",ledger-acme-food,inr,50.5,"
+    );
+}
+
+#[test]
+fn test_export_invalid() {
+    match do_export("balance-sheet") {
+        Err(Error::InvalidInput(_)) => (),
+        _ => panic!("expected invalid input"),
+    }
+    match to_period(Some("01-04-2020"), None) {
+        Err(Error::InvalidDate(_)) => (),
+        _ => panic!("expected invalid date"),
+    }
+}
+
+#[test]
+fn test_export_base_commodity() {
+    let mut store = make_store();
+    let w = types::Workspace::new("test".to_string()).set_commodity("commodity-usd".to_string());
+    store.put(w).unwrap();
+
+    let created = chrono::Utc.ymd(2020, 5, 3).and_hms(10, 0, 0);
+    let je = testing::make_entry("travel", created, "card", "travel", ("usd", 20.0));
+    store.put(je).unwrap();
+
+    let (from, to) = to_period(Some("2020-04-01"), Some("2021-03-31")).ok().unwrap();
+    let (fmt, mut out): (util::AmountFormat, Vec<u8>) = Default::default();
+    export(&mut store, "trial-balance", from, to, false, &fmt, &mut out)
+        .ok()
+        .unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    let totals: Vec<&str> = out.lines().filter(|l| l.starts_with("total")).collect();
+    assert_eq!(totals.len(), 2, "{}", out);
+    assert!(totals[0].contains("usd"), "{}", out);
+    assert!(totals[1].contains("inr"), "{}", out);
+}
+
+#[test]
+fn test_report_persist() {
+    use crate::db_files::Db;
+
+    let mut store = make_store();
+    let (from, to) = to_period(Some("2020-04-01"), Some("2021-03-31")).unwrap();
+    let fmt = util::AmountFormat::default();
+    let report = to_report(&mut store, "trial-balance", from, to, false, &fmt).unwrap();
+    assert!(report.output.contains("acme"), "{}", report.output);
+
+    let mut dir = std::env::temp_dir();
+    dir.push("ledger47-test");
+    dir.push("test_report_persist");
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    let w = types::Workspace::new("test".to_string());
+    let mut db = Db::create(dir.as_os_str(), w).unwrap();
+
+    db.put(report.clone()).unwrap();
+    db.commit().unwrap();
+    let file = dir.join("reports").join(format!("{}.json", report.to_key()));
+    assert!(file.is_file(), "{:?}", file);
+
+    let key: types::KeyReport = ("trial-balance".to_string(), report.generated).into();
+    let loaded = db.get_by(&key).unwrap();
+    assert_eq!(loaded.name, "trial-balance");
+    assert_eq!(loaded.output, report.output);
+    assert_eq!((loaded.from, loaded.to), (from, to));
+
+    let reports: Vec<types::Report> = db.iter().unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(reports.len(), 1);
+}
+
+#[test]
+fn test_trial_balance_hide_zero() {
+    let mut store = make_store();
+    // moves cash to bank and back, leaving bank at zero for the period.
+    for (day, from, to) in [(3, "cash", "bank"), (4, "bank", "cash")].iter() {
+        let created = chrono::Utc.ymd(2020, 5, *day).and_hms(10, 0, 0);
+        let je = testing::make_entry("transfer", created, from, to, ("inr", 25.0));
+        store.put(je).unwrap();
+    }
+    let (from, to) = to_period(Some("2020-04-01"), Some("2021-03-31")).unwrap();
+    let fmt = util::AmountFormat::default();
+
+    let report = to_report(&mut store, "trial-balance", from, to, false, &fmt).unwrap();
+    assert!(report.output.contains("ledger-acme-bank"), "{}", report.output);
+
+    let report = to_report(&mut store, "trial-balance", from, to, true, &fmt).unwrap();
+    assert!(!report.output.contains("ledger-acme-bank"), "{}", report.output);
+    assert!(report.output.contains("ledger-acme-cash"), "{}", report.output);
+    assert!(report.output.contains("ledger-acme-food"), "{}", report.output);
+
+    let mut tb = TrialBalance::new("inr".to_string());
+    for entry in store.iter_journal(from, to).unwrap() {
+        tb.reduce(&entry.unwrap()).unwrap();
+    }
+    assert_eq!(tb.to_balances().len(), 3);
+    tb.set_hide_zero(true);
+    assert_eq!(tb.to_balances().len(), 2);
+}
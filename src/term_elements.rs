@@ -9,12 +9,14 @@ use log::{debug, trace};
 use unicode_width::UnicodeWidthChar;
 
 use std::{
+    cmp,
     convert::TryInto,
     fmt,
     io::Write,
     iter::FromIterator,
     ops::{self, RangeBounds},
     result,
+    str::FromStr,
     sync::mpsc,
 };
 
@@ -22,15 +24,27 @@ use crate::{
     app::Application,
     edit_buffer::{Buffer, EditRes},
     event::{self, Event},
+    keymap::Action,
+    layout,
 };
 use ledger::{
-    core::{Error, Result, Store},
-    err_at, util,
+    core::{Durable, Error, Result, Store},
+    err_at, types, util,
 };
 
 pub const MIN_COL: u64 = 1;
 pub const MIN_ROW: u64 = 1;
 
+// marker rendered next to an edit element holding invalid input.
+pub const ERROR_MARKER: char = '!';
+// marker rendered next to an edit line whose content is at its limit.
+pub const LIMIT_MARKER: char = '▸';
+
+// below this width headline drops the period and shows only the date.
+pub const HEAD_PERIOD_WIDTH: u16 = 32;
+// below this width headline abbreviates the date to `%d-%b`.
+pub const HEAD_SHORT_DATE_WIDTH: u16 = 12;
+
 pub const BG_LAYER: Color = Color::AnsiValue(235);
 pub const BG_EDIT: Color = Color::AnsiValue(232);
 pub const BG_BUTTON: Color = Color::AnsiValue(243);
@@ -46,9 +60,12 @@ pub const FG_EDIT: Color = Color::AnsiValue(15);
 pub const FG_SECTION: Color = Color::AnsiValue(11);
 pub const FG_FIELD: Color = Color::AnsiValue(159);
 pub const FG_MANDATORY: Color = Color::AnsiValue(160);
+pub const FG_ERROR: Color = Color::AnsiValue(196);
 pub const FG_STATUS: Color = Color::AnsiValue(15);
 pub const FG_BUTTON: Color = Color::AnsiValue(255);
 pub const FG_BUTTON_HL: Color = Color::AnsiValue(232);
+pub const FG_DEBIT: Color = Color::AnsiValue(71);
+pub const FG_CREDIT: Color = Color::AnsiValue(167);
 
 macro_rules! impl_command {
     ($e:tt) => {
@@ -72,6 +89,7 @@ macro_rules! element_method_dispatch {
             Element::Span(em) => em.$method(),
             Element::StatusLine(em) => em.$method(),
             Element::Button(em) => em.$method(),
+            Element::ListBox(em) => em.$method(),
         }
     };
     ($self:expr, $method:ident, $($e:expr),*) => {
@@ -83,10 +101,25 @@ macro_rules! element_method_dispatch {
             Element::Span(em) => em.$method($($e),*),
             Element::StatusLine(em) => em.$method($($e),*),
             Element::Button(em) => em.$method($($e),*),
+            Element::ListBox(em) => em.$method($($e),*),
         }
     };
 }
 
+// app wide clipboard, holds the text last copied from an edit element.
+#[derive(Clone, Debug, Default)]
+pub struct Clipboard(Option<String>);
+
+impl Clipboard {
+    pub fn copy(&mut self, text: String) {
+        self.0 = Some(text);
+    }
+
+    pub fn to_text(&self) -> Option<String> {
+        self.0.clone()
+    }
+}
+
 pub enum Element {
     HeadLine(HeadLine),
     Border(Border),
@@ -95,6 +128,7 @@ pub enum Element {
     EditBox(EditBox),
     StatusLine(StatusLine),
     Button(Button),
+    ListBox(ListBox),
 }
 
 impl Element {
@@ -111,6 +145,7 @@ impl Element {
             Element::Span(em) => em.vp.contain_cell(col, row),
             Element::StatusLine(em) => em.vp.contain_cell(col, row),
             Element::Button(em) => em.vp.contain_cell(col, row),
+            Element::ListBox(em) => em.vp.contain_cell(col, row),
         }
     }
 
@@ -121,6 +156,93 @@ impl Element {
         element_method_dispatch!(self, refresh, app, force)
     }
 
+    // replace the content of edit elements, return false for elements
+    // that are not editable.
+    pub fn set_content(&mut self, text: &str) -> Result<bool> {
+        match self {
+            Element::EditLine(em) => em.set_content(text).map(|_| true),
+            Element::EditBox(em) => em.set_content(text).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    // content of edit elements, None for other elements.
+    pub fn to_content(&self) -> Option<String> {
+        match self {
+            Element::EditLine(em) => Some(em.buffer.to_string()),
+            Element::EditBox(em) => Some(em.buffer.to_string()),
+            _ => None,
+        }
+    }
+
+    // true if edit element has no content, other elements hold none.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Element::EditLine(em) => em.buffer.to_string().is_empty(),
+            Element::EditBox(em) => em.buffer.to_string().is_empty(),
+            _ => true,
+        }
+    }
+
+    // shift element by `row_off` rows, used to reflow form layout.
+    pub fn move_by(&mut self, row_off: i16) -> Result<()> {
+        match self {
+            Element::EditLine(em) => {
+                em.vp = em.vp.clone().move_by(0, row_off);
+                em.edit_vp = em.edit_vp.clone().move_by(0, row_off);
+                em.tc_line = em.make_term_cache();
+            }
+            Element::EditBox(em) => {
+                em.vp = em.vp.clone().move_by(0, row_off);
+                em.edit_vp = em.edit_vp.clone().move_by(0, row_off);
+                em.tc_line = em.make_term_cache();
+            }
+            Element::Span(em) => em.vp = em.vp.clone().move_by(0, row_off),
+            Element::Button(em) => {
+                em.vp = em.vp.clone().move_by(0, row_off);
+                em.tc_normal = em.make_term_cache(BG_BUTTON, FG_BUTTON);
+                em.tc_highlt = em.make_term_cache(BG_BUTTON_HL, FG_BUTTON_HL);
+            }
+            Element::ListBox(em) => em.vp = em.vp.clone().move_by(0, row_off),
+            _ => err_at!(Fatal, msg: format!("element can't be moved"))?,
+        }
+        Ok(())
+    }
+
+    // clone a row element, placed `row_off` rows away, with no content.
+    pub fn clone_row(&self, row_off: i16) -> Result<Element> {
+        let mut em = match self {
+            Element::EditLine(em) => Element::EditLine(em.clone()),
+            Element::EditBox(em) => Element::EditBox(em.clone()),
+            Element::Span(em) => Element::Span(em.clone()),
+            Element::Button(em) => Element::Button(em.clone()),
+            Element::ListBox(em) => Element::ListBox(em.clone()),
+            _ => err_at!(Fatal, msg: format!("element can't be cloned"))?,
+        };
+        em.set_content("")?;
+        match &mut em {
+            Element::EditLine(em) => {
+                em.set_error(None);
+                em.focus = false;
+            }
+            Element::EditBox(em) => {
+                em.set_error(None);
+                em.focus = false;
+            }
+            Element::Button(em) => {
+                em.focus = false;
+                em.render_type = "normal";
+            }
+            Element::ListBox(em) => {
+                em.set_rows(vec![]);
+                em.focus = false;
+            }
+            _ => (),
+        }
+        em.move_by(row_off)?;
+        Ok(em)
+    }
+
     pub fn focus<S>(&mut self, app: &mut Application<S>) -> Result<()>
     where
         S: Store,
@@ -135,6 +257,40 @@ impl Element {
         element_method_dispatch!(self, leave, app)
     }
 
+    // copy content of edit elements to `clipboard`, return false for
+    // elements that are not editable.
+    pub fn copy_to(&self, clipboard: &mut Clipboard) -> bool {
+        match self.to_content() {
+            Some(text) => {
+                clipboard.copy(text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // insert clipboard text at the cursor of edit elements, return false
+    // for elements that are not editable or an empty clipboard.
+    pub fn paste_from(&mut self, clipboard: &Clipboard) -> Result<bool> {
+        let text = match clipboard.to_text() {
+            Some(text) => text,
+            None => return Ok(false),
+        };
+        match self {
+            Element::EditLine(em) => {
+                // single line, flatten line breaks.
+                let text: Vec<&str> = text.lines().collect();
+                em.insert_text(&text.join(" "));
+                Ok(true)
+            }
+            Element::EditBox(em) => {
+                em.insert_text(&text);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     pub fn handle_event<S>(
         &mut self,
         app: &mut Application<S>,
@@ -143,6 +299,32 @@ impl Element {
     where
         S: Store,
     {
+        match app.to_action(&evnt) {
+            Some(Action::Copy) if self.copy_to(app.as_mut_clipboard()) => {
+                let n = self.to_content().unwrap_or_default().chars().count();
+                app.log_status(&format!("copied {} chars", n));
+                return Ok(None);
+            }
+            Some(Action::Paste) if self.paste_from(&app.to_clipboard())? => {
+                // re-focus to place the cursor after the pasted text.
+                self.focus(app)?;
+                return Ok(None);
+            }
+            Some(Action::EditExternal) => {
+                if let Element::EditBox(em) = self {
+                    em.edit_external(app)?;
+                    return Ok(None);
+                }
+            }
+            Some(Action::InsertToday) => {
+                if let Element::EditLine(em) = self {
+                    if em.insert_today(app)? {
+                        return Ok(None);
+                    }
+                }
+            }
+            _ => (),
+        }
         element_method_dispatch!(self, handle_event, app, evnt)
     }
 }
@@ -239,14 +421,16 @@ impl Viewport {
         self.row
     }
 
+    // for zero width, right is left of the origin column.
     #[inline]
     pub fn to_right(&self) -> u16 {
-        self.col + self.width - 1
+        layout::last(self.col, self.width)
     }
 
+    // for zero height, bottom is above the origin row.
     #[inline]
     pub fn to_bottom(&self) -> u16 {
-        self.row + self.height - 1
+        layout::last(self.row, self.height)
     }
 
     #[inline]
@@ -261,7 +445,7 @@ impl Viewport {
     pub fn to_cursor(&self) -> (u16, u16) {
         let (col, row) = self.to_origin();
         let (coff, roff) = self.to_cursor_off();
-        (col + coff, row + roff)
+        (layout::add(col, coff), layout::add(row, roff))
     }
 
     fn to_ed_cursor(&self, ed_origin: (usize, usize)) -> (usize, usize) {
@@ -282,23 +466,25 @@ impl Viewport {
         let crow = ((self.row + self.vp_cursor_off.1) as isize) + rdiff;
 
         let top = (self.to_top() + self.scroll_off) as isize;
-        let bottom = (self.to_bottom() - self.scroll_off) as isize;
+        let bottom = layout::sub(self.to_bottom(), self.scroll_off) as isize;
 
         let (vp_col, ed_col): (u16, usize) = if ccol < (self.to_left() as isize) {
             (0, ed_cursor.0)
         } else if ccol > (self.to_right() as isize) {
-            (self.width - 1, ed_cursor.0 - (self.width as usize) + 1)
+            let w = layout::sub(self.width, 1);
+            (w, ed_cursor.0.saturating_sub(w as usize))
         } else {
             let new_col: u16 = ccol.try_into().unwrap();
-            (new_col - self.col, self.ed_origin.0)
+            (layout::sub(new_col, self.col), self.ed_origin.0)
         };
         let (vp_row, ed_row): (u16, usize) = if crow < top {
             (0, ed_cursor.1)
         } else if crow > bottom {
-            (self.height - 1, ed_cursor.1 - (self.height as usize) + 1)
+            let h = layout::sub(self.height, 1);
+            (h, ed_cursor.1.saturating_sub(h as usize))
         } else {
             let new_row: u16 = crow.try_into().unwrap();
-            (new_row - self.row, self.ed_origin.1)
+            (layout::sub(new_row, self.row), self.ed_origin.1)
         };
 
         trace!(
@@ -319,6 +505,8 @@ pub struct HeadLine {
     vp: Viewport,
     date: chrono::Date<chrono::Local>,
     period: (chrono::Date<chrono::Local>, chrono::Date<chrono::Local>),
+    // shown on the left, EG: workspace opened in safe mode.
+    banner: String,
 
     rx: mpsc::Receiver<Event>,
 }
@@ -330,6 +518,7 @@ impl Default for HeadLine {
             vp: Default::default(),
             date: chrono::Local::now().date(),
             period: util::date_to_period(chrono::Local::now().date()),
+            banner: Default::default(),
 
             rx,
         }
@@ -353,10 +542,16 @@ impl HeadLine {
             vp,
             date,
             period,
+            banner: Default::default(),
 
             rx,
         })
     }
+
+    pub fn set_banner(&mut self, banner: &str) -> &mut Self {
+        self.banner = banner.to_string();
+        self
+    }
 }
 
 impl HeadLine {
@@ -364,26 +559,21 @@ impl HeadLine {
     where
         S: Store,
     {
-        let refresh = loop {
-            match self.rx.try_recv() {
-                Ok(Event::Date(date)) => {
-                    self.date = date;
-                    break Ok(true);
-                }
-                Ok(Event::Period { from, to }) => {
-                    self.period = (from, to);
-                    break Ok(true);
-                }
-                Ok(_) => break Ok(false),
-                Err(mpsc::TryRecvError::Empty) => break Ok(false),
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    break err_at!(IOError, msg: format!("refresh"))
-                }
+        let (date, period) = (&mut self.date, &mut self.period);
+        let refresh = event::drain(&self.rx, |evnt| match evnt {
+            Event::Date(d) => {
+                *date = d;
+                true
             }
-        }?;
+            Event::Period { from, to } => {
+                *period = (from, to);
+                true
+            }
+            _ => false,
+        })?;
 
         if refresh || force {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
 
         Ok(())
@@ -429,29 +619,47 @@ impl fmt::Display for HeadLine {
 
         let mut s: String = Default::default();
 
-        let s_date = self.date.format("%d-%b-%y").to_string();
+        let s_date = if width < HEAD_SHORT_DATE_WIDTH {
+            self.date.format("%d-%b").to_string()
+        } else {
+            self.date.format("%d-%b-%y").to_string()
+        };
         let ss_date = style::style(&s_date).on(BG_LAYER).with(FG_DATE);
-        let s_per0 = self.period.0.format("%d-%b-%y").to_string();
-        let ss_per0 = style::style(&s_per0).on(BG_LAYER).with(FG_PERIOD);
-        let s_per1 = self.period.1.format("%d-%b-%y").to_string();
-        let ss_per1 = style::style(&s_per1).on(BG_LAYER).with(FG_PERIOD);
+
+        let (n, content) = if width < HEAD_PERIOD_WIDTH {
+            (s_date.len(), ss_date.to_string())
+        } else {
+            let s_per0 = self.period.0.format("%d-%b-%y").to_string();
+            let ss_per0 = style::style(&s_per0).on(BG_LAYER).with(FG_PERIOD);
+            let s_per1 = self.period.1.format("%d-%b-%y").to_string();
+            let ss_per1 = style::style(&s_per1).on(BG_LAYER).with(FG_PERIOD);
+            let content = format!(
+                "{}{}{}{}{}",
+                ss_per0,
+                style::style("..").on(BG_LAYER).with(FG_BORDER),
+                ss_per1,
+                style::style(" ").on(BG_LAYER).with(FG_BORDER),
+                ss_date
+            );
+            (s_per0.len() + s_per1.len() + s_date.len() + 3, content)
+        };
+
+        // banner takes what is left after period and date.
+        let banner: String = {
+            let m = layout::remaining(width, n + 1);
+            self.banner.chars().take(m).collect()
+        };
+        s.push_str(&style::style(&banner).on(BG_LAYER).with(FG_ERROR).to_string());
 
         s.push_str(&{
-            let n = (width as usize) - s_per0.len() - s_per1.len() - s_date.len() - 3;
+            let n = layout::remaining(width, n + banner.chars().count());
             style::style(&String::from_iter(repeat(' ').take(n)))
                 .on(BG_LAYER)
                 .to_string()
         });
-        s.push_str(&format!(
-            "{}{}{}{}{}",
-            ss_per0,
-            style::style("..").on(BG_LAYER).with(FG_BORDER),
-            ss_per1,
-            style::style(" ").on(BG_LAYER).with(FG_BORDER),
-            ss_date
-        ));
-
-        write!(f, "{}", cursor::MoveTo(col - 1, row - 1).to_string())?;
+        s.push_str(&content);
+
+        write!(f, "{}", move_to(col, row).to_string())?;
         write!(f, "{}", s)
     }
 }
@@ -505,12 +713,12 @@ impl Border {
     {
         if self.focus && self.render_type == "normal" {
             self.render_type = "highlt";
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         } else if !self.focus && self.render_type == "highlt" {
             self.render_type = "normal";
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         } else if force {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
 
         Ok(())
@@ -545,9 +753,13 @@ impl Border {
 
         let (col, row) = {
             let (col, row) = self.vp.to_origin();
-            (col - 1, row - 1)
+            (layout::sub(col, 1), layout::sub(row, 1))
         };
         let (ht, wd) = self.vp.to_size();
+        if ht == 0 || wd == 0 {
+            return String::default();
+        }
+        let (right, bottom) = (layout::last(col, wd), layout::last(row, ht));
         let mut s: String = Default::default();
 
         // top
@@ -555,28 +767,28 @@ impl Border {
         s.push_str(&String::from_iter(repeat('─').take(wd as usize)));
         // right
         for h in 0..ht {
-            s.push_str(&cursor::MoveTo(col + wd - 1, row + h).to_string());
+            s.push_str(&cursor::MoveTo(right, layout::add(row, h)).to_string());
             s.push_str("│");
         }
         // botton
-        s.push_str(&cursor::MoveTo(col, row + ht - 1).to_string());
+        s.push_str(&cursor::MoveTo(col, bottom).to_string());
         s.push_str(&String::from_iter(repeat('─').take(wd as usize)));
         // left
         for h in 0..ht {
-            s.push_str(&cursor::MoveTo(col, row + h).to_string());
+            s.push_str(&cursor::MoveTo(col, layout::add(row, h)).to_string());
             s.push_str("│");
         }
         // top-left corner
         s.push_str(&cursor::MoveTo(col, row).to_string());
         s.push_str("╭");
         // top-right corner
-        s.push_str(&cursor::MoveTo(col + wd - 1, row).to_string());
+        s.push_str(&cursor::MoveTo(right, row).to_string());
         s.push_str("╮");
         // bottom-right corner
-        s.push_str(&cursor::MoveTo(col + wd - 1, row + ht - 1).to_string());
+        s.push_str(&cursor::MoveTo(right, bottom).to_string());
         s.push_str("╯");
         // bottom-left corner
-        s.push_str(&cursor::MoveTo(col, row + ht - 1).to_string());
+        s.push_str(&cursor::MoveTo(col, bottom).to_string());
         s.push_str("╰");
 
         s
@@ -602,13 +814,16 @@ impl fmt::Display for Border {
             _ => unreachable!(),
         }
 
-        // render title
-        let (col, _) = self.vp.to_origin();
-        let col = col + 2;
+        // render title, clipped to leave the corners.
+        if ht == 0 {
+            return Ok(());
+        }
+        let title: String = self.title.chars().take(layout::remaining(wd, 4)).collect();
+        let col = layout::add(col, 2);
         let mut title_span: String = Default::default();
-        title_span.push_str(&cursor::MoveTo(col - 1, row - 1).to_string());
+        title_span.push_str(&move_to(col, row).to_string());
         title_span.push_str(
-            &style::style(self.title.clone())
+            &style::style(title)
                 .on(BG_LAYER)
                 .with(FG_TITLE)
                 .to_string(),
@@ -642,6 +857,18 @@ impl Span {
         self.fg = color;
         self
     }
+
+    // replace content, padded to the span's width so that it overwrites
+    // longer content rendered earlier.
+    pub fn set_content(&mut self, content: &str) -> &mut Self {
+        use std::iter::repeat;
+
+        let (_, width) = self.vp.to_size();
+        let content: String = content.chars().take(width as usize).collect();
+        let n = layout::remaining(width, content.chars().count());
+        self.content = content + &String::from_iter(repeat(' ').take(n));
+        self
+    }
 }
 
 impl Span {
@@ -650,7 +877,7 @@ impl Span {
         S: Store,
     {
         if force {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
         Ok(())
     }
@@ -691,7 +918,7 @@ impl fmt::Display for Span {
             width
         );
 
-        write!(f, "{}", cursor::MoveTo(col - 1, row - 1).to_string())?;
+        write!(f, "{}", move_to(col, row).to_string())?;
         write!(
             f,
             "{}",
@@ -706,6 +933,13 @@ impl fmt::Display for Span {
 pub struct StatusLine {
     vp: Viewport,
     line: String,
+    // (col, row) of the cursor in the focused edit element.
+    cursor: Option<(usize, usize)>,
+    // command being typed, in command mode.
+    cmd: Option<Buffer>,
+    // store has uncommitted changes.
+    modified: bool,
+    dirty: bool,
 }
 
 impl_command!(StatusLine);
@@ -721,7 +955,57 @@ impl StatusLine {
             let (_, width) = vp.to_size();
             String::from_iter(repeat(' ').take(width as usize))
         };
-        Ok(StatusLine { vp, line })
+        Ok(StatusLine {
+            vp,
+            line,
+            cursor: None,
+            cmd: None,
+            modified: false,
+            dirty: false,
+        })
+    }
+
+    // enter command mode, key events are captured by the status line
+    // until the command is taken.
+    pub fn start_cmd(&mut self) -> Result<()> {
+        self.cmd = Some(Buffer::empty()?);
+        self.dirty = true;
+        Ok(())
+    }
+
+    // leave command mode and return the command text.
+    pub fn take_cmd(&mut self) -> Option<String> {
+        self.dirty = true;
+        self.cmd.take().map(|buffer| buffer.to_string())
+    }
+
+    // screen (col, row) of the cursor in command mode.
+    pub fn to_cmd_cursor(&self) -> Option<(u16, u16)> {
+        let (col, row) = self.vp.to_origin();
+        let (_, width) = self.vp.to_size();
+        self.cmd.as_ref().map(|buffer| {
+            let (c, _) = buffer.cursor_position();
+            let c = cmp::min(c as u16 + 1, layout::sub(width, 1));
+            (layout::add(col, c), row)
+        })
+    }
+
+    pub fn to_cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        if self.cursor != cursor {
+            self.cursor = cursor;
+            self.dirty = true;
+        }
+    }
+
+    pub fn set_modified(&mut self, modified: bool) {
+        if self.modified != modified {
+            self.modified = modified;
+            self.dirty = true;
+        }
     }
 
     pub fn log(&mut self, msg: &str) {
@@ -733,10 +1017,9 @@ impl StatusLine {
 
         let (_, width) = self.vp.to_size();
         self.line = msg.to_string();
-        if self.line.len() < (width as usize) {
-            let n = (width as usize) - self.line.len();
-            self.line += &String::from_iter(repeat(' ').take(n));
-        }
+        let n = layout::remaining(width, self.line.len());
+        self.line += &String::from_iter(repeat(' ').take(n));
+        self.dirty = true;
     }
 }
 
@@ -745,8 +1028,9 @@ impl StatusLine {
     where
         S: Store,
     {
-        if force {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+        if force || self.dirty {
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
+            self.dirty = false;
         }
 
         Ok(())
@@ -767,11 +1051,21 @@ impl StatusLine {
         Ok(())
     }
 
-    fn handle_event<S>(&mut self, _app: &mut Application<S>, evnt: Event) -> Result<Option<Event>>
+    pub fn handle_event<S>(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>>
     where
         S: Store,
     {
-        Ok(Some(evnt))
+        match self.cmd.as_mut() {
+            Some(buffer) => {
+                let EditRes { evnt, .. } = buffer.handle_event(evnt)?;
+                self.dirty = true;
+                if let Some((col, row)) = self.to_cmd_cursor() {
+                    app.move_cursor(col, row)?;
+                }
+                Ok(evnt)
+            }
+            None => Ok(Some(evnt)),
+        }
     }
 }
 
@@ -788,12 +1082,31 @@ impl fmt::Display for StatusLine {
             width
         );
 
-        write!(f, "{}", cursor::MoveTo(col - 1, row - 1).to_string())?;
-        write!(
-            f,
-            "{}",
-            style::style(self.line.clone()).on(BG_LAYER).with(FG_STATUS)
-        )
+        let line = match (&self.cmd, self.cursor) {
+            (Some(buffer), _) => {
+                let line = format!(":{}", buffer.to_string());
+                let line: String = line.chars().take(width as usize).collect();
+                let n = layout::remaining(width, line.chars().count());
+                line + &String::from_iter(std::iter::repeat(' ').take(n))
+            }
+            (None, cursor) => {
+                let mut tags = vec![];
+                if self.modified {
+                    tags.push("modified".to_string());
+                }
+                if let Some((c, r)) = cursor {
+                    tags.push(format!("Ln {}, Col {}", r + 1, c + 1));
+                }
+                let pos = tags.join("  ");
+                let n = layout::remaining(width, pos.len());
+                let mut line = String::from_iter(self.line.chars().take(n));
+                line.push_str(&pos);
+                line
+            }
+        };
+
+        write!(f, "{}", move_to(col, row).to_string())?;
+        write!(f, "{}", style::style(line).on(BG_LAYER).with(FG_STATUS))
     }
 }
 
@@ -869,12 +1182,12 @@ impl Button {
     {
         if self.focus && self.render_type == "normal" {
             self.render_type = "highlt";
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         } else if !self.focus && self.render_type == "highlt" {
             self.render_type = "normal";
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         } else if force {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
 
         Ok(())
@@ -920,7 +1233,7 @@ impl Button {
 
         let mut s: String = Default::default();
 
-        s.push_str(&cursor::MoveTo(col - 1, row - 1).to_string());
+        s.push_str(&move_to(col, row).to_string());
         s.push_str(&style::SetBackgroundColor(bg).to_string());
         s.push_str(&style::SetForegroundColor(fg).to_string());
         if self.bold {
@@ -931,8 +1244,7 @@ impl Button {
                 let w: usize = self.text.chars().filter_map(char::width).sum();
                 w as u16
             };
-            let l_width = (width - t_width) / 2;
-            let r_width = width - t_width - l_width;
+            let (l_width, r_width) = layout::center(width, t_width);
             String::from_iter(repeat(' ').take(l_width as usize))
                 + self.text.as_str()
                 + String::from_iter(repeat(' ').take(r_width as usize)).as_str()
@@ -963,6 +1275,449 @@ impl fmt::Display for Button {
     }
 }
 
+// move to 1-based (col, row) of a viewport, cursor::MoveTo is 0-based.
+// Saturates at terminal origin, so that a viewport misplaced at column or
+// row 0 renders at the edge instead of panicking.
+pub fn move_to(col: u16, row: u16) -> cursor::MoveTo {
+    cursor::MoveTo(layout::sub(col, 1), layout::sub(row, 1))
+}
+
+// shape and blink of the edit cursor, set via --cursor-style.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorStyle {
+    // terminal's own shape, with blinking enabled.
+    Default,
+    BlinkingBlock,
+    SteadyBlock,
+    BlinkingUnderline,
+    SteadyUnderline,
+    BlinkingBar,
+    SteadyBar,
+}
+
+impl Default for CursorStyle {
+    fn default() -> CursorStyle {
+        CursorStyle::Default
+    }
+}
+
+impl FromStr for CursorStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<CursorStyle> {
+        match s.trim() {
+            "default" => Ok(CursorStyle::Default),
+            "blinking-block" => Ok(CursorStyle::BlinkingBlock),
+            "steady-block" => Ok(CursorStyle::SteadyBlock),
+            "blinking-underline" => Ok(CursorStyle::BlinkingUnderline),
+            "steady-underline" => Ok(CursorStyle::SteadyUnderline),
+            "blinking-bar" => Ok(CursorStyle::BlinkingBar),
+            "steady-bar" => Ok(CursorStyle::SteadyBar),
+            _ => err_at!(InvalidInput, msg: format!("invalid cursor style {:?}", s)),
+        }
+    }
+}
+
+impl_command!(CursorStyle);
+
+// DECSCUSR sequence, crossterm has no command for cursor shape.
+impl fmt::Display for CursorStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let n = match self {
+            CursorStyle::Default => {
+                return write!(f, "{}", cursor::EnableBlinking.ansi_code());
+            }
+            CursorStyle::BlinkingBlock => 1,
+            CursorStyle::SteadyBlock => 2,
+            CursorStyle::BlinkingUnderline => 3,
+            CursorStyle::SteadyUnderline => 4,
+            CursorStyle::BlinkingBar => 5,
+            CursorStyle::SteadyBar => 6,
+        };
+        write!(f, "\x1b[{} q", n)
+    }
+}
+
+// editor for long notes, refer EditBox. Empty `editor` to use $EDITOR,
+// else vi.
+pub fn to_editor(editor: &str) -> String {
+    match editor {
+        "" => std::env::var("EDITOR")
+            .ok()
+            .filter(|e| !e.trim().is_empty())
+            .unwrap_or("vi".to_string()),
+        editor => editor.to_string(),
+    }
+}
+
+// row of text segments, each segment rendered in its own color.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyledRow {
+    segments: Vec<(String, Color)>,
+}
+
+impl StyledRow {
+    pub fn push(&mut self, text: &str, fg: Color) -> &mut Self {
+        self.segments.push((text.to_string(), fg));
+        self
+    }
+
+    // one line row for any value, refer Durable::summary().
+    pub fn from_summary<V>(value: &V) -> StyledRow
+    where
+        V: Durable,
+    {
+        let mut row = StyledRow::default();
+        row.push(&value.summary(), FG_EDIT);
+        row
+    }
+
+    // entry's date, payee and note summary, followed by its debit legs
+    // and credit legs in distinct colors, amounts formatted with `fmt`.
+    pub fn from_entry(entry: &types::JournalEntry, fmt: &util::AmountFormat) -> StyledRow {
+        let mut row = StyledRow::default();
+        row.push(&entry.created.format("%d-%b-%y ").to_string(), FG_DATE)
+            .push(&entry.payee, FG_EDIT);
+        if entry.reconciled {
+            row.push(" ✓", FG_DEBIT);
+        }
+        if !entry.attachments.is_empty() {
+            row.push(&format!(" 📎{}", entry.attachments.len()), FG_EDIT_INLINE);
+        }
+        let (summary, _) = types::note_summary(&entry.note);
+        if !summary.is_empty() {
+            row.push(&format!(" ({})", summary), FG_EDIT_INLINE);
+        }
+        for d in entry.debitors.iter() {
+            let (_, lname): (String, String) = d.ledger.clone().into();
+            let amount = fmt.format_amount(d.commodity.value);
+            row.push(&format!(" {} {} Dr", lname, amount), FG_DEBIT);
+        }
+        for c in entry.creditors.iter() {
+            let (_, lname): (String, String) = c.ledger.clone().into();
+            let amount = fmt.format_amount(c.commodity.value);
+            row.push(&format!(" {} {} Cr", lname, amount), FG_CREDIT);
+        }
+        row
+    }
+
+    pub fn to_segments(&self) -> Vec<(String, Color)> {
+        self.segments.clone()
+    }
+
+    // render segments within `width` columns on `bg`, truncating or
+    // padding as necessary.
+    pub fn to_ansi(&self, width: u16, bg: Color) -> String {
+        let mut s = style::SetBackgroundColor(bg).to_string();
+        let mut n = 0;
+        for (text, fg) in self.segments.iter() {
+            let mut seg = String::default();
+            for ch in text.chars() {
+                let w = ch.width().unwrap_or(0);
+                if (n + w) > (width as usize) {
+                    break;
+                }
+                seg.push(ch);
+                n += w;
+            }
+            if !seg.is_empty() {
+                s.push_str(&style::SetForegroundColor(*fg).to_string());
+                s.push_str(&seg);
+            }
+        }
+        let pad = layout::remaining(width, n);
+        s.push_str(&String::from_iter(std::iter::repeat(' ').take(pad)));
+        s
+    }
+
+    // render segments as columns, segment `i` truncated or padded to
+    // `widths[i]` cells. Zero width columns are hidden, visible columns
+    // are separated by a one cell gap.
+    pub fn to_ansi_columns(&self, widths: &[u16], bg: Color) -> String {
+        use std::iter::repeat;
+
+        let mut s = style::SetBackgroundColor(bg).to_string();
+        let mut first = true;
+        let empty = (String::default(), FG_EDIT);
+        for (i, width) in widths.iter().enumerate() {
+            if *width == 0 {
+                continue;
+            } else if !first {
+                s.push(' ');
+            }
+            first = false;
+
+            let (text, fg) = self.segments.get(i).unwrap_or(&empty);
+            let mut cell = String::default();
+            let mut n = 0;
+            for ch in text.trim().chars() {
+                let w = ch.width().unwrap_or(0);
+                if (n + w) > (*width as usize) {
+                    break;
+                }
+                cell.push(ch);
+                n += w;
+            }
+            cell.push_str(&String::from_iter(repeat(' ').take(layout::remaining(*width, n))));
+            s.push_str(&style::SetForegroundColor(*fg).to_string());
+            s.push_str(&cell);
+        }
+        s
+    }
+}
+
+// sort order of a list box, by key name, refer ListBox::sort_by().
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortKey {
+    pub name: String,
+    pub ascending: bool,
+}
+
+// scrollable list of styled rows, with one selected row.
+#[derive(Clone, Default)]
+pub struct ListBox {
+    vp: Viewport,
+    rows: Vec<StyledRow>,
+    // display order, as indices into `rows`.
+    order: Vec<usize>,
+    sort_key: Option<SortKey>,
+    // when set, row segments are laid out as columns.
+    columns: Vec<layout::Column>,
+    selected: usize,
+    top: usize,
+    focus: bool,
+}
+
+impl_command!(ListBox);
+
+impl ListBox {
+    pub fn new<S>(_app: &mut Application<S>, vp: Viewport, rows: Vec<StyledRow>) -> Result<ListBox>
+    where
+        S: Store,
+    {
+        Ok(ListBox {
+            vp,
+            order: (0..rows.len()).collect(),
+            rows,
+            ..Default::default()
+        })
+    }
+
+    pub fn set_viewport(&mut self, vp: Viewport) -> &mut Self {
+        self.vp = vp;
+        self.select(self.selected);
+        self
+    }
+
+    pub fn set_columns(&mut self, columns: Vec<layout::Column>) -> &mut Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn set_rows(&mut self, rows: Vec<StyledRow>) -> &mut Self {
+        self.order = (0..rows.len()).collect();
+        self.rows = rows;
+        self.selected = 0;
+        self.top = 0;
+        self.sort_key = None;
+        self
+    }
+
+    // replace the row at index `row`, as passed to new() or set_rows(),
+    // keeping the order and selection.
+    pub fn set_row(&mut self, row: usize, value: StyledRow) -> &mut Self {
+        if let Some(r) = self.rows.get_mut(row) {
+            *r = value;
+        }
+        self
+    }
+
+    // re-order rows using `cmp` over row indices, as passed to new() or
+    // set_rows(), reversed for descending `key`. Selection stays on the
+    // same row.
+    pub fn sort_by<F>(&mut self, key: SortKey, mut cmp: F) -> &mut Self
+    where
+        F: FnMut(usize, usize) -> cmp::Ordering,
+    {
+        let selected = self.to_selected();
+        let ascending = key.ascending;
+        self.order.sort_by(|x, y| match cmp(*x, *y) {
+            ord if ascending => ord,
+            ord => ord.reverse(),
+        });
+        if let Some(row) = selected {
+            let off = self.order.iter().position(|r| *r == row).unwrap_or(0);
+            self.select(off);
+        }
+        self.sort_key = Some(key);
+        self
+    }
+
+    pub fn to_sort_key(&self) -> Option<SortKey> {
+        self.sort_key.clone()
+    }
+
+    // index of the selected row, as passed to new() or set_rows().
+    pub fn to_selected(&self) -> Option<usize> {
+        self.order.get(self.selected).cloned()
+    }
+
+    // select row at index `row`, as passed to new() or set_rows().
+    pub fn set_selected(&mut self, row: usize) -> &mut Self {
+        if let Some(off) = self.order.iter().position(|r| *r == row) {
+            self.select(off);
+        }
+        self
+    }
+
+    fn select(&mut self, selected: usize) {
+        let (height, _) = self.vp.to_size();
+        self.selected = cmp::min(selected, self.rows.len().saturating_sub(1));
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= (self.top + height as usize) {
+            self.top = self.selected + 1 - (height as usize);
+        }
+    }
+}
+
+impl ListBox {
+    pub fn refresh<S>(&mut self, app: &mut Application<S>, force: bool) -> Result<()>
+    where
+        S: Store,
+    {
+        if force || self.focus {
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
+        }
+        Ok(())
+    }
+
+    fn focus<S>(&mut self, _app: &mut Application<S>) -> Result<()>
+    where
+        S: Store,
+    {
+        trace!("Focus list-box");
+        self.focus = true;
+        Ok(())
+    }
+
+    fn leave<S>(&mut self, _app: &mut Application<S>) -> Result<()>
+    where
+        S: Store,
+    {
+        self.focus = false;
+        Ok(())
+    }
+
+    fn handle_event<S>(&mut self, _app: &mut Application<S>, evnt: Event) -> Result<Option<Event>>
+    where
+        S: Store,
+    {
+        let (height, _) = self.vp.to_size();
+        match evnt.to_key_code() {
+            Some(KeyCode::Up) => self.select(self.selected.saturating_sub(1)),
+            Some(KeyCode::Down) => self.select(self.selected + 1),
+            Some(KeyCode::PageUp) => self.select(self.selected.saturating_sub(height as usize)),
+            Some(KeyCode::PageDown) => self.select(self.selected + (height as usize)),
+            Some(KeyCode::Home) => self.select(0),
+            Some(KeyCode::End) => self.select(self.rows.len()),
+            _ => return Ok(Some(evnt)),
+        }
+        Ok(None)
+    }
+}
+
+impl fmt::Display for ListBox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "ListBox::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let widths = match self.columns.len() {
+            0 => None,
+            _ => Some(layout::solve_columns(width, &self.columns)),
+        };
+        let empty = StyledRow::default();
+        for r in 0..height {
+            let off = self.top + (r as usize);
+            let bg = if self.focus && off == self.selected {
+                BG_EDIT
+            } else {
+                BG_LAYER
+            };
+            let srow = match self.order.get(off) {
+                Some(r) => &self.rows[*r],
+                None => &empty,
+            };
+            write!(f, "{}", move_to(col, row + r).to_string())?;
+            match &widths {
+                Some(widths) => {
+                    let s = srow.to_ansi_columns(widths, bg);
+                    let used: u16 = widths.iter().filter(|w| **w > 0).map(|w| w + 1).sum();
+                    let pad = layout::remaining(width, layout::sub(used, 1) as usize);
+                    write!(f, "{}{}", s, String::from_iter(std::iter::repeat(' ').take(pad)))?;
+                }
+                None => write!(f, "{}", srow.to_ansi(width, bg))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// split pasted text, a tab-separated row like one copied from a
+// spreadsheet, into fields. Return None if the text is not tabular.
+pub fn split_tabular(text: &str) -> Option<Vec<String>> {
+    let line = text.lines().next().unwrap_or("");
+    if line.contains('\t') {
+        Some(line.split('\t').map(|s| s.trim().to_string()).collect())
+    } else {
+        None
+    }
+}
+
+// fill `fields`, in order, into edit elements at offsets `offs`, skipping
+// elements that are not editable. Return the number of offsets spanned,
+// upto and including the last filled element.
+pub fn fill_fields(elements: &mut [Element], offs: &[usize], fields: &[String]) -> Result<usize> {
+    let mut fields = fields.iter().peekable();
+    let mut n = 0;
+    for (i, off) in offs.iter().enumerate() {
+        let field = match fields.peek() {
+            Some(field) => field.to_string(),
+            None => break,
+        };
+        if elements[*off].set_content(&field)? {
+            fields.next();
+            n = i + 1;
+        }
+    }
+    Ok(n)
+}
+
+// truncate `field` label to leave at least one column, out of `width`,
+// for editing. Return the label and its display width.
+fn fit_field(field: &str, width: u16) -> (String, u16) {
+    let max_width = layout::sub(width, 1) as usize;
+    let (mut label, mut w_label) = (String::default(), 0);
+    for ch in field.chars() {
+        let w = ch.width().unwrap_or(0);
+        if (w_label + w) > max_width {
+            break;
+        }
+        label.push(ch);
+        w_label += w;
+    }
+    (label, w_label as u16)
+}
+
 #[derive(Clone)]
 pub struct EditLine {
     vp: Viewport,
@@ -972,6 +1727,12 @@ pub struct EditLine {
     inline: String,
     buffer: Buffer,
     focus: bool,
+    // validation error for the current content, if any.
+    error: Option<String>,
+    // maximum number of characters, inserts beyond are dropped.
+    max_len: Option<usize>,
+    // date fields hold a date in this format, EG: `%d-%b-%y`.
+    date_format: Option<String>,
 
     tc_line: String,
 }
@@ -991,6 +1752,9 @@ impl EditLine {
             inline: Default::default(),
             buffer: Buffer::empty()?,
             focus: false,
+            error: None,
+            max_len: None,
+            date_format: None,
 
             tc_line: Default::default(),
         };
@@ -1006,15 +1770,82 @@ impl EditLine {
         self
     }
 
+    pub fn set_content(&mut self, text: &str) -> Result<&mut Self> {
+        self.buffer = Buffer::from_reader(text.as_bytes())?;
+        self.inline.clear();
+        self.tc_line = self.make_term_cache();
+        Ok(self)
+    }
+
+    // insert `text` at the cursor, as if typed. Text beyond the
+    // maximum length is dropped.
+    pub fn insert_text(&mut self, text: &str) -> &mut Self {
+        let text: String = match self.max_len {
+            Some(max) => {
+                let n = max.saturating_sub(self.get_content_len());
+                text.chars().take(n).collect()
+            }
+            None => text.to_string(),
+        };
+        let ed_cursor = self.buffer.insert_str(&text);
+        self.edit_vp.apply_ed_cursor(ed_cursor);
+        self.inline.clear();
+        self.tc_line = self.make_term_cache();
+        self
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    // make this a date field, holding dates in `format`.
+    pub fn set_date_field(&mut self, format: &str) -> &mut Self {
+        self.date_format = Some(format.to_string());
+        self
+    }
+
+    // replace the content of a date field with today's date, return
+    // false if this is not a date field.
+    fn insert_today<S>(&mut self, app: &mut Application<S>) -> Result<bool>
+    where
+        S: Store,
+    {
+        let text = match &self.date_format {
+            Some(format) => chrono::Local::today().format(format).to_string(),
+            None => return Ok(false),
+        };
+        self.set_content("")?.insert_text(&text);
+        self.focus(app)?;
+        Ok(true)
+    }
+
+    fn is_full(&self) -> bool {
+        match self.max_len {
+            Some(max) => self.get_content_len() >= max,
+            None => false,
+        }
+    }
+
+    // mark this element as invalid, None to clear the error.
+    pub fn set_error(&mut self, error: Option<String>) -> &mut Self {
+        self.error = error;
+        self
+    }
+
+    pub fn to_error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
     pub fn set_field(&mut self, field: &str) -> &mut Self {
-        self.field = field.to_string();
         self.edit_vp = {
             let (_, width) = self.edit_vp.to_size();
-            let w_field: usize = self.field.chars().filter_map(char::width).sum();
+            let (field, w_field) = fit_field(field, width);
+            self.field = field;
             self.edit_vp
                 .clone()
                 .move_by(w_field as i16, 0)
-                .resize_to(1, width - (w_field as u16))
+                .resize_to(1, cmp::max(layout::sub(width, w_field), 1))
         };
         self.tc_line = self.make_term_cache();
         self
@@ -1024,7 +1855,9 @@ impl EditLine {
         self.mandatory = mandatory;
         self.edit_vp = {
             let (_, width) = self.edit_vp.to_size();
-            self.edit_vp.clone().resize_to(1, width - 1)
+            self.edit_vp
+                .clone()
+                .resize_to(1, cmp::max(layout::sub(width, 1), 1))
         };
         self.tc_line = self.make_term_cache();
         self
@@ -1050,6 +1883,13 @@ impl EditLine {
         })
     }
 
+    fn get_content_len(&self) -> usize {
+        match self.buffer.view_lines(0).first() {
+            Some(line) => line.trim_end_matches(&['\r', '\n'][..]).chars().count(),
+            None => 0,
+        }
+    }
+
     fn make_term_cache(&self) -> String {
         use std::iter::repeat;
 
@@ -1062,10 +1902,10 @@ impl EditLine {
         let edit_line = {
             let inline = String::from_iter(self.inline.chars().take(ed_width as usize));
             let w_inline = inline.chars().collect::<Vec<char>>().len();
-            inline + &String::from_iter(repeat(' ').take((ed_width as usize) - w_inline))
+            inline + &String::from_iter(repeat(' ').take(layout::remaining(ed_width, w_inline)))
         };
 
-        s.push_str(&cursor::MoveTo(col - 1, row - 1).to_string());
+        s.push_str(&move_to(col, row).to_string());
         if self.field.len() > 0 {
             s.push_str(
                 &style::style(&self.field)
@@ -1090,7 +1930,7 @@ impl EditLine {
         }
 
         let buf_line = self.get_buffer_line();
-        s.push_str(&cursor::MoveTo(ed_col - 1, ed_row - 1).to_string());
+        s.push_str(&move_to(ed_col, ed_row).to_string());
         s.push_str(&style::style(buf_line).on(BG_EDIT).with(FG_EDIT).to_string());
 
         s
@@ -1105,8 +1945,8 @@ impl EditLine {
         if force {
             self.tc_line = self.make_term_cache();
         }
-        if self.focus {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+        if force || self.focus {
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
 
         Ok(())
@@ -1128,15 +1968,20 @@ impl EditLine {
             self.vp.to_cursor_off()
         );
         app.move_cursor(ed_col, ed_row)?;
+        app.set_edit_cursor(Some(self.buffer.cursor_position()));
+        if let Some(error) = &self.error {
+            app.log_status(error);
+        }
         self.focus = true;
 
         Ok(())
     }
 
-    fn leave<S>(&mut self, _app: &mut Application<S>) -> Result<()>
+    fn leave<S>(&mut self, app: &mut Application<S>) -> Result<()>
     where
         S: Store,
     {
+        app.set_edit_cursor(None);
         self.focus = false;
         Ok(())
     }
@@ -1154,6 +1999,11 @@ impl EditLine {
             | (_, Some(KeyCode::PageDown))
             | (_, Some(KeyCode::Tab)) => Ok(Some(evnt)),
             (m, Some(KeyCode::BackTab)) if m.is_empty() => Ok(Some(evnt)),
+            (m, Some(KeyCode::Char(_)))
+                if !m.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) && self.is_full() =>
+            {
+                Ok(None)
+            }
             _ => match self.buffer.handle_event(evnt)? {
                 EditRes {
                     col_at,
@@ -1168,6 +2018,7 @@ impl EditLine {
 
         let (ed_col, ed_row) = self.edit_vp.to_cursor();
         app.move_cursor(ed_col, ed_row)?;
+        app.set_edit_cursor(Some(self.buffer.cursor_position()));
 
         Ok(evnt)
     }
@@ -1190,8 +2041,50 @@ impl fmt::Display for EditLine {
 
         let buf_line = self.get_buffer_line();
         let (ed_col, ed_row) = self.edit_vp.to_origin();
-        write!(f, "{}", cursor::MoveTo(ed_col - 1, ed_row - 1))?;
-        write!(f, "{}", style::style(buf_line).on(BG_EDIT).with(FG_EDIT))
+        write!(f, "{}", move_to(ed_col, ed_row))?;
+        write!(f, "{}", style::style(buf_line).on(BG_EDIT).with(FG_EDIT))?;
+
+        // mark content that is scrolled out of view.
+        let (_, ed_width) = self.edit_vp.to_size();
+        let (ed_o_col, _) = self.edit_vp.to_ed_origin();
+        let n = self.get_content_len();
+        let (left, right) = scroll_markers(ed_o_col, ed_width as usize, n);
+        if left {
+            write!(f, "{}", move_to(ed_col, ed_row))?;
+            write!(f, "{}", style::style('‹').on(BG_EDIT).with(FG_EDIT_INLINE))?;
+        }
+        if right {
+            write!(f, "{}", move_to(layout::last(ed_col, ed_width), ed_row))?;
+            write!(f, "{}", style::style('›').on(BG_EDIT).with(FG_EDIT_INLINE))?;
+        }
+
+        // error marker takes the cell after the edit area, else the
+        // limit marker once content reaches the maximum length.
+        if self.error.is_some() {
+            write!(f, "{}", move_to(layout::add(ed_col, ed_width), ed_row))?;
+            write!(f, "{}", style::style(ERROR_MARKER).on(BG_LAYER).with(FG_ERROR))?;
+        } else if self.is_full() {
+            write!(f, "{}", move_to(layout::add(ed_col, ed_width), ed_row))?;
+            write!(f, "{}", style::style(LIMIT_MARKER).on(BG_LAYER).with(FG_EDIT_INLINE))?;
+        }
+
+        Ok(())
+    }
+}
+
+// return whether content, of `n` chars, extends beyond the edit width
+// on the (left, right), when viewed from column `ed_col`.
+fn scroll_markers(ed_col: usize, ed_width: usize, n: usize) -> (bool, bool) {
+    (ed_col > 0, n > ed_col + ed_width)
+}
+
+// return the length indicator, as `n/max`, once content of `n` chars
+// reaches 90% of `max`.
+fn limit_indicator(n: usize, max: usize) -> Option<String> {
+    if (n * 10) >= (max * 9) {
+        Some(format!("{}/{}", n, max))
+    } else {
+        None
     }
 }
 
@@ -1204,6 +2097,9 @@ pub struct EditBox {
     inline: String,
     buffer: Buffer,
     focus: bool,
+    // validation error for the current content, if any.
+    error: Option<String>,
+    max_len: Option<usize>,
 
     tc_line: String,
 }
@@ -1223,6 +2119,8 @@ impl EditBox {
             inline: Default::default(),
             buffer: Buffer::empty()?,
             focus: false,
+            error: None,
+            max_len: None,
 
             tc_line: Default::default(),
         };
@@ -1238,25 +2136,70 @@ impl EditBox {
         self
     }
 
+    pub fn set_content(&mut self, text: &str) -> Result<&mut Self> {
+        self.buffer = Buffer::from_reader(text.as_bytes())?;
+        self.inline.clear();
+        self.tc_line = self.make_term_cache();
+        Ok(self)
+    }
+
+    // insert `text` at the cursor, as if typed.
+    pub fn insert_text(&mut self, text: &str) -> &mut Self {
+        let ed_cursor = self.buffer.insert_str(text);
+        self.edit_vp.apply_ed_cursor(ed_cursor);
+        self.inline.clear();
+        self.tc_line = self.make_term_cache();
+        self
+    }
+
+    // mark this element as invalid, None to clear the error.
+    pub fn set_error(&mut self, error: Option<String>) -> &mut Self {
+        self.error = error;
+        self
+    }
+
+    pub fn to_error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
     pub fn set_field(&mut self, field: &str) -> &mut Self {
-        self.field = field.to_string();
         self.edit_vp = {
             let (height, width) = self.edit_vp.to_size();
-            let w_field: usize = self.field.chars().filter_map(char::width).sum();
+            let (field, w_field) = fit_field(field, width);
+            self.field = field;
             self.edit_vp
                 .clone()
                 .move_by(w_field as i16, 0)
-                .resize_to(height, width - (w_field as u16))
+                .resize_to(height, cmp::max(layout::sub(width, w_field), 1))
         };
         self.tc_line = self.make_term_cache();
         self
     }
 
+    // hand the content over to the user's editor, and reload it with
+    // the cursor at the start.
+    fn edit_external<S>(&mut self, app: &mut Application<S>) -> Result<()>
+    where
+        S: Store,
+    {
+        let text = app.edit_external(&self.buffer.to_string())?;
+        self.set_content(&text)?;
+        self.edit_vp.apply_ed_cursor(self.buffer.cursor_position());
+        self.focus(app)
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
     pub fn set_mandatory(&mut self, mandatory: bool) -> &mut Self {
         self.mandatory = mandatory;
         self.edit_vp = {
             let (height, width) = self.edit_vp.to_size();
-            self.edit_vp.clone().resize_to(height, width - 1)
+            self.edit_vp
+                .clone()
+                .resize_to(height, cmp::max(layout::sub(width, 1), 1))
         };
         self.tc_line = self.make_term_cache();
         self
@@ -1273,10 +2216,10 @@ impl EditBox {
         let edit_line = {
             let inline = String::from_iter(self.inline.chars().take(ed_width as usize));
             let w_inline = inline.chars().collect::<Vec<char>>().len();
-            inline + &String::from_iter(repeat(' ').take((ed_width as usize) - w_inline))
+            inline + &String::from_iter(repeat(' ').take(layout::remaining(ed_width, w_inline)))
         };
 
-        s.push_str(&cursor::MoveTo(col - 1, row - 1).to_string());
+        s.push_str(&move_to(col, row).to_string());
         if !self.field.is_empty() {
             s.push_str(
                 &style::style(&self.field)
@@ -1313,9 +2256,9 @@ impl EditBox {
             if !self.inline.is_empty() {
                 self.tc_line = self.make_term_cache();
             }
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         } else if self.focus {
-            err_at!(Fatal, queue!(app.as_mut_stdout(), self))?;
+            err_at!(Fatal, queue!(app.as_mut_out(), self))?;
         }
 
         Ok(())
@@ -1337,14 +2280,19 @@ impl EditBox {
             self.vp.to_cursor_off()
         );
         app.move_cursor(ed_col, ed_row)?;
+        app.set_edit_cursor(Some(self.buffer.cursor_position()));
+        if let Some(error) = &self.error {
+            app.log_status(error);
+        }
         self.focus = true;
         Ok(())
     }
 
-    fn leave<S>(&mut self, _app: &mut Application<S>) -> Result<()>
+    fn leave<S>(&mut self, app: &mut Application<S>) -> Result<()>
     where
         S: Store,
     {
+        app.set_edit_cursor(None);
         self.focus = false;
         Ok(())
     }
@@ -1375,6 +2323,7 @@ impl EditBox {
 
         let (ed_col, ed_row) = self.edit_vp.to_cursor();
         app.move_cursor(ed_col, ed_row)?;
+        app.set_edit_cursor(Some(self.buffer.cursor_position()));
 
         Ok(evnt)
     }
@@ -1407,7 +2356,7 @@ impl fmt::Display for EditBox {
             write!(
                 f,
                 "{}",
-                cursor::MoveTo(ed_o_col - 1, ed_o_row + (i as u16) - 1).to_string()
+                move_to(ed_o_col, layout::add(ed_o_row, i as u16)).to_string()
             )?;
             write!(
                 f,
@@ -1430,11 +2379,32 @@ impl fmt::Display for EditBox {
             write!(
                 f,
                 "{}",
-                cursor::MoveTo(ed_o_col - 1, ed_o_row + (i as u16) - 1).to_string()
+                move_to(ed_o_col, layout::add(ed_o_row, i as u16)).to_string()
             )?;
             write!(f, "{}", style::style(line).on(BG_EDIT).with(FG_EDIT))?;
         }
 
+        let n = self.buffer.to_string().chars().count();
+        if let Some(ind) = self.max_len.and_then(|max| limit_indicator(n, max)) {
+            let w_ind = ind.chars().count() as u16;
+            if w_ind < ed_width {
+                let ind_col = layout::add(ed_o_col, ed_width - w_ind);
+                let ind_row = layout::last(ed_o_row, height);
+                write!(f, "{}", move_to(ind_col, ind_row).to_string())?;
+                write!(f, "{}", style::style(ind).on(BG_EDIT).with(FG_MANDATORY))?;
+            }
+        }
+
+        // error marker takes the cell after the edit area, on first row.
+        if self.error.is_some() {
+            write!(f, "{}", move_to(layout::add(ed_o_col, ed_width), ed_o_row))?;
+            write!(f, "{}", style::style(ERROR_MARKER).on(BG_LAYER).with(FG_ERROR))?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[path = "term_elements_test.rs"]
+mod term_elements_test;
@@ -0,0 +1,471 @@
+use chrono::TimeZone;
+
+use super::*;
+use crate::app::{self, key, key_with};
+
+fn make_headline(width: u16) -> HeadLine {
+    let date = chrono::Local.ymd(2020, 4, 15);
+    HeadLine {
+        vp: Viewport::new(1, 1, 1, width),
+        date,
+        period: util::date_to_period(date),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_headline_width() {
+    // wide enough for period and full date.
+    let s = make_headline(80).to_string();
+    assert!(s.contains("01-Apr-20"), "{}", s);
+    assert!(s.contains("31-Mar-21"), "{}", s);
+    assert!(s.contains("15-Apr-20"), "{}", s);
+
+    // period is dropped.
+    let s = make_headline(HEAD_PERIOD_WIDTH - 1).to_string();
+    assert!(!s.contains("01-Apr-20"), "{}", s);
+    assert!(!s.contains("31-Mar-21"), "{}", s);
+    assert!(s.contains("15-Apr-20"), "{}", s);
+
+    // date is abbreviated.
+    let s = make_headline(HEAD_SHORT_DATE_WIDTH - 1).to_string();
+    assert!(!s.contains("01-Apr"), "{}", s);
+    assert!(!s.contains("15-Apr-20"), "{}", s);
+    assert!(s.contains("15-Apr"), "{}", s);
+}
+
+#[test]
+fn test_scroll_markers() {
+    // content fits.
+    assert_eq!(scroll_markers(0, 10, 0), (false, false));
+    assert_eq!(scroll_markers(0, 10, 10), (false, false));
+    // content overflows on the right.
+    assert_eq!(scroll_markers(0, 10, 11), (false, true));
+    // scrolled to the end.
+    assert_eq!(scroll_markers(5, 10, 15), (true, false));
+    // scrolled into the middle.
+    assert_eq!(scroll_markers(5, 10, 30), (true, true));
+}
+
+#[test]
+fn test_limit_indicator() {
+    assert_eq!(limit_indicator(0, 100), None);
+    assert_eq!(limit_indicator(89, 100), None);
+    assert_eq!(limit_indicator(90, 100), Some("90/100".to_string()));
+    assert_eq!(limit_indicator(100, 100), Some("100/100".to_string()));
+}
+
+fn make_edit_line(width: u16) -> EditLine {
+    let vp = Viewport::new(1, 1, 1, width);
+    EditLine {
+        vp: vp.clone(),
+        edit_vp: vp,
+        field: Default::default(),
+        mandatory: false,
+        inline: Default::default(),
+        buffer: Buffer::empty().unwrap(),
+        focus: false,
+        error: None,
+        max_len: None,
+        date_format: None,
+
+        tc_line: Default::default(),
+    }
+}
+
+#[test]
+fn test_edit_line_narrow_field() {
+    // label fits.
+    let mut em = make_edit_line(20);
+    em.set_mandatory(true).set_field("Aliases :");
+    assert_eq!(em.field, "Aliases :");
+    assert_eq!(em.edit_vp.to_size(), (1, 10));
+
+    // label is wider than the line.
+    let mut em = make_edit_line(5);
+    em.set_mandatory(true).set_field("Aliases :");
+    assert_eq!(em.field, "Ali");
+    assert_eq!(em.edit_vp.to_size(), (1, 1));
+    assert!(em.to_string().contains("Ali"));
+
+    let mut em = make_edit_line(1);
+    em.set_mandatory(true).set_field("Aliases :");
+    assert_eq!(em.field, "");
+    assert_eq!(em.edit_vp.to_size(), (1, 1));
+    em.to_string();
+
+    assert_eq!(fit_field("Name :", 3), ("Na".to_string(), 2));
+    assert_eq!(fit_field("₹₹₹", 3), ("₹₹".to_string(), 2));
+}
+
+#[test]
+fn test_edit_line_error() {
+    let mut name = make_edit_line(20);
+    name.set_field("Name :");
+    let mut symbol = make_edit_line(20);
+    symbol.set_field("Symbol :");
+    assert!(!name.to_string().contains(ERROR_MARKER));
+
+    name.set_error(Some("name must be alphanumeric".to_string()));
+    symbol.set_error(Some("symbol too long".to_string()));
+    assert_eq!(name.to_error().unwrap(), "name must be alphanumeric");
+    assert_eq!(symbol.to_error().unwrap(), "symbol too long");
+    assert!(name.to_string().contains(ERROR_MARKER));
+    assert!(symbol.to_string().contains(ERROR_MARKER));
+
+    // fixing one field leaves the other in error.
+    name.set_error(None);
+    assert!(name.to_error().is_none());
+    assert!(!name.to_string().contains(ERROR_MARKER));
+    assert_eq!(symbol.to_error().unwrap(), "symbol too long");
+}
+
+#[test]
+fn test_edit_line_max_len() {
+    let store = app::make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let mut em = make_edit_line(20);
+    em.set_field("Symbol :").set_max_len(3);
+    for ch in "us".chars() {
+        em.handle_event(&mut app, key(KeyCode::Char(ch))).unwrap();
+    }
+    assert!(!em.to_string().contains(LIMIT_MARKER));
+    em.handle_event(&mut app, key(KeyCode::Char('d'))).unwrap();
+    assert_eq!(app.to_edit_cursor(), Some((3, 0)));
+    assert!(em.to_string().contains(LIMIT_MARKER));
+
+    // typing past the limit is a no-op.
+    let evnt = em.handle_event(&mut app, key(KeyCode::Char('x'))).unwrap();
+    assert!(evnt.is_none());
+    assert_eq!(em.buffer.to_string(), "usd");
+    assert_eq!(app.to_edit_cursor(), Some((3, 0)));
+    let shift_x = key_with(KeyCode::Char('X'), KeyModifiers::SHIFT);
+    assert!(em.handle_event(&mut app, shift_x).unwrap().is_none());
+    assert_eq!(em.buffer.to_string(), "usd");
+
+    // editing keys still apply at the limit.
+    em.handle_event(&mut app, key(KeyCode::Backspace)).unwrap();
+    em.handle_event(&mut app, key(KeyCode::Char('x'))).unwrap();
+    assert_eq!(em.buffer.to_string(), "usx");
+
+    // pasted text is cut at the limit.
+    let mut em = make_edit_line(20);
+    em.set_max_len(3).insert_text("rupee");
+    assert_eq!(em.buffer.to_string(), "rup");
+}
+
+#[test]
+fn test_edit_line_insert_today() {
+    let store = app::make_store();
+    let (mut app, _out) = Application::headless(store).unwrap();
+    let alt_t = key_with(KeyCode::Char('t'), KeyModifiers::ALT);
+
+    let mut em = make_edit_line(20);
+    em.set_field("Date :").set_date_field("%d-%b-%y");
+    em.insert_text("01-Jan");
+    let mut element = Element::EditLine(em);
+    let evnt = element.handle_event(&mut app, alt_t.clone()).unwrap();
+    assert!(evnt.is_none());
+
+    let today = chrono::Local::today().format("%d-%b-%y").to_string();
+    assert_eq!(element.to_content(), Some(today.clone()));
+    assert_eq!(app.to_edit_cursor(), Some((today.chars().count(), 0)));
+
+    // other fields are left alone.
+    let mut element = Element::EditLine(make_edit_line(20));
+    element.handle_event(&mut app, alt_t).unwrap();
+    assert_eq!(element.to_content(), Some("".to_string()));
+}
+
+#[test]
+fn test_paste_tabular() {
+    assert_eq!(split_tabular("rent"), None);
+    let fields = split_tabular("rent\t 1200 \tassets\nignored").unwrap();
+    assert_eq!(fields, vec!["rent", "1200", "assets"]);
+
+    let mut elements: Vec<Element> = (0..4)
+        .map(|_| Element::EditLine(make_edit_line(20)))
+        .collect();
+    let n = fill_fields(&mut elements, &[1, 2, 3, 0], &fields).unwrap();
+    assert_eq!(n, 3);
+
+    let contents: Vec<String> = elements
+        .iter()
+        .map(|em| match em {
+            Element::EditLine(em) => em.buffer.to_string(),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(contents, vec!["", "rent", "1200", "assets"]);
+}
+
+#[test]
+fn test_styled_row_from_entry() {
+    let created = chrono::Utc.ymd(2020, 4, 15).and_hms(10, 0, 0);
+    let mut je = types::JournalEntry::new("coffee".to_string(), created);
+    let value = ("inr".to_string(), 100.0);
+    je.add_creditor("acme".to_string(), "cash".to_string(), value.clone())
+        .unwrap();
+    je.add_debitor("acme".to_string(), "food".to_string(), value)
+        .unwrap();
+
+    let fmt = util::AmountFormat::default();
+    let row = StyledRow::from_entry(&je, &fmt);
+    let segments = row.to_segments();
+    assert_eq!(segments.len(), 4);
+    assert_eq!(segments[0], ("15-Apr-20 ".to_string(), FG_DATE));
+    assert_eq!(segments[2], (" food 100.00 Dr".to_string(), FG_DEBIT));
+    assert_eq!(segments[3], (" cash 100.00 Cr".to_string(), FG_CREDIT));
+
+    let s = row.to_ansi(80, BG_LAYER);
+    assert!(s.contains(&style::SetForegroundColor(FG_DEBIT).to_string()));
+    assert!(s.contains(&style::SetForegroundColor(FG_CREDIT).to_string()));
+
+    // credit leg does not fit.
+    let s = row.to_ansi(20, BG_LAYER);
+    assert!(s.contains(&style::SetForegroundColor(FG_DEBIT).to_string()));
+    assert!(!s.contains(&style::SetForegroundColor(FG_CREDIT).to_string()));
+
+    // only the note's summary line is listed.
+    je.note = "lunch\nwith the team".to_string();
+    let segments = StyledRow::from_entry(&je, &fmt).to_segments();
+    assert_eq!(segments.len(), 5);
+    assert_eq!(segments[2], (" (lunch)".to_string(), FG_EDIT_INLINE));
+
+    // reconciled entries are marked after the payee.
+    je.toggle_reconciled();
+    let segments = StyledRow::from_entry(&je, &fmt).to_segments();
+    assert_eq!(segments.len(), 6);
+    assert_eq!(segments[2], (" ✓".to_string(), FG_DEBIT));
+    assert!(!segments.iter().any(|(text, _)| text.contains('📎')));
+
+    // entries with attachments show their count.
+    je.add_attachment("receipt-0415.pdf").unwrap();
+    je.add_attachment("bill.png").unwrap();
+    let segments = StyledRow::from_entry(&je, &fmt).to_segments();
+    assert_eq!(segments.len(), 7);
+    assert_eq!(segments[3], (" 📎2".to_string(), FG_EDIT_INLINE));
+}
+
+#[test]
+fn test_styled_row_columns() {
+    let mut row = StyledRow::default();
+    row.push("15-Apr-20 ", FG_DATE).push(" coffee", FG_EDIT).push(" cash", FG_CREDIT);
+
+    let fg = |color| style::SetForegroundColor(color).to_string();
+    let s = row.to_ansi_columns(&[9, 4, 5], BG_LAYER);
+    let cols = format!("{}15-Apr-20 {}coff {}cash ", fg(FG_DATE), fg(FG_EDIT), fg(FG_CREDIT));
+    assert!(s.contains(&cols), "{:?}", s);
+
+    // hidden column is skipped along with its gap.
+    let s = row.to_ansi_columns(&[9, 0, 5], BG_LAYER);
+    let cols = format!("{}15-Apr-20 {}cash ", fg(FG_DATE), fg(FG_CREDIT));
+    assert!(s.contains(&cols), "{:?}", s);
+    assert!(!s.contains(&style::SetForegroundColor(FG_EDIT).to_string()));
+}
+
+#[test]
+fn test_element_clone_row() {
+    let mut em = Element::EditLine(make_edit_line(20));
+    em.set_content("cash").unwrap();
+    assert!(!em.is_empty());
+
+    let row = em.clone_row(2).unwrap();
+    assert!(row.is_empty());
+    assert!(row.contain_cell(1, 3));
+    assert!(!row.contain_cell(1, 1));
+    assert!(!em.is_empty());
+}
+
+#[test]
+fn test_viewport_zero_size_origin() {
+    let vp = Viewport::new(5, 3, 0, 0);
+    assert_eq!(vp.to_right(), 4);
+    assert_eq!(vp.to_bottom(), 2);
+    assert!(!vp.contain_cell(5, 3));
+
+    let vp = Viewport::new(0, 0, 0, 0);
+    assert_eq!((vp.to_right(), vp.to_bottom()), (0, 0));
+    assert_eq!(
+        move_to(0, 0).to_string(),
+        cursor::MoveTo(0, 0).to_string()
+    );
+    assert_eq!(
+        move_to(3, 2).to_string(),
+        cursor::MoveTo(2, 1).to_string()
+    );
+
+    // elements at zero origin render at the terminal edge.
+    let mut em = make_edit_line(0);
+    em.vp = Viewport::new(0, 0, 1, 0);
+    em.edit_vp = em.vp.clone();
+    em.to_string();
+    let mut vp = Viewport::new(0, 0, 1, 0);
+    vp.apply_ed_cursor((3, 0));
+    assert_eq!(vp.to_cursor_off(), (0, 0));
+}
+
+#[test]
+fn test_clipboard_copy_paste() {
+    let mut clipboard = Clipboard::default();
+    let mut em = Element::EditLine(make_edit_line(20));
+    assert!(!em.paste_from(&clipboard).unwrap());
+
+    em.set_content("weekly groceries").unwrap();
+    assert!(em.copy_to(&mut clipboard));
+    assert_eq!(clipboard.to_text().unwrap(), "weekly groceries");
+
+    let mut other = Element::EditLine(make_edit_line(20));
+    assert!(other.paste_from(&clipboard).unwrap());
+    assert_eq!(other.to_content().unwrap(), "weekly groceries");
+    // pasted again at the cursor.
+    assert!(other.paste_from(&clipboard).unwrap());
+    assert_eq!(other.to_content().unwrap(), "weekly groceriesweekly groceries");
+
+    // line breaks are flattened for single line elements.
+    clipboard.copy("milk\neggs".to_string());
+    let mut em = Element::EditLine(make_edit_line(20));
+    em.paste_from(&clipboard).unwrap();
+    assert_eq!(em.to_content().unwrap(), "milk eggs");
+
+    // not editable.
+    let mut em = Element::ListBox(ListBox::default());
+    assert!(!em.copy_to(&mut clipboard));
+    assert!(!em.paste_from(&clipboard).unwrap());
+    assert_eq!(clipboard.to_text().unwrap(), "milk\neggs");
+}
+
+#[test]
+fn test_cursor_style() {
+    let style: CursorStyle = "default".parse().unwrap();
+    assert_eq!(style, CursorStyle::default());
+    assert_eq!(style.ansi_code(), cursor::EnableBlinking.ansi_code());
+
+    let testcases = vec![
+        ("blinking-block", "\x1b[1 q"),
+        ("steady-block", "\x1b[2 q"),
+        ("blinking-underline", "\x1b[3 q"),
+        ("steady-underline", "\x1b[4 q"),
+        ("blinking-bar", "\x1b[5 q"),
+        ("steady-bar", "\x1b[6 q"),
+    ];
+    for (name, code) in testcases.into_iter() {
+        let style: CursorStyle = name.parse().unwrap();
+        assert_eq!(style.ansi_code(), code, "{}", name);
+    }
+
+    assert!("hollow".parse::<CursorStyle>().is_err());
+}
+
+#[test]
+fn test_elements_minimal_size() {
+    let sizes = vec![(0, 0), (0, 1), (1, 0), (1, 1), (1, 2), (2, 2), (3, 1)];
+    for (col, row) in vec![(0, 0), (1, 1)].into_iter() {
+        for (height, width) in sizes.clone().into_iter() {
+            let vp = Viewport::new(col, row, height, width);
+
+            let mut em = make_headline(width);
+            em.vp = vp.clone();
+            em.to_string();
+
+            let mut em = Border {
+                vp: vp.clone(),
+                title: " journal ".to_string(),
+                focus: false,
+                tc_normal: Default::default(),
+                tc_highlt: Default::default(),
+                render_type: "normal",
+            };
+            em.tc_normal = em.make_term_cache();
+            em.to_string();
+
+            let mut em = Button {
+                vp: vp.clone(),
+                text: "submit".to_string(),
+                render_type: "normal",
+                ..Default::default()
+            };
+            em.tc_normal = em.make_term_cache(BG_BUTTON, FG_BUTTON);
+            em.to_string();
+
+            let mut em = make_edit_line(width);
+            em.vp = vp.clone();
+            em.edit_vp = vp.clone();
+            em.set_field("payee").set_inline("who").set_mandatory(true);
+            em.set_error(Some("invalid".to_string()));
+            em.to_string();
+
+            let mut em = EditBox {
+                vp: vp.clone(),
+                edit_vp: vp.clone(),
+                field: Default::default(),
+                mandatory: false,
+                inline: Default::default(),
+                buffer: Buffer::empty().unwrap(),
+                focus: false,
+                error: Some("invalid".to_string()),
+                max_len: Some(10),
+
+                tc_line: Default::default(),
+            };
+            em.set_field("note").set_inline("notes");
+            em.to_string();
+        }
+    }
+}
+
+#[test]
+fn test_border_refresh_out() {
+    let store = app::make_store();
+    let (mut app, mut out) = Application::headless(store).unwrap();
+    out.clear();
+
+    let vp = Viewport::new(1, 2, 5, 20);
+    let mut em = Border::new(&mut app, vp, "ledgers".to_string()).unwrap();
+    em.refresh(&mut app, true /*force*/).unwrap();
+
+    let text = out.to_text();
+    for ch in vec!["╭", "╮", "╯", "╰", "─", "│", " ledgers "].into_iter() {
+        assert!(text.contains(ch), "{:?} {:?}", ch, text);
+    }
+}
+
+#[test]
+fn test_list_box_sort() {
+    let names = vec!["cafe", "baker", "grocer"];
+    let rows = names
+        .iter()
+        .map(|name| {
+            let mut row = StyledRow::default();
+            row.push(name, FG_EDIT);
+            row
+        })
+        .collect();
+    let mut list = ListBox::default();
+    list.set_viewport(Viewport::new(1, 1, 2, 20)).set_rows(rows);
+    assert_eq!(list.to_sort_key(), None);
+    assert_eq!(list.to_selected(), Some(0));
+
+    // selection follows the row.
+    let key = SortKey {
+        name: "name".to_string(),
+        ascending: true,
+    };
+    list.sort_by(key.clone(), |x, y| names[x].cmp(names[y]));
+    assert_eq!(list.order, vec![1, 0, 2]);
+    assert_eq!(list.to_selected(), Some(0));
+    assert_eq!(list.selected, 1);
+    assert_eq!(list.to_sort_key(), Some(key));
+
+    let key = SortKey {
+        name: "name".to_string(),
+        ascending: false,
+    };
+    list.sort_by(key, |x, y| names[x].cmp(names[y]));
+    assert_eq!(list.order, vec![2, 0, 1]);
+    assert_eq!(list.to_selected(), Some(0));
+
+    // resetting rows drops the sort order.
+    list.set_rows(vec![StyledRow::default()]);
+    assert_eq!((list.order.clone(), list.to_sort_key()), (vec![0], None));
+}
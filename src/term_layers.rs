@@ -1,14 +1,24 @@
-use crossterm::{cursor, event::KeyCode, style, Command as TermCommand};
+use crossterm::{event::KeyCode, style, Command as TermCommand};
 use log::trace;
+use unicode_width::UnicodeWidthChar;
 
-use std::{iter::FromIterator, marker};
+use std::{
+    cmp, collections::BTreeMap, convert::TryInto, fmt, iter::FromIterator, marker, path, result,
+};
 
 use crate::{
     app::Application,
+    command,
     event::Event,
+    keymap::Action,
+    layout,
     term_elements::{self as te},
 };
-use ledger::core::{Result, Store};
+use ledger::{
+    aggregates,
+    core::{Durable, Error, Result, Store},
+    db_files, err_at, rates, types, util,
+};
 
 pub enum Layer<S>
 where
@@ -16,6 +26,14 @@ where
 {
     NewWorkspace(NewWorkspace<S>),
     OpenCompany(OpenCompany<S>),
+    PickWorkspace(PickWorkspace<S>),
+    EntryBrowser(EntryBrowser<S>),
+    EntryDetail(EntryDetail<S>),
+    LedgerDetail(LedgerDetail<S>),
+    Navigator(Navigator<S>),
+    Calendar(Calendar<S>),
+    Prompt(Prompt<S>),
+    EntryForm(EntryForm<S>),
 }
 
 impl<S> Layer<S>
@@ -26,6 +44,14 @@ where
         match self {
             Layer::NewWorkspace(layer) => layer.focus(app),
             Layer::OpenCompany(layer) => layer.focus(app),
+            Layer::PickWorkspace(layer) => layer.focus(app),
+            Layer::EntryBrowser(layer) => layer.focus(app),
+            Layer::EntryDetail(layer) => layer.focus(app),
+            Layer::LedgerDetail(layer) => layer.focus(app),
+            Layer::Navigator(layer) => layer.focus(app),
+            Layer::Calendar(layer) => layer.focus(app),
+            Layer::Prompt(layer) => layer.focus(app),
+            Layer::EntryForm(layer) => layer.focus(app),
         }
     }
 
@@ -33,6 +59,14 @@ where
         match self {
             Layer::NewWorkspace(layer) => layer.refresh(app, force),
             Layer::OpenCompany(layer) => layer.refresh(app, force),
+            Layer::PickWorkspace(layer) => layer.refresh(app, force),
+            Layer::EntryBrowser(layer) => layer.refresh(app, force),
+            Layer::EntryDetail(layer) => layer.refresh(app, force),
+            Layer::LedgerDetail(layer) => layer.refresh(app, force),
+            Layer::Navigator(layer) => layer.refresh(app, force),
+            Layer::Calendar(layer) => layer.refresh(app, force),
+            Layer::Prompt(layer) => layer.refresh(app, force),
+            Layer::EntryForm(layer) => layer.refresh(app, force),
         }
     }
 
@@ -40,6 +74,14 @@ where
         match self {
             Layer::NewWorkspace(layer) => layer.handle_event(app, evnt),
             Layer::OpenCompany(layer) => layer.handle_event(app, evnt),
+            Layer::PickWorkspace(layer) => layer.handle_event(app, evnt),
+            Layer::EntryBrowser(layer) => layer.handle_event(app, evnt),
+            Layer::EntryDetail(layer) => layer.handle_event(app, evnt),
+            Layer::LedgerDetail(layer) => layer.handle_event(app, evnt),
+            Layer::Navigator(layer) => layer.handle_event(app, evnt),
+            Layer::Calendar(layer) => layer.handle_event(app, evnt),
+            Layer::Prompt(layer) => layer.handle_event(app, evnt),
+            Layer::EntryForm(layer) => layer.handle_event(app, evnt),
         }
     }
 
@@ -47,6 +89,14 @@ where
         match self {
             Layer::NewWorkspace(layer) => layer.leave(app),
             Layer::OpenCompany(layer) => layer.leave(app),
+            Layer::PickWorkspace(layer) => layer.leave(app),
+            Layer::EntryBrowser(layer) => layer.leave(app),
+            Layer::EntryDetail(layer) => layer.leave(app),
+            Layer::LedgerDetail(layer) => layer.leave(app),
+            Layer::Navigator(layer) => layer.leave(app),
+            Layer::Calendar(layer) => layer.leave(app),
+            Layer::Prompt(layer) => layer.leave(app),
+            Layer::EntryForm(layer) => layer.leave(app),
         }
     }
 }
@@ -61,6 +111,14 @@ where
         match self {
             Layer::NewWorkspace(layer) => layer.ansi_code(),
             Layer::OpenCompany(layer) => layer.ansi_code(),
+            Layer::PickWorkspace(layer) => layer.ansi_code(),
+            Layer::EntryBrowser(layer) => layer.ansi_code(),
+            Layer::EntryDetail(layer) => layer.ansi_code(),
+            Layer::LedgerDetail(layer) => layer.ansi_code(),
+            Layer::Navigator(layer) => layer.ansi_code(),
+            Layer::Calendar(layer) => layer.ansi_code(),
+            Layer::Prompt(layer) => layer.ansi_code(),
+            Layer::EntryForm(layer) => layer.ansi_code(),
         }
     }
 }
@@ -90,10 +148,17 @@ where
             let input_vp = vp.clone().move_by(5, 3).resize_to(1, 60);
             let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
             em.set_inline("Enter workspace name, only alphanumeric and '_'")
-                .set_mandatory(true);
+                .set_mandatory(true)
+                .set_max_len(MAX_NAME_LEN);
             em.refresh(app, true /*force*/)?;
             em
         };
+        let ws_input_layout = {
+            let input_vp = vp.clone().move_by(5, 4).resize_to(1, 60);
+            let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
+            em.set_inline("Journal layout, 'files' (default) or 'month-log'");
+            em
+        };
         let comm_head = {
             let content = "Enter default commodity details";
             let comm_vp = vp.clone().move_by(5, 5).resize_to(1, 60);
@@ -106,14 +171,16 @@ where
             let mut em = te::EditLine::new(app, comm_vp).ok().unwrap();
             em.set_inline("Name of the commodity, only alphanumeric")
                 .set_mandatory(true)
-                .set_field("Name    :");
+                .set_field("Name    :")
+                .set_max_len(MAX_NAME_LEN);
             em
         };
         let comm_input_symbol = {
             let comm_vp = vp.clone().move_by(8, 9).resize_to(1, 60);
             let mut em = te::EditLine::new(app, comm_vp).ok().unwrap();
             em.set_inline("Symbol for commodity, EG: '₹'")
-                .set_field("Symbol  :");
+                .set_field("Symbol  :")
+                .set_max_len(MAX_SYMBOL_LEN);
             em
         };
         let comm_input_aliases = {
@@ -134,7 +201,8 @@ where
             let comm_vp = vp.clone().move_by(8, 15).resize_to(7, 60);
             let mut em = te::EditBox::new(app, comm_vp).ok().unwrap();
             em.set_inline("Any notes for user consumption")
-                .set_field("Notes   :");
+                .set_field("Notes   :")
+                .set_max_len(app.to_max_note_len());
             em
         };
         let button_ok = {
@@ -149,6 +217,7 @@ where
         let elements = vec![
             te::Element::Border(border),
             te::Element::EditLine(ws_input_name),
+            te::Element::EditLine(ws_input_layout),
             te::Element::Span(comm_head),
             te::Element::EditLine(comm_input_name),
             te::Element::EditLine(comm_input_symbol),
@@ -161,7 +230,7 @@ where
         Ok(NewWorkspace {
             vp,
             elements,
-            focus: TabOffsets::new(vec![1, 3, 4, 5, 6, 7, 8, 0]),
+            focus: TabOffsets::new(vec![1, 2, 4, 5, 6, 7, 8, 9, 0]),
 
             _phantom_s: marker::PhantomData,
         })
@@ -191,42 +260,71 @@ where
     }
 
     pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        if paste_fields(app, &mut self.elements, &mut self.focus, &evnt)? {
+            self.focus_element(app)?;
+            self.refresh(app, true /*force*/)?;
+            return Ok(None);
+        }
+
         let off = self.focus.current();
         let evnt = self.elements[off as usize].handle_event(app, evnt)?;
 
         match evnt {
-            Some(Event::Submit) => Ok(None),
-            Some(evnt) => match (evnt.to_modifiers(), evnt.to_key_code()) {
-                (m, Some(code)) => match code {
-                    KeyCode::Esc if m.is_empty() => match self.focus.tab_to(0) {
-                        Some(old_off) => {
-                            self.elements[old_off].leave(app)?;
-                            self.focus_element(app)?;
-                            app.hide_cursor()?;
-                            Ok(None)
-                        }
-                        None => Ok(None),
-                    },
-                    KeyCode::Enter | KeyCode::Tab => {
-                        let old_off = self.focus.tab();
-                        self.elements[old_off].leave(app)?;
-                        self.focus_element(app)?;
-                        Ok(None)
-                    }
-                    KeyCode::BackTab => {
-                        let old_off = self.focus.back_tab();
+            Some(Event::Submit) => Ok(Some(Event::CreateWorkspace)),
+            Some(evnt) => match app.to_action(&evnt) {
+                Some(Action::Submit) => Ok(Some(Event::CreateWorkspace)),
+                Some(Action::Cancel) => match self.focus.tab_to(0) {
+                    Some(old_off) => {
                         self.elements[old_off].leave(app)?;
                         self.focus_element(app)?;
+                        app.hide_cursor()?;
                         Ok(None)
                     }
-                    _ => Ok(Some(evnt)),
+                    None => Ok(None),
                 },
+                Some(Action::NextField) => {
+                    let old_off = self.focus.tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                Some(Action::PrevField) => {
+                    let old_off = self.focus.back_tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
                 _ => Ok(Some(evnt)),
             },
             None => Ok(None),
         }
     }
 
+    // workspace and its base commodity, from the form fields.
+    pub fn to_values(&self) -> Result<(types::Workspace, types::Commodity)> {
+        let text = |off: usize| self.elements[off].to_content().unwrap_or_default();
+
+        let name = text(1).trim().to_string();
+        if name.is_empty() || !util::str_as_anuh(&name) {
+            err_at!(InvalidInput, msg: format!("workspace name {:?}", name))?;
+        }
+        let layout: db_files::JournalLayout = match text(2).trim() {
+            "" => Default::default(),
+            layout => layout.parse()?,
+        };
+        let mut commodity: types::Commodity =
+            (text(4), text(5), text(6), text(7), text(8)).try_into()?;
+        commodity.aliases.retain(|alias| !alias.is_empty());
+        commodity.tags.retain(|tag| !tag.is_empty());
+        if commodity.name.is_empty() {
+            err_at!(InvalidInput, msg: format!("commodity name is required"))?;
+        }
+
+        let mut w = types::Workspace::new(name).set_commodity(commodity.to_key());
+        w.set_journal_layout(&layout.to_string());
+        Ok((w, commodity))
+    }
+
     fn focus_element(&mut self, app: &mut Application<S>) -> Result<()> {
         let em_idx = self.focus.current();
         trace!("Focus layer_new_workspace em_idx:{}", em_idx);
@@ -265,7 +363,7 @@ where
         let mut output: String = Default::default();
         let s = String::from_iter(repeat(' ').take(width as usize));
         for r in 0..height {
-            output.push_str(&cursor::MoveTo(col - 1, row + r).to_string());
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
             output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
         }
         for element in self.elements.iter() {
@@ -301,7 +399,8 @@ where
             let input_vp = vp.clone().move_by(5, 3).resize_to(1, 60);
             let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
             em.set_inline("Enter workspace name, only alphanumeric and '_'")
-                .set_mandatory(true);
+                .set_mandatory(true)
+                .set_max_len(MAX_NAME_LEN);
             em.refresh(app, true /*force*/)?;
             em
         };
@@ -317,14 +416,16 @@ where
             let mut em = te::EditLine::new(app, comm_vp).ok().unwrap();
             em.set_inline("Name of the commodity, only alphanumeric")
                 .set_mandatory(true)
-                .set_field("Name    :");
+                .set_field("Name    :")
+                .set_max_len(MAX_NAME_LEN);
             em
         };
         let comm_input_symbol = {
             let comm_vp = vp.clone().move_by(8, 9).resize_to(1, 60);
             let mut em = te::EditLine::new(app, comm_vp).ok().unwrap();
             em.set_inline("Symbol for commodity, EG: '₹'")
-                .set_field("Symbol  :");
+                .set_field("Symbol  :")
+                .set_max_len(MAX_SYMBOL_LEN);
             em
         };
         let comm_input_aliases = {
@@ -345,7 +446,8 @@ where
             let comm_vp = vp.clone().move_by(8, 15).resize_to(7, 60);
             let mut em = te::EditBox::new(app, comm_vp).ok().unwrap();
             em.set_inline("Any notes for user consumption")
-                .set_field("Notes   :");
+                .set_field("Notes   :")
+                .set_max_len(app.to_max_note_len());
             em
         };
         let button_ok = {
@@ -402,36 +504,40 @@ where
     }
 
     pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        if paste_fields(app, &mut self.elements, &mut self.focus, &evnt)? {
+            self.focus_element(app)?;
+            self.refresh(app, true /*force*/)?;
+            return Ok(None);
+        }
+
         let off = self.focus.current();
         let evnt = self.elements[off as usize].handle_event(app, evnt)?;
 
         match evnt {
             Some(Event::Submit) => Ok(None),
-            Some(evnt) => match (evnt.to_modifiers(), evnt.to_key_code()) {
-                (m, Some(code)) => match code {
-                    KeyCode::Esc if m.is_empty() => match self.focus.tab_to(0) {
-                        Some(old_off) => {
-                            self.elements[old_off].leave(app)?;
-                            self.focus_element(app)?;
-                            app.hide_cursor()?;
-                            Ok(None)
-                        }
-                        None => Ok(None),
-                    },
-                    KeyCode::Enter | KeyCode::Tab => {
-                        let old_off = self.focus.tab();
+            Some(evnt) => match app.to_action(&evnt) {
+                Some(Action::Submit) => Ok(None),
+                Some(Action::Cancel) => match self.focus.tab_to(0) {
+                    Some(old_off) => {
                         self.elements[old_off].leave(app)?;
                         self.focus_element(app)?;
+                        app.hide_cursor()?;
                         Ok(None)
                     }
-                    KeyCode::BackTab => {
-                        let old_off = self.focus.back_tab();
-                        self.elements[old_off].leave(app)?;
-                        self.focus_element(app)?;
-                        Ok(None)
-                    }
-                    _ => Ok(Some(evnt)),
+                    None => Ok(None),
                 },
+                Some(Action::NextField) => {
+                    let old_off = self.focus.tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                Some(Action::PrevField) => {
+                    let old_off = self.focus.back_tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
                 _ => Ok(Some(evnt)),
             },
             None => Ok(None),
@@ -476,7 +582,7 @@ where
         let mut output: String = Default::default();
         let s = String::from_iter(repeat(' ').take(width as usize));
         for r in 0..height {
-            output.push_str(&cursor::MoveTo(col - 1, row + r).to_string());
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
             output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
         }
         for element in self.elements.iter() {
@@ -487,41 +593,2182 @@ where
     }
 }
 
-#[derive(Debug)]
-struct TabOffsets(Vec<usize>);
+// pick a workspace, from a list of (name, dir), to open.
+pub struct PickWorkspace<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    elements: Vec<te::Element>,
+    focus: TabOffsets,
+    workspaces: Vec<(String, path::PathBuf)>,
 
-impl TabOffsets {
-    fn new(offs: Vec<usize>) -> TabOffsets {
-        TabOffsets(offs)
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> PickWorkspace<S>
+where
+    S: Store,
+{
+    pub fn new(
+        app: &mut Application<S>,
+        workspaces: Vec<(String, path::PathBuf)>,
+    ) -> Result<PickWorkspace<S>> {
+        let vp = app.to_viewport();
+
+        let border = te::Border::new(app, vp.clone(), "Open workspace".to_string())
+            .ok()
+            .unwrap();
+
+        let mut elements = vec![te::Element::Border(border)];
+        for (i, (name, _)) in workspaces.iter().enumerate() {
+            let w_name: usize = name.chars().filter_map(char::width).sum();
+            let width = cmp::max(w_name + 4, 20) as u16;
+            let button_vp = vp.clone().move_by(5, 3 + (i as i16) * 2).resize_to(1, width);
+            let em = te::Button::new(app, button_vp, name, te::ButtonType::Submit)
+                .ok()
+                .unwrap();
+            elements.push(te::Element::Button(em));
+        }
+
+        let mut offs: Vec<usize> = (1..elements.len()).collect();
+        offs.push(0);
+
+        Ok(PickWorkspace {
+            vp,
+            elements,
+            focus: TabOffsets::new(offs),
+            workspaces,
+
+            _phantom_s: marker::PhantomData,
+        })
     }
+}
 
-    fn current(&self) -> usize {
-        self.0.first().unwrap().clone()
+impl<S> PickWorkspace<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
     }
 
-    fn tab(&mut self) -> usize {
-        let old_off = self.0.remove(0);
-        self.0.push(old_off);
-        old_off
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.focus_element(app)?;
+        Ok(())
     }
 
-    fn back_tab(&mut self) -> usize {
-        let old_off = self.current();
-        let off = self.0.pop().unwrap();
-        self.0.insert(0, off);
-        old_off
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = self.focus.current();
+        self.elements[off as usize].leave(app)?;
+        Ok(())
     }
 
-    fn tab_to(&mut self, off: usize) -> Option<usize> {
-        for (i, val) in self.0.clone().into_iter().enumerate() {
-            if off == val && i == 0 {
-                return None;
-            } else if off == val {
-                let old_off = self.0.remove(i);
-                self.0.insert(0, off);
-                return Some(old_off);
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        let off = self.focus.current();
+        let evnt = self.elements[off as usize].handle_event(app, evnt)?;
+
+        match evnt {
+            Some(Event::Submit) if off > 0 => {
+                let (_, dir) = self.workspaces[off - 1].clone();
+                Ok(Some(Event::OpenWorkspace(dir)))
+            }
+            Some(Event::Submit) => Ok(None),
+            Some(evnt) => match app.to_action(&evnt) {
+                Some(Action::NextField) | Some(Action::MoveDown) => {
+                    let old_off = self.focus.tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                Some(Action::PrevField) | Some(Action::MoveUp) => {
+                    let old_off = self.focus.back_tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                _ => Ok(Some(evnt)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn focus_element(&mut self, app: &mut Application<S>) -> Result<()> {
+        let em_idx = self.focus.current();
+        trace!("Focus layer_pick_workspace em_idx:{}", em_idx);
+
+        self.elements[em_idx].focus(app)?;
+        app.hide_cursor()?;
+
+        Ok(())
+    }
+}
+
+impl<S> TermCommand for PickWorkspace<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "PickWorkspace::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// maximum characters typed into name and symbol fields.
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 4;
+
+// width of the totals panel in entry browser.
+const TOTALS_WIDTH: u16 = 36;
+
+// browse journal entries, with a panel to the right of the list showing
+// per commodity totals of the selected entry.
+pub struct EntryBrowser<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, list of entries, followed by a span for every panel row.
+    elements: Vec<te::Element>,
+    entries: Vec<types::JournalEntry>,
+    selected: Option<usize>,
+    fmt: util::AmountFormat,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> EntryBrowser<S>
+where
+    S: Store,
+{
+    pub fn new(
+        app: &mut Application<S>,
+        entries: Vec<types::JournalEntry>,
+    ) -> Result<EntryBrowser<S>> {
+        let vp = app.to_viewport();
+        let (height, width) = vp.to_size();
+        let height = layout::sub(height, 4);
+        // one column gap between the list and the panel.
+        let (l_width, r_width) = layout::split(layout::sub(width, 5), TOTALS_WIDTH);
+        let fmt = app.to_amount_format();
+
+        let border = te::Border::new(app, vp.clone(), "Entries".to_string())
+            .ok()
+            .unwrap();
+        let list = {
+            let list_vp = vp.clone().move_by(2, 2).resize_to(height, l_width);
+            let rows = entries
+                .iter()
+                .map(|e| te::StyledRow::from_entry(e, &fmt))
+                .collect();
+            let mut list = te::ListBox::new(app, list_vp, rows).ok().unwrap();
+            let key = EntrySort::Date.to_sort_key(true /*ascending*/);
+            list.sort_by(key, |x, y| {
+                EntrySort::Date.compare(&entries[x], &entries[y])
+            });
+            list
+        };
+
+        let mut elements = vec![te::Element::Border(border), te::Element::ListBox(list)];
+        for r in 0..height {
+            let panel_vp = vp
+                .clone()
+                .move_by(3 + l_width as i16, 2 + r as i16)
+                .resize_to(1, r_width);
+            let mut em = te::Span::new(app, panel_vp, "").ok().unwrap();
+            em.set_fg_color(te::FG_EDIT);
+            elements.push(te::Element::Span(em));
+        }
+
+        let mut layer = EntryBrowser {
+            vp,
+            elements,
+            entries,
+            selected: None,
+            fmt,
+
+            _phantom_s: marker::PhantomData,
+        };
+        layer.update_panel();
+
+        Ok(layer)
+    }
+
+    // sort the list by `by`, ascending, or toggle the direction if the
+    // list is already sorted by `by`.
+    fn sort_entries(&mut self, by: EntrySort) {
+        let entries = &self.entries;
+        if let te::Element::ListBox(em) = &mut self.elements[1] {
+            let ascending = match em.to_sort_key() {
+                Some(key) if key.name == by.to_string() => !key.ascending,
+                _ => true,
+            };
+            em.sort_by(by.to_sort_key(ascending), |x, y| {
+                by.compare(&entries[x], &entries[y])
+            });
+        }
+    }
+
+    // flip the reconciled mark of the selected entry and put it back.
+    fn reconcile_selected(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = match self.selected {
+            Some(off) if off < self.entries.len() => off,
+            _ => return Ok(()),
+        };
+        let mut value = self.entries[off].clone();
+        let msg = match value.toggle_reconciled() {
+            true => "reconciled",
+            false => "unreconciled",
+        };
+        app.put_value(value.clone())?;
+        app.log_status(&format!("{} {}", msg, value.payee));
+
+        if let te::Element::ListBox(em) = &mut self.elements[1] {
+            em.set_row(off, te::StyledRow::from_entry(&value, &self.fmt));
+        }
+        self.entries[off] = value;
+        self.elements[1].refresh(app, true /*force*/)
+    }
+
+    // put a copy of the selected entry dated now, refer
+    // JournalEntry::to_repeat(), and list it first.
+    fn repeat_selected(&mut self, app: &mut Application<S>) -> Result<()> {
+        let entry = match self.selected.and_then(|off| self.entries.get(off)) {
+            Some(entry) => entry.to_repeat(),
+            None => return Ok(()),
+        };
+        app.put_value(entry.clone())?;
+        app.log_status(&format!("repeated {}", entry.payee));
+
+        self.entries.insert(0, entry);
+        let fmt = &self.fmt;
+        let rows = self
+            .entries
+            .iter()
+            .map(|e| te::StyledRow::from_entry(e, fmt))
+            .collect();
+        if let te::Element::ListBox(em) = &mut self.elements[1] {
+            em.set_rows(rows);
+        }
+        self.update_panel();
+        self.refresh(app, true /*force*/)
+    }
+
+    // delete the selected entry from the workspace and the list, undo
+    // brings it back.
+    fn delete_selected(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = match self.selected {
+            Some(off) if off < self.entries.len() => off,
+            _ => return Ok(()),
+        };
+        let entry: types::JournalEntry = app.delete_value(&self.entries[off].to_key())?;
+        app.log_status(&format!("deleted {}", entry.payee));
+
+        self.entries.remove(off);
+        let fmt = &self.fmt;
+        let rows = self
+            .entries
+            .iter()
+            .map(|e| te::StyledRow::from_entry(e, fmt))
+            .collect();
+        if let te::Element::ListBox(em) = &mut self.elements[1] {
+            em.set_rows(rows);
+        }
+        self.update_panel();
+        self.refresh(app, true /*force*/)
+    }
+
+    // recompute the totals panel for the entry selected in the list.
+    fn update_panel(&mut self) {
+        self.selected = match &self.elements[1] {
+            te::Element::ListBox(em) => em.to_selected(),
+            _ => None,
+        };
+        let lines = match self.selected.and_then(|off| self.entries.get(off)) {
+            Some(entry) => totals_text(&entry_totals(entry), &self.fmt),
+            None => vec![],
+        };
+        for (i, em) in self.elements[2..].iter_mut().enumerate() {
+            if let te::Element::Span(em) = em {
+                em.set_content(lines.get(i).map(String::as_str).unwrap_or(""));
             }
         }
-        unreachable!()
     }
 }
+
+impl<S> EntryBrowser<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_entry_browser");
+        self.elements[1].focus(app)?;
+        app.hide_cursor()?;
+        Ok(())
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.elements[1].leave(app)?;
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        let evnt = match self.elements[1].handle_event(app, evnt)? {
+            Some(evnt) if app.to_action(&evnt) == Some(Action::Reconcile) => {
+                self.reconcile_selected(app)?;
+                None
+            }
+            Some(evnt) if app.to_action(&evnt) == Some(Action::Repeat) => {
+                self.repeat_selected(app)?;
+                None
+            }
+            Some(evnt) if app.to_action(&evnt) == Some(Action::RemoveRow) => {
+                self.delete_selected(app)?;
+                None
+            }
+            Some(evnt) => match app.to_action(&evnt).and_then(EntrySort::from_action) {
+                Some(by) => {
+                    self.sort_entries(by);
+                    self.elements[1].refresh(app, true /*force*/)?;
+                    None
+                }
+                None => Some(evnt),
+            },
+            None => None,
+        };
+
+        let selected = match &self.elements[1] {
+            te::Element::ListBox(em) => em.to_selected(),
+            _ => None,
+        };
+        if selected != self.selected {
+            self.update_panel();
+            for em in self.elements[2..].iter_mut() {
+                em.refresh(app, true /*force*/)?;
+            }
+        }
+
+        match evnt {
+            Some(evnt) if is_open(app, &evnt) => {
+                match self.selected.and_then(|off| self.entries.get(off)) {
+                    Some(entry) => Ok(Some(Event::OpenEntry(entry.to_key()))),
+                    None => Ok(None),
+                }
+            }
+            evnt => Ok(evnt),
+        }
+    }
+}
+
+impl<S> TermCommand for EntryBrowser<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "EntryBrowser::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// legs of a journal entry, debits followed by credits. Opening a leg
+// jumps to its ledger's detail. Amounts can be shown in another
+// commodity, cycled with Action::DisplayCurrency, the entry itself is
+// never changed. Action::SaveTemplate saves it as a named template.
+pub struct EntryDetail<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, followed by the list of legs.
+    elements: Vec<te::Element>,
+    legs: Vec<types::KeyLedger>,
+    entry: types::JournalEntry,
+    // commodity amounts are shown in, None for the entry's own.
+    currency: Option<String>,
+    fmt: util::AmountFormat,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> EntryDetail<S>
+where
+    S: Store,
+{
+    pub fn new(app: &mut Application<S>, entry: &types::JournalEntry) -> Result<EntryDetail<S>> {
+        let vp = app.to_viewport();
+        let (height, width) = vp.to_size();
+
+        let border = te::Border::new(app, vp.clone(), entry.payee.clone())
+            .ok()
+            .unwrap();
+
+        let fmt = app.to_amount_format();
+        let (legs, rows) = entry_rows(entry, &fmt);
+        let list = {
+            let list_vp = vp
+                .clone()
+                .move_by(2, 2)
+                .resize_to(layout::sub(height, 4), layout::sub(width, 4));
+            te::ListBox::new(app, list_vp, rows).ok().unwrap()
+        };
+
+        Ok(EntryDetail {
+            vp,
+            elements: vec![te::Element::Border(border), te::Element::ListBox(list)],
+            legs,
+            entry: entry.clone(),
+            currency: None,
+            fmt,
+
+            _phantom_s: marker::PhantomData,
+        })
+    }
+
+    pub fn to_currency(&self) -> Option<String> {
+        self.currency.clone()
+    }
+
+    // move the selected leg up, or down, among the entry's debitors or
+    // creditors and save the entry in that order. Selection follows the
+    // leg, amounts are back in the entry's own commodity.
+    fn move_leg(&mut self, app: &mut Application<S>, up: bool) -> Result<()> {
+        let selected = match &self.elements[1] {
+            te::Element::ListBox(em) => em.to_selected(),
+            _ => None,
+        };
+        let n = self.entry.debitors.len();
+        let off = match selected {
+            Some(off) if off < n => self.entry.move_debitor(off, up)?,
+            Some(off) => n + self.entry.move_creditor(off - n, up)?,
+            None => return Ok(()),
+        };
+        app.put_value(self.entry.clone())?;
+
+        let (legs, rows) = entry_rows(&self.entry, &self.fmt);
+        if let te::Element::ListBox(list) = &mut self.elements[1] {
+            list.set_rows(rows).set_selected(off);
+        }
+        self.legs = legs;
+        self.currency = None;
+        Ok(())
+    }
+
+    // show amounts in the commodity after the current one, by name, and
+    // back to the entry's own after the last. Rates are from the store's
+    // commodity values, refer rates::ManualRates.
+    fn next_currency(&mut self, app: &mut Application<S>) -> Result<()> {
+        let store = app.as_mut();
+        let mut names = vec![];
+        for commodity in store.iter::<types::Commodity>()? {
+            names.push(commodity?.name);
+        }
+        names.sort();
+
+        let currency = match &self.currency {
+            None => names.into_iter().next(),
+            Some(curr) => names.into_iter().find(|name| name > curr),
+        };
+        let entry = match &currency {
+            Some(name) => {
+                let base = store.to_workspace()?.to_base_commodity();
+                let rates = rates::ManualRates::from_store(store, &base)?;
+                rates::convert_entry(&self.entry, name, &rates)?
+            }
+            None => self.entry.clone(),
+        };
+
+        let (_, rows) = entry_rows(&entry, &self.fmt);
+        if let te::Element::ListBox(list) = &mut self.elements[1] {
+            list.set_rows(rows);
+        }
+        self.currency = currency;
+        Ok(())
+    }
+}
+
+impl<S> EntryDetail<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_entry_detail");
+        self.elements[1].focus(app)?;
+        app.hide_cursor()?;
+        Ok(())
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.elements[1].leave(app)?;
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        if app.to_action(&evnt) == Some(Action::DisplayCurrency) {
+            match self.next_currency(app) {
+                Ok(()) => match &self.currency {
+                    Some(name) => app.log_status(&format!("amounts in {}", name)),
+                    None => app.log_status(""),
+                },
+                Err(err) => app.log_status(&format!("{}", err)),
+            }
+            self.refresh(app, true)?;
+            return Ok(None);
+        }
+        if app.to_action(&evnt) == Some(Action::SaveTemplate) {
+            return Ok(Some(Event::SaveTemplate(self.entry.to_key())));
+        }
+        let up = match app.to_action(&evnt) {
+            Some(Action::MoveUp) => Some(true),
+            Some(Action::MoveDown) => Some(false),
+            _ => None,
+        };
+        if let Some(up) = up {
+            if let Err(err) = self.move_leg(app, up) {
+                app.log_status(&format!("{}", err));
+            }
+            self.refresh(app, true /*force*/)?;
+            return Ok(None);
+        }
+
+        let evnt = self.elements[1].handle_event(app, evnt)?;
+
+        let selected = match &self.elements[1] {
+            te::Element::ListBox(em) => em.to_selected(),
+            _ => None,
+        };
+        match evnt {
+            Some(evnt) if is_open(app, &evnt) => {
+                match selected.and_then(|off| self.legs.get(off)) {
+                    Some(key) => Ok(Some(Event::OpenLedger(key.clone()))),
+                    None => Ok(None),
+                }
+            }
+            evnt => Ok(evnt),
+        }
+    }
+}
+
+impl<S> TermCommand for EntryDetail<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "EntryDetail::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// journal entries that touch a ledger, most recent last.
+pub struct LedgerDetail<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, followed by the list of entries.
+    elements: Vec<te::Element>,
+    key: types::KeyLedger,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> LedgerDetail<S>
+where
+    S: Store,
+{
+    // entries not touching the ledger are skipped.
+    pub fn new(
+        app: &mut Application<S>,
+        key: types::KeyLedger,
+        entries: &[types::JournalEntry],
+    ) -> Result<LedgerDetail<S>> {
+        let vp = app.to_viewport();
+        let (height, width) = vp.to_size();
+
+        let (cname, lname): (String, String) = key.clone().into();
+        let title = format!("{}:{}", cname, lname);
+        let border = te::Border::new(app, vp.clone(), title).ok().unwrap();
+
+        let lkey = key.to_string();
+        let fmt = app.to_amount_format();
+        let rows = entries
+            .iter()
+            .filter(|e| {
+                let ledgers = e.creditors.iter().map(|c| &c.ledger);
+                let mut ledgers = ledgers.chain(e.debitors.iter().map(|d| &d.ledger));
+                ledgers.any(|l| l.to_string() == lkey)
+            })
+            .map(|e| te::StyledRow::from_entry(e, &fmt))
+            .collect();
+        let list = {
+            let list_vp = vp
+                .clone()
+                .move_by(2, 2)
+                .resize_to(layout::sub(height, 4), layout::sub(width, 4));
+            te::ListBox::new(app, list_vp, rows).ok().unwrap()
+        };
+
+        Ok(LedgerDetail {
+            vp,
+            elements: vec![te::Element::Border(border), te::Element::ListBox(list)],
+            key,
+
+            _phantom_s: marker::PhantomData,
+        })
+    }
+
+    pub fn to_key(&self) -> types::KeyLedger {
+        self.key.clone()
+    }
+}
+
+impl<S> LedgerDetail<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_ledger_detail");
+        self.elements[1].focus(app)?;
+        app.hide_cursor()?;
+        Ok(())
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.elements[1].leave(app)?;
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        self.elements[1].handle_event(app, evnt)
+    }
+}
+
+impl<S> TermCommand for LedgerDetail<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "LedgerDetail::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// Enter, or the submit action, opens the selected row of a list layer.
+fn is_open<S>(app: &Application<S>, evnt: &Event) -> bool
+where
+    S: Store,
+{
+    evnt.to_key_code() == Some(KeyCode::Enter) || app.to_action(evnt) == Some(Action::Submit)
+}
+
+// on paste, distribute a tab-separated clipboard row, like one copied
+// from a spreadsheet, across the focused and subsequent edit elements in
+// tab order, leaving focus on the last one filled. Return false if the
+// event is not a paste or the clipboard is not tabular, the caller shall
+// re-focus and refresh otherwise.
+fn paste_fields<S>(
+    app: &mut Application<S>,
+    elements: &mut [te::Element],
+    focus: &mut TabOffsets,
+    evnt: &Event,
+) -> Result<bool>
+where
+    S: Store,
+{
+    let fields = match app.to_action(evnt) {
+        Some(Action::Paste) => app.to_clipboard().to_text(),
+        _ => None,
+    };
+    let fields = match fields.as_ref().and_then(|text| te::split_tabular(text)) {
+        Some(fields) => fields,
+        None => return Ok(false),
+    };
+
+    let offs = focus.to_offsets();
+    let n = te::fill_fields(elements, &offs, &fields)?;
+    for _ in 1..n {
+        let old_off = focus.tab();
+        elements[old_off].leave(app)?;
+    }
+    Ok(true)
+}
+
+// ledgers and rows of an entry's legs, debits followed by credits.
+fn entry_rows(
+    entry: &types::JournalEntry,
+    fmt: &util::AmountFormat,
+) -> (Vec<types::KeyLedger>, Vec<te::StyledRow>) {
+    let mut legs = vec![];
+    let mut rows = vec![];
+    for d in entry.debitors.iter() {
+        rows.push(leg_row(&d.ledger, &d.commodity, "Dr", te::FG_DEBIT, fmt));
+        legs.push(d.ledger.clone());
+    }
+    for c in entry.creditors.iter() {
+        rows.push(leg_row(&c.ledger, &c.commodity, "Cr", te::FG_CREDIT, fmt));
+        legs.push(c.ledger.clone());
+    }
+    (legs, rows)
+}
+
+// leg of an entry, EG: `acme:cash 100.00 inr Dr`.
+fn leg_row(
+    ledger: &types::KeyLedger,
+    commodity: &types::Commodity,
+    side: &str,
+    fg: style::Color,
+    fmt: &util::AmountFormat,
+) -> te::StyledRow {
+    let (cname, lname): (String, String) = ledger.clone().into();
+    let amount = fmt.format_amount(commodity.value);
+    let mut row = te::StyledRow::default();
+    row.push(&format!("{}:{} ", cname, lname), te::FG_EDIT)
+        .push(&format!("{} {} {}", amount, commodity.name, side), fg);
+    row
+}
+
+// miller columns over companies and their ledgers, the left pane lists
+// companies and the right pane the selected company's ledgers. Left and
+// Right move focus between panes, opening a ledger jumps to its detail.
+pub struct Navigator<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, followed by the company and ledger panes.
+    elements: Vec<te::Element>,
+    companies: Vec<String>,
+    ledgers: Vec<types::KeyLedger>,
+    // company whose ledgers are listed.
+    company: Option<String>,
+    // focused pane, offset into elements.
+    focus: usize,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> Navigator<S>
+where
+    S: Store,
+{
+    pub fn new(app: &mut Application<S>) -> Result<Navigator<S>> {
+        let vp = app.to_viewport();
+        let (height, width) = vp.to_size();
+
+        let border = te::Border::new(app, vp.clone(), "Ledgers".to_string())
+            .ok()
+            .unwrap();
+
+        let mut values = vec![];
+        for company in app.as_mut().iter::<types::Company>()? {
+            values.push(company?);
+        }
+        values.sort_by(|a: &types::Company, b| a.name.cmp(&b.name));
+        let companies: Vec<String> = values.iter().map(|c| c.name.clone()).collect();
+
+        let inner = layout::sub(width, 4);
+        let (left, right) = layout::split(inner, inner / 2);
+        let pane_vp = vp.clone().move_by(2, 2);
+        let height = layout::sub(height, 4);
+        let left_pane = {
+            let rows = values.iter().map(te::StyledRow::from_summary).collect();
+            let vp = pane_vp.clone().resize_to(height, layout::sub(left, 1));
+            te::ListBox::new(app, vp, rows)?
+        };
+        let right_pane = {
+            let vp = pane_vp.move_by(left as i16, 0).resize_to(height, right);
+            te::ListBox::new(app, vp, vec![])?
+        };
+
+        let mut layer = Navigator {
+            vp,
+            elements: vec![
+                te::Element::Border(border),
+                te::Element::ListBox(left_pane),
+                te::Element::ListBox(right_pane),
+            ],
+            companies,
+            ledgers: vec![],
+            company: None,
+            focus: 1,
+
+            _phantom_s: marker::PhantomData,
+        };
+        layer.populate(app)?;
+
+        Ok(layer)
+    }
+
+    pub fn to_company(&self) -> Option<String> {
+        self.company.clone()
+    }
+
+    pub fn to_ledgers(&self) -> Vec<types::KeyLedger> {
+        self.ledgers.clone()
+    }
+
+    fn to_selected(&self, off: usize) -> Option<usize> {
+        match &self.elements[off] {
+            te::Element::ListBox(em) => em.to_selected(),
+            _ => None,
+        }
+    }
+
+    // list the selected company's ledgers in the right pane, if the
+    // selection changed.
+    fn populate(&mut self, app: &mut Application<S>) -> Result<()> {
+        let company = self.to_selected(1).and_then(|off| self.companies.get(off).cloned());
+        if company == self.company {
+            return Ok(());
+        }
+
+        let ledgers = match &company {
+            Some(name) => aggregates::ledgers_for_company(app.as_mut(), name)?,
+            None => vec![],
+        };
+        let rows = ledgers.iter().map(te::StyledRow::from_summary).collect();
+        if let te::Element::ListBox(em) = &mut self.elements[2] {
+            em.set_rows(rows);
+        }
+        self.ledgers = ledgers
+            .into_iter()
+            .map(|l| (l.company, l.name).into())
+            .collect();
+        self.company = company;
+
+        Ok(())
+    }
+
+    fn move_focus(&mut self, app: &mut Application<S>, focus: usize) -> Result<()> {
+        if focus != self.focus {
+            self.elements[self.focus].leave(app)?;
+            self.focus = focus;
+            self.elements[self.focus].focus(app)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> Navigator<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_navigator");
+        self.elements[self.focus].focus(app)?;
+        app.hide_cursor()?;
+        Ok(())
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.elements[self.focus].leave(app)?;
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        // plain arrows only, alt-arrows navigate layer history.
+        let code = match &evnt {
+            Event::Key { code, modifiers } if modifiers.is_empty() => Some(code.clone()),
+            _ => None,
+        };
+        match code {
+            Some(KeyCode::Left) if self.focus == 2 => {
+                self.move_focus(app, 1)?;
+                self.refresh(app, true)?;
+                return Ok(None);
+            }
+            Some(KeyCode::Right) if self.focus == 1 && !self.ledgers.is_empty() => {
+                self.move_focus(app, 2)?;
+                self.refresh(app, true)?;
+                return Ok(None);
+            }
+            _ => (),
+        }
+
+        let evnt = self.elements[self.focus].handle_event(app, evnt)?;
+        match (self.focus, evnt) {
+            (1, None) => {
+                self.populate(app)?;
+                self.refresh(app, true)?;
+                Ok(None)
+            }
+            (1, Some(evnt)) if is_open(app, &evnt) && !self.ledgers.is_empty() => {
+                self.move_focus(app, 2)?;
+                self.refresh(app, true)?;
+                Ok(None)
+            }
+            (2, Some(evnt)) if is_open(app, &evnt) => {
+                match self.to_selected(2).and_then(|off| self.ledgers.get(off)) {
+                    Some(key) => Ok(Some(Event::OpenLedger(key.clone()))),
+                    None => Ok(None),
+                }
+            }
+            (_, evnt) => Ok(evnt),
+        }
+    }
+}
+
+impl<S> TermCommand for Navigator<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "Navigator::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// month calendar around a date, laid out from the configured first day
+// of the week, refer Application::to_week_start(). Arrow keys move the date by a
+// day or a week, Enter picks it.
+pub struct Calendar<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, followed by a span for every calendar line.
+    elements: Vec<te::Element>,
+    date: chrono::NaiveDate,
+    week_start: util::WeekStart,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> Calendar<S>
+where
+    S: Store,
+{
+    pub fn new(app: &mut Application<S>, date: chrono::NaiveDate) -> Result<Calendar<S>> {
+        let vp = app.to_viewport();
+        let (_, width) = vp.to_size();
+
+        let border = te::Border::new(app, vp.clone(), "Calendar".to_string())
+            .ok()
+            .unwrap();
+        let mut elements = vec![te::Element::Border(border)];
+        // title, weekday names and upto 6 weeks.
+        for r in 0..8 {
+            let line_vp = vp.clone().move_by(4, 2 + r as i16).resize_to(1, layout::sub(width, 8));
+            let mut em = te::Span::new(app, line_vp, "").ok().unwrap();
+            em.set_fg_color(te::FG_EDIT);
+            elements.push(te::Element::Span(em));
+        }
+
+        let mut layer = Calendar {
+            vp,
+            elements,
+            date,
+            week_start: app.to_week_start(),
+
+            _phantom_s: marker::PhantomData,
+        };
+        layer.update_lines();
+
+        Ok(layer)
+    }
+
+    pub fn to_date(&self) -> chrono::NaiveDate {
+        self.date
+    }
+
+    fn update_lines(&mut self) {
+        let lines = calendar_text(self.date, self.week_start);
+        for (i, em) in self.elements[1..].iter_mut().enumerate() {
+            if let te::Element::Span(em) = em {
+                em.set_content(lines.get(i).map(String::as_str).unwrap_or(""));
+            }
+        }
+    }
+}
+
+impl<S> Calendar<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_calendar");
+        app.hide_cursor()?;
+        Ok(())
+    }
+
+    pub fn leave(&mut self, _app: &mut Application<S>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        use chrono::TimeZone;
+
+        let days = match evnt.to_key_code() {
+            Some(KeyCode::Left) => -1,
+            Some(KeyCode::Right) => 1,
+            Some(KeyCode::Up) => -7,
+            Some(KeyCode::Down) => 7,
+            _ if is_open(app, &evnt) => {
+                return match chrono::Local.from_local_date(&self.date).single() {
+                    Some(date) => Ok(Some(Event::Date(date))),
+                    None => Ok(None),
+                };
+            }
+            _ => return Ok(Some(evnt)),
+        };
+        if let Some(date) = self
+            .date
+            .checked_add_signed(chrono::Duration::days(days))
+        {
+            self.date = date;
+            self.update_lines();
+            for em in self.elements[1..].iter_mut() {
+                em.refresh(app, true /*force*/)?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S> TermCommand for Calendar<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "Calendar::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// width of the prompt box, narrower on small terminals.
+const PROMPT_WIDTH: u16 = 60;
+
+// what a prompt asks for, handed back to the application along with the
+// answer, refer Event::Answer.
+#[derive(Clone)]
+pub enum Question {
+    // date to go to.
+    Goto,
+    // name to save the entry as a template under.
+    TemplateName(types::JournalEntry),
+    // value for placeholder `name` of the template, `values` are the
+    // placeholders answered so far.
+    Placeholder {
+        template: types::Template,
+        values: BTreeMap<String, String>,
+        name: String,
+    },
+}
+
+impl Question {
+    fn to_title(&self) -> String {
+        match self {
+            Question::Goto => "Go to date".to_string(),
+            Question::TemplateName(entry) => format!("Save {} as template", entry.payee),
+            Question::Placeholder { template, .. } => format!("Template {}", template.name),
+        }
+    }
+
+    fn to_field(&self) -> String {
+        match self {
+            Question::Goto => "Date :".to_string(),
+            Question::TemplateName(_) => "Name :".to_string(),
+            Question::Placeholder { name, .. } => format!("{} :", name),
+        }
+    }
+}
+
+// single line input in a box over the layer below. Enter hands the text
+// to the application as Event::Answer, the prompt stays open if the
+// answer is not accepted.
+pub struct Prompt<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, followed by the input.
+    elements: Vec<te::Element>,
+    question: Question,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> Prompt<S>
+where
+    S: Store,
+{
+    pub fn new(app: &mut Application<S>, question: Question) -> Result<Prompt<S>> {
+        let vp = {
+            let (_, width) = app.to_viewport().to_size();
+            let width = cmp::min(layout::sub(width, 8), PROMPT_WIDTH);
+            app.to_viewport().move_by(4, 4).resize_to(5, width)
+        };
+        let (_, width) = vp.to_size();
+
+        let border = te::Border::new(app, vp.clone(), question.to_title())
+            .ok()
+            .unwrap();
+        let input = {
+            let input_vp = vp.clone().move_by(2, 2).resize_to(1, layout::sub(width, 4));
+            let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
+            em.set_field(&question.to_field()).set_mandatory(true);
+            match &question {
+                Question::Goto => {
+                    em.set_date_field(command::DATE_FORMAT);
+                }
+                Question::Placeholder { name, .. } if name == types::DATE_PLACEHOLDER => {
+                    em.set_date_field(command::DATE_FORMAT);
+                }
+                _ => (),
+            }
+            em
+        };
+
+        Ok(Prompt {
+            vp,
+            elements: vec![te::Element::Border(border), te::Element::EditLine(input)],
+            question,
+
+            _phantom_s: marker::PhantomData,
+        })
+    }
+
+    pub fn to_question(&self) -> Question {
+        self.question.clone()
+    }
+}
+
+impl<S> Prompt<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        Ok(())
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        trace!("Focus layer_prompt");
+        self.elements[1].focus(app)
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.elements[1].leave(app)
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        match self.elements[1].handle_event(app, evnt)? {
+            Some(evnt) if is_open(app, &evnt) => {
+                let text = self.elements[1].to_content().unwrap_or_default();
+                Ok(Some(Event::Answer(text.trim().to_string())))
+            }
+            evnt => Ok(evnt),
+        }
+    }
+}
+
+impl<S> TermCommand for Prompt<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "Prompt::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+
+        output
+    }
+}
+
+// (column, width, inline text) of the ledger, commodity and amount in
+// a leg row of the entry form.
+const LEG_FIELDS: [(i16, u16, &str); 3] = [
+    (8, 30, "company:ledger"),
+    (40, 12, "commodity"),
+    (54, 14, "amount"),
+];
+
+// form to add a journal entry, payee and date followed by rows of debit
+// and credit legs. Ctrl-n adds a leg row below the focused legs, Ctrl-d
+// removes the focused leg row if it is empty, Alt-Up and Alt-Down move
+// the focused leg row. Footer shows the running imbalance of the legs.
+// Alternately, the whole entry is typed in the quick entry field, refer
+// util::QuickEntry, and Enter adds it.
+pub struct EntryForm<S>
+where
+    S: Store,
+{
+    vp: te::Viewport,
+    // border, quick entry, payee, date, debit heading, debit legs, credit
+    // heading, credit legs, followed by the submit button.
+    elements: Vec<te::Element>,
+    debits: LegRows,
+    credits: LegRows,
+    focus: TabOffsets,
+    footer: te::StatusLine,
+    fmt: util::AmountFormat,
+
+    _phantom_s: marker::PhantomData<S>,
+}
+
+impl<S> EntryForm<S>
+where
+    S: Store,
+{
+    // leg rows are laid out, and pre-filled, from `legs`.
+    pub fn new(
+        app: &mut Application<S>,
+        date: chrono::NaiveDate,
+        legs: EntryLegs,
+    ) -> Result<EntryForm<S>> {
+        let vp = app.to_viewport();
+        let (height, width) = vp.to_size();
+
+        let border = te::Border::new(app, vp.clone(), "New journal entry".to_string())
+            .ok()
+            .unwrap();
+        let quick = {
+            let input_vp = vp.clone().move_by(5, 3).resize_to(1, 60);
+            let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
+            em.set_inline("payee | company:ledger amount [commodity] | ...")
+                .set_field("Quick   :");
+            em
+        };
+        let payee = {
+            let input_vp = vp.clone().move_by(5, 5).resize_to(1, 60);
+            let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
+            em.set_inline("Paid to, or received from")
+                .set_mandatory(true)
+                .set_field("Payee   :");
+            em
+        };
+        let date = {
+            let input_vp = vp.clone().move_by(5, 6).resize_to(1, 30);
+            let mut em = te::EditLine::new(app, input_vp).ok().unwrap();
+            em.set_mandatory(true).set_field("Date    :");
+            em.set_date_field(command::DATE_FORMAT);
+            em.set_content(&date.format(command::DATE_FORMAT).to_string())?;
+            em
+        };
+        let heading = |app: &mut Application<S>, row: i16, content: &str| {
+            let head_vp = vp.clone().move_by(5, row).resize_to(1, 60);
+            let mut em = te::Span::new(app, head_vp, content).ok().unwrap();
+            em.set_fg_color(te::FG_SECTION);
+            te::Element::Span(em)
+        };
+
+        let mut elements = vec![
+            te::Element::Border(border),
+            te::Element::EditLine(quick),
+            te::Element::EditLine(payee),
+            te::Element::EditLine(date),
+            heading(app, 8, "Debit, ledger commodity and amount"),
+        ];
+        let mut row = 9;
+        for leg in legs.debits.iter() {
+            elements.extend(Self::leg_fields(app, &vp, row, leg)?);
+            row += 1;
+        }
+        let debits = LegRows::new(5, LEG_FIELDS.len(), legs.debits.len(), 1);
+        elements.push(heading(app, row + 1, "Credit, ledger commodity and amount"));
+        row += 2;
+        for leg in legs.credits.iter() {
+            elements.extend(Self::leg_fields(app, &vp, row, leg)?);
+            row += 1;
+        }
+        let credits = LegRows::new(debits.to_end() + 1, LEG_FIELDS.len(), legs.credits.len(), 1);
+        let button_ok = {
+            let button_vp = vp.clone().move_by(8, row + 1).resize_to(1, 4);
+            let mut em = te::Button::new(app, button_vp, "ok", te::ButtonType::Submit)
+                .ok()
+                .unwrap();
+            em.set_bold(true);
+            em
+        };
+        elements.push(te::Element::Button(button_ok));
+
+        let mut offs = vec![1, 2, 3];
+        offs.extend(debits.start..debits.to_end());
+        offs.extend(credits.start..credits.to_end());
+        offs.extend(vec![credits.to_end(), 0]);
+        let footer = {
+            let footer_vp = vp
+                .clone()
+                .move_by(2, layout::sub(height, 2) as i16)
+                .resize_to(1, layout::sub(width, 4));
+            te::StatusLine::new(app, footer_vp)?
+        };
+
+        let mut form = EntryForm {
+            vp,
+            elements,
+            debits,
+            credits,
+            focus: TabOffsets::new(offs),
+            footer,
+            fmt: app.to_amount_format(),
+
+            _phantom_s: marker::PhantomData,
+        };
+        form.refresh_balance();
+        Ok(form)
+    }
+
+    fn refresh_balance(&mut self) {
+        let (elements, fmt) = (&self.elements, &self.fmt);
+        refresh_balance(&mut self.footer, elements, &self.debits, &self.credits, fmt)
+    }
+
+    // edit fields of a leg row at `row`, pre-filled with `leg`.
+    fn leg_fields(
+        app: &mut Application<S>,
+        vp: &te::Viewport,
+        row: i16,
+        leg: &[String; 3],
+    ) -> Result<Vec<te::Element>> {
+        let mut fields = vec![];
+        for ((col, width, inline), text) in LEG_FIELDS.iter().zip(leg.iter()) {
+            let leg_vp = vp.clone().move_by(*col, row).resize_to(1, *width);
+            let mut em = te::EditLine::new(app, leg_vp).ok().unwrap();
+            em.set_inline(inline);
+            if !text.is_empty() {
+                em.set_content(text)?;
+            }
+            fields.push(te::Element::EditLine(em));
+        }
+        Ok(fields)
+    }
+
+    // journal entry from the form fields, legs without a commodity are
+    // booked under `commodity` and empty leg rows are skipped. A filled
+    // quick entry takes the place of payee and leg fields.
+    pub fn to_entry(&self, commodity: &str) -> Result<types::JournalEntry> {
+        let text = |off: usize| self.elements[off].to_content().unwrap_or_default();
+
+        let date = util::parse_date_flexible(&text(3))?;
+        let created = {
+            let time = chrono::Utc::now().time();
+            chrono::DateTime::from_utc(date.and_time(time), chrono::Utc)
+        };
+        let quick = text(1);
+        if !quick.trim().is_empty() {
+            let quick: util::QuickEntry = quick.parse()?;
+            return types::JournalEntry::from_quick_entry(&quick, created, commodity);
+        }
+
+        let payee = text(2).trim().to_string();
+        if payee.is_empty() {
+            err_at!(InvalidInput, msg: format!("payee is required"))?;
+        }
+
+        let mut je = types::JournalEntry::new(payee, created);
+        for (rows, debit) in vec![(&self.debits, true), (&self.credits, false)].into_iter() {
+            for row in rows.to_contents(&self.elements).into_iter() {
+                let (ledger, name, amount) = match row.as_slice() {
+                    row if row.iter().all(|field| field.trim().is_empty()) => continue,
+                    [ledger, name, amount] => (ledger.trim(), name.trim(), amount.trim()),
+                    _ => continue,
+                };
+                let (company, ledger) = match ledger.split(':').collect::<Vec<&str>>().as_slice() {
+                    [company, ledger] if !company.is_empty() && !ledger.is_empty() => {
+                        (company.to_string(), ledger.to_string())
+                    }
+                    _ => err_at!(InvalidInput, msg: format!("leg ledger {:?}", ledger))?,
+                };
+                let name = match name {
+                    "" => commodity.to_string(),
+                    name => name.to_lowercase(),
+                };
+                let amount: f64 = err_at!(
+                    InvalidInput,
+                    amount.replace(',', "").parse(),
+                    format!("leg amount {:?}", amount)
+                )?;
+                if debit {
+                    je.add_debitor(company, ledger, (name, amount))?;
+                } else {
+                    je.add_creditor(company, ledger, (name, amount))?;
+                }
+            }
+        }
+        je.validate()?;
+
+        Ok(je)
+    }
+
+    // add an empty leg row below the debit or credit legs holding focus.
+    fn add_leg(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = self.focus.current();
+        if self.debits.to_row(off).is_some() {
+            self.debits.add_row(&mut self.elements, &mut self.focus)?;
+            self.credits.start += self.debits.row_len;
+        } else if self.credits.to_row(off).is_some() {
+            self.credits.add_row(&mut self.elements, &mut self.focus)?;
+        } else {
+            return Ok(());
+        }
+        self.refresh_balance();
+        app.set_redraw();
+        Ok(())
+    }
+
+    // remove the focused leg row, only if it is empty, focus moves to
+    // the field that follows it.
+    fn remove_leg(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = self.focus.current();
+        let removed = if self.debits.to_row(off).is_some() {
+            let removed = self.debits.remove_row(&mut self.elements, &mut self.focus, off)?;
+            if removed {
+                self.credits.start -= self.debits.row_len;
+            }
+            removed
+        } else if self.credits.to_row(off).is_some() {
+            self.credits.remove_row(&mut self.elements, &mut self.focus, off)?
+        } else {
+            return Ok(());
+        };
+
+        if removed {
+            self.refresh_balance();
+            self.focus_element(app)?;
+            app.set_redraw();
+        } else {
+            app.log_status("only an empty leg row can be removed, the last one is kept");
+        }
+        Ok(())
+    }
+
+    // move the focused leg row up, or down, among the debit or credit
+    // legs, focus follows the row.
+    fn move_leg(&mut self, app: &mut Application<S>, up: bool) -> Result<()> {
+        let off = self.focus.current();
+        let new_off = if self.debits.to_row(off).is_some() {
+            self.debits.move_row(&mut self.elements, off, up)?
+        } else if self.credits.to_row(off).is_some() {
+            self.credits.move_row(&mut self.elements, off, up)?
+        } else {
+            return Ok(());
+        };
+
+        if let Some(old_off) = self.focus.tab_to(new_off) {
+            self.elements[old_off].leave(app)?;
+        }
+        self.refresh_balance();
+        self.focus_element(app)?;
+        self.refresh(app, true /*force*/)
+    }
+
+    fn focus_element(&mut self, app: &mut Application<S>) -> Result<()> {
+        let em_idx = self.focus.current();
+        trace!("Focus layer_entry_form em_idx:{}", em_idx);
+
+        self.elements[em_idx].focus(app)?;
+        if em_idx == 0 {
+            app.hide_cursor()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> EntryForm<S>
+where
+    S: Store,
+{
+    pub fn refresh(&mut self, app: &mut Application<S>, force: bool) -> Result<()> {
+        for em in self.elements.iter_mut() {
+            em.refresh(app, force)?
+        }
+        self.footer.refresh(app, force)
+    }
+
+    pub fn focus(&mut self, app: &mut Application<S>) -> Result<()> {
+        self.focus_element(app)
+    }
+
+    pub fn leave(&mut self, app: &mut Application<S>) -> Result<()> {
+        let off = self.focus.current();
+        self.elements[off].leave(app)
+    }
+
+    pub fn handle_event(&mut self, app: &mut Application<S>, evnt: Event) -> Result<Option<Event>> {
+        if paste_fields(app, &mut self.elements, &mut self.focus, &evnt)? {
+            self.refresh_balance();
+            self.focus_element(app)?;
+            self.refresh(app, true /*force*/)?;
+            return Ok(None);
+        }
+
+        let off = self.focus.current();
+        let evnt = self.elements[off].handle_event(app, evnt)?;
+        if self.debits.to_row(off).is_some() || self.credits.to_row(off).is_some() {
+            self.refresh_balance();
+        }
+
+        match evnt {
+            Some(Event::Submit) => Ok(Some(Event::SubmitEntry)),
+            Some(evnt) if off == 1 && is_open(app, &evnt) && !self.elements[off].is_empty() => {
+                Ok(Some(Event::SubmitEntry))
+            }
+            Some(evnt) => match app.to_action(&evnt) {
+                Some(Action::Submit) => Ok(Some(Event::SubmitEntry)),
+                Some(Action::AddRow) => {
+                    self.add_leg(app)?;
+                    Ok(None)
+                }
+                Some(Action::RemoveRow) => {
+                    self.remove_leg(app)?;
+                    Ok(None)
+                }
+                Some(Action::MoveUp) => {
+                    self.move_leg(app, true /*up*/)?;
+                    Ok(None)
+                }
+                Some(Action::MoveDown) => {
+                    self.move_leg(app, false /*up*/)?;
+                    Ok(None)
+                }
+                // leave the fields first, then the form.
+                Some(Action::Cancel) => match self.focus.tab_to(0) {
+                    Some(old_off) => {
+                        self.elements[old_off].leave(app)?;
+                        self.focus_element(app)?;
+                        Ok(None)
+                    }
+                    None => Ok(Some(evnt)),
+                },
+                Some(Action::NextField) => {
+                    let old_off = self.focus.tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                Some(Action::PrevField) => {
+                    let old_off = self.focus.back_tab();
+                    self.elements[old_off].leave(app)?;
+                    self.focus_element(app)?;
+                    Ok(None)
+                }
+                _ => Ok(Some(evnt)),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+impl<S> TermCommand for EntryForm<S>
+where
+    S: Store,
+{
+    type AnsiType = String;
+
+    fn ansi_code(&self) -> Self::AnsiType {
+        use std::iter::repeat;
+
+        let (col, row) = self.vp.to_origin();
+        let (height, width) = self.vp.to_size();
+
+        trace!(
+            "EntryForm::Viewport col:{} row:{} height:{} width:{}",
+            col,
+            row,
+            height,
+            width
+        );
+
+        let mut output: String = Default::default();
+        let s = String::from_iter(repeat(' ').take(width as usize));
+        for r in 0..height {
+            output.push_str(&te::move_to(col, row + r + 1).to_string());
+            output.push_str(&style::style(&s).on(te::BG_LAYER).to_string());
+        }
+        for element in self.elements.iter() {
+            output.push_str(&element.to_string());
+        }
+        output.push_str(&self.footer.to_string());
+
+        output
+    }
+}
+
+// lines of a month calendar for `date`, the month and year, weekday
+// names from `start`, followed by a line for every week of the month.
+// `date` is marked within brackets, EG: `[15]`.
+pub fn calendar_text(date: chrono::NaiveDate, start: util::WeekStart) -> Vec<String> {
+    use chrono::Datelike;
+
+    let names = match start {
+        util::WeekStart::Monday => ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"],
+        util::WeekStart::Sunday => ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"],
+    };
+    let mut lines = vec![
+        date.format("%B %Y").to_string(),
+        names.iter().map(|name| format!(" {} ", name)).collect::<Vec<String>>().join(""),
+    ];
+    for week in util::month_grid(date.year(), date.month(), start).into_iter() {
+        let line: Vec<String> = week
+            .iter()
+            .map(|day| match day {
+                Some(day) if *day == date.day() => format!("[{:>2}]", day),
+                Some(day) => format!(" {:>2} ", day),
+                None => "    ".to_string(),
+            })
+            .collect();
+        lines.push(line.join("").trim_end().to_string());
+    }
+    lines
+}
+
+// sort keys for journal entries in entry browser.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntrySort {
+    Date,
+    Payee,
+    Amount,
+}
+
+impl fmt::Display for EntrySort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            EntrySort::Date => write!(f, "date"),
+            EntrySort::Payee => write!(f, "payee"),
+            EntrySort::Amount => write!(f, "amount"),
+        }
+    }
+}
+
+impl EntrySort {
+    pub fn from_action(action: Action) -> Option<EntrySort> {
+        match action {
+            Action::SortDate => Some(EntrySort::Date),
+            Action::SortPayee => Some(EntrySort::Payee),
+            Action::SortAmount => Some(EntrySort::Amount),
+            _ => None,
+        }
+    }
+
+    pub fn to_sort_key(&self, ascending: bool) -> te::SortKey {
+        te::SortKey {
+            name: self.to_string(),
+            ascending,
+        }
+    }
+
+    // payees compare ignoring case, amounts are the sum of debit legs.
+    // Ties are broken by date.
+    pub fn compare(&self, x: &types::JournalEntry, y: &types::JournalEntry) -> cmp::Ordering {
+        let amount = |e: &types::JournalEntry| -> f64 {
+            e.debitors.iter().map(|d| d.commodity.value).sum::<f64>()
+        };
+        let ord = match self {
+            EntrySort::Date => cmp::Ordering::Equal,
+            EntrySort::Payee => x.payee.to_lowercase().cmp(&y.payee.to_lowercase()),
+            EntrySort::Amount => amount(x)
+                .partial_cmp(&amount(y))
+                .unwrap_or(cmp::Ordering::Equal),
+        };
+        ord.then_with(|| x.created.cmp(&y.created))
+    }
+}
+
+// (commodity, debit, credit) totals of a journal entry, one for each
+// commodity, shown in the totals panel of entry browser.
+pub fn entry_totals(entry: &types::JournalEntry) -> Vec<(String, f64, f64)> {
+    entry
+        .to_commodity_totals()
+        .into_iter()
+        .map(|(name, (credit, debit))| (name, debit, credit))
+        .collect()
+}
+
+// rows of the totals panel, EG: `inr 100.00 Dr 100.00 Cr`, followed by
+// `balanced` or the remaining imbalance.
+pub fn totals_text(totals: &[(String, f64, f64)], fmt: &util::AmountFormat) -> Vec<String> {
+    let mut lines: Vec<String> = totals
+        .iter()
+        .map(|(name, debit, credit)| {
+            let (debit, credit) = (fmt.format_amount(*debit), fmt.format_amount(*credit));
+            format!("{} {} Dr {} Cr", name, debit, credit)
+        })
+        .collect();
+
+    let imbalance: Vec<(String, f64)> = totals
+        .iter()
+        .map(|(name, debit, credit)| (name.clone(), debit - credit))
+        .filter(|(_, value)| value.abs() >= types::BALANCE_EPSILON)
+        .collect();
+    lines.push(imbalance_text(&imbalance, fmt));
+
+    lines
+}
+
+// rows of creditor or debitor legs in an entry form, every row has the
+// same element layout, `row_len` elements, and is `height` rows tall.
+// Elements from `start + n_rows * row_len` onwards follow the legs.
+#[derive(Debug)]
+struct LegRows {
+    start: usize,
+    row_len: usize,
+    n_rows: usize,
+    height: u16,
+}
+
+impl LegRows {
+    fn new(start: usize, row_len: usize, n_rows: usize, height: u16) -> LegRows {
+        LegRows {
+            start,
+            row_len,
+            n_rows,
+            height,
+        }
+    }
+
+    fn to_end(&self) -> usize {
+        self.start + self.n_rows * self.row_len
+    }
+
+    // row index of element offset `off`, if it belongs to a leg row.
+    fn to_row(&self, off: usize) -> Option<usize> {
+        if off >= self.start && off < self.to_end() {
+            Some((off - self.start) / self.row_len)
+        } else {
+            None
+        }
+    }
+
+    // append a leg row, cloned from the last one, and push the elements
+    // below it down by a row.
+    fn add_row(&mut self, elements: &mut Vec<te::Element>, focus: &mut TabOffsets) -> Result<()> {
+        let (end, height) = (self.to_end(), self.height as i16);
+        for em in elements[end..].iter_mut() {
+            em.move_by(height)?;
+        }
+        let mut row = vec![];
+        for em in elements[(end - self.row_len)..end].iter() {
+            row.push(em.clone_row(height)?);
+        }
+        for (i, em) in row.into_iter().enumerate() {
+            elements.insert(end + i, em);
+        }
+        focus.insert_after(end - 1, end, self.row_len);
+        self.n_rows += 1;
+
+        Ok(())
+    }
+
+    // remove the leg row holding element offset `off`, if all its edit
+    // elements are empty, and pull the elements below it up by a row.
+    // Atleast one row is always left. Return whether the row was removed.
+    fn remove_row(
+        &mut self,
+        elements: &mut Vec<te::Element>,
+        focus: &mut TabOffsets,
+        off: usize,
+    ) -> Result<bool> {
+        let row = match self.to_row(off) {
+            Some(_) if self.n_rows < 2 => return Ok(false),
+            Some(row) => row,
+            None => return Ok(false),
+        };
+        let at = self.start + row * self.row_len;
+        if !elements[at..(at + self.row_len)].iter().all(|em| em.is_empty()) {
+            return Ok(false);
+        }
+
+        elements.drain(at..(at + self.row_len));
+        for em in elements[at..].iter_mut() {
+            em.move_by(-(self.height as i16))?;
+        }
+        focus.remove(at, self.row_len);
+        self.n_rows -= 1;
+
+        Ok(true)
+    }
+
+    // move the leg row holding element offset `off` one row up, or down,
+    // by swapping contents with its neighbour, refer types::move_leg().
+    // Return the element offset that now holds the moved content.
+    fn move_row(&self, elements: &mut [te::Element], off: usize, up: bool) -> Result<usize> {
+        let row = match self.to_row(off) {
+            Some(row) => row,
+            None => return Ok(off),
+        };
+        let mut rows = self.to_contents(elements);
+        let new_row = types::move_leg(&mut rows, row, up)?;
+        for r in vec![row, new_row].into_iter() {
+            let at = self.start + r * self.row_len;
+            for (em, content) in elements[at..].iter_mut().zip(rows[r].iter()) {
+                em.set_content(content)?;
+            }
+        }
+
+        Ok(off - row * self.row_len + new_row * self.row_len)
+    }
+
+    // content of every element in each leg row, empty for elements that
+    // are not editable.
+    fn to_contents(&self, elements: &[te::Element]) -> Vec<Vec<String>> {
+        elements[self.start..self.to_end()]
+            .chunks(self.row_len)
+            .map(|row| row.iter().map(|em| em.to_content().unwrap_or_default()).collect())
+            .collect()
+    }
+}
+
+// initial leg fields of a new entry form, one debit and one credit row,
+// each row is (ledger, commodity, amount) text. The first credit leg is
+// pre-filled with the workspace's default ledger, if configured.
+#[derive(Debug, PartialEq)]
+pub struct EntryLegs {
+    pub debits: Vec<[String; 3]>,
+    pub credits: Vec<[String; 3]>,
+}
+
+impl EntryLegs {
+    pub fn new(w: &types::Workspace) -> EntryLegs {
+        let mut credit: [String; 3] = Default::default();
+        if let Some(ledger) = w.to_default_ledger() {
+            let (cname, lname): (String, String) = ledger.into();
+            credit[0] = format!("{}:{}", cname, lname);
+        }
+
+        EntryLegs {
+            debits: vec![Default::default()],
+            credits: vec![credit],
+        }
+    }
+}
+
+// running imbalance for each commodity from the leg fields of an entry
+// form, legs are (commodity, amount) text. Positive value is the credit
+// needed to balance the entry, negative value is the debit needed. Legs
+// with missing commodity or unparsable amount are skipped.
+pub fn leg_imbalance(debits: &[(String, String)], credits: &[(String, String)]) -> Vec<(String, f64)> {
+    let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+    let legs = debits
+        .iter()
+        .map(|leg| (leg, 1.0))
+        .chain(credits.iter().map(|leg| (leg, -1.0)));
+    for ((name, amount), sign) in legs {
+        let name = name.trim().to_lowercase();
+        match amount.trim().replace(',', "").parse::<f64>() {
+            Ok(value) if !name.is_empty() => *totals.entry(name).or_insert(0.0) += sign * value,
+            _ => (),
+        }
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, value)| value.abs() >= types::BALANCE_EPSILON)
+        .collect()
+}
+
+// text for the balance footer below the legs, EG: `remaining inr 40.00 Cr`.
+pub fn imbalance_text(imbalance: &[(String, f64)], fmt: &util::AmountFormat) -> String {
+    if imbalance.is_empty() {
+        return "balanced".to_string();
+    }
+
+    let items: Vec<String> = imbalance
+        .iter()
+        .map(|(name, value)| {
+            let side = if *value > 0.0 { "Cr" } else { "Dr" };
+            format!("{} {} {}", name, fmt.format_amount(value.abs()), side)
+        })
+        .collect();
+    format!("remaining {}", items.join(", "))
+}
+
+// recompute the footer from the leg rows, on every change to the legs.
+// Both debit and credit rows are laid out as (ledger, commodity, amount).
+fn refresh_balance(
+    footer: &mut te::StatusLine,
+    elements: &[te::Element],
+    debits: &LegRows,
+    credits: &LegRows,
+    fmt: &util::AmountFormat,
+) {
+    let to_legs = |rows: &LegRows| -> Vec<(String, String)> {
+        rows.to_contents(elements)
+            .into_iter()
+            .filter_map(|row| match row.as_slice() {
+                [_, commodity, amount, ..] => Some((commodity.clone(), amount.clone())),
+                _ => None,
+            })
+            .collect()
+    };
+    let imbalance = leg_imbalance(&to_legs(debits), &to_legs(credits));
+    footer.log(&imbalance_text(&imbalance, fmt));
+}
+
+#[derive(Debug)]
+struct TabOffsets(Vec<usize>);
+
+impl TabOffsets {
+    fn new(offs: Vec<usize>) -> TabOffsets {
+        TabOffsets(offs)
+    }
+
+    fn current(&self) -> usize {
+        self.0.first().unwrap().clone()
+    }
+
+    // element offsets in tab order, starting from the current one.
+    fn to_offsets(&self) -> Vec<usize> {
+        self.0.clone()
+    }
+
+    fn tab(&mut self) -> usize {
+        let old_off = self.0.remove(0);
+        self.0.push(old_off);
+        old_off
+    }
+
+    fn back_tab(&mut self) -> usize {
+        let old_off = self.current();
+        let off = self.0.pop().unwrap();
+        self.0.insert(0, off);
+        old_off
+    }
+
+    // insert `n` new element offsets, starting at `at`, into the tab order
+    // right after `anchor`. Existing offsets from `at` are shifted by `n`.
+    fn insert_after(&mut self, anchor: usize, at: usize, n: usize) {
+        for off in self.0.iter_mut() {
+            if *off >= at {
+                *off += n;
+            }
+        }
+        let i = self.0.iter().position(|off| *off == anchor).unwrap();
+        for (j, off) in (at..(at + n)).enumerate() {
+            self.0.insert(i + 1 + j, off);
+        }
+    }
+
+    // remove element offsets `at..at+n` from the tab order, offsets after
+    // them are shifted back by `n`.
+    fn remove(&mut self, at: usize, n: usize) {
+        self.0.retain(|off| *off < at || *off >= (at + n));
+        for off in self.0.iter_mut() {
+            if *off >= (at + n) {
+                *off -= n;
+            }
+        }
+    }
+
+    // make `off` the current offset, keeping the tab order, and return
+    // the offset that was current. None if `off` is already current.
+    fn tab_to(&mut self, off: usize) -> Option<usize> {
+        match self.0.iter().position(|val| *val == off) {
+            Some(0) => None,
+            Some(i) => {
+                let old_off = self.current();
+                self.0.rotate_left(i);
+                Some(old_off)
+            }
+            None => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "term_layers_test.rs"]
+mod term_layers_test;
@@ -0,0 +1,218 @@
+use chrono::TimeZone;
+
+use super::*;
+
+#[test]
+fn test_tab_offsets_insert_remove() {
+    let mut focus = TabOffsets::new(vec![2, 3, 4, 5, 6]);
+    focus.tab();
+    focus.insert_after(4, 5, 2);
+    assert_eq!(focus.to_offsets(), vec![3, 4, 5, 6, 7, 8, 2]);
+    assert_eq!(focus.current(), 3);
+
+    focus.remove(5, 2);
+    assert_eq!(focus.to_offsets(), vec![3, 4, 5, 6, 2]);
+    // removing the current offset moves focus to the next one.
+    focus.remove(3, 1);
+    assert_eq!(focus.to_offsets(), vec![3, 4, 5, 2]);
+}
+
+#[test]
+fn test_tab_offsets_tab_to() {
+    let mut focus = TabOffsets::new(vec![1, 2, 4, 5, 0]);
+    assert_eq!(focus.tab_to(1), None);
+    // tab order is kept.
+    assert_eq!(focus.tab_to(4), Some(1));
+    assert_eq!(focus.to_offsets(), vec![4, 5, 0, 1, 2]);
+    assert_eq!(focus.tab_to(0), Some(4));
+    assert_eq!(focus.to_offsets(), vec![0, 1, 2, 4, 5]);
+}
+
+fn make_list_box(row: u16) -> te::Element {
+    let mut em = te::ListBox::default();
+    em.set_viewport(te::Viewport::new(1, row, 1, 10));
+    te::Element::ListBox(em)
+}
+
+#[test]
+fn test_leg_rows_add_remove() {
+    // a heading, one leg row of two elements, and a button below.
+    let mut elements = vec![
+        make_list_box(1),
+        make_list_box(2),
+        make_list_box(2),
+        make_list_box(3),
+    ];
+    let mut focus = TabOffsets::new(vec![1, 2, 3]);
+    let mut legs = LegRows::new(1, 2, 1, 1);
+
+    legs.add_row(&mut elements, &mut focus).unwrap();
+    assert_eq!(elements.len(), 6);
+    assert_eq!(focus.to_offsets(), vec![1, 2, 3, 4, 5]);
+    assert!(elements[3].contain_cell(1, 3));
+    assert!(elements[4].contain_cell(1, 3));
+    assert!(elements[5].contain_cell(1, 4));
+
+    legs.add_row(&mut elements, &mut focus).unwrap();
+    assert_eq!(elements.len(), 8);
+    assert_eq!(focus.to_offsets(), vec![1, 2, 3, 4, 5, 6, 7]);
+
+    // remove the middle row, rows below are pulled up.
+    assert!(legs.remove_row(&mut elements, &mut focus, 4).unwrap());
+    assert_eq!(elements.len(), 6);
+    assert_eq!(focus.to_offsets(), vec![1, 2, 3, 4, 5]);
+    assert!(elements[3].contain_cell(1, 3));
+    assert!(elements[5].contain_cell(1, 4));
+
+    // not a leg row.
+    assert!(!legs.remove_row(&mut elements, &mut focus, 5).unwrap());
+    assert!(legs.remove_row(&mut elements, &mut focus, 3).unwrap());
+    // last row is never removed.
+    assert!(!legs.remove_row(&mut elements, &mut focus, 1).unwrap());
+    assert_eq!(elements.len(), 4);
+    assert_eq!(focus.to_offsets(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_leg_imbalance() {
+    let leg = |name: &str, amount: &str| (name.to_string(), amount.to_string());
+
+    // only the first debit leg is filled in.
+    let mut debits = vec![leg("inr", "100"), leg("", "")];
+    let mut credits = vec![leg("", "")];
+    let imbalance = leg_imbalance(&debits, &credits);
+    assert_eq!(imbalance, vec![("inr".to_string(), 100.0)]);
+    assert_eq!(imbalance_text(&imbalance, &Default::default()), "remaining inr 100.00 Cr");
+
+    credits[0] = leg("INR", "60.00");
+    let imbalance = leg_imbalance(&debits, &credits);
+    assert_eq!(imbalance, vec![("inr".to_string(), 40.0)]);
+
+    // half typed amount is skipped.
+    credits.push(leg("inr", "4x"));
+    assert_eq!(leg_imbalance(&debits, &credits), vec![("inr".to_string(), 40.0)]);
+
+    credits[1] = leg("inr", "40");
+    debits[1] = leg("usd", "5");
+    let imbalance = leg_imbalance(&debits, &credits);
+    assert_eq!(imbalance, vec![("usd".to_string(), 5.0)]);
+
+    credits.push(leg("usd", "7.5"));
+    let imbalance = leg_imbalance(&debits, &credits);
+    assert_eq!(imbalance, vec![("usd".to_string(), -2.5)]);
+    assert_eq!(imbalance_text(&imbalance, &Default::default()), "remaining usd 2.50 Dr");
+
+    debits.push(leg("usd", "2.5"));
+    let imbalance = leg_imbalance(&debits, &credits);
+    assert!(imbalance.is_empty());
+    assert_eq!(imbalance_text(&imbalance, &Default::default()), "balanced");
+}
+
+#[test]
+fn test_entry_legs_default_ledger() {
+    let mut w = types::Workspace::new("test".to_string());
+    let legs = EntryLegs::new(&w);
+    assert_eq!(legs.debits, vec![<[String; 3]>::default()]);
+    assert_eq!(legs.credits, vec![<[String; 3]>::default()]);
+
+    let checking: types::KeyLedger = ("acme".to_string(), "checking".to_string()).into();
+    w.set_default_ledger(Some(checking));
+    let legs = EntryLegs::new(&w);
+    assert_eq!(legs.debits, vec![<[String; 3]>::default()]);
+    assert_eq!(legs.credits[0][0], "acme:checking");
+    assert!(legs.credits[0][1].is_empty() && legs.credits[0][2].is_empty());
+
+    w.set_default_ledger(None);
+    assert_eq!(EntryLegs::new(&w).credits[0][0], "");
+}
+
+#[test]
+fn test_entry_totals() {
+    let created = chrono::Utc.ymd(2020, 4, 15).and_hms(10, 0, 0);
+    let mut je = types::JournalEntry::new("trip".to_string(), created);
+    je.add_debitor("acme".to_string(), "travel".to_string(), ("inr".to_string(), 100.0))
+        .unwrap();
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("inr".to_string(), 60.0))
+        .unwrap();
+    je.add_creditor("acme".to_string(), "bank".to_string(), ("inr".to_string(), 40.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "travel".to_string(), ("usd".to_string(), 5.0))
+        .unwrap();
+
+    let totals = entry_totals(&je);
+    assert_eq!(
+        totals,
+        vec![
+            ("inr".to_string(), 100.0, 100.0),
+            ("usd".to_string(), 5.0, 0.0),
+        ]
+    );
+    assert_eq!(
+        totals_text(&totals, &Default::default()),
+        vec![
+            "inr 100.00 Dr 100.00 Cr".to_string(),
+            "usd 5.00 Dr 0.00 Cr".to_string(),
+            "remaining usd 5.00 Cr".to_string(),
+        ]
+    );
+
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("usd".to_string(), 5.0))
+        .unwrap();
+    let lines = totals_text(&entry_totals(&je), &Default::default());
+    assert_eq!(lines.last().unwrap(), "balanced");
+
+    let empty = types::JournalEntry::new("none".to_string(), created);
+    assert!(entry_totals(&empty).is_empty());
+    assert_eq!(totals_text(&[], &Default::default()), vec!["balanced".to_string()]);
+}
+
+#[test]
+fn test_entry_sort() {
+    let make_entry = |payee: &str, day: u32, amount: f64| {
+        let created = chrono::Utc.ymd(2020, 6, day).and_hms(9, 0, 0);
+        let mut je = types::JournalEntry::new(payee.to_string(), created);
+        let comm = ("inr".to_string(), amount);
+        je.add_creditor("acme".to_string(), "cash".to_string(), comm.clone())
+            .unwrap();
+        je.add_debitor("acme".to_string(), "food".to_string(), comm)
+            .unwrap();
+        je
+    };
+    let entries = vec![
+        make_entry("grocer", 3, 50.0),
+        make_entry("Baker", 1, 200.0),
+        make_entry("cafe", 2, 50.0),
+    ];
+    let sorted = |by: EntrySort| -> Vec<String> {
+        let mut items = entries.clone();
+        items.sort_by(|x, y| by.compare(x, y));
+        items.into_iter().map(|e| e.payee).collect()
+    };
+
+    assert_eq!(sorted(EntrySort::Date), vec!["Baker", "cafe", "grocer"]);
+    // payees ignore case.
+    assert_eq!(sorted(EntrySort::Payee), vec!["Baker", "cafe", "grocer"]);
+    // equal amounts fall back to date.
+    assert_eq!(sorted(EntrySort::Amount), vec!["cafe", "grocer", "Baker"]);
+
+    assert_eq!(EntrySort::from_action(Action::SortPayee), Some(EntrySort::Payee));
+    assert_eq!(EntrySort::from_action(Action::Submit), None);
+    let key = EntrySort::Amount.to_sort_key(false);
+    assert_eq!((key.name.as_str(), key.ascending), ("amount", false));
+}
+
+#[test]
+fn test_calendar_text() {
+    let date = chrono::NaiveDate::from_ymd(2020, 7, 15);
+    let lines = calendar_text(date, util::WeekStart::Monday);
+    assert_eq!(lines[0], "July 2020");
+    assert_eq!(lines[1], " Mo  Tu  We  Th  Fr  Sa  Su ");
+    // 1st of July 2020 is a wednesday.
+    assert_eq!(lines[2], "          1   2   3   4   5");
+    assert_eq!(lines[4], " 13  14 [15] 16  17  18  19");
+    assert_eq!(lines.len(), 7);
+
+    let lines = calendar_text(date, util::WeekStart::Sunday);
+    assert_eq!(lines[1], " Su  Mo  Tu  We  Th  Fr  Sa ");
+    assert_eq!(lines[2], "              1   2   3   4");
+}
@@ -4,17 +4,103 @@ use uuid;
 
 use std::{
     cmp,
+    collections::BTreeMap,
     convert::{TryFrom, TryInto},
     fmt, result,
 };
 
 use crate::{
-    core::{Durable, Error, Result, Store},
+    core::{Durable, Error, Result, Store, TypedKey},
     util,
 };
 
 pub type Key = String;
 
+// credit and debit totals within this difference are treated as equal.
+pub const BALANCE_EPSILON: f64 = 0.000_001;
+
+// default limit, in chars, for journal-entry notes, refer
+// JournalEntry::validate_note().
+pub const MAX_NOTE_LEN: usize = 10_000;
+
+// chart of accounts categories, ledger groups allowed in new workspaces.
+pub const ACCOUNT_GROUPS: [&'static str; 5] = ["asset", "liability", "equity", "income", "expense"];
+
+pub fn default_account_groups() -> Vec<String> {
+    ACCOUNT_GROUPS.iter().map(|g| g.to_string()).collect()
+}
+
+// default limit, in days, for entries dated in the future.
+pub const MAX_FUTURE_DAYS: usize = 365;
+
+// bounds for journal entry dates, refer JournalEntry::check_date().
+#[derive(Clone, Debug)]
+pub struct DateCheck {
+    // entries dated further ahead than this many days are odd.
+    pub max_future_days: usize,
+    // entries dated before this are odd, default is the date the
+    // workspace was created, set it to book older entries.
+    pub not_before: Option<chrono::NaiveDate>,
+    // reject entries with odd dates, else warn.
+    pub reject: bool,
+}
+
+impl Default for DateCheck {
+    fn default() -> DateCheck {
+        DateCheck {
+            max_future_days: MAX_FUTURE_DAYS,
+            not_before: None,
+            reject: false,
+        }
+    }
+}
+
+fn check_note_len(note: &str, limit: usize) -> Result<()> {
+    let n = note.chars().count();
+    if n > limit {
+        err_at!(InvalidInput, msg: format!("note length {} exceeds {}", n, limit))
+    } else {
+        Ok(())
+    }
+}
+
+// split note into a one-line summary, its first line, and the body that
+// follows. Blank lines separating summary from body are dropped.
+pub fn note_summary(note: &str) -> (String, String) {
+    let note = note.trim_start_matches(|ch| ch == '\r' || ch == '\n');
+    match note.find('\n') {
+        Some(n) => {
+            let summary = note[..n].trim_end().to_string();
+            let body = note[(n + 1)..].trim_start_matches(|ch| ch == '\r' || ch == '\n');
+            (summary, body.trim_end().to_string())
+        }
+        None => (note.trim_end().to_string(), String::default()),
+    }
+}
+
+// names are joined with `-` into keys, percent-escape `-` within a name,
+// and `%` itself, so that keys split back into the same names. Names
+// without either are unchanged, as are their keys and file names.
+fn escape_key_part(name: &str) -> String {
+    let mut s = String::with_capacity(name.len());
+    for ch in name.chars() {
+        match ch {
+            '%' => s.push_str("%25"),
+            '-' => s.push_str("%2D"),
+            ch => s.push(ch),
+        }
+    }
+    s
+}
+
+fn unescape_key_part(part: &str) -> String {
+    part.replace("%2D", "-").replace("%25", "%")
+}
+
+// on-disk format version for workspaces created by this build, refer
+// db_files::Migration for upgrading older workspaces.
+pub const WORKSPACE_VERSION: u64 = 6;
+
 #[derive(Clone, JsonSerialize)]
 pub struct Workspace {
     pub doc_type: String,
@@ -24,6 +110,35 @@ pub struct Workspace {
     pub commodity: Key,
     pub remotes: Vec<String>,
     pub txn_uuid: u128,
+    pub version: u64,
+    // ledger key pre-filled as the first credit leg of new entries, empty
+    // if not configured.
+    pub default_ledger: Key,
+    // chart of accounts, groups allowed for ledgers in this workspace.
+    pub account_groups: Vec<String>,
+    // refuse to commit while journal entries changed since the last
+    // commit are unbalanced.
+    pub strict_balance: bool,
+    // journal entries are not expected before this, refer
+    // JournalEntry::check_date().
+    #[json(to_string)]
+    pub created: chrono::DateTime<chrono::Utc>,
+    // layout of journal entries on disk, refer db_files::JournalLayout.
+    pub journal_layout: String,
+}
+
+// fields added to workspace.json by each version, with the value they
+// decode to for workspaces persisted before that version.
+fn workspace_fields(jval: &Json) -> Vec<(u64, &'static str, Json)> {
+    let created = jval.get("/updated").unwrap_or(Json::Null);
+    vec![
+        (1, "version", 0_u64.into()),
+        (2, "default_ledger", String::default().into()),
+        (3, "account_groups", default_account_groups().into()),
+        (4, "strict_balance", false.into()),
+        (5, "created", created),
+        (6, "journal_layout", "files".to_string().into()),
+    ]
 }
 
 // TryFrom<(name, commodity-key, remotes)>
@@ -63,6 +178,12 @@ impl TryFrom<(String, String, String)> for Workspace {
             commodity,
             remotes,
             txn_uuid: Default::default(),
+            version: WORKSPACE_VERSION,
+            default_ledger: Default::default(),
+            account_groups: default_account_groups(),
+            strict_balance: false,
+            created: chrono::Utc::now(),
+            journal_layout: "files".to_string(),
         };
         w.doc_type = w.to_type();
 
@@ -79,6 +200,12 @@ impl Default for Workspace {
             commodity: Default::default(),
             remotes: Default::default(),
             txn_uuid: Default::default(),
+            version: WORKSPACE_VERSION,
+            default_ledger: Default::default(),
+            account_groups: default_account_groups(),
+            strict_balance: false,
+            created: chrono::Utc::now(),
+            journal_layout: "files".to_string(),
         };
         w.doc_type = w.to_type();
 
@@ -107,6 +234,36 @@ impl Workspace {
         self.txn_uuid = uuid;
         self
     }
+
+    pub fn set_account_groups(&mut self, groups: Vec<String>) -> &mut Self {
+        self.account_groups = groups;
+        self
+    }
+
+    pub fn set_strict_balance(&mut self, strict: bool) -> &mut Self {
+        self.strict_balance = strict;
+        self
+    }
+
+    pub fn set_created(&mut self, created: chrono::DateTime<chrono::Utc>) -> &mut Self {
+        self.created = created;
+        self
+    }
+
+    // chosen when the workspace is created, entries put under one layout
+    // are not visible under the other.
+    pub fn set_journal_layout(&mut self, layout: &str) -> &mut Self {
+        self.journal_layout = layout.to_string();
+        self
+    }
+
+    pub fn set_default_ledger(&mut self, ledger: Option<KeyLedger>) -> &mut Self {
+        self.default_ledger = match ledger {
+            Some(ledger) => ledger.to_string(),
+            None => Default::default(),
+        };
+        self
+    }
 }
 
 impl Workspace {
@@ -124,6 +281,46 @@ impl Workspace {
     fn to_txn_uuid(&mut self) -> u128 {
         self.txn_uuid
     }
+
+    // return the name of the workspace's base commodity.
+    pub fn to_base_commodity(&self) -> String {
+        let key = self.commodity.as_str();
+        unescape_key_part(key.trim_start_matches("commodity-"))
+    }
+
+    pub fn to_default_ledger(&self) -> Option<KeyLedger> {
+        match self.default_ledger.as_str() {
+            "" => None,
+            key => Some(KeyLedger(key.to_string())),
+        }
+    }
+
+    // ledger groups must be in the workspace's chart of accounts.
+    pub fn check_groups(&self, groups: &[String]) -> Result<()> {
+        match groups.iter().find(|g| !self.account_groups.contains(g)) {
+            Some(group) => {
+                let msg = format!("group {:?} not in chart of accounts", group);
+                err_at!(InvalidInput, msg: msg)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // encode in the shape persisted by `version`, to test decoding and
+    // migrating older workspaces.
+    #[cfg(test)]
+    pub(crate) fn encode_as(&self, version: u64) -> Result<String> {
+        let mut jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
+        for (since, field, _) in workspace_fields(&jval).into_iter() {
+            if since > version {
+                err_at!(ConvertFail, jval.delete(&format!("/{}", field)))?;
+            }
+        }
+        if version > 0 {
+            err_at!(ConvertFail, jval.set("/version", version.into()))?;
+        }
+        Ok(jval.to_string())
+    }
 }
 
 impl Durable for Workspace {
@@ -135,13 +332,37 @@ impl Durable for Workspace {
         self.to_type()
     }
 
+    // EG: `personal inr`, name followed by the base commodity.
+    fn summary(&self) -> String {
+        format!("{} {}", self.name, self.to_base_commodity())
+    }
+
     fn encode(&self) -> Result<String> {
         let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
         Ok(jval.to_string())
     }
 
+    // fields missing in older workspaces are filled in, as of the version
+    // they were persisted with, those persisted before versioning as
+    // version 0.
     fn decode(&mut self, from: &str) -> Result<()> {
-        let jval: Json = err_at!(InvalidJson, from.parse())?;
+        let mut jval: Json = err_at!(InvalidJson, from.parse())?;
+        let version = match jval.get("/version") {
+            Ok(version) => match version.to_integer() {
+                Some(n) if n >= 0 => n as u64,
+                _ => err_at!(InvalidJson, msg: format!("workspace version {}", version))?,
+            },
+            Err(_) => 0,
+        };
+        if version > WORKSPACE_VERSION {
+            let msg = format!("workspace version {} newer than {}", version, WORKSPACE_VERSION);
+            err_at!(InvalidFile, msg: msg)?
+        }
+        for (since, field, value) in workspace_fields(&jval).into_iter() {
+            if since > version {
+                err_at!(InvalidJson, jval.set(&format!("/{}", field), value))?;
+            }
+        }
         *self = err_at!(InvalidJson, jval.try_into())?;
         Ok(())
     }
@@ -153,14 +374,14 @@ pub struct KeyCommodity(String);
 // (commodity-name,)
 impl From<(String,)> for KeyCommodity {
     fn from((name,): (String,)) -> KeyCommodity {
-        KeyCommodity(format!("commodity-{}", name))
+        KeyCommodity(format!("commodity-{}", escape_key_part(&name)))
     }
 }
 
 impl From<KeyCommodity> for (String,) {
     fn from(ck: KeyCommodity) -> (String,) {
         match ck.0.split('-').collect::<Vec<&str>>().as_slice() {
-            ["commodity", name] => (name.to_string(),),
+            ["commodity", name] => (unescape_key_part(name),),
             _ => unreachable!(),
         }
     }
@@ -178,6 +399,10 @@ impl From<Json> for KeyCommodity {
     }
 }
 
+impl TypedKey for KeyCommodity {
+    type Value = Commodity;
+}
+
 impl fmt::Display for KeyCommodity {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         write!(f, "{}", self.0)
@@ -292,6 +517,12 @@ impl TryFrom<(String, String, String, String, String)> for Commodity {
 }
 
 impl Commodity {
+    // value labelled with commodity's code or symbol, refer
+    // util::AmountFormat.
+    pub fn to_display(&self, fmt: &util::AmountFormat) -> String {
+        fmt.format_commodity(self.value, &self.name, &self.symbol)
+    }
+
     fn new(name: String, value: f64) -> Commodity {
         let mut c = Commodity {
             doc_type: Default::default(),
@@ -319,6 +550,12 @@ impl Durable for Commodity {
         ck.to_string()
     }
 
+    // EG: `usd 75.50 $`, name, value and symbol if any.
+    fn summary(&self) -> String {
+        let s = format!("{} {} {}", self.name, util::format_amount(self.value), self.symbol);
+        s.trim_end().to_string()
+    }
+
     fn encode(&self) -> Result<String> {
         let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
         Ok(jval.to_string())
@@ -337,14 +574,14 @@ pub struct KeyCompany(String);
 // (company-name,)
 impl From<(String,)> for KeyCompany {
     fn from((name,): (String,)) -> KeyCompany {
-        KeyCompany(format!("company-{}", name))
+        KeyCompany(format!("company-{}", escape_key_part(&name)))
     }
 }
 
 impl From<KeyCompany> for (String,) {
     fn from(ck: KeyCompany) -> (String,) {
         match ck.0.split('-').collect::<Vec<&str>>().as_slice() {
-            ["company", name] => (name.to_string(),),
+            ["company", name] => (unescape_key_part(name),),
             _ => unreachable!(),
         }
     }
@@ -362,6 +599,10 @@ impl From<Json> for KeyCompany {
     }
 }
 
+impl TypedKey for KeyCompany {
+    type Value = Company;
+}
+
 impl fmt::Display for KeyCompany {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         write!(f, "{}", self.0)
@@ -493,6 +734,10 @@ impl Durable for Company {
         ck.to_string()
     }
 
+    fn summary(&self) -> String {
+        self.name.clone()
+    }
+
     fn encode(&self) -> Result<String> {
         let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
         Ok(jval.to_string())
@@ -505,12 +750,13 @@ impl Durable for Company {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct KeyLedger(String);
 
 // (company-name, ledger-name)
 impl From<(String, String)> for KeyLedger {
     fn from((cname, lname): (String, String)) -> KeyLedger {
+        let (cname, lname) = (escape_key_part(&cname), escape_key_part(&lname));
         KeyLedger(format!("ledger-{}-{}", cname, lname))
     }
 }
@@ -518,7 +764,7 @@ impl From<(String, String)> for KeyLedger {
 impl From<KeyLedger> for (String, String) {
     fn from(ck: KeyLedger) -> (String, String) {
         match ck.0.split('-').collect::<Vec<&str>>().as_slice() {
-            ["ledger", cname, lname] => (cname.to_string(), lname.to_string()),
+            ["ledger", cname, lname] => (unescape_key_part(cname), unescape_key_part(lname)),
             _ => unreachable!(),
         }
     }
@@ -536,6 +782,10 @@ impl From<Json> for KeyLedger {
     }
 }
 
+impl TypedKey for KeyLedger {
+    type Value = Ledger;
+}
+
 impl fmt::Display for KeyLedger {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         write!(f, "{}", self.0)
@@ -556,6 +806,42 @@ pub struct Ledger {
     pub aliases: Vec<String>,
     pub tags: Vec<String>,
     pub note: String,
+    // numeric account code, EG: `1000` for cash, empty if not assigned.
+    pub code: String,
+}
+
+// ledger as persisted before account codes.
+#[derive(Clone, JsonSerialize)]
+pub(crate) struct LedgerV0 {
+    pub doc_type: String,
+    pub name: String,
+    #[json(to_string)]
+    pub created: chrono::DateTime<chrono::Utc>,
+    #[json(to_string)]
+    pub updated: chrono::DateTime<chrono::Utc>,
+    pub company: Key,
+
+    pub groups: Vec<String>,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    pub note: String,
+}
+
+impl From<LedgerV0> for Ledger {
+    fn from(l: LedgerV0) -> Ledger {
+        Ledger {
+            doc_type: l.doc_type,
+            name: l.name,
+            created: l.created,
+            updated: l.updated,
+            company: l.company,
+            groups: l.groups,
+            aliases: l.aliases,
+            tags: l.tags,
+            note: l.note,
+            code: Default::default(),
+        }
+    }
 }
 
 impl Default for Ledger {
@@ -570,6 +856,7 @@ impl Default for Ledger {
             aliases: Default::default(),
             tags: Default::default(),
             note: Default::default(),
+            code: Default::default(),
         };
         l.doc_type = l.to_type();
 
@@ -621,6 +908,7 @@ impl TryFrom<(String, String, String, String, String, String, String)> for Ledge
                 .map_err(|_| err.clone())?
                 .into_iter()
                 .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
                 .collect();
             for group in groups.iter() {
                 let group = group.trim().to_string();
@@ -671,6 +959,7 @@ impl TryFrom<(String, String, String, String, String, String, String)> for Ledge
             aliases,
             tags,
             note,
+            code: Default::default(),
         };
         l.doc_type = l.to_type();
 
@@ -690,11 +979,31 @@ impl Ledger {
             aliases: Default::default(),
             tags: Default::default(),
             note: Default::default(),
+            code: Default::default(),
         };
         l.doc_type = l.to_type();
 
         l
     }
+
+    // assign an account code, digits only, None to clear it.
+    pub fn set_code(&mut self, code: Option<&str>) -> Result<&mut Self> {
+        self.code = match code.map(|c| c.trim()) {
+            Some(code) if code.is_empty() || !code.chars().all(|ch| ch.is_ascii_digit()) => {
+                err_at!(InvalidInput, msg: format!("account code {:?} not numeric", code))?
+            }
+            Some(code) => code.to_string(),
+            None => Default::default(),
+        };
+        Ok(self)
+    }
+
+    pub fn to_code(&self) -> Option<String> {
+        match self.code.as_str() {
+            "" => None,
+            code => Some(code.to_string()),
+        }
+    }
 }
 
 impl Durable for Ledger {
@@ -707,6 +1016,117 @@ impl Durable for Ledger {
         lk.to_string()
     }
 
+    // EG: `acme:cash asset`, account followed by its groups.
+    fn summary(&self) -> String {
+        let s = format!("{}:{} {}", self.company, self.name, self.groups.join(","));
+        s.trim_end().to_string()
+    }
+
+    fn encode(&self) -> Result<String> {
+        let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
+        Ok(jval.to_string())
+    }
+
+    // ledgers persisted before account codes, told apart by the missing
+    // code, decode without a code.
+    fn decode(&mut self, from: &str) -> Result<()> {
+        let jval: Json = err_at!(InvalidJson, from.parse())?;
+        *self = match jval.get("/code") {
+            Ok(_) => err_at!(InvalidJson, jval.try_into())?,
+            Err(_) => {
+                let l: LedgerV0 = err_at!(InvalidJson, jval.try_into())?;
+                l.into()
+            }
+        };
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyBudget(String);
+
+// (ledger-key, year, month)
+impl From<(KeyLedger, i32, u32)> for KeyBudget {
+    fn from((lk, year, month): (KeyLedger, i32, u32)) -> KeyBudget {
+        let (cname, lname): (String, String) = lk.into();
+        let (cname, lname) = (escape_key_part(&cname), escape_key_part(&lname));
+        KeyBudget(format!("budget-{}-{}-{:04}-{:02}", cname, lname, year, month))
+    }
+}
+
+impl TypedKey for KeyBudget {
+    type Value = Budget;
+}
+
+impl fmt::Display for KeyBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+// monthly budget for a ledger, amount is in base commodity.
+#[derive(Clone, JsonSerialize)]
+pub struct Budget {
+    pub doc_type: String,
+    pub ledger: Key,
+    pub year: i32,
+    pub month: u32,
+    pub amount: f64,
+    #[json(to_string)]
+    pub updated: chrono::DateTime<chrono::Utc>,
+}
+
+impl Default for Budget {
+    fn default() -> Budget {
+        let mut b = Budget {
+            doc_type: Default::default(),
+            ledger: Default::default(),
+            year: Default::default(),
+            month: Default::default(),
+            amount: Default::default(),
+            updated: chrono::Utc::now(),
+        };
+        b.doc_type = b.to_type();
+
+        b
+    }
+}
+
+impl Budget {
+    pub fn new(ledger: KeyLedger, (year, month): (i32, u32), amount: f64) -> Result<Budget> {
+        if month < 1 || month > 12 {
+            err_at!(InvalidInput, msg: format!("budget month {}", month))?;
+        }
+        let mut b: Budget = Default::default();
+        b.ledger = ledger.to_string();
+        b.year = year;
+        b.month = month;
+        b.amount = amount;
+        Ok(b)
+    }
+
+    pub fn to_ledger(&self) -> KeyLedger {
+        KeyLedger(self.ledger.clone())
+    }
+}
+
+impl Durable for Budget {
+    fn to_type(&self) -> String {
+        "budget".to_string()
+    }
+
+    fn to_key(&self) -> String {
+        let bk: KeyBudget = (self.to_ledger(), self.year, self.month).into();
+        bk.to_string()
+    }
+
+    // EG: `acme:food 2020-06 500.00`.
+    fn summary(&self) -> String {
+        let (cname, lname): (String, String) = self.to_ledger().into();
+        let amount = util::format_amount(self.amount);
+        format!("{}:{} {}-{:02} {}", cname, lname, self.year, self.month, amount)
+    }
+
     fn encode(&self) -> Result<String> {
         let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
         Ok(jval.to_string())
@@ -719,6 +1139,393 @@ impl Durable for Ledger {
     }
 }
 
+#[derive(Clone)]
+pub struct KeyReport(String);
+
+// (report-name, generated)
+impl From<(String, chrono::DateTime<chrono::Utc>)> for KeyReport {
+    fn from((name, generated): (String, chrono::DateTime<chrono::Utc>)) -> KeyReport {
+        KeyReport(format!("report-{}-{}", name, generated.format("%Y%m%d%H%M%S")))
+    }
+}
+
+impl TypedKey for KeyReport {
+    type Value = Report;
+}
+
+impl fmt::Display for KeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+// generated report, its parameters and rendered text, cached so that
+// expensive reports can be revisited.
+#[derive(Clone, JsonSerialize)]
+pub struct Report {
+    pub doc_type: String,
+    pub name: String,
+    #[json(to_string)]
+    pub from: chrono::DateTime<chrono::Utc>,
+    #[json(to_string)]
+    pub to: chrono::DateTime<chrono::Utc>,
+    #[json(to_string)]
+    pub generated: chrono::DateTime<chrono::Utc>,
+    pub output: String,
+}
+
+impl Default for Report {
+    fn default() -> Report {
+        let now = chrono::Utc::now();
+        let mut r = Report {
+            doc_type: Default::default(),
+            name: Default::default(),
+            from: now.clone(),
+            to: now.clone(),
+            generated: now,
+            output: Default::default(),
+        };
+        r.doc_type = r.to_type();
+
+        r
+    }
+}
+
+impl Report {
+    pub fn new(
+        name: String,
+        (from, to): (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+        output: String,
+    ) -> Report {
+        let mut r: Report = Default::default();
+        r.name = name;
+        r.from = from;
+        r.to = to;
+        r.output = output;
+        r
+    }
+}
+
+impl Durable for Report {
+    fn to_type(&self) -> String {
+        "report".to_string()
+    }
+
+    fn to_key(&self) -> String {
+        let rk: KeyReport = (self.name.clone(), self.generated.clone()).into();
+        rk.to_string()
+    }
+
+    // EG: `balance 2020-01-01..2020-12-31`.
+    fn summary(&self) -> String {
+        let (from, to) = (self.from.format("%Y-%m-%d"), self.to.format("%Y-%m-%d"));
+        format!("{} {}..{}", self.name, from, to)
+    }
+
+    fn encode(&self) -> Result<String> {
+        let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
+        Ok(jval.to_string())
+    }
+
+    fn decode(&mut self, from: &str) -> Result<()> {
+        let jval: Json = err_at!(InvalidJson, from.parse())?;
+        *self = err_at!(InvalidJson, jval.try_into())?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyTemplate(String);
+
+// (template-name,)
+impl From<(String,)> for KeyTemplate {
+    fn from((name,): (String,)) -> KeyTemplate {
+        KeyTemplate(format!("template-{}", name))
+    }
+}
+
+impl TypedKey for KeyTemplate {
+    type Value = Template;
+}
+
+impl fmt::Display for KeyTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+// named recurring transaction, captured from a journal entry and
+// instantiated later as a fresh entry with the same legs. Payee, tags
+// and note can hold placeholders like `{amount}`, substituted on
+// instantiation, refer Template::instantiate().
+#[derive(Clone, JsonSerialize)]
+pub struct Template {
+    pub doc_type: String,
+    pub name: String,
+    pub payee: String,
+    pub creditors: Vec<Creditor>,
+    pub debitors: Vec<Debitor>,
+    pub tags: Vec<String>,
+    pub note: String,
+    // placeholder for each leg's amount, creditors followed by debitors,
+    // empty for legs with a fixed amount.
+    pub amounts: Vec<String>,
+}
+
+// template as persisted before placeholders.
+#[derive(Clone, JsonSerialize)]
+pub(crate) struct TemplateV0 {
+    pub doc_type: String,
+    pub name: String,
+    pub payee: String,
+    pub creditors: Vec<Creditor>,
+    pub debitors: Vec<Debitor>,
+    pub tags: Vec<String>,
+    pub note: String,
+}
+
+impl From<TemplateV0> for Template {
+    fn from(t: TemplateV0) -> Template {
+        Template {
+            doc_type: t.doc_type,
+            name: t.name,
+            payee: t.payee,
+            creditors: t.creditors,
+            debitors: t.debitors,
+            tags: t.tags,
+            note: t.note,
+            amounts: Default::default(),
+        }
+    }
+}
+
+// placeholder setting the instantiated entry's date, EG: `{date}`.
+pub const DATE_PLACEHOLDER: &'static str = "date";
+
+// return placeholder names, without braces, in `text`, EG: `amount`
+// for `{amount}`. Names are alphanumeric or `_`.
+pub fn scan_placeholders(text: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut rest = text;
+    while let Some(n) = rest.find('{') {
+        rest = &rest[(n + 1)..];
+        match rest.find('}') {
+            Some(m) => {
+                let name = &rest[..m];
+                let ok = name.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+                if !name.is_empty() && ok {
+                    names.push(name.to_string());
+                    rest = &rest[(m + 1)..];
+                }
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+// replace placeholders in `text` with their value, placeholders without
+// a value are left as is.
+fn substitute(text: &str, values: &BTreeMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for (name, value) in values.iter() {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+impl Default for Template {
+    fn default() -> Template {
+        let mut t = Template {
+            doc_type: Default::default(),
+            name: Default::default(),
+            payee: Default::default(),
+            creditors: Default::default(),
+            debitors: Default::default(),
+            tags: Default::default(),
+            note: Default::default(),
+            amounts: Default::default(),
+        };
+        t.doc_type = t.to_type();
+
+        t
+    }
+}
+
+impl Template {
+    pub fn from_entry(name: &str, entry: &JournalEntry) -> Result<Template> {
+        let name = name.trim().to_string();
+        if name.is_empty() || util::str_as_anuh(name.as_str()) == false {
+            err_at!(InvalidInput, msg: format!("template name {:?}", name))?;
+        }
+
+        let mut t: Template = Default::default();
+        t.name = name;
+        t.payee = entry.payee.clone();
+        t.creditors = entry.creditors.clone();
+        t.debitors = entry.debitors.clone();
+        t.tags = entry.tags.clone();
+        t.note = entry.note.clone();
+        Ok(t)
+    }
+
+    // new journal entry, created at `created`, from this template.
+    pub fn to_entry(&self, created: chrono::DateTime<chrono::Utc>) -> JournalEntry {
+        let mut je = JournalEntry::new(self.payee.clone(), created);
+        je.creditors = self.creditors.clone();
+        je.debitors = self.debitors.clone();
+        je.tags = self.tags.clone();
+        je.note = self.note.clone();
+        je
+    }
+
+    // take the amount of leg `n`, creditors followed by debitors, from
+    // `placeholder` on instantiation, EG: `{amount}`.
+    pub fn set_amount_placeholder(&mut self, n: usize, placeholder: &str) -> Result<&mut Self> {
+        let n_legs = self.creditors.len() + self.debitors.len();
+        if n >= n_legs {
+            err_at!(InvalidInput, msg: format!("leg {} of {}", n, n_legs))?;
+        }
+        match scan_placeholders(placeholder).as_slice() {
+            [name] if placeholder.trim() == format!("{{{}}}", name) => (),
+            _ => err_at!(InvalidInput, msg: format!("placeholder {:?}", placeholder))?,
+        }
+
+        self.amounts.resize(n_legs, Default::default());
+        self.amounts[n] = placeholder.trim().to_string();
+        Ok(self)
+    }
+
+    // return the placeholders, without braces, in order of appearance
+    // and without duplicates.
+    pub fn to_placeholders(&self) -> Vec<String> {
+        let texts = std::iter::once(&self.payee)
+            .chain(self.amounts.iter())
+            .chain(self.tags.iter())
+            .chain(std::iter::once(&self.note));
+        let mut names: Vec<String> = vec![];
+        for name in texts.flat_map(|text| scan_placeholders(text)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    // check `value` for placeholder `name` before instantiating, amount
+    // placeholders take a number and `{date}` a date.
+    pub fn check_placeholder(&self, name: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        if name == DATE_PLACEHOLDER {
+            util::parse_date_flexible(value)?;
+        }
+        let placeholder = format!("{{{}}}", name);
+        if self.amounts.iter().any(|amount| amount == &placeholder) {
+            err_at!(
+                InvalidInput,
+                value.parse::<f64>(),
+                format!("amount {} {:?}", placeholder, value)
+            )?;
+        }
+        Ok(())
+    }
+
+    // new journal entry from this template, `prompt` is called with each
+    // placeholder's name and returns its value. Amount placeholders take
+    // a number and `{date}` a date in any util::DATE_INPUT_FORMATS, which
+    // replaces the date of `created`.
+    pub fn instantiate<F>(
+        &self,
+        created: chrono::DateTime<chrono::Utc>,
+        mut prompt: F,
+    ) -> Result<JournalEntry>
+    where
+        F: FnMut(&str) -> Result<String>,
+    {
+        let mut values = BTreeMap::new();
+        for name in self.to_placeholders().into_iter() {
+            let value = prompt(&name)?.trim().to_string();
+            values.insert(name, value);
+        }
+
+        let created = match values.get(DATE_PLACEHOLDER) {
+            Some(date) => {
+                let date = util::parse_date_flexible(date)?;
+                let time = created.time();
+                chrono::DateTime::from_utc(date.and_time(time), chrono::Utc)
+            }
+            None => created,
+        };
+
+        let mut je = self.to_entry(created);
+        je.payee = substitute(&self.payee, &values);
+        je.tags = self.tags.iter().map(|t| substitute(t, &values)).collect();
+        je.note = substitute(&self.note, &values);
+
+        let n_creditors = je.creditors.len();
+        for (n, amount) in self.amounts.iter().enumerate() {
+            if amount.is_empty() {
+                continue;
+            }
+            let text = substitute(amount, &values);
+            let value: f64 = err_at!(
+                InvalidInput,
+                text.parse(),
+                format!("amount {} {:?}", amount, text)
+            )?;
+            let leg = match n.checked_sub(n_creditors) {
+                None => je.creditors.get_mut(n).map(|c| &mut c.commodity),
+                Some(m) => je.debitors.get_mut(m).map(|d| &mut d.commodity),
+            };
+            match leg {
+                Some(commodity) => commodity.value = value,
+                None => {
+                    let msg = format!("template {} has no leg for amount {}", self.name, amount);
+                    err_at!(InvalidInput, msg: msg)?
+                }
+            }
+        }
+
+        je.validate()?;
+        Ok(je)
+    }
+}
+
+impl Durable for Template {
+    fn to_type(&self) -> String {
+        "template".to_string()
+    }
+
+    fn to_key(&self) -> String {
+        let tk: KeyTemplate = (self.name.clone(),).into();
+        tk.to_string()
+    }
+
+    // EG: `rent landlord`, name followed by the payee.
+    fn summary(&self) -> String {
+        format!("{} {}", self.name, self.payee).trim_end().to_string()
+    }
+
+    fn encode(&self) -> Result<String> {
+        let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
+        Ok(jval.to_string())
+    }
+
+    // templates persisted before placeholders, told apart by the missing
+    // amounts, decode with fixed amounts.
+    fn decode(&mut self, from: &str) -> Result<()> {
+        let jval: Json = err_at!(InvalidJson, from.parse())?;
+        *self = match jval.get("/amounts") {
+            Ok(_) => err_at!(InvalidJson, jval.try_into())?,
+            Err(_) => {
+                let t: TemplateV0 = err_at!(InvalidJson, jval.try_into())?;
+                t.into()
+            }
+        };
+        Ok(())
+    }
+}
+
 #[derive(Clone, JsonSerialize)]
 pub struct Creditor {
     pub ledger: KeyLedger,
@@ -872,6 +1679,25 @@ pub struct JournalEntry {
     pub debitors: Vec<Debitor>,
     pub tags: Vec<String>,
     pub note: String,
+    // matched against a bank statement.
+    pub reconciled: bool,
+    // append-only annotations, kept apart from the note, refer
+    // JournalEntry::add_comment().
+    pub comments: Vec<String>,
+    // file names of receipts and statements, refer
+    // JournalEntry::add_attachment().
+    pub attachments: Vec<String>,
+}
+
+// fields added to journal entries after they were first persisted, with
+// the value they decode to for entries that lack them. Journal entries
+// carry no version, older entries are told apart by the fields missing.
+fn journal_fields() -> Vec<(&'static str, Json)> {
+    vec![
+        ("reconciled", false.into()),
+        ("comments", Vec::<String>::new().into()),
+        ("attachments", Vec::<String>::new().into()),
+    ]
 }
 
 impl Eq for JournalEntry {}
@@ -906,6 +1732,9 @@ impl Default for JournalEntry {
             debitors: Default::default(),
             tags: Default::default(),
             note: Default::default(),
+            reconciled: false,
+            comments: Default::default(),
+            attachments: Default::default(),
         };
         je.doc_type = je.to_type();
 
@@ -954,6 +1783,9 @@ impl TryFrom<(u128, String, String, String, String)> for JournalEntry {
             debitors: Default::default(),
             tags,
             note,
+            reconciled: false,
+            comments: Default::default(),
+            attachments: Default::default(),
         };
         je.doc_type = je.to_type();
 
@@ -962,7 +1794,7 @@ impl TryFrom<(u128, String, String, String, String)> for JournalEntry {
 }
 
 impl JournalEntry {
-    fn new(payee: String, created: chrono::DateTime<chrono::Utc>) -> JournalEntry {
+    pub fn new(payee: String, created: chrono::DateTime<chrono::Utc>) -> JournalEntry {
         let mut je = JournalEntry {
             doc_type: Default::default(),
             uuid: uuid::Uuid::new_v4().as_u128(),
@@ -973,13 +1805,140 @@ impl JournalEntry {
             debitors: Default::default(),
             tags: Default::default(),
             note: Default::default(),
+            reconciled: false,
+            comments: Default::default(),
+            attachments: Default::default(),
         };
 
         je.doc_type = je.to_type();
         je
     }
 
-    fn add_creditor(
+    // clone this entry as a new entry, with a fresh uuid and created
+    // now. Useful for repeating transactions, which start unreconciled.
+    pub fn to_repeat(&self) -> JournalEntry {
+        let mut je = self.clone();
+        je.uuid = uuid::Uuid::new_v4().as_u128();
+        je.created = chrono::Utc::now();
+        je.updated = je.created.clone();
+        je.reconciled = false;
+        je.comments = Default::default();
+        je.attachments = Default::default();
+        je
+    }
+
+    // attach a receipt or statement by its file name, attaching the same
+    // name again is a no-op.
+    pub fn add_attachment(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(|ch| ch == '\r' || ch == '\n') {
+            err_at!(InvalidInput, msg: format!("attachment {:?}", name))?;
+        }
+        if !self.attachments.iter().any(|a| a == name) {
+            self.attachments.push(name.to_string());
+            self.updated = chrono::Utc::now();
+        }
+        Ok(())
+    }
+
+    // append a comment by `author`, the note and earlier comments are
+    // left as they are.
+    pub fn add_comment(&mut self, author: &str, text: &str) -> Result<()> {
+        self.add_comment_at(chrono::Utc::now(), author, text)
+    }
+
+    // comments are held as `<rfc3339> <author>: <text>`, EG:
+    // `2020-06-01T10:00:00+00:00 alice: checked with receipt`.
+    pub fn add_comment_at(
+        &mut self,
+        at: chrono::DateTime<chrono::Utc>,
+        author: &str,
+        text: &str,
+    ) -> Result<()> {
+        let author = author.trim();
+        if author.is_empty() || author.contains(|ch: char| ch.is_whitespace() || ch == ':') {
+            err_at!(InvalidInput, msg: format!("comment author {:?}", author))?;
+        }
+        let text = text.replace(|ch| ch == '\r' || ch == '\n', " ");
+        if text.trim().is_empty() {
+            err_at!(InvalidInput, msg: format!("empty comment"))?;
+        }
+        check_note_len(&text, MAX_NOTE_LEN)?;
+
+        let comment = format!("{} {}: {}", at.to_rfc3339(), author, text.trim());
+        self.comments.push(comment);
+        self.updated = chrono::Utc::now();
+        Ok(())
+    }
+
+    // comments as (at, author, text), oldest first. Comments that don't
+    // parse are skipped.
+    pub fn to_comments(&self) -> Vec<(chrono::DateTime<chrono::Utc>, String, String)> {
+        let mut comments = vec![];
+        for comment in self.comments.iter() {
+            let mut parts = comment.splitn(2, ' ');
+            let at = parts.next().and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok());
+            let rest = parts.next().and_then(|rest| {
+                let mut parts = rest.splitn(2, ": ");
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            });
+            if let (Some(at), Some((author, text))) = (at, rest) {
+                comments.push((at.with_timezone(&chrono::Utc), author, text));
+            }
+        }
+        comments
+    }
+
+    // warn about a likely mistyped date, more than `check.max_future_days`
+    // after `now`, or before `check.not_before` defaulting to when the
+    // workspace was created.
+    pub fn check_date(
+        &self,
+        w: &Workspace,
+        now: chrono::DateTime<chrono::Utc>,
+        check: &DateCheck,
+    ) -> Option<String> {
+        let date = self.created.date().naive_utc();
+        let days = check.max_future_days;
+        if self.created > now + chrono::Duration::days(days as i64) {
+            let date = date.format("%Y-%m-%d");
+            Some(format!("entry date {} more than {} days ahead", date, days))
+        } else {
+            let (since, what) = match check.not_before {
+                Some(since) => (since, "books start"),
+                None => (w.created.date().naive_utc(), "workspace created"),
+            };
+            if date < since {
+                let (date, since) = (date.format("%Y-%m-%d"), since.format("%Y-%m-%d"));
+                Some(format!("entry date {} before {} {}", date, what, since))
+            } else {
+                None
+            }
+        }
+    }
+
+    // same as check_date(), but fails with the warning when `check`
+    // rejects odd dates.
+    pub fn validate_date(
+        &self,
+        w: &Workspace,
+        now: chrono::DateTime<chrono::Utc>,
+        check: &DateCheck,
+    ) -> Result<Option<String>> {
+        match self.check_date(w, now, check) {
+            Some(msg) if check.reject => err_at!(InvalidDate, msg: msg),
+            warning => Ok(warning),
+        }
+    }
+
+    // flip the reconciled mark and return the new state.
+    pub fn toggle_reconciled(&mut self) -> bool {
+        self.reconciled = !self.reconciled;
+        self.updated = chrono::Utc::now();
+        self.reconciled
+    }
+
+    pub fn add_creditor(
         &mut self,
         company: String,
         ledger: String,
@@ -990,7 +1949,7 @@ impl JournalEntry {
         Ok(())
     }
 
-    fn add_debitor(
+    pub fn add_debitor(
         &mut self,
         company: String,
         ledger: String,
@@ -1000,6 +1959,153 @@ impl JournalEntry {
         self.debitors.push(debitor);
         Ok(())
     }
+
+    // build an entry from a parsed quick-entry line, legs without a
+    // commodity are booked under `commodity`.
+    pub fn from_quick_entry(
+        quick: &util::QuickEntry,
+        created: chrono::DateTime<chrono::Utc>,
+        commodity: &str,
+    ) -> Result<JournalEntry> {
+        let mut je = JournalEntry::new(quick.payee.clone(), created);
+        for leg in quick.legs.iter() {
+            let name = leg.commodity.clone().unwrap_or(commodity.to_string());
+            let (company, ledger) = (leg.company.clone(), leg.ledger.clone());
+            if leg.amount < 0.0 {
+                je.add_creditor(company, ledger, (name, leg.amount.abs()))?;
+            } else {
+                je.add_debitor(company, ledger, (name, leg.amount))?;
+            }
+        }
+        je.validate()?;
+        Ok(je)
+    }
+
+    // move the creditor leg at `index` one row up, or down, and return
+    // its new index. Legs already at either end stay put.
+    pub fn move_creditor(&mut self, index: usize, up: bool) -> Result<usize> {
+        move_leg(&mut self.creditors, index, up)
+    }
+
+    // move the debitor leg at `index` one row up, or down, and return
+    // its new index. Legs already at either end stay put.
+    pub fn move_debitor(&mut self, index: usize, up: bool) -> Result<usize> {
+        move_leg(&mut self.debitors, index, up)
+    }
+
+    // return (credit, debit) totals for each commodity in this entry.
+    pub fn to_commodity_totals(&self) -> BTreeMap<String, (f64, f64)> {
+        let mut totals: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+        for c in self.creditors.iter() {
+            let total = totals.entry(c.commodity.name.clone()).or_default();
+            total.0 += c.commodity.value;
+        }
+        for d in self.debitors.iter() {
+            let total = totals.entry(d.commodity.name.clone()).or_default();
+            total.1 += d.commodity.value;
+        }
+        totals
+    }
+
+    // an entry is balanced when, for every commodity, credits add up
+    // to debits.
+    pub fn is_balanced(&self) -> bool {
+        self.to_commodity_totals()
+            .values()
+            .all(|(credit, debit)| (credit - debit).abs() < BALANCE_EPSILON)
+    }
+
+    // fail if the note is longer than `limit` chars, the limit is
+    // configured by the application, refer MAX_NOTE_LEN.
+    pub fn validate_note(&self, limit: usize) -> Result<()> {
+        check_note_len(&self.note, limit)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        // individual zero legs are fine, as long as some leg moves value.
+        let mut values = {
+            let credits = self.creditors.iter().map(|c| c.commodity.value);
+            credits
+                .chain(self.debitors.iter().map(|d| d.commodity.value))
+                .peekable()
+        };
+        if values.peek().is_some() && values.all(|v| v.abs() < BALANCE_EPSILON) {
+            let msg = format!("entry {:?} has only zero amounts", self.payee);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+
+        for (name, (credit, debit)) in self.to_commodity_totals().into_iter() {
+            let has_credit = self.creditors.iter().any(|c| c.commodity.name == name);
+            let has_debit = self.debitors.iter().any(|d| d.commodity.name == name);
+            match (has_credit, has_debit) {
+                (true, false) => {
+                    let msg = format!("commodity {} appears only as credit", name);
+                    err_at!(InvalidInput, msg: msg)
+                }
+                (false, true) => {
+                    let msg = format!("commodity {} appears only as debit", name);
+                    err_at!(InvalidInput, msg: msg)
+                }
+                _ if (credit - debit).abs() >= BALANCE_EPSILON => {
+                    let msg = format!("commodity {} imbalance {}", name, debit - credit);
+                    err_at!(InvalidInput, msg: msg)
+                }
+                _ => Ok(()),
+            }?;
+        }
+
+        Ok(())
+    }
+
+    // absorb, for every commodity, an imbalance within `tolerance` into
+    // a rounding leg against `rounding_ledger`, then validate. Return the
+    // number of rounding legs added.
+    pub fn balance_with_rounding(
+        &mut self,
+        tolerance: f64,
+        rounding_ledger: KeyLedger,
+    ) -> Result<usize> {
+        let (company, ledger): (String, String) = rounding_ledger.into();
+
+        let mut n = 0;
+        for (name, (credit, debit)) in self.to_commodity_totals().into_iter() {
+            let diff = debit - credit;
+            if diff.abs() < BALANCE_EPSILON {
+                continue;
+            } else if diff.abs() > (tolerance + BALANCE_EPSILON) {
+                let msg = format!("commodity {} imbalance {} beyond rounding", name, diff);
+                err_at!(InvalidInput, msg: msg)?;
+            }
+
+            let commodity = (name, diff.abs());
+            if diff > 0.0 {
+                self.add_creditor(company.clone(), ledger.clone(), commodity)?;
+            } else {
+                self.add_debitor(company.clone(), ledger.clone(), commodity)?;
+            }
+            n += 1;
+        }
+        self.validate()?;
+
+        Ok(n)
+    }
+}
+
+// move the leg at `index` one row up, or down, and return its new index,
+// legs at either end stay put. Entry form's leg rows move the same way.
+pub fn move_leg<T>(legs: &mut Vec<T>, index: usize, up: bool) -> Result<usize> {
+    if index >= legs.len() {
+        err_at!(InvalidInput, msg: format!("leg {} out of {}", index, legs.len()))?;
+    }
+
+    let new_index = match up {
+        true if index > 0 => index - 1,
+        false if (index + 1) < legs.len() => index + 1,
+        _ => index,
+    };
+    legs.swap(index, new_index);
+
+    Ok(new_index)
 }
 
 impl Durable for JournalEntry {
@@ -1017,14 +2123,37 @@ impl Durable for JournalEntry {
         jek.into()
     }
 
+    // EG: `01-Jun-20 grocer 100.00 inr`, date, payee and the debit total
+    // of each commodity.
+    fn summary(&self) -> String {
+        let mut s = format!("{} {}", self.created.format("%d-%b-%y"), self.payee);
+        for (name, (_, debit)) in self.to_commodity_totals().into_iter() {
+            s.push_str(&format!(" {} {}", util::format_amount(debit), name));
+        }
+        s
+    }
+
     fn encode(&self) -> Result<String> {
         let jval: Json = err_at!(ConvertFail, self.clone().try_into())?;
         Ok(jval.to_string())
     }
 
+    // entries persisted before reconciliation decode as unreconciled,
+    // before comments and attachments without any, refer
+    // journal_fields().
     fn decode(&mut self, from: &str) -> Result<()> {
-        let jval: Json = err_at!(InvalidJson, from.parse())?;
+        let mut jval: Json = err_at!(InvalidJson, from.parse())?;
+        for (field, value) in journal_fields().into_iter() {
+            let path = format!("/{}", field);
+            if jval.get(&path).is_err() {
+                err_at!(InvalidJson, jval.set(&path, value))?;
+            }
+        }
         *self = err_at!(InvalidJson, jval.try_into())?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[path = "types_test.rs"]
+mod types_test;
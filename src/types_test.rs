@@ -0,0 +1,705 @@
+use chrono::TimeZone;
+
+use super::*;
+
+#[test]
+fn test_journal_entry_repeat() {
+    let created = chrono::Utc.ymd(2020, 1, 1).and_hms(10, 0, 0);
+    let mut je = JournalEntry::new("coffee".to_string(), created);
+    je.add_creditor(
+        "acme".to_string(),
+        "cash".to_string(),
+        ("inr".to_string(), 100.0),
+    )
+    .unwrap();
+    je.add_debitor(
+        "acme".to_string(),
+        "food".to_string(),
+        ("inr".to_string(), 100.0),
+    )
+    .unwrap();
+
+    let rep = je.to_repeat();
+    assert_ne!(rep.uuid, je.uuid);
+    assert_eq!(rep.created.date(), chrono::Utc::today());
+    assert_eq!(rep.updated, rep.created);
+    assert_eq!(rep.payee, je.payee);
+
+    assert_eq!(rep.creditors.len(), je.creditors.len());
+    for (x, y) in rep.creditors.iter().zip(je.creditors.iter()) {
+        assert_eq!(x.ledger.to_string(), y.ledger.to_string());
+        assert_eq!(x.commodity.name, y.commodity.name);
+        assert_eq!(x.commodity.value, y.commodity.value);
+    }
+    assert_eq!(rep.debitors.len(), je.debitors.len());
+    for (x, y) in rep.debitors.iter().zip(je.debitors.iter()) {
+        assert_eq!(x.ledger.to_string(), y.ledger.to_string());
+        assert_eq!(x.commodity.name, y.commodity.name);
+        assert_eq!(x.commodity.value, y.commodity.value);
+    }
+}
+
+fn make_entry(credits: &[(&str, f64)], debits: &[(&str, f64)]) -> JournalEntry {
+    let created = chrono::Utc.ymd(2020, 1, 1).and_hms(10, 0, 0);
+    let mut je = JournalEntry::new("test".to_string(), created);
+    for (name, value) in credits.iter() {
+        let comm = (name.to_string(), *value);
+        je.add_creditor("acme".to_string(), "cash".to_string(), comm)
+            .unwrap();
+    }
+    for (name, value) in debits.iter() {
+        let comm = (name.to_string(), *value);
+        je.add_debitor("acme".to_string(), "bank".to_string(), comm)
+            .unwrap();
+    }
+    je
+}
+
+#[test]
+fn test_journal_entry_validate() {
+    let je = make_entry(&[("inr", 100.0)], &[("inr", 60.0), ("inr", 40.0)]);
+    assert!(je.is_balanced());
+    assert!(je.validate().is_ok());
+
+    let je = make_entry(&[("inr", 100.0)], &[("inr", 60.0)]);
+    assert!(!je.is_balanced());
+    match je.validate() {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("imbalance"), "{}", msg),
+        _ => panic!("expected imbalance"),
+    }
+
+    let je = make_entry(&[("usd", 100.0)], &[("inr", 100.0)]);
+    assert!(!je.is_balanced());
+    match je.validate() {
+        Err(Error::InvalidInput(msg)) => {
+            assert!(msg.contains("commodity inr appears only as debit"), "{}", msg)
+        }
+        _ => panic!("expected one-sided commodity"),
+    }
+
+    let je = make_entry(&[("usd", 100.0), ("inr", 10.0)], &[("inr", 10.0)]);
+    match je.validate() {
+        Err(Error::InvalidInput(msg)) => {
+            assert!(msg.contains("commodity usd appears only as credit"), "{}", msg)
+        }
+        _ => panic!("expected one-sided commodity"),
+    }
+}
+
+#[test]
+fn test_journal_entry_zero_amounts() {
+    let je = make_entry(&[("inr", 0.0)], &[("inr", 0.0), ("inr", 0.0)]);
+    assert!(je.is_balanced());
+    match je.validate() {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("zero amounts"), "{}", msg),
+        _ => panic!("expected all-zero entry to fail"),
+    }
+
+    // one zero leg, alongside legs that move value.
+    let je = make_entry(&[("inr", 100.0)], &[("inr", 100.0), ("inr", 0.0)]);
+    assert!(je.validate().is_ok());
+}
+
+#[test]
+fn test_journal_entry_balance_with_rounding() {
+    let rounding: KeyLedger = ("acme".to_string(), "rounding".to_string()).into();
+
+    // converted legs leave a cent short on the debit side.
+    let mut je = make_entry(&[("inr", 100.0)], &[("inr", 33.33), ("inr", 66.66)]);
+    assert!(!je.is_balanced());
+    assert_eq!(je.balance_with_rounding(0.01, rounding.clone()).unwrap(), 1);
+    assert!(je.is_balanced());
+    assert_eq!(je.debitors.len(), 3);
+    let leg = je.debitors.last().unwrap();
+    assert_eq!(leg.ledger.to_string(), rounding.to_string());
+    assert!((leg.commodity.value - 0.01).abs() < BALANCE_EPSILON);
+
+    let mut je = make_entry(&[("inr", 99.99)], &[("inr", 50.0), ("inr", 50.0)]);
+    assert_eq!(je.balance_with_rounding(0.01, rounding.clone()).unwrap(), 1);
+    assert!(je.is_balanced());
+    let leg = je.creditors.last().unwrap();
+    assert_eq!(leg.ledger.to_string(), rounding.to_string());
+
+    // already balanced, nothing to absorb.
+    let mut je = make_entry(&[("inr", 100.0)], &[("inr", 60.0), ("inr", 40.0)]);
+    assert_eq!(je.balance_with_rounding(0.01, rounding.clone()).unwrap(), 0);
+    assert_eq!(je.debitors.len(), 2);
+
+    let mut je = make_entry(&[("inr", 100.0)], &[("inr", 60.0)]);
+    match je.balance_with_rounding(0.01, rounding.clone()) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("beyond rounding"), "{}", msg),
+        _ => panic!("expected imbalance beyond rounding"),
+    }
+}
+
+#[test]
+fn test_journal_entry_note_limit() {
+    let make = |note: String| -> Result<JournalEntry> {
+        let created = "2020-01-01T10:00:00Z".to_string();
+        let tags = "".to_string();
+        (1_u128, "test".to_string(), created, tags, note).try_into()
+    };
+
+    // limit is in chars, not bytes.
+    let note: String = std::iter::repeat('₹').take(MAX_NOTE_LEN).collect();
+    let mut je = make(note.clone()).unwrap();
+    assert!(je.validate_note(MAX_NOTE_LEN).is_ok());
+
+    je.note = note + "x";
+    match je.validate_note(MAX_NOTE_LEN) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("note length"), "{}", msg),
+        _ => panic!("expected note too long"),
+    }
+    // the limit is configured by the caller.
+    assert!(je.validate_note(MAX_NOTE_LEN + 1).is_ok());
+    assert!(je.validate_note(10).is_err());
+}
+
+#[test]
+fn test_journal_entry_move_leg() {
+    let mut je = make_entry(&[], &[("inr", 10.0), ("inr", 20.0), ("inr", 30.0)]);
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("inr".to_string(), 60.0))
+        .unwrap();
+    let values = |je: &JournalEntry| -> Vec<f64> {
+        je.debitors.iter().map(|d| d.commodity.value).collect()
+    };
+
+    // top and bottom legs stay put.
+    assert_eq!(je.move_debitor(0, true).unwrap(), 0);
+    assert_eq!(je.move_debitor(2, false).unwrap(), 2);
+    assert_eq!(values(&je), vec![10.0, 20.0, 30.0]);
+
+    // middle leg moves both ways.
+    assert_eq!(je.move_debitor(1, true).unwrap(), 0);
+    assert_eq!(values(&je), vec![20.0, 10.0, 30.0]);
+    assert_eq!(je.move_debitor(1, false).unwrap(), 2);
+    assert_eq!(je.move_debitor(2, false).unwrap(), 2);
+    assert_eq!(values(&je), vec![20.0, 30.0, 10.0]);
+
+    // ordering does not affect balance and survives encoding.
+    assert!(je.validate().is_ok());
+    let mut out: JournalEntry = Default::default();
+    out.decode(&je.encode().unwrap()).unwrap();
+    assert_eq!(values(&out), vec![20.0, 30.0, 10.0]);
+
+    assert_eq!(je.move_creditor(0, true).unwrap(), 0);
+    assert!(je.move_creditor(1, true).is_err());
+    assert!(je.move_debitor(3, false).is_err());
+}
+
+#[test]
+fn test_get_by_typed_key() {
+    let mut store = crate::mem_store::testing::make_store();
+    let value: Commodity = ("inr".to_string(), 10.0).into();
+    store.put(value).unwrap();
+    store
+        .put(Company {
+            name: "acme".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    store
+        .put(Ledger {
+            name: "cash".to_string(),
+            company: "acme".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // value type follows from the key type, a KeyCommodity can only
+    // fetch a Commodity, `get_by::<KeyCommodity>` into a Company won't
+    // compile.
+    let ck: KeyCommodity = ("inr".to_string(),).into();
+    let value: Commodity = store.get_by(&ck).unwrap();
+    assert_eq!(value.value, 10.0);
+
+    let ck: KeyCompany = ("acme".to_string(),).into();
+    assert_eq!(store.get_by(&ck).unwrap().name, "acme");
+
+    let lk: KeyLedger = ("acme".to_string(), "cash".to_string()).into();
+    assert_eq!(store.get_by(&lk).unwrap().company, "acme");
+
+    let ck: KeyCommodity = ("usd".to_string(),).into();
+    match store.get_by(&ck) {
+        Err(Error::KeyNotFound(_)) => (),
+        _ => panic!("expected key not found"),
+    }
+}
+
+#[test]
+fn test_workspace_default_ledger() {
+    let mut w = Workspace::new("test".to_string());
+    assert!(w.to_default_ledger().is_none());
+
+    let lk: KeyLedger = ("acme".to_string(), "checking".to_string()).into();
+    w.set_default_ledger(Some(lk.clone()));
+
+    let mut out: Workspace = Default::default();
+    out.decode(&w.encode().unwrap()).unwrap();
+    assert_eq!(out.to_default_ledger().unwrap().to_string(), lk.to_string());
+
+    // version 1 workspaces decode with no default ledger.
+    let mut out: Workspace = Default::default();
+    out.decode(&w.encode_as(1).unwrap()).unwrap();
+    assert_eq!(out.version, 1);
+    assert!(out.to_default_ledger().is_none());
+}
+
+#[test]
+fn test_ledger_account_groups() {
+    let make_ledger = |groups: &str| -> Result<Ledger> {
+        (
+            "cash".to_string(),
+            "2020-01-01T00:00:00Z".to_string(),
+            "acme".to_string(),
+            groups.to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )
+            .try_into()
+    };
+
+    let ledger = make_ledger("asset").unwrap();
+    assert_eq!(ledger.groups, vec!["asset".to_string()]);
+    assert!(make_ledger("").unwrap().groups.is_empty());
+
+    // groups are checked against the workspace's chart of accounts.
+    let mut w = Workspace::new("test".to_string());
+    w.check_groups(&ledger.groups).unwrap();
+    match w.check_groups(&make_ledger("asset,petty").unwrap().groups) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("petty"), "{}", msg),
+        _ => panic!("expected unknown group"),
+    }
+
+    w.set_account_groups(vec!["asset".to_string(), "cash".to_string()]);
+    let mut out: Workspace = Default::default();
+    out.decode(&w.encode().unwrap()).unwrap();
+    assert_eq!(out.account_groups, w.account_groups);
+
+    // version 2 workspaces decode with the default chart of accounts.
+    out.decode(&w.encode_as(2).unwrap()).unwrap();
+    assert_eq!(out.version, 2);
+    assert_eq!(out.account_groups, default_account_groups());
+}
+
+#[test]
+fn test_workspace_strict_balance() {
+    let mut w = Workspace::new("test".to_string());
+    w.set_strict_balance(true);
+    let mut out: Workspace = Default::default();
+    out.decode(&w.encode().unwrap()).unwrap();
+    assert!(out.strict_balance);
+
+    // version 3 workspaces decode with strict balance disabled.
+    out.decode(&w.encode_as(3).unwrap()).unwrap();
+    assert_eq!(out.version, 3);
+    assert!(!out.strict_balance);
+}
+
+#[test]
+fn test_note_summary() {
+    let split = |note: &str| -> (String, String) { note_summary(note) };
+    let pair = |s: &str, b: &str| (s.to_string(), b.to_string());
+
+    assert_eq!(split(""), pair("", ""));
+    assert_eq!(split("groceries"), pair("groceries", ""));
+    assert_eq!(split("groceries\n"), pair("groceries", ""));
+    assert_eq!(
+        split("groceries\nmilk, eggs\nbread"),
+        pair("groceries", "milk, eggs\nbread")
+    );
+    assert_eq!(
+        split("groceries\r\n\r\nmilk, eggs\n"),
+        pair("groceries", "milk, eggs")
+    );
+    assert_eq!(split("\n\ngroceries\nmilk"), pair("groceries", "milk"));
+}
+
+#[test]
+fn test_journal_entry_reconciled() {
+    let mut je = make_entry(&[("inr", 100.0)], &[("inr", 100.0)]);
+    assert!(!je.reconciled);
+    assert!(je.toggle_reconciled());
+    assert!(!je.to_repeat().reconciled);
+
+    let mut out: JournalEntry = Default::default();
+    out.decode(&je.encode().unwrap()).unwrap();
+    assert!(out.reconciled);
+    assert!(!out.toggle_reconciled());
+
+    // legacy entries, without the flag, decode as unreconciled.
+    let mut jval: Json = je.clone().try_into().unwrap();
+    for field in ["reconciled", "comments", "attachments"].iter() {
+        jval.delete(&format!("/{}", field)).unwrap();
+    }
+    let mut out: JournalEntry = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert_eq!(out.uuid, je.uuid);
+    assert!(!out.reconciled);
+}
+
+#[test]
+fn test_journal_entry_comments() {
+    let mut je = make_entry(&[("inr", 100.0)], &[("inr", 100.0)]);
+    je.note = "monthly groceries".to_string();
+
+    let at = chrono::Utc.ymd(2020, 6, 1).and_hms(10, 0, 0);
+    je.add_comment_at(at, "alice", "checked with\nreceipt").unwrap();
+    je.add_comment_at(at, "bob", "split: 60/40").unwrap();
+    assert!(je.add_comment_at(at, "", "no author").is_err());
+    assert!(je.add_comment_at(at, "carol smith", "spaced author").is_err());
+    assert!(je.add_comment_at(at, "carol", "  ").is_err());
+    assert_eq!(
+        je.comments,
+        vec![
+            "2020-06-01T10:00:00+00:00 alice: checked with receipt".to_string(),
+            "2020-06-01T10:00:00+00:00 bob: split: 60/40".to_string(),
+        ]
+    );
+    assert_eq!(je.note, "monthly groceries");
+    assert!(je.to_repeat().comments.is_empty());
+
+    let mut out: JournalEntry = Default::default();
+    out.decode(&je.encode().unwrap()).unwrap();
+    let comments = out.to_comments();
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[0], (at, "alice".to_string(), "checked with receipt".to_string()));
+    assert_eq!(comments[1].1, "bob");
+    assert_eq!(comments[1].2, "split: 60/40");
+
+    // entries persisted before comments decode without any.
+    let mut jval: Json = je.clone().try_into().unwrap();
+    jval.set("/reconciled", true.into()).unwrap();
+    jval.delete("/comments").unwrap();
+    jval.delete("/attachments").unwrap();
+    let mut out: JournalEntry = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert_eq!(out.uuid, je.uuid);
+    assert!(out.reconciled);
+    assert!(out.comments.is_empty());
+    assert_eq!(out.note, "monthly groceries");
+
+    // entries persisted before attachments decode without any.
+    let mut jval: Json = je.clone().try_into().unwrap();
+    jval.delete("/attachments").unwrap();
+    let mut out: JournalEntry = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert_eq!(out.comments.len(), 2);
+    assert!(out.attachments.is_empty());
+}
+
+#[test]
+fn test_journal_entry_attachments() {
+    let mut je = JournalEntry::new("grocer".to_string(), chrono::Utc::now());
+    je.add_attachment(" receipt.pdf ").unwrap();
+    je.add_attachment("receipt.pdf").unwrap();
+    assert_eq!(je.attachments, vec!["receipt.pdf".to_string()]);
+    assert!(je.add_attachment("  ").is_err());
+    assert!(je.add_attachment("a\nb").is_err());
+
+    let mut out: JournalEntry = Default::default();
+    out.decode(&je.encode().unwrap()).unwrap();
+    assert_eq!(out.attachments, je.attachments);
+    // repeats don't carry the receipts of the original.
+    assert!(je.to_repeat().attachments.is_empty());
+}
+
+#[test]
+fn test_journal_entry_check_date() {
+    let mut w = Workspace::new("test".to_string());
+    w.set_created(chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0));
+    let now = chrono::Utc.ymd(2024, 6, 1).and_hms(9, 0, 0);
+    let mut check = DateCheck::default();
+
+    let normal = JournalEntry::new("grocer".to_string(), now);
+    assert_eq!(normal.check_date(&w, now, &check), None);
+    let ahead = JournalEntry::new("rent".to_string(), chrono::Utc.ymd(2024, 12, 1).and_hms(9, 0, 0));
+    assert_eq!(ahead.check_date(&w, now, &check), None);
+
+    // year mistyped as 2204.
+    let typo = JournalEntry::new("grocer".to_string(), chrono::Utc.ymd(2204, 6, 1).and_hms(9, 0, 0));
+    let msg = typo.check_date(&w, now, &check).unwrap();
+    assert!(msg.contains("2204-06-01") && msg.contains("365 days ahead"), "{}", msg);
+
+    let past = JournalEntry::new("grocer".to_string(), chrono::Utc.ymd(2019, 12, 31).and_hms(9, 0, 0));
+    let msg = past.check_date(&w, now, &check).unwrap();
+    assert!(msg.contains("before workspace created 2020-01-01"), "{}", msg);
+    // books can start before the workspace was created.
+    check.not_before = Some(chrono::NaiveDate::from_ymd(2019, 4, 1));
+    assert_eq!(past.check_date(&w, now, &check), None);
+    let older = JournalEntry::new("grocer".to_string(), chrono::Utc.ymd(2019, 3, 31).and_hms(9, 0, 0));
+    let msg = older.check_date(&w, now, &check).unwrap();
+    assert!(msg.contains("before books start 2019-04-01"), "{}", msg);
+
+    // warned by default, rejected when configured.
+    assert!(typo.validate_date(&w, now, &check).unwrap().is_some());
+    assert_eq!(normal.validate_date(&w, now, &check).unwrap(), None);
+    check.reject = true;
+    match typo.validate_date(&w, now, &check) {
+        Err(Error::InvalidDate(msg)) => assert!(msg.contains("2204"), "{}", msg),
+        _ => panic!("expected rejected date"),
+    }
+
+    // workspaces persisted before version 5 decode with a creation date.
+    let mut out: Workspace = Default::default();
+    out.decode(&w.encode_as(4).unwrap()).unwrap();
+    assert_eq!(out.version, 4);
+    assert_eq!(out.created, w.updated);
+    out.decode(&w.encode().unwrap()).unwrap();
+    assert_eq!(out.created, w.created);
+
+    // workspaces persisted before version 6 keep one file per entry.
+    let mut w = w.clone();
+    w.set_journal_layout("month-log");
+    out.decode(&w.encode_as(5).unwrap()).unwrap();
+    assert_eq!(out.journal_layout, "files");
+    out.decode(&w.encode().unwrap()).unwrap();
+    assert_eq!(out.journal_layout, "month-log");
+
+    // a damaged workspace is not mistaken for an older version.
+    let v0 = w.encode_as(0).unwrap();
+    let text = format!("{{\"version\":{},{}", WORKSPACE_VERSION, &v0[1..]);
+    assert!(out.decode(&text).is_err());
+    let text = format!("{{\"version\":{},{}", WORKSPACE_VERSION + 1, &v0[1..]);
+    match out.decode(&text) {
+        Err(Error::InvalidFile(msg)) => assert!(msg.contains("newer"), "{}", msg),
+        _ => panic!("expected newer version"),
+    }
+}
+
+#[test]
+fn test_ledger_code() {
+    let mut ledger = Ledger {
+        name: "cash".to_string(),
+        company: "acme".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(ledger.to_code(), None);
+    ledger.set_code(Some("1000")).unwrap();
+    assert_eq!(ledger.to_code(), Some("1000".to_string()));
+
+    let mut out: Ledger = Default::default();
+    out.decode(&ledger.encode().unwrap()).unwrap();
+    assert_eq!(out.to_code(), Some("1000".to_string()));
+
+    // legacy ledgers, without a code, decode without one.
+    let legacy = LedgerV0 {
+        doc_type: ledger.doc_type.clone(),
+        name: ledger.name.clone(),
+        created: ledger.created.clone(),
+        updated: ledger.updated.clone(),
+        company: ledger.company.clone(),
+        groups: vec![],
+        aliases: vec![],
+        tags: vec![],
+        note: "".to_string(),
+    };
+    let jval: Json = legacy.try_into().unwrap();
+    let mut out: Ledger = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert_eq!(out.name, "cash");
+    assert_eq!(out.to_code(), None);
+
+    // a corrupt code is an error, not a legacy ledger.
+    let mut jval: Json = ledger.clone().try_into().unwrap();
+    jval.set("/code", 1000_u64.into()).unwrap();
+    let mut out: Ledger = Default::default();
+    assert!(out.decode(&jval.to_string()).is_err());
+
+    ledger.set_code(None).unwrap();
+    assert_eq!(ledger.to_code(), None);
+}
+
+#[test]
+fn test_key_hyphenated_names() {
+    let ck: KeyCompany = ("my-company".to_string(),).into();
+    assert_eq!(ck.to_string(), "company-my%2Dcompany");
+    let (name,): (String,) = ck.into();
+    assert_eq!(name, "my-company");
+
+    let ck: KeyCommodity = ("usd-cash%".to_string(),).into();
+    let (name,): (String,) = ck.into();
+    assert_eq!(name, "usd-cash%");
+
+    let lk: KeyLedger = ("my-company".to_string(), "petty-cash".to_string()).into();
+    assert_eq!(lk.to_string(), "ledger-my%2Dcompany-petty%2Dcash");
+    let (cname, lname): (String, String) = lk.into();
+    assert_eq!((cname.as_str(), lname.as_str()), ("my-company", "petty-cash"));
+
+    // names without hyphens keep their keys.
+    let lk: KeyLedger = ("acme".to_string(), "cash".to_string()).into();
+    assert_eq!(lk.to_string(), "ledger-acme-cash");
+}
+
+#[test]
+fn test_durable_summary() {
+    let w = Workspace::new("personal".to_string()).set_commodity("commodity-inr".to_string());
+    assert_eq!(w.summary(), "personal inr");
+
+    let mut commodity: Commodity = ("usd".to_string(), 75.5).into();
+    commodity.symbol = "$".to_string();
+    let s = commodity.summary();
+    assert!(s.starts_with("usd ") && s.contains("75") && s.ends_with('$'), "{}", s);
+
+    let company = Company {
+        name: "acme".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(company.summary(), "acme");
+
+    let ledger = Ledger {
+        name: "cash".to_string(),
+        company: "acme".to_string(),
+        groups: vec!["asset".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(ledger.summary(), "acme:cash asset");
+
+    let lk: KeyLedger = ("acme".to_string(), "food".to_string()).into();
+    let budget = Budget {
+        ledger: lk.to_string(),
+        year: 2020,
+        month: 6,
+        amount: 500.0,
+        ..Default::default()
+    };
+    let s = budget.summary();
+    assert!(s.starts_with("acme:food 2020-06 ") && s.contains("500"), "{}", s);
+
+    let report = Report {
+        name: "balance".to_string(),
+        from: chrono::Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        to: chrono::Utc.ymd(2020, 12, 31).and_hms(0, 0, 0),
+        ..Default::default()
+    };
+    assert_eq!(report.summary(), "balance 2020-01-01..2020-12-31");
+
+    let template = Template {
+        name: "rent".to_string(),
+        payee: "landlord".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(template.summary(), "rent landlord");
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let mut je = JournalEntry::new("grocer".to_string(), created);
+    je.add_creditor("acme".to_string(), "cash".to_string(), ("inr".to_string(), 100.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "food".to_string(), ("inr".to_string(), 100.0))
+        .unwrap();
+    let s = je.summary();
+    assert!(s.starts_with("01-Jun-20 grocer "), "{}", s);
+    assert!(s.contains("100") && s.ends_with(" inr"), "{}", s);
+}
+
+#[test]
+fn test_template() {
+    let created = chrono::Utc.ymd(2020, 5, 1).and_hms(9, 0, 0);
+    let mut je = JournalEntry::new("landlord".to_string(), created);
+    je.add_creditor("acme".to_string(), "bank".to_string(), ("inr".to_string(), 900.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "rent".to_string(), ("inr".to_string(), 900.0))
+        .unwrap();
+    je.note = "monthly rent".to_string();
+
+    assert!(Template::from_entry("rent month", &je).is_err());
+    assert!(Template::from_entry("  ", &je).is_err());
+    let template = Template::from_entry("rent", &je).unwrap();
+    assert_eq!(template.to_key(), "template-rent");
+
+    let mut out: Template = Default::default();
+    out.decode(&template.encode().unwrap()).unwrap();
+    let template = out;
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let entry = template.to_entry(created);
+    assert_ne!(entry.uuid, je.uuid);
+    assert_eq!(entry.created, created);
+    assert_eq!((entry.payee.as_str(), entry.note.as_str()), ("landlord", "monthly rent"));
+    assert!(entry.validate().is_ok());
+
+    assert_eq!(entry.creditors.len(), 1);
+    assert_eq!(entry.creditors[0].ledger.to_string(), je.creditors[0].ledger.to_string());
+    assert_eq!(entry.creditors[0].commodity.value, 900.0);
+    assert_eq!(entry.debitors.len(), 1);
+    assert_eq!(entry.debitors[0].ledger.to_string(), je.debitors[0].ledger.to_string());
+    assert_eq!(entry.debitors[0].commodity.name, "inr");
+}
+
+#[test]
+fn test_template_placeholders() {
+    let created = chrono::Utc.ymd(2020, 5, 1).and_hms(9, 0, 0);
+    let mut je = JournalEntry::new("electricity".to_string(), created);
+    je.add_creditor("acme".to_string(), "bank".to_string(), ("inr".to_string(), 1.0))
+        .unwrap();
+    je.add_debitor("acme".to_string(), "utility".to_string(), ("inr".to_string(), 1.0))
+        .unwrap();
+    je.note = "bill for {month}".to_string();
+
+    let mut template = Template::from_entry("power", &je).unwrap();
+    assert!(template.set_amount_placeholder(2, "{amount}").is_err());
+    assert!(template.set_amount_placeholder(0, "amount").is_err());
+    template.set_amount_placeholder(0, "{amount}").unwrap();
+    template.set_amount_placeholder(1, "{amount}").unwrap();
+
+    let mut out: Template = Default::default();
+    out.decode(&template.encode().unwrap()).unwrap();
+    let template = out;
+    assert_eq!(template.to_placeholders(), vec!["amount".to_string(), "month".to_string()]);
+    assert!(template.check_placeholder("amount", "lots").is_err());
+    assert!(template.check_placeholder("amount", " 12.5 ").is_ok());
+    assert!(template.check_placeholder("month", "june").is_ok());
+    assert!(template.check_placeholder("date", "june").is_err());
+
+    let created = chrono::Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+    let mut prompted = vec![];
+    let entry = template
+        .instantiate(created, |name| {
+            prompted.push(name.to_string());
+            match name {
+                "amount" => Ok("1250.50".to_string()),
+                _ => Ok("june".to_string()),
+            }
+        })
+        .unwrap();
+    assert_eq!(prompted, vec!["amount".to_string(), "month".to_string()]);
+    assert_eq!(entry.creditors[0].commodity.value, 1250.50);
+    assert_eq!(entry.debitors[0].commodity.value, 1250.50);
+    assert_eq!(entry.note, "bill for june");
+    assert_eq!(entry.created, created);
+
+    // amounts must be numbers.
+    let res = template.instantiate(created, |_| Ok("lots".to_string()));
+    assert!(res.is_err());
+
+    // `{date}` replaces the date of the instantiated entry.
+    let mut template = template.clone();
+    template.payee = "electricity {date}".to_string();
+    let entry = template
+        .instantiate(created, |name| match name {
+            "date" => Ok("15/06/2020".to_string()),
+            _ => Ok("10".to_string()),
+        })
+        .unwrap();
+    assert_eq!(entry.created, chrono::Utc.ymd(2020, 6, 15).and_hms(9, 0, 0));
+    assert_eq!(entry.payee, "electricity 15/06/2020");
+
+    // a stored template with more amounts than legs fails, not panics.
+    let mut jval: Json = template.clone().try_into().unwrap();
+    let amounts = vec!["".to_string(), "".to_string(), "{extra}".to_string()];
+    jval.set("/amounts", amounts.into()).unwrap();
+    let mut out: Template = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert!(out.instantiate(created, |_| Ok("10".to_string())).is_err());
+
+    // templates persisted before placeholders decode with fixed amounts,
+    // while corrupt amounts are an error.
+    let mut jval: Json = template.clone().try_into().unwrap();
+    jval.delete("/amounts").unwrap();
+    let mut out: Template = Default::default();
+    out.decode(&jval.to_string()).unwrap();
+    assert!(out.amounts.is_empty());
+    jval.set("/amounts", 10_u64.into()).unwrap();
+    assert!(out.decode(&jval.to_string()).is_err());
+}
@@ -1,6 +1,7 @@
 use chrono::{self, Datelike};
+use unicode_width::UnicodeWidthChar;
 
-use std::str::FromStr;
+use std::{env, fmt, result, str::FromStr};
 
 use crate::core::{Error, Result};
 
@@ -68,6 +69,320 @@ macro_rules! json_to_native_string_array {
     };
 }
 
+// number of decimal places for converted and formatted amounts.
+pub const AMOUNT_PRECISION: usize = 2;
+
+// differences within this are treated as exact ties, absorbs the binary
+// representation error of decimals like 1.005.
+const TIE_EPSILON: f64 = 0.000_000_001;
+
+// rounding applied when converting and formatting amounts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundingMode {
+    // ties away from zero, 2.5 -> 3, -2.5 -> -3.
+    HalfUp,
+    // ties to the nearest even digit, banker's rounding, 2.5 -> 2.
+    HalfEven,
+}
+
+impl Default for RoundingMode {
+    fn default() -> RoundingMode {
+        RoundingMode::HalfUp
+    }
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            RoundingMode::HalfUp => write!(f, "half-up"),
+            RoundingMode::HalfEven => write!(f, "half-even"),
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RoundingMode> {
+        match s.trim() {
+            "half-up" => Ok(RoundingMode::HalfUp),
+            "half-even" => Ok(RoundingMode::HalfEven),
+            _ => err_at!(InvalidInput, msg: format!("invalid rounding mode {:?}", s)),
+        }
+    }
+}
+
+impl RoundingMode {
+    // round `value` to `precision` decimal places.
+    pub fn round(&self, value: f64, precision: usize) -> f64 {
+        let scale = 10_f64.powi(precision as i32);
+        let scaled = value.abs() * scale;
+        let (floor, fract) = (scaled.floor(), scaled - scaled.floor());
+
+        let rounded = if (fract - 0.5).abs() < TIE_EPSILON {
+            match self {
+                RoundingMode::HalfUp => floor + 1.0,
+                RoundingMode::HalfEven if (floor % 2.0) == 0.0 => floor,
+                RoundingMode::HalfEven => floor + 1.0,
+            }
+        } else {
+            scaled.round()
+        };
+
+        match rounded / scale {
+            v if v == 0.0 => 0.0,
+            v if value.is_sign_negative() => -v,
+            v => v,
+        }
+    }
+}
+
+// how amounts are rounded and labelled for display, from the command
+// line. The default is how stored summaries and the ledger export format
+// amounts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AmountFormat {
+    pub rounding: RoundingMode,
+    pub locale: NumberLocale,
+    pub style: AmountStyle,
+    pub symbols: SymbolRange,
+}
+
+impl AmountFormat {
+    // format `value` to AMOUNT_PRECISION, using this rounding mode and
+    // number locale.
+    pub fn format_amount(&self, value: f64) -> String {
+        let value = self.rounding.round(value, AMOUNT_PRECISION);
+        self.locale.format(value)
+    }
+
+    // format `value` labelled with its commodity, using this style and
+    // symbol range.
+    pub fn format_commodity(&self, value: f64, name: &str, symbol: &str) -> String {
+        let amount = self.format_amount(value);
+        self.style.format(&amount, name, symbol, self.symbols)
+    }
+}
+
+// format `value` to AMOUNT_PRECISION, refer AmountFormat::default().
+pub fn format_amount(value: f64) -> String {
+    AmountFormat::default().format_amount(value)
+}
+
+// group and decimal separators for formatted amounts, independent of the
+// commodity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberLocale {
+    // no grouping, decimal point, EG: `1234.56`.
+    Plain,
+    // comma grouping, decimal point, EG: `1,234.56`.
+    Us,
+    // dot grouping, decimal comma, EG: `1.234,56`.
+    Eu,
+    // comma grouping in lakh and crore, decimal point, EG: `1,23,456.78`.
+    In,
+}
+
+impl Default for NumberLocale {
+    fn default() -> NumberLocale {
+        NumberLocale::Plain
+    }
+}
+
+impl fmt::Display for NumberLocale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            NumberLocale::Plain => write!(f, "plain"),
+            NumberLocale::Us => write!(f, "us"),
+            NumberLocale::Eu => write!(f, "eu"),
+            NumberLocale::In => write!(f, "in"),
+        }
+    }
+}
+
+impl FromStr for NumberLocale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<NumberLocale> {
+        match s.trim() {
+            "plain" => Ok(NumberLocale::Plain),
+            "us" => Ok(NumberLocale::Us),
+            "eu" => Ok(NumberLocale::Eu),
+            "in" => Ok(NumberLocale::In),
+            _ => err_at!(InvalidInput, msg: format!("invalid number locale {:?}", s)),
+        }
+    }
+}
+
+impl NumberLocale {
+    // format `value` to AMOUNT_PRECISION with this locale's separators,
+    // round `value` beforehand, refer AmountFormat::format_amount().
+    pub fn format(&self, value: f64) -> String {
+        let digits = format!("{:.*}", AMOUNT_PRECISION, value.abs());
+        let (int, fract) = match digits.find('.') {
+            Some(n) => (&digits[..n], &digits[(n + 1)..]),
+            None => (digits.as_str(), ""),
+        };
+        let (group, decimal) = match self {
+            NumberLocale::Plain => (None, '.'),
+            NumberLocale::Us => (Some(','), '.'),
+            NumberLocale::Eu => (Some('.'), ','),
+            NumberLocale::In => (Some(','), '.'),
+        };
+
+        let mut s = String::default();
+        if value < 0.0 {
+            s.push('-');
+        }
+        for (i, ch) in int.chars().enumerate() {
+            match group {
+                Some(group) if i > 0 && self.is_group_at(int.len() - i) => s.push(group),
+                _ => (),
+            }
+            s.push(ch);
+        }
+        if !fract.is_empty() {
+            s.push(decimal);
+            s.push_str(fract);
+        }
+        s
+    }
+
+    // whether a group separator precedes the digit with `n` digits,
+    // itself included, to its right. Lakh and crore group the thousands
+    // and then every two digits.
+    fn is_group_at(&self, n: usize) -> bool {
+        match self {
+            NumberLocale::In => n == 3 || (n > 3 && (n - 3) % 2 == 0),
+            _ => n % 3 == 0,
+        }
+    }
+}
+
+// how to label amounts with their commodity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmountStyle {
+    // commodity code, EG: `INR 1200.00`.
+    Code,
+    // commodity symbol, EG: `₹1200.00`, falls back to code.
+    Symbol,
+}
+
+impl Default for AmountStyle {
+    fn default() -> AmountStyle {
+        AmountStyle::Code
+    }
+}
+
+impl fmt::Display for AmountStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            AmountStyle::Code => write!(f, "code"),
+            AmountStyle::Symbol => write!(f, "symbol"),
+        }
+    }
+}
+
+impl FromStr for AmountStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<AmountStyle> {
+        match s.trim() {
+            "code" => Ok(AmountStyle::Code),
+            "symbol" => Ok(AmountStyle::Symbol),
+            _ => err_at!(InvalidInput, msg: format!("invalid amount style {:?}", s)),
+        }
+    }
+}
+
+impl AmountStyle {
+    // label a formatted `amount` with commodity `name` or its `symbol`, a
+    // symbol outside `symbols` falls back to `name`.
+    pub fn format(&self, amount: &str, name: &str, symbol: &str, symbols: SymbolRange) -> String {
+        match self {
+            AmountStyle::Symbol if symbols.is_renderable(symbol) => {
+                match amount.strip_prefix('-') {
+                    Some(amount) => format!("-{}{}", symbol, amount),
+                    None => format!("{}{}", symbol, amount),
+                }
+            }
+            _ => format!("{} {}", name.to_uppercase(), amount),
+        }
+    }
+}
+
+// characters terminals are trusted to have glyphs for, commodity symbols
+// with other characters are labelled by commodity code instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolRange {
+    Ascii,
+    // basic multilingual plane, less private use area.
+    Bmp,
+    Any,
+}
+
+impl Default for SymbolRange {
+    fn default() -> SymbolRange {
+        SymbolRange::Bmp
+    }
+}
+
+impl fmt::Display for SymbolRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            SymbolRange::Ascii => write!(f, "ascii"),
+            SymbolRange::Bmp => write!(f, "bmp"),
+            SymbolRange::Any => write!(f, "any"),
+        }
+    }
+}
+
+impl FromStr for SymbolRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SymbolRange> {
+        match s.trim() {
+            "ascii" => Ok(SymbolRange::Ascii),
+            "bmp" => Ok(SymbolRange::Bmp),
+            "any" => Ok(SymbolRange::Any),
+            _ => err_at!(InvalidInput, msg: format!("invalid symbol range {:?}", s)),
+        }
+    }
+}
+
+impl SymbolRange {
+    // guess from locale, terminals outside a UTF-8 locale are assumed to
+    // render ascii alone.
+    pub fn from_env() -> SymbolRange {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|val| !val.is_empty())
+            .unwrap_or_default()
+            .to_lowercase();
+        SymbolRange::from_locale(&locale)
+    }
+
+    fn from_locale(locale: &str) -> SymbolRange {
+        match locale.contains("utf-8") || locale.contains("utf8") {
+            true => SymbolRange::Bmp,
+            false => SymbolRange::Ascii,
+        }
+    }
+
+    // whether `symbol` is non-empty and every char in it is printable,
+    // with non-zero width, within this range.
+    pub fn is_renderable(&self, symbol: &str) -> bool {
+        let in_range = |ch: char| match self {
+            SymbolRange::Ascii => ch.is_ascii(),
+            SymbolRange::Bmp => ch <= '\u{FFFF}' && !('\u{E000}'..='\u{F8FF}').contains(&ch),
+            SymbolRange::Any => true,
+        };
+        let width = |ch: char| ch.width().unwrap_or(0);
+        !symbol.is_empty() && symbol.chars().all(|ch| width(ch) > 0 && in_range(ch))
+    }
+}
+
 pub fn date_to_period<T>(date: chrono::Date<T>) -> (chrono::Date<T>, chrono::Date<T>)
 where
     T: chrono::TimeZone,
@@ -81,6 +396,103 @@ where
     }
 }
 
+// first day of the week, for calendar layouts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeekStart {
+    Sunday,
+    Monday,
+}
+
+impl Default for WeekStart {
+    fn default() -> WeekStart {
+        WeekStart::Monday
+    }
+}
+
+impl fmt::Display for WeekStart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            WeekStart::Sunday => write!(f, "sunday"),
+            WeekStart::Monday => write!(f, "monday"),
+        }
+    }
+}
+
+impl FromStr for WeekStart {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<WeekStart> {
+        match s.trim() {
+            "sunday" => Ok(WeekStart::Sunday),
+            "monday" => Ok(WeekStart::Monday),
+            _ => err_at!(InvalidInput, msg: format!("invalid week start {:?}", s)),
+        }
+    }
+}
+
+impl WeekStart {
+    // column, 0-based, of `weekday` in a week starting on this day.
+    pub fn to_column(&self, weekday: chrono::Weekday) -> usize {
+        match self {
+            WeekStart::Sunday => weekday.num_days_from_sunday() as usize,
+            WeekStart::Monday => weekday.num_days_from_monday() as usize,
+        }
+    }
+}
+
+// date input formats, ISO, the display format with english month
+// abbreviations, and numeric day first.
+pub const DATE_INPUT_FORMATS: [&'static str; 3] = ["%Y-%m-%d", "%d-%b-%y", "%d/%m/%Y"];
+
+// parse a date typed by the user in any of DATE_INPUT_FORMATS, EG:
+// `2024-04-01`, `01-Apr-24` or `01/04/2024`.
+pub fn parse_date_flexible(s: &str) -> Result<chrono::NaiveDate> {
+    let s = s.trim();
+    let date = DATE_INPUT_FORMATS
+        .iter()
+        .filter_map(|f| chrono::NaiveDate::parse_from_str(s, f).ok())
+        .next();
+    match date {
+        Some(date) => Ok(date),
+        None => {
+            let msg = format!("{:?}, expected yyyy-mm-dd, dd-mon-yy or dd/mm/yyyy", s);
+            err_at!(InvalidDate, msg: msg)
+        }
+    }
+}
+
+// calendar grid for `month` of `year`, one row per week, each cell holding
+// the day of month. Cells before the first day and after the last day are
+// blank. Empty grid for an invalid month.
+pub fn month_grid(year: i32, month: u32, start: WeekStart) -> Vec<[Option<u32>; 7]> {
+    let first = match chrono::NaiveDate::from_ymd_opt(year, month, 1) {
+        Some(first) => first,
+        None => return vec![],
+    };
+
+    let mut grid = vec![];
+    let mut week = [None; 7];
+    let mut col = start.to_column(first.weekday());
+    let mut date = first;
+    while date.month() == month {
+        week[col] = Some(date.day());
+        col += 1;
+        if col == 7 {
+            grid.push(week);
+            week = [None; 7];
+            col = 0;
+        }
+        date = match date.succ_opt() {
+            Some(date) => date,
+            None => break,
+        };
+    }
+    if col > 0 {
+        grid.push(week);
+    }
+    grid
+}
+
 pub fn csv<T>(s: String) -> Result<Vec<T>>
 where
     T: FromStr,
@@ -118,3 +530,100 @@ pub fn str_as_anuhdc(s: &str) -> bool {
     }
     true
 }
+
+// leg of a quick entry, positive amount is a debit and negative amount
+// is a credit. Commodity is optional, left to the caller's default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuickLeg {
+    pub company: String,
+    pub ledger: String,
+    pub amount: f64,
+    pub commodity: Option<String>,
+}
+
+// quick entry typed as a single line, payee followed by legs separated
+// by `|`, each leg as `company:ledger amount [commodity]`, EG:
+// `rent | acme:checking -1200 | acme:rent 1200`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuickEntry {
+    pub payee: String,
+    pub legs: Vec<QuickLeg>,
+}
+
+impl FromStr for QuickEntry {
+    type Err = Error;
+
+    // legs must balance for every commodity, legs without a commodity
+    // balance among themselves.
+    fn from_str(s: &str) -> Result<QuickEntry> {
+        let mut parts = s.split('|').map(|p| p.trim());
+        let payee = match parts.next() {
+            Some(payee) if !payee.is_empty() => payee.to_string(),
+            _ => err_at!(InvalidInput, msg: format!("quick entry missing payee"))?,
+        };
+
+        let mut legs = vec![];
+        for part in parts {
+            legs.push(parse_quick_leg(part)?);
+        }
+        if legs.len() < 2 {
+            err_at!(InvalidInput, msg: format!("quick entry needs two or more legs"))?;
+        }
+
+        let mut totals: Vec<(Option<String>, f64)> = vec![];
+        for leg in legs.iter() {
+            match totals.iter_mut().find(|(c, _)| c == &leg.commodity) {
+                Some((_, total)) => *total += leg.amount,
+                None => totals.push((leg.commodity.clone(), leg.amount)),
+            }
+        }
+        for (commodity, total) in totals.into_iter() {
+            if total.abs() >= crate::types::BALANCE_EPSILON {
+                let name = commodity.unwrap_or_default();
+                let msg = format!("quick entry imbalance {} {}", total, name);
+                err_at!(InvalidInput, msg: msg.trim_end())?;
+            }
+        }
+
+        Ok(QuickEntry { payee, legs })
+    }
+}
+
+fn parse_quick_leg(part: &str) -> Result<QuickLeg> {
+    let tokens: Vec<&str> = part.split_whitespace().collect();
+    let (account, amount, commodity) = match tokens.as_slice() {
+        [account, amount] => (*account, *amount, None),
+        [account, amount, commodity] => (*account, *amount, Some(commodity.to_lowercase())),
+        _ => err_at!(InvalidInput, msg: format!("quick entry leg {:?}", part))?,
+    };
+
+    let (company, ledger) = match account.split(':').collect::<Vec<&str>>().as_slice() {
+        [company, ledger] if !company.is_empty() && !ledger.is_empty() => {
+            (company.to_string(), ledger.to_string())
+        }
+        _ => err_at!(InvalidInput, msg: format!("quick entry account {:?}", account))?,
+    };
+    if !str_as_anuh(&company) || !str_as_anuh(&ledger) {
+        err_at!(InvalidInput, msg: format!("quick entry account {:?}", account))?;
+    }
+
+    let amount: f64 = err_at!(
+        InvalidInput,
+        amount.replace(',', "").parse(),
+        format!("quick entry amount {:?}", amount)
+    )?;
+    if amount == 0.0 {
+        err_at!(InvalidInput, msg: format!("quick entry zero amount {:?}", part))?;
+    }
+
+    Ok(QuickLeg {
+        company,
+        ledger,
+        amount,
+        commodity,
+    })
+}
+
+#[cfg(test)]
+#[path = "util_test.rs"]
+mod util_test;
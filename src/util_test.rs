@@ -0,0 +1,241 @@
+use super::*;
+
+#[test]
+fn test_rounding_mode() {
+    let (up, even) = (RoundingMode::HalfUp, RoundingMode::HalfEven);
+
+    // exact .5 boundaries.
+    assert_eq!(up.round(2.5, 0), 3.0);
+    assert_eq!(even.round(2.5, 0), 2.0);
+    assert_eq!(up.round(3.5, 0), 4.0);
+    assert_eq!(even.round(3.5, 0), 4.0);
+    assert_eq!(up.round(-2.5, 0), -3.0);
+    assert_eq!(even.round(-2.5, 0), -2.0);
+    assert_eq!(up.round(0.125, 2), 0.13);
+    assert_eq!(even.round(0.125, 2), 0.12);
+    // decimal ties that are not exact in binary.
+    assert_eq!(up.round(1.005, 2), 1.01);
+    assert_eq!(even.round(1.005, 2), 1.0);
+    assert_eq!(up.round(2.675, 2), 2.68);
+    assert_eq!(even.round(2.675, 2), 2.68);
+
+    // away from ties both modes agree.
+    assert_eq!(up.round(2.4999, 0), 2.0);
+    assert_eq!(even.round(2.5001, 0), 3.0);
+    assert_eq!(even.round(-0.001, 2), 0.0);
+    assert!(even.round(-0.001, 2).is_sign_positive());
+
+    assert_eq!(RoundingMode::default(), up);
+    assert_eq!("half-even".parse::<RoundingMode>().unwrap(), even);
+    assert_eq!(up.to_string(), "half-up");
+    assert!("half-down".parse::<RoundingMode>().is_err());
+}
+
+#[test]
+fn test_amount_format() {
+    let (up, even) = (
+        AmountFormat::default(),
+        AmountFormat {
+            rounding: RoundingMode::HalfEven,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(up.format_amount(0.125), "0.13");
+    assert_eq!(even.format_amount(0.125), "0.12");
+    assert_eq!(even.format_amount(-2.675), "-2.68");
+    assert_eq!(format_amount(1.005), "1.01");
+    assert_eq!(even.format_commodity(1.005, "inr", "₹"), "INR 1.00");
+
+    let eu = AmountFormat {
+        locale: NumberLocale::Eu,
+        ..Default::default()
+    };
+    assert_eq!(eu.format_amount(-1234.565), "-1.234,57");
+    assert_eq!(eu.format_commodity(0.5, "eur", "€"), "EUR 0,50");
+
+    let symbol = AmountFormat {
+        style: AmountStyle::Symbol,
+        ..eu
+    };
+    assert_eq!(symbol.format_commodity(-0.5, "eur", "€"), "-€0,50");
+}
+
+#[test]
+fn test_amount_style() {
+    let (code, symbol) = (AmountStyle::Code, AmountStyle::Symbol);
+    let bmp = SymbolRange::default();
+
+    assert_eq!(code.format("1200.00", "inr", "₹", bmp), "INR 1200.00");
+    assert_eq!(symbol.format("1200.00", "inr", "₹", bmp), "₹1200.00");
+    assert_eq!(symbol.format("-12.50", "inr", "₹", bmp), "-₹12.50");
+    // no symbol, fallback to code.
+    assert_eq!(symbol.format("1200.00", "inr", "", bmp), "INR 1200.00");
+
+    assert_eq!(AmountStyle::default(), code);
+    assert_eq!("symbol".parse::<AmountStyle>().unwrap(), symbol);
+    assert_eq!(symbol.to_string(), "symbol");
+    assert!("sym".parse::<AmountStyle>().is_err());
+}
+
+#[test]
+fn test_symbol_range() {
+    let (ascii, bmp, any) = (SymbolRange::Ascii, SymbolRange::Bmp, SymbolRange::Any);
+
+    assert!(bmp.is_renderable("₹") && any.is_renderable("₹"));
+    assert!(!ascii.is_renderable("₹"));
+    assert!(ascii.is_renderable("$") && ascii.is_renderable("Rs"));
+    // empty, zero-width or non-printing symbols are never rendered.
+    for symbol in ["", "\u{200B}", "\u{0301}", "\u{7}"].iter() {
+        assert!(!any.is_renderable(symbol), "{:?}", symbol);
+    }
+    // private use and beyond the basic plane need a trusting terminal.
+    for symbol in ["\u{E000}", "\u{1F4B0}"].iter() {
+        assert!(!bmp.is_renderable(symbol), "{:?}", symbol);
+        assert!(any.is_renderable(symbol), "{:?}", symbol);
+    }
+
+    assert_eq!(SymbolRange::from_locale("en_in.utf-8"), bmp);
+    assert_eq!(SymbolRange::from_locale("c"), ascii);
+    assert_eq!(SymbolRange::default(), bmp);
+    assert_eq!("any".parse::<SymbolRange>().unwrap(), any);
+    assert_eq!(ascii.to_string(), "ascii");
+    assert!("utf8".parse::<SymbolRange>().is_err());
+
+    // unrenderable symbols fall back to the code.
+    let symbol = AmountStyle::Symbol;
+    assert_eq!(symbol.format("10.00", "xyz", "\u{200B}", bmp), "XYZ 10.00");
+    assert_eq!(symbol.format("10.00", "xyz", "\u{E000}", bmp), "XYZ 10.00");
+    assert_eq!(symbol.format("10.00", "usd", "$", ascii), "$10.00");
+    assert_eq!(symbol.format("10.00", "inr", "₹", ascii), "INR 10.00");
+}
+
+#[test]
+fn test_number_locale() {
+    let (plain, us, eu) = (NumberLocale::Plain, NumberLocale::Us, NumberLocale::Eu);
+
+    assert_eq!(plain.format(1234.56), "1234.56");
+    assert_eq!(us.format(1234.56), "1,234.56");
+    assert_eq!(eu.format(1234.56), "1.234,56");
+
+    assert_eq!(us.format(-1234567.891), "-1,234,567.89");
+    assert_eq!(eu.format(-1234567.891), "-1.234.567,89");
+    assert_eq!(us.format(123.0), "123.00");
+    assert_eq!(eu.format(0.5), "0,50");
+    assert_eq!(us.format(100000.0), "100,000.00");
+
+    assert_eq!(NumberLocale::default(), plain);
+    assert_eq!("eu".parse::<NumberLocale>().unwrap(), eu);
+    assert_eq!(us.to_string(), "us");
+    assert!("de".parse::<NumberLocale>().is_err());
+}
+
+#[test]
+fn test_month_grid() {
+    // july 2020 starts on a wednesday and has 31 days.
+    let grid = month_grid(2020, 7, WeekStart::Monday);
+    assert_eq!(grid.len(), 5);
+    assert_eq!(grid[0], [None, None, Some(1), Some(2), Some(3), Some(4), Some(5)]);
+    assert_eq!(grid[1][0], Some(6));
+    assert_eq!(grid[4], [Some(27), Some(28), Some(29), Some(30), Some(31), None, None]);
+
+    let grid = month_grid(2020, 7, WeekStart::Sunday);
+    assert_eq!(grid.len(), 5);
+    assert_eq!(grid[0], [None, None, None, Some(1), Some(2), Some(3), Some(4)]);
+    assert_eq!(grid[1][0], Some(5));
+    assert_eq!(grid[4], [Some(26), Some(27), Some(28), Some(29), Some(30), Some(31), None]);
+
+    // february 2021 starts on a monday, fills four whole weeks.
+    let grid = month_grid(2021, 2, WeekStart::Monday);
+    assert_eq!(grid.len(), 4);
+    assert_eq!(grid[0][0], Some(1));
+    assert_eq!(grid[3][6], Some(28));
+    assert_eq!(month_grid(2021, 2, WeekStart::Sunday).len(), 5);
+
+    assert!(month_grid(2021, 13, WeekStart::Monday).is_empty());
+
+    assert_eq!(WeekStart::default(), WeekStart::Monday);
+    assert_eq!("sunday".parse::<WeekStart>().unwrap(), WeekStart::Sunday);
+    assert_eq!(WeekStart::Sunday.to_string(), "sunday");
+    assert!("friday".parse::<WeekStart>().is_err());
+}
+
+#[test]
+fn test_parse_date_flexible() {
+    let date = chrono::NaiveDate::from_ymd(2024, 4, 1);
+    assert_eq!(parse_date_flexible("2024-04-01").unwrap(), date);
+    assert_eq!(parse_date_flexible("01-Apr-24").unwrap(), date);
+    assert_eq!(parse_date_flexible(" 01/04/2024 ").unwrap(), date);
+
+    for s in ["", "apples", "31-Feb-24", "2024-13-01", "01.04.2024"].iter() {
+        match parse_date_flexible(s) {
+            Err(Error::InvalidDate(msg)) => assert!(msg.contains("yyyy-mm-dd"), "{}", msg),
+            _ => panic!("expected invalid date for {:?}", s),
+        }
+    }
+}
+
+#[test]
+fn test_number_locale_lakh() {
+    let (us, inr) = (NumberLocale::Us, NumberLocale::In);
+
+    assert_eq!(us.format(12345678.0), "12,345,678.00");
+    assert_eq!(inr.format(12345678.0), "1,23,45,678.00");
+
+    assert_eq!(inr.format(123.0), "123.00");
+    assert_eq!(inr.format(1234.5), "1,234.50");
+    assert_eq!(inr.format(123456.0), "1,23,456.00");
+    assert_eq!(inr.format(-1234567.891), "-12,34,567.89");
+    assert_eq!(inr.format(1000000000.0), "1,00,00,00,000.00");
+
+    assert_eq!("in".parse::<NumberLocale>().unwrap(), inr);
+    assert_eq!(inr.to_string(), "in");
+}
+
+#[test]
+fn test_quick_entry() {
+    let quick: QuickEntry = "rent | acme:checking -1,200 | acme:rent 1200".parse().unwrap();
+    assert_eq!(quick.payee, "rent");
+    assert_eq!(quick.legs.len(), 2);
+    assert_eq!(quick.legs[0].company, "acme");
+    assert_eq!(quick.legs[0].ledger, "checking");
+    assert_eq!(quick.legs[0].amount, -1200.0);
+    assert_eq!(quick.legs[1].ledger, "rent");
+    assert_eq!(quick.legs[1].amount, 1200.0);
+    assert_eq!(quick.legs[1].commodity, None);
+
+    let text = "fx | acme:bank -10 USD | acme:cash 10 usd | acme:food 5 | acme:cash -5";
+    let quick: QuickEntry = text.parse().unwrap();
+    assert_eq!(quick.legs.len(), 4);
+    assert_eq!(quick.legs[0].commodity, Some("usd".to_string()));
+
+    // missing balance.
+    let err = "rent | acme:checking -1200 | acme:rent 1100"
+        .parse::<QuickEntry>()
+        .unwrap_err();
+    assert!(err.to_string().contains("imbalance"), "{}", err);
+    let err = "fx | acme:bank -10 usd | acme:cash 10 inr"
+        .parse::<QuickEntry>()
+        .unwrap_err();
+    assert!(err.to_string().contains("imbalance"), "{}", err);
+    assert!("rent | acme:checking -1200".parse::<QuickEntry>().is_err());
+    assert!(" | acme:a -1 | acme:b 1".parse::<QuickEntry>().is_err());
+
+    // malformed account tokens.
+    for text in &[
+        "rent | checking -1200 | acme:rent 1200",
+        "rent | acme: -1200 | acme:rent 1200",
+        "rent | acme:bank:x -1200 | acme:rent 1200",
+        "rent | ac$me:bank -1200 | acme:rent 1200",
+    ] {
+        let err = text.parse::<QuickEntry>().unwrap_err();
+        assert!(err.to_string().contains("account"), "{} {}", text, err);
+    }
+    for text in &[
+        "rent | acme:bank | acme:rent 1200",
+        "rent | acme:bank -12x | acme:rent 1200",
+        "rent | acme:bank 0 | acme:rent 0",
+    ] {
+        assert!(text.parse::<QuickEntry>().is_err(), "{}", text);
+    }
+}
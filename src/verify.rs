@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    core::{Result, Store},
+    types,
+};
+
+// integrity scan over a store's journal, done in chunks so that it can
+// be interleaved with user input. Each journal entry shall validate and
+// refer only to existing ledgers.
+pub struct Verifier {
+    iter: Option<Box<dyn Iterator<Item = Result<types::JournalEntry>>>>,
+    // ledgers known to exist.
+    ledgers: BTreeSet<String>,
+    n_entries: usize,
+    issues: Vec<String>,
+}
+
+impl Verifier {
+    pub fn new<S>(store: &mut S) -> Result<Verifier>
+    where
+        S: Store,
+    {
+        let from = chrono::MIN_DATETIME;
+        let iter = store.iter_journal(from, chrono::MAX_DATETIME)?;
+
+        Ok(Verifier {
+            iter: Some(iter),
+            ledgers: BTreeSet::new(),
+            n_entries: 0,
+            issues: vec![],
+        })
+    }
+
+    // verify the next `chunk` journal entries, return true when the scan
+    // is complete. Calling after completion is a no-op.
+    pub fn step<S>(&mut self, store: &mut S, chunk: usize) -> Result<bool>
+    where
+        S: Store,
+    {
+        let mut iter = match self.iter.take() {
+            Some(iter) => iter,
+            None => return Ok(true),
+        };
+
+        for _ in 0..chunk {
+            match iter.next() {
+                Some(Ok(entry)) => self.verify_entry(store, &entry),
+                Some(Err(err)) => self.issues.push(format!("{}", err)),
+                None => return Ok(true),
+            }
+        }
+
+        self.iter = Some(iter);
+        Ok(false)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.iter.is_none()
+    }
+
+    pub fn to_entry_count(&self) -> usize {
+        self.n_entries
+    }
+
+    pub fn to_issues(&self) -> Vec<String> {
+        self.issues.clone()
+    }
+
+    fn verify_entry<S>(&mut self, store: &mut S, entry: &types::JournalEntry)
+    where
+        S: Store,
+    {
+        self.n_entries += 1;
+        let key = format!("{}", entry.created.format("%Y-%m-%d"));
+
+        if let Err(err) = entry.validate() {
+            self.issues.push(format!("{} {:?} {}", key, entry.payee, err));
+        }
+
+        let ledgers = entry
+            .creditors
+            .iter()
+            .map(|c| &c.ledger)
+            .chain(entry.debitors.iter().map(|d| &d.ledger));
+        for ledger in ledgers {
+            let name = ledger.to_string();
+            if self.ledgers.contains(&name) {
+                continue;
+            }
+            match store.get_by(ledger) {
+                Ok(_) => {
+                    self.ledgers.insert(name);
+                }
+                Err(_) => {
+                    let msg = format!("{} {:?} missing {}", key, entry.payee, name);
+                    self.issues.push(msg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "verify_test.rs"]
+mod verify_test;
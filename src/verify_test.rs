@@ -0,0 +1,66 @@
+use chrono::offset::TimeZone;
+
+use super::*;
+use crate::mem_store::testing::{self, make_ledger};
+
+fn make_entry(day: u32, creditor: &str, debitor: &str) -> types::JournalEntry {
+    let created = chrono::Utc.ymd(2020, 1, day).and_hms(10, 0, 0);
+    let payee = format!("day-{}", day);
+    testing::make_entry(&payee, created, creditor, debitor, ("inr", 10.0))
+}
+
+#[test]
+fn test_verifier_chunked() {
+    let mut store = testing::make_store();
+    store.put(make_ledger("cash")).unwrap();
+    store.put(make_ledger("food")).unwrap();
+
+    for day in 1..=10 {
+        store.put(make_entry(day, "cash", "food")).unwrap();
+    }
+    // one imbalanced entry and one against a missing ledger.
+    let mut je = make_entry(11, "cash", "food");
+    je.debitors[0].commodity.value = 9.0;
+    store.put(je).unwrap();
+    store.put(make_entry(12, "cash", "rent")).unwrap();
+
+    let mut verifier = Verifier::new(&mut store).unwrap();
+    assert_eq!(verifier.step(&mut store, 5).unwrap(), false);
+    assert_eq!(verifier.to_entry_count(), 5);
+    assert!(verifier.to_issues().is_empty());
+
+    assert_eq!(verifier.step(&mut store, 5).unwrap(), false);
+    assert_eq!(verifier.to_entry_count(), 10);
+    assert!(!verifier.is_done());
+
+    assert_eq!(verifier.step(&mut store, 5).unwrap(), true);
+    assert!(verifier.is_done());
+    assert_eq!(verifier.to_entry_count(), 12);
+
+    let issues = verifier.to_issues();
+    assert_eq!(issues.len(), 2, "{:?}", issues);
+    assert!(issues[0].contains("imbalance"), "{}", issues[0]);
+    assert!(issues[1].contains("missing ledger-acme-rent"), "{}", issues[1]);
+
+    // no-op after completion.
+    assert_eq!(verifier.step(&mut store, 5).unwrap(), true);
+    assert_eq!(verifier.to_issues().len(), 2);
+}
+
+#[test]
+fn test_verifier_before_epoch() {
+    let mut store = testing::make_store();
+    store.put(make_ledger("cash")).unwrap();
+
+    // entries dated before 1970 are part of the full scan.
+    let created = chrono::Utc.ymd(1965, 6, 1).and_hms(10, 0, 0);
+    let je = testing::make_entry("old", created, "cash", "food", ("inr", 10.0));
+    store.put(je).unwrap();
+
+    let mut verifier = Verifier::new(&mut store).unwrap();
+    assert_eq!(verifier.step(&mut store, 5).unwrap(), true);
+    assert_eq!(verifier.to_entry_count(), 1);
+    let issues = verifier.to_issues();
+    assert_eq!(issues.len(), 1, "{:?}", issues);
+    assert!(issues[0].contains("missing ledger-acme-food"), "{}", issues[0]);
+}